@@ -0,0 +1,17 @@
+-- sqlite only drops columns from 3.35 on, so the table is rebuilt without it
+CREATE TABLE question_without_archived (
+    qid INTEGER PRIMARY KEY NOT NULL,
+    title TEXT NOT NULL,
+    title_slug TEXT,
+    prompt TEXT DEFAULT "",
+    difficulty TEXT CHECK( difficulty in ("EASY", "MEDIUM", "HARD") ),
+    source INTEGER,
+    source_qid INTEGER,
+    FOREIGN KEY(source) REFERENCES source(sid)
+);
+
+INSERT INTO question_without_archived (qid, title, title_slug, prompt, difficulty, source, source_qid)
+    SELECT qid, title, title_slug, prompt, difficulty, source, source_qid FROM question;
+
+DROP TABLE question;
+ALTER TABLE question_without_archived RENAME TO question;
@@ -0,0 +1 @@
+ALTER TABLE question ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
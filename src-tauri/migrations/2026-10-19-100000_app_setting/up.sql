@@ -0,0 +1,4 @@
+CREATE TABLE app_setting (
+    name TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS code_snippet;
+DROP TABLE IF EXISTS question_metadata;
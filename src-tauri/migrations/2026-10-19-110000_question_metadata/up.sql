@@ -0,0 +1,21 @@
+CREATE TABLE question_metadata (
+    qid INTEGER PRIMARY KEY NOT NULL,
+    paid_only BOOLEAN NOT NULL DEFAULT FALSE,
+    acceptance_rate REAL,
+    likes INTEGER,
+    dislikes INTEGER,
+    hints TEXT NOT NULL DEFAULT "[]",
+    example_testcases TEXT,
+    similar_questions TEXT NOT NULL DEFAULT "[]",
+    details_fetched BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
+
+CREATE TABLE code_snippet (
+    csid INTEGER PRIMARY KEY NOT NULL,
+    qid INTEGER NOT NULL,
+    lang TEXT NOT NULL,
+    lang_slug TEXT NOT NULL,
+    code TEXT NOT NULL,
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
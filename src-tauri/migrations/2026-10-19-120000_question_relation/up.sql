@@ -0,0 +1,9 @@
+CREATE TABLE question_relation (
+    relid INTEGER PRIMARY KEY NOT NULL,
+    qid INTEGER NOT NULL,
+    related_qid INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    FOREIGN KEY(qid) REFERENCES question(qid),
+    FOREIGN KEY(related_qid) REFERENCES question(qid),
+    UNIQUE(qid, related_qid, kind)
+);
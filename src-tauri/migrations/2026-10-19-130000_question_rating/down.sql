@@ -0,0 +1,21 @@
+-- sqlite only drops columns from 3.35 on, so the table is rebuilt without it
+CREATE TABLE question_metadata_without_rating (
+    qid INTEGER PRIMARY KEY NOT NULL,
+    paid_only BOOLEAN NOT NULL DEFAULT FALSE,
+    acceptance_rate REAL,
+    likes INTEGER,
+    dislikes INTEGER,
+    hints TEXT NOT NULL DEFAULT "[]",
+    example_testcases TEXT,
+    similar_questions TEXT NOT NULL DEFAULT "[]",
+    details_fetched BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
+
+INSERT INTO question_metadata_without_rating (qid, paid_only, acceptance_rate, likes, dislikes,
+        hints, example_testcases, similar_questions, details_fetched)
+    SELECT qid, paid_only, acceptance_rate, likes, dislikes,
+        hints, example_testcases, similar_questions, details_fetched FROM question_metadata;
+
+DROP TABLE question_metadata;
+ALTER TABLE question_metadata_without_rating RENAME TO question_metadata;
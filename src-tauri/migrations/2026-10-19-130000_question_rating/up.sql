@@ -0,0 +1 @@
+ALTER TABLE question_metadata ADD COLUMN rating INTEGER;
@@ -0,0 +1,5 @@
+CREATE TABLE feed_source (
+    sid INTEGER PRIMARY KEY NOT NULL,
+    location TEXT NOT NULL,
+    FOREIGN KEY(sid) REFERENCES source(sid)
+);
@@ -0,0 +1,17 @@
+-- sqlite only drops columns from 3.35 on, so the table is rebuilt without it
+CREATE TABLE user_without_locale (
+    uid INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    hide_diff BOOLEAN DEFAULT FALSE,
+    hide_cat BOOLEAN DEFAULT FALSE,
+    hide_solved BOOLEAN DEFAULT FALSE,
+    dark_mode BOOLEAN DEFAULT FALSE
+);
+
+INSERT INTO user_without_locale (uid, name, hide_diff, hide_cat, hide_solved, dark_mode)
+    SELECT uid, name, hide_diff, hide_cat, hide_solved, dark_mode FROM user;
+
+DROP TABLE user;
+ALTER TABLE user_without_locale RENAME TO user;
+
+DROP TABLE IF EXISTS question_translation;
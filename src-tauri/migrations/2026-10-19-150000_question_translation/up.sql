@@ -0,0 +1,10 @@
+CREATE TABLE question_translation (
+    qid INTEGER NOT NULL,
+    locale TEXT NOT NULL,
+    title TEXT NOT NULL,
+    prompt TEXT,
+    PRIMARY KEY(qid, locale),
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
+
+ALTER TABLE user ADD COLUMN prompt_locale TEXT NOT NULL DEFAULT 'en';
@@ -0,0 +1,6 @@
+CREATE TABLE prompt_render (
+    hash TEXT PRIMARY KEY NOT NULL,
+    html TEXT NOT NULL,
+    markdown TEXT NOT NULL,
+    text TEXT NOT NULL
+);
@@ -0,0 +1,9 @@
+CREATE TABLE prompt_asset (
+    source_url TEXT PRIMARY KEY NOT NULL,
+    hash TEXT NOT NULL,
+    mime TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    last_used BIGINT NOT NULL
+);
+
+CREATE INDEX prompt_asset_hash ON prompt_asset (hash);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS question_constraint;
+DROP TABLE IF EXISTS question_example;
+DROP TABLE IF EXISTS question_structure;
@@ -0,0 +1,29 @@
+CREATE TABLE question_structure (
+    qid INTEGER PRIMARY KEY NOT NULL,
+    prompt_hash TEXT NOT NULL,
+    max_input_size BIGINT,
+    expected_complexity TEXT,
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
+
+CREATE TABLE question_example (
+    qid INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    input TEXT NOT NULL,
+    output TEXT NOT NULL,
+    explanation TEXT,
+    PRIMARY KEY(qid, position),
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
+
+CREATE TABLE question_constraint (
+    qid INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    subject TEXT,
+    lower_bound BIGINT,
+    upper_bound BIGINT,
+    is_size BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY(qid, position),
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
@@ -0,0 +1,14 @@
+CREATE TABLE test_case (
+    tcid INTEGER PRIMARY KEY NOT NULL,
+    uid INTEGER NOT NULL,
+    qid INTEGER NOT NULL,
+    input TEXT NOT NULL,
+    expected TEXT NOT NULL,
+    comparator TEXT NOT NULL DEFAULT 'exact',
+    note TEXT,
+    caught_bug BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY(uid) REFERENCES user(uid),
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
+
+CREATE INDEX test_case_uid_qid ON test_case(uid, qid);
@@ -0,0 +1,18 @@
+DROP TABLE IF EXISTS code_template;
+
+-- sqlite only drops columns from 3.35 on, so the table is rebuilt without it
+CREATE TABLE user_without_language (
+    uid INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    hide_diff BOOLEAN DEFAULT FALSE,
+    hide_cat BOOLEAN DEFAULT FALSE,
+    hide_solved BOOLEAN DEFAULT FALSE,
+    dark_mode BOOLEAN DEFAULT FALSE,
+    prompt_locale TEXT NOT NULL DEFAULT 'en'
+);
+
+INSERT INTO user_without_language (uid, name, hide_diff, hide_cat, hide_solved, dark_mode, prompt_locale)
+    SELECT uid, name, hide_diff, hide_cat, hide_solved, dark_mode, prompt_locale FROM user;
+
+DROP TABLE user;
+ALTER TABLE user_without_language RENAME TO user;
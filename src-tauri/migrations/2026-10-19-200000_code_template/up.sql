@@ -0,0 +1,14 @@
+ALTER TABLE user ADD COLUMN preferred_language TEXT NOT NULL DEFAULT 'python3';
+
+CREATE TABLE code_template (
+    ctid INTEGER PRIMARY KEY NOT NULL,
+    uid INTEGER NOT NULL,
+    tid INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    lang_slug TEXT NOT NULL,
+    code TEXT NOT NULL,
+    FOREIGN KEY(uid) REFERENCES user(uid),
+    FOREIGN KEY(tid) REFERENCES topic(tid)
+);
+
+CREATE UNIQUE INDEX code_template_uid_tid_name ON code_template(uid, tid, name, lang_slug);
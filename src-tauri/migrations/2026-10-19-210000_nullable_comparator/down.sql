@@ -0,0 +1,20 @@
+-- cases without a comparator go back to exact, the old default
+CREATE TABLE test_case_exact_comparator (
+    tcid INTEGER PRIMARY KEY NOT NULL,
+    uid INTEGER NOT NULL,
+    qid INTEGER NOT NULL,
+    input TEXT NOT NULL,
+    expected TEXT NOT NULL,
+    comparator TEXT NOT NULL DEFAULT 'exact',
+    note TEXT,
+    caught_bug BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY(uid) REFERENCES user(uid),
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
+
+INSERT INTO test_case_exact_comparator (tcid, uid, qid, input, expected, comparator, note, caught_bug)
+    SELECT tcid, uid, qid, input, expected, COALESCE(comparator, 'exact'), note, caught_bug FROM test_case;
+
+DROP TABLE test_case;
+ALTER TABLE test_case_exact_comparator RENAME TO test_case;
+CREATE INDEX test_case_uid_qid ON test_case(uid, qid);
@@ -0,0 +1,22 @@
+-- sqlite can't drop NOT NULL from a column, so the table is rebuilt. a NULL
+-- comparator means the one the question's return type calls for, picked
+-- when the case is run
+CREATE TABLE test_case_nullable_comparator (
+    tcid INTEGER PRIMARY KEY NOT NULL,
+    uid INTEGER NOT NULL,
+    qid INTEGER NOT NULL,
+    input TEXT NOT NULL,
+    expected TEXT NOT NULL,
+    comparator TEXT,
+    note TEXT,
+    caught_bug BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY(uid) REFERENCES user(uid),
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
+
+INSERT INTO test_case_nullable_comparator (tcid, uid, qid, input, expected, comparator, note, caught_bug)
+    SELECT tcid, uid, qid, input, expected, comparator, note, caught_bug FROM test_case;
+
+DROP TABLE test_case;
+ALTER TABLE test_case_nullable_comparator RENAME TO test_case;
+CREATE INDEX test_case_uid_qid ON test_case(uid, qid);
@@ -0,0 +1,22 @@
+-- sqlite only drops columns from 3.35 on, so the table is rebuilt without it
+CREATE TABLE question_metadata_without_frontend_id (
+    qid INTEGER PRIMARY KEY NOT NULL,
+    paid_only BOOLEAN NOT NULL DEFAULT FALSE,
+    acceptance_rate REAL,
+    likes INTEGER,
+    dislikes INTEGER,
+    hints TEXT NOT NULL DEFAULT "[]",
+    example_testcases TEXT,
+    similar_questions TEXT NOT NULL DEFAULT "[]",
+    details_fetched BOOLEAN NOT NULL DEFAULT FALSE,
+    rating INTEGER,
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
+
+INSERT INTO question_metadata_without_frontend_id (qid, paid_only, acceptance_rate, likes, dislikes,
+        hints, example_testcases, similar_questions, details_fetched, rating)
+    SELECT qid, paid_only, acceptance_rate, likes, dislikes,
+        hints, example_testcases, similar_questions, details_fetched, rating FROM question_metadata;
+
+DROP TABLE question_metadata;
+ALTER TABLE question_metadata_without_frontend_id RENAME TO question_metadata;
@@ -0,0 +1,23 @@
+CREATE TABLE review_log (
+    rlid INTEGER PRIMARY KEY NOT NULL,
+    uid INTEGER NOT NULL,
+    qid INTEGER NOT NULL,
+    reviewed_at BIGINT NOT NULL,
+    grade INTEGER NOT NULL,
+    FOREIGN KEY(uid) REFERENCES user(uid),
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
+
+CREATE INDEX review_log_uid_qid ON review_log(uid, qid);
+
+CREATE TABLE review_state (
+    uid INTEGER NOT NULL,
+    qid INTEGER NOT NULL,
+    due_at BIGINT NOT NULL,
+    interval_days INTEGER NOT NULL,
+    ease DOUBLE NOT NULL,
+    repetitions INTEGER NOT NULL,
+    PRIMARY KEY(uid, qid),
+    FOREIGN KEY(uid) REFERENCES user(uid),
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
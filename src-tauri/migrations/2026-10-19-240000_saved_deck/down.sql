@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS deck_question;
+DROP TABLE IF EXISTS deck;
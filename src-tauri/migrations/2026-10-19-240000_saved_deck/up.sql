@@ -0,0 +1,15 @@
+CREATE TABLE deck (
+    did INTEGER PRIMARY KEY NOT NULL,
+    uid INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    FOREIGN KEY(uid) REFERENCES user(uid),
+    UNIQUE(uid, name)
+);
+
+CREATE TABLE deck_question (
+    did INTEGER NOT NULL,
+    qid INTEGER NOT NULL,
+    PRIMARY KEY(did, qid),
+    FOREIGN KEY(did) REFERENCES deck(did),
+    FOREIGN KEY(qid) REFERENCES question(qid)
+);
@@ -4,8 +4,11 @@ use std::collections::{HashMap, HashSet};
 use std::env;
 use diesel::sqlite::SqliteConnection;
 use diesel::prelude::*;
-use crate::network::structs::{ResponseQuestion, QuestionList};
+use crate::sources::SourceQuestion;
+use crate::db::metadata::{select_list_metadata, store_list_metadata, ListMetadata};
 use crate::db::models::*;
+use crate::prompts::render::render_query_results;
+use crate::prompts::translation::localize_query_results;
 
 
 pub const LEETCODE_SOURCE_ID: i32 = 1;
@@ -21,25 +24,35 @@ pub fn db_connect() -> SqliteConnection {
         .expect(&format!("Error connecting to {}", db_url))
 }
 
+#[cfg(test)]
+pub fn db_connect_in_memory() -> SqliteConnection {
+    // fresh migrated db for unit tests that should not touch DATABASE_URL
+    let conn = SqliteConnection::establish(":memory:")
+        .expect("Error connecting to in-memory db");
+    crate::embedded_migrations::run(&conn).expect("migration error");
+    conn
+}
+
 
-pub async fn insert_all_lc_q_base_info(
+pub async fn insert_all_q_base_info(
     conn: &SqliteConnection, 
-    response_questions: QuestionList
+    src_id: i32,
+    source_questions: Vec<SourceQuestion>
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // fetch all base leetcode question info over network and put it in db
+    // put base info for questions listed by a question source in db
 
     let mut db_new_questions: Vec<NewQuestion> = vec![];
-    for response_question in &response_questions.questions {
-        db_new_questions.push(
-            db_format_question(response_question, LEETCODE_SOURCE_ID)?);
+    for source_question in &source_questions {
+        db_new_questions.push(db_format_question(source_question, src_id));
     }
     insert_new_questions(db_new_questions, conn).await?;
+    store_list_metadata(conn, src_id, &source_questions)?;
 
     let mut db_new_question_topics: Vec<NewQuestionTopic> = vec![];
     let mut all_topics = select_all_topics_into_map(conn).await?;
-    for rq in response_questions.questions {
+    for sq in source_questions {
         let new_topics: Vec<NewQuestionTopic> = db_format_question_topics(
-            rq, Some(LEETCODE_SOURCE_ID), None, conn, &mut all_topics).await?;
+            sq, Some(src_id), None, conn, &mut all_topics).await?;
         for topic in new_topics {
             db_new_question_topics.push(topic);
         }
@@ -51,28 +64,27 @@ pub async fn insert_all_lc_q_base_info(
 
 
 fn db_format_question(
-    response_question: &ResponseQuestion,
+    source_question: &SourceQuestion,
     src_id: i32
-) -> Result<NewQuestion, Box<dyn std::error::Error>> {
+) -> NewQuestion {
     // reshape struct for parsing response into struct for db insert
-    // note that prompt is init to empty string. LC graphql api
-    // only allows querying for prompt on individual questions
-
-    let new_question = NewQuestion {
-        title: response_question.title.clone(),
-        title_slug: response_question.titleSlug.clone(),
-        difficulty: response_question.difficulty.to_uppercase(),
-        prompt: "".to_string(),
+    // note that prompt is init to empty string unless the source listed it.
+    // sources like LC graphql api only allow querying for prompt on
+    // individual questions
+
+    NewQuestion {
+        title: source_question.title.clone(),
+        title_slug: source_question.title_slug.clone(),
+        difficulty: source_question.difficulty.clone(),
+        prompt: source_question.prompt.clone().unwrap_or_default(),
         source: src_id,
-        source_qid: response_question.questionId.parse::<i32>()?,
-    };
-
-    Ok(new_question)
+        source_qid: source_question.source_qid,
+    }
 }
 
 
 async fn db_format_question_topics(
-    response_question: ResponseQuestion,
+    source_question: SourceQuestion,
     src_id: Option<i32>,
     db_qid: Option<i32>,
     conn: &SqliteConnection,
@@ -84,9 +96,9 @@ async fn db_format_question_topics(
     }
 
     let mut question_topics: Vec<NewQuestionTopic> = vec![];
-    let src_qid = response_question.questionId.parse::<i32>()?;
+    let src_qid = source_question.source_qid;
     let need_determine_db_qid = db_qid.is_none();
-    for topic in response_question.topicTags {
+    for topic_name in source_question.topics {
         let qid: i32;
         if need_determine_db_qid {
             qid = select_qid_from_src_info(src_id.unwrap(), src_qid, conn).await?;
@@ -94,12 +106,12 @@ async fn db_format_question_topics(
         else { qid = db_qid.unwrap(); }
 
         let tid: i32;
-        if all_topics.contains_key(&topic.name) {
-            tid = *all_topics.get(&topic.name).unwrap();
+        if all_topics.contains_key(&topic_name) {
+            tid = *all_topics.get(&topic_name).unwrap();
         }
         else {
-            tid = insert_new_topic(&topic.name, conn).await?.tid;
-            all_topics.insert(topic.name, tid);
+            tid = insert_new_topic(&topic_name, conn).await?.tid;
+            all_topics.insert(topic_name, tid);
         }
 
         question_topics.push(NewQuestionTopic { qid, tid })
@@ -159,6 +171,34 @@ async fn insert_new_topic(
     Ok(inserted)
 }
 
+pub fn insert_topic(conn: &SqliteConnection, new_name: &str) ->
+Result<i32, Box<dyn std::error::Error>> {
+    // insert a topic and return its tid, for callers outside the lc preload
+    use crate::db::schema::topic::dsl::*;
+
+    diesel::insert_into(topic)
+        .values(&NewTopic { name: new_name.to_string() })
+        .execute(conn)?;
+    let inserted: i32 = topic.order_by(tid.desc()).select(tid).first(conn)?;
+
+    Ok(inserted)
+}
+
+pub fn select_or_insert_topic(conn: &SqliteConnection, topic_name: &str) ->
+Result<i32, Box<dyn std::error::Error>> {
+    use crate::db::schema::topic::dsl::*;
+
+    let existing: Option<i32> = topic
+        .filter(name.eq(topic_name))
+        .select(tid)
+        .first::<i32>(conn)
+        .optional()?;
+    match existing {
+        Some(existing_tid) => Ok(existing_tid),
+        None => insert_topic(conn, topic_name),
+    }
+}
+
 async fn insert_new_question_topics(
     ts: Vec<NewQuestionTopic>, 
     conn: &SqliteConnection
@@ -200,26 +240,79 @@ async fn insert_new_questions(
     Ok(inserted)
 }
 
-pub async fn count_lc_questions_in_db(conn: &SqliteConnection) -> 
+pub fn select_solution_notes_by_qid(conn: &SqliteConnection, the_uid: i32) ->
+Result<HashMap<i32, Vec<String>>, Box<dyn std::error::Error>> {
+    // a user's solution notes grouped by question, oldest first
+    use crate::db::schema::solution::dsl::*;
+
+    let solution_rows: Vec<Solution> = solution
+        .filter(uid.eq(the_uid))
+        .order_by(sid.asc())
+        .load(conn)?;
+    let mut notes_by_qid: HashMap<i32, Vec<String>> = HashMap::new();
+    for s in solution_rows {
+        notes_by_qid.entry(s.qid).or_default().push(s.notes);
+    }
+
+    Ok(notes_by_qid)
+}
+
+pub fn select_topic_names(conn: &SqliteConnection) ->
+Result<HashMap<i32, String>, Box<dyn std::error::Error>> {
+    use crate::db::schema::topic::dsl::*;
+
+    let topic_rows: Vec<Topic> = topic.load(conn)?;
+
+    Ok(topic_rows.into_iter().map(|t| (t.tid, t.name)).collect())
+}
+
+pub fn select_app_setting(conn: &SqliteConnection, setting_name: &str) ->
+Result<Option<String>, Box<dyn std::error::Error>> {
+    use crate::db::schema::app_setting::dsl::*;
+
+    Ok(app_setting
+        .filter(name.eq(setting_name))
+        .select(value)
+        .first::<String>(conn)
+        .optional()?)
+}
+
+pub fn set_app_setting(conn: &SqliteConnection, setting_name: &str, setting_value: &str) ->
+Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema::app_setting::dsl::*;
+
+    diesel::replace_into(app_setting)
+        .values((name.eq(setting_name), value.eq(setting_value)))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub async fn count_source_questions_in_db(conn: &SqliteConnection, src_id: i32) -> 
 Result<i64, Box<dyn std::error::Error>>  {
     use crate::db::schema::question::dsl::*;
     let count = question
-        .filter(source.eq(LEETCODE_SOURCE_ID))
+        .filter(source.eq(src_id))
         .count()
         .first::<i64>(conn)?;
     
     Ok(count)
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Default)]
 pub struct QuestionOptions {
-    user: i32,
-    diff: Option<Vec<String>>,
-    topics: Option<Vec<i32>>,//X
-    solved: Option<Vec<bool>>,//X
-    source_ids: Option<Vec<i32>>,
-    starred: Option<Vec<bool>>,//X
-    range: Option<Vec<(i32, i32)>>,
+    pub user: i32,
+    pub diff: Option<Vec<String>>,
+    pub topics: Option<Vec<i32>>,//X
+    pub solved: Option<Vec<bool>>,//X
+    pub source_ids: Option<Vec<i32>>,
+    pub starred: Option<Vec<bool>>,//X
+    pub range: Option<Vec<(i32, i32)>>,
+    pub hide_paid_only: Option<bool>,
+    // percentage, questions with no known acceptance rate are left out
+    pub acceptance_below: Option<f64>,
+    // archived questions (dropped by their source) are left out unless set
+    pub include_archived: Option<bool>,
 }
 
 type QuestionStarQTopicSolutionJoin = (
@@ -231,16 +324,23 @@ type QuestionStarQTopicSolutionJoin = (
 
 #[derive(Serialize, Debug)]
 pub struct QuestionQueryResult {
-    qid: i32,
-    starred: bool,
-    solved: bool,
-    topics: Vec<i32>,
-    title: String,
-    title_slug: Option<String>,
-    prompt: Option<String>,
-    difficulty: Option<String>,
-    source: Option<i32>,
-    source_qid: Option<i32>,
+    pub qid: i32,
+    pub starred: bool,
+    pub solved: bool,
+    pub topics: Vec<i32>,
+    pub title: String,
+    pub title_slug: Option<String>,
+    pub prompt: Option<String>,
+    // prompt is sanitized html by the time it leaves a command, see prompts::render
+    pub prompt_markdown: Option<String>,
+    pub prompt_text: Option<String>,
+    pub difficulty: Option<String>,
+    pub source: Option<i32>,
+    pub source_qid: Option<i32>,
+    pub archived: bool,
+    pub paid_only: bool,
+    pub acceptance_rate: Option<f64>,
+    pub rating: Option<i32>,
 }
 
 pub struct FilterSets {
@@ -260,15 +360,24 @@ pub async fn query_questions(options: QuestionOptions) -> Result<HashMap<i32, Qu
         return Err("invalid range field".into());
     }
 
-    let join_rows = join_question_soln_topic_star(options.user.clone())?;
+    let conn = db_connect();
+    let the_user = options.user;
+    let join_rows = join_question_soln_topic_star(&conn, options.user)?;
+    let (hide_paid_only, acceptance_below) = (options.hide_paid_only, options.acceptance_below);
     let filtered_questions = filter_question_soln_topic_join(options, join_rows);
+    let metadata = select_list_metadata(&conn)?;
+
+    let with_metadata = apply_question_metadata(filtered_questions, &metadata, hide_paid_only, acceptance_below);
+
+    let localized = localize_query_results(&conn, the_user, with_metadata)?;
 
-    Ok(filtered_questions)
+    render_query_results(&conn, localized)
 }
 
 fn filter_question_soln_topic_join(options: QuestionOptions, join_rows: Vec<QuestionStarQTopicSolutionJoin>) -> HashMap<i32, QuestionQueryResult> {
     let QuestionOptions { 
-        user: _, diff, topics, solved, source_ids, starred, range 
+        user: _, diff, topics, solved, source_ids, starred, range,
+        hide_paid_only: _, acceptance_below: _, include_archived
     } = options;
 
     let mut filter_sets = FilterSets {
@@ -323,8 +432,10 @@ fn filter_question_soln_topic_join(options: QuestionOptions, join_rows: Vec<Ques
             star_.is_none() && !filter_sets.starred.as_ref().unwrap().contains(&false)) || 
             (star_.is_some() && !filter_sets.starred.as_ref().unwrap().contains(&true));
 
+        let hidden_archived = question_.archived && !include_archived.unwrap_or(false);
+
         if out_of_range || bad_difficulty || bad_topic || 
-            bad_solve_status || bad_source || bad_starred_status {
+            bad_solve_status || bad_source || bad_starred_status || hidden_archived {
             continue;
         }
         
@@ -337,9 +448,15 @@ fn filter_question_soln_topic_join(options: QuestionOptions, join_rows: Vec<Ques
                 title: question_.title.clone(),
                 title_slug: question_.title_slug.clone(),
                 prompt: question_.prompt.clone(),
+                prompt_markdown: None,
+                prompt_text: None,
                 difficulty: question_.difficulty.clone(),
                 source: question_.source,
-                source_qid: question_.source_qid
+                source_qid: question_.source_qid,
+                archived: question_.archived,
+                paid_only: false,
+                acceptance_rate: None,
+                rating: None,
             };
             filtered_map.insert(question_.qid, new_q);
         }
@@ -354,6 +471,31 @@ fn filter_question_soln_topic_join(options: QuestionOptions, join_rows: Vec<Ques
     filtered_map
 }
 
+fn apply_question_metadata(
+    mut questions: HashMap<i32, QuestionQueryResult>,
+    metadata: &ListMetadata,
+    hide_paid_only: Option<bool>,
+    acceptance_below: Option<f64>
+) -> HashMap<i32, QuestionQueryResult> {
+    // metadata lives in its own table, filled in and filtered on after the
+    // join. questions without a metadata row are free with an unknown rate
+    for (the_qid, q) in questions.iter_mut() {
+        if let Some(listed) = metadata.get(the_qid) {
+            q.paid_only = listed.paid_only;
+            q.acceptance_rate = listed.acceptance_rate;
+            q.rating = listed.rating;
+        }
+    }
+    questions.retain(|_, q| {
+        let bad_paid_only = hide_paid_only == Some(true) && q.paid_only;
+        let bad_acceptance = acceptance_below.is_some() && (
+            q.acceptance_rate.is_none() || q.acceptance_rate.unwrap() >= acceptance_below.unwrap());
+        !bad_paid_only && !bad_acceptance
+    });
+
+    questions
+}
+
 fn empty_query_option(options: &QuestionOptions) -> bool {
     (options.diff.is_some() && options.diff.as_ref().unwrap().len() == 0) ||
     (options.topics.is_some() && options.topics.as_ref().unwrap().len() == 0) ||
@@ -363,10 +505,9 @@ fn empty_query_option(options: &QuestionOptions) -> bool {
     (options.range.is_some() && options.range.as_ref().unwrap().len() == 0)
 }
 
-fn join_question_soln_topic_star(uid: i32) -> Result<Vec<QuestionStarQTopicSolutionJoin>, Box<dyn std::error::Error>> {
+fn join_question_soln_topic_star(conn: &SqliteConnection, uid: i32) -> Result<Vec<QuestionStarQTopicSolutionJoin>, Box<dyn std::error::Error>> {
     use crate::db::schema;
     use schema::question::dsl::*;
-    let conn = db_connect();
     // get all potentially relevant rows containing quesiton info for a user.
     // simple and not too expensive. there will very rarely be more than
     // ~50000 rows here, and we are reading straight from disk with sqlite.
@@ -391,7 +532,7 @@ fn join_question_soln_topic_star(uid: i32) -> Result<Vec<QuestionStarQTopicSolut
             // ignore solution rows w/ uid != user during join
             schema::solution::uid.eq(uid)) 
         ))
-        .load(&conn)?;
+        .load(conn)?;
 
     Ok(join_rows)
 }
@@ -420,6 +561,7 @@ fn invalid_range(mut ranges: Vec<(i32, i32)>) -> bool {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::db::metadata::ListedMetadata;
     
     #[test]
     fn test_empty_query_option_none_fields() {
@@ -431,6 +573,7 @@ mod test {
             source_ids: Some(vec![1]),
             starred: Some(vec![true]),
             range: Some(vec![(1, 2)]),
+            ..QuestionOptions::default()
         };
         assert_eq!(empty_query_option(&test_options), false);
         test_options.diff = Some(vec!["EASY".to_string()]);
@@ -460,6 +603,7 @@ mod test {
             source_ids: None,
             starred: None,
             range: None,
+            ..QuestionOptions::default()
         };
         assert_eq!(empty_query_option(&test_options), true);
         test_options.diff = None;
@@ -490,6 +634,7 @@ mod test {
             user: 1,
             diff: None, topics: None, solved: None, 
             source_ids: None, starred: None, range: None,
+            ..QuestionOptions::default()
         };
 
         let mut join_rows: Vec<QuestionStarQTopicSolutionJoin> = vec![];
@@ -500,7 +645,8 @@ mod test {
                 title: "test_question".to_string(), 
 
                 title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                source: None, source_qid: None 
+                source: None, source_qid: None,
+                archived: false,
             };
             join_rows.push((test_q, None, None, None));
 
@@ -510,7 +656,10 @@ mod test {
 
                 topics: vec![], starred: false, solved: false,
                 title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                source: None, source_qid: None
+                source: None, source_qid: None,
+                archived: false,
+                paid_only: false, acceptance_rate: None, rating: None,
+                prompt_markdown: None, prompt_text: None,
             };
             expected_result_map.insert(test_qid, test_query_result);
         }
@@ -521,6 +670,33 @@ mod test {
         
     }
 
+    #[test]
+    fn test_filter_question_soln_topic_join_hides_archived() {
+        let mut test_options = QuestionOptions {
+            user: 1,
+            diff: None, topics: None, solved: None, 
+            source_ids: None, starred: None, range: None,
+            ..QuestionOptions::default()
+        };
+        let join_rows = || -> Vec<QuestionStarQTopicSolutionJoin> {
+            (1..TEST_QUESTIONS + 1).map(|test_qid| (Question {
+                qid: test_qid, 
+                title: "test_question".to_string(), 
+                title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
+                source: Some(LEETCODE_SOURCE_ID), source_qid: Some(test_qid),
+                archived: test_qid % 2 == 0,
+            }, None, None, None)).collect()
+        };
+
+        let filter_result = filter_question_soln_topic_join(test_options.clone(), join_rows());
+        assert_eq!(filter_result.len(), (TEST_QUESTIONS / 2) as usize);
+        assert!(filter_result.values().all(|q| !q.archived));
+
+        test_options.include_archived = Some(true);
+        let filter_result = filter_question_soln_topic_join(test_options, join_rows());
+        assert_eq!(filter_result.len(), TEST_QUESTIONS as usize);
+    }
+
     #[test]
     fn test_invalid_range_1() {
         let valid_range = vec![(2, 3), (1, 1), (100, 10000)];
@@ -547,6 +723,7 @@ mod test {
             diff: None, topics: None, solved: None, 
             source_ids: None, starred: None, 
             range: Some(test_range.clone()),
+            ..QuestionOptions::default()
         };
 
         let mut join_rows: Vec<QuestionStarQTopicSolutionJoin> = vec![];
@@ -557,7 +734,8 @@ mod test {
                 title: "test_question".to_string(), 
 
                 title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                source: None, source_qid: None 
+                source: None, source_qid: None,
+                archived: false,
             };
             join_rows.push((test_q, None, None, None));
 
@@ -569,7 +747,10 @@ mod test {
 
                         topics: vec![], starred: false, solved: false,
                         title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                        source: None, source_qid: None
+                        source: None, source_qid: None,
+                        archived: false,
+                        paid_only: false, acceptance_rate: None, rating: None,
+                prompt_markdown: None, prompt_text: None,
                     };
                     expected_result_map.insert(test_qid, test_query_result);
                     break;
@@ -589,6 +770,7 @@ mod test {
             source_ids: Some(vec![SOURCELESS_QUESTION_SOURCE_ID]),
 
             diff: None, topics: None, solved: None, starred: None, range: None,
+            ..QuestionOptions::default()
         };
 
         let mut join_rows: Vec<QuestionStarQTopicSolutionJoin> = vec![];
@@ -602,6 +784,7 @@ mod test {
 
                 title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
                 source_qid: None,
+                archived: false,
             };
             join_rows.push((test_q, None, None, None));
 
@@ -613,7 +796,10 @@ mod test {
     
                     topics: vec![], starred: false, solved: false,
                     title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                    source_qid: None, 
+                    source_qid: None,
+                    archived: false,
+                    paid_only: false, acceptance_rate: None, rating: None,
+                prompt_markdown: None, prompt_text: None,
                 };
                 expected_result_map.insert(test_qid, test_query_result);
             }
@@ -631,6 +817,7 @@ mod test {
             topics: Some(vec![TOPICLESS_QUESTION_TOPIC_ID]),
 
             diff: None, source_ids: None, solved: None, starred: None, range: None,
+            ..QuestionOptions::default()
         };
 
         let mut join_rows: Vec<QuestionStarQTopicSolutionJoin> = vec![];
@@ -642,7 +829,8 @@ mod test {
                 title: "test_question".to_string(), 
 
                 title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                source_qid: None, source: None, 
+                source_qid: None, source: None,
+                archived: false,
             };
             let test_qt = QuestionTopic {
                 relid: test_qid,
@@ -658,7 +846,10 @@ mod test {
     
                     topics: vec![], starred: false, solved: false,
                     title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                    source_qid: None, 
+                    source_qid: None,
+                    archived: false,
+                    paid_only: false, acceptance_rate: None, rating: None,
+                prompt_markdown: None, prompt_text: None,
                 };
 
                 expected_result_map.insert(test_qid, test_query_result);
@@ -683,6 +874,7 @@ mod test {
             diff: Some(FILTERED_DIFFS.map(|s| s.to_string()).to_vec()), 
 
             source_ids: None, solved: None, starred: None, range: None, topics: None,
+            ..QuestionOptions::default()
         };
 
         let mut join_rows: Vec<QuestionStarQTopicSolutionJoin> = vec![];
@@ -697,7 +889,8 @@ mod test {
                 difficulty: Some(DIFFS[diff_idx].to_string()),
 
                 title_slug: None, prompt: None,  
-                source_qid: None, source: None, 
+                source_qid: None, source: None,
+                archived: false,
             };
 
             join_rows.push((test_q, None, None, None));
@@ -711,6 +904,9 @@ mod test {
                     topics: vec![], starred: false, solved: false,
                     title_slug: None, prompt: None, 
                     source_qid: None, source: None,
+                    archived: false,
+                    paid_only: false, acceptance_rate: None, rating: None,
+                prompt_markdown: None, prompt_text: None,
                 };
                 expected_result_map.insert(test_qid, test_query_result);
             }
@@ -734,6 +930,7 @@ mod test {
             topics: Some(Vec::from(FILTERED_TOPICS)),
 
             diff: None, source_ids: None, solved: None, starred: None, range: None,
+            ..QuestionOptions::default()
         };
 
         let mut join_rows: Vec<QuestionStarQTopicSolutionJoin> = vec![];
@@ -746,7 +943,8 @@ mod test {
                 title: "test_question".to_string(), 
 
                 title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                source_qid: None, source: None, 
+                source_qid: None, source: None,
+                archived: false,
             };
             let test_qt = QuestionTopic {
                 relid: test_qid,
@@ -763,7 +961,10 @@ mod test {
     
                     topics: vec![], starred: false, solved: false,
                     title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                    source_qid: None, 
+                    source_qid: None,
+                    archived: false,
+                    paid_only: false, acceptance_rate: None, rating: None,
+                prompt_markdown: None, prompt_text: None,
                 };
 
                 expected_result_map.insert(test_qid, test_query_result);
@@ -787,6 +988,7 @@ mod test {
 
             diff: None, topics: None,  
             source_ids: None, starred: None, range: None,
+            ..QuestionOptions::default()
         };
 
         let mut join_rows: Vec<QuestionStarQTopicSolutionJoin> = vec![];
@@ -797,7 +999,8 @@ mod test {
                 title: "test_question".to_string(), 
 
                 title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                source: None, source_qid: None 
+                source: None, source_qid: None,
+                archived: false,
             };
             
             if test_qid % 2 == 1 {
@@ -814,7 +1017,10 @@ mod test {
 
                     topics: vec![], starred: false,
                     title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                    source: None, source_qid: None
+                    source: None, source_qid: None,
+                    archived: false,
+                    paid_only: false, acceptance_rate: None, rating: None,
+                prompt_markdown: None, prompt_text: None,
                 };
                 expected_result_map.insert(test_qid, test_query_result);
                 join_rows.push((test_q, None, None, Some(test_solution)));
@@ -837,6 +1043,7 @@ mod test {
             source_ids: Some(Vec::from(FILTERED_SOURCES)),
 
             diff: None, topics: None, solved: None, starred: None, range: None,
+            ..QuestionOptions::default()
         };
 
         let mut join_rows: Vec<QuestionStarQTopicSolutionJoin> = vec![];
@@ -851,6 +1058,7 @@ mod test {
 
                 title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
                 source_qid: None,
+                archived: false,
             };
             join_rows.push((test_q, None, None, None));
 
@@ -863,7 +1071,10 @@ mod test {
     
                     topics: vec![], starred: false, solved: false,
                     title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                    source_qid: None, 
+                    source_qid: None,
+                    archived: false,
+                    paid_only: false, acceptance_rate: None, rating: None,
+                prompt_markdown: None, prompt_text: None,
                 };
                 expected_result_map.insert(test_qid, test_query_result);
             }
@@ -882,6 +1093,7 @@ mod test {
 
             diff: None, topics: None,  
             source_ids: None, solved: None, range: None,
+            ..QuestionOptions::default()
         };
 
         let mut join_rows: Vec<QuestionStarQTopicSolutionJoin> = vec![];
@@ -892,7 +1104,8 @@ mod test {
                 title: "test_question".to_string(), 
 
                 title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                source: None, source_qid: None 
+                source: None, source_qid: None,
+                archived: false,
             };
             
             if test_qid % 2 == 0 {
@@ -908,7 +1121,10 @@ mod test {
 
                     topics: vec![], solved: false,
                     title_slug: None, prompt: None, difficulty: Some(EASY.to_string()), 
-                    source: None, source_qid: None
+                    source: None, source_qid: None,
+                    archived: false,
+                    paid_only: false, acceptance_rate: None, rating: None,
+                prompt_markdown: None, prompt_text: None,
                 };
                 expected_result_map.insert(test_qid, test_query_result);
                 join_rows.push((test_q, Some(test_star), None, None));
@@ -923,6 +1139,43 @@ mod test {
         assert!(filter_result_hashmaps_match(filter_result, expected_result_map));
     }
 
+    #[test]
+    fn test_apply_question_metadata() {
+        let mut questions: HashMap<i32, QuestionQueryResult> = HashMap::new();
+        for test_qid in 1..5 {
+            questions.insert(test_qid, QuestionQueryResult {
+                qid: test_qid,
+                title: "test_question".to_string(),
+
+                topics: vec![], starred: false, solved: false,
+                title_slug: None, prompt: None, difficulty: Some(EASY.to_string()),
+                source: None, source_qid: None,
+                archived: false,
+                paid_only: false, acceptance_rate: None, rating: None,
+                prompt_markdown: None, prompt_text: None,
+            });
+        }
+        // question 4 has no metadata row
+        let listed = |paid_only: bool, rate: f64| ListedMetadata {
+            paid_only, acceptance_rate: Some(rate), rating: None
+        };
+        let metadata: ListMetadata = HashMap::from_iter(vec![
+            (1, listed(false, 35.0)),
+            (2, listed(true, 20.0)),
+            (3, listed(false, 55.5)),
+        ]);
+
+        let all = apply_question_metadata(questions, &metadata, None, None);
+        assert_eq!(all.len(), 4);
+        assert!(all[&2].paid_only);
+        assert_eq!(all[&3].acceptance_rate, Some(55.5));
+
+        let free = apply_question_metadata(all, &metadata, Some(true), None);
+        assert_eq!(free.len(), 3);
+        let hard_going = apply_question_metadata(free, &metadata, Some(true), Some(40.0));
+        assert_eq!(hard_going.keys().copied().collect::<Vec<i32>>(), vec![1]);
+    }
+
     fn filter_result_hashmaps_match(map1: HashMap<i32, QuestionQueryResult>, map2: HashMap<i32, QuestionQueryResult>) -> bool {
         map1.len() == map2.len() && map1.keys().all(|k| map2.contains_key(k))
     }
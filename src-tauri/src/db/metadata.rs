@@ -0,0 +1,316 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::models::{CodeSnippet, NewCodeSnippet, QuestionMetadata};
+use crate::sources::{SourceDetails, SourceQuestion};
+
+// extra per question info from sources that have it. paid only, the
+// acceptance rate, rating and the number the source shows (leetcode's
+// frontend id, which drifts from its questionId) come with the question
+// list, so every listed
+// question gets a row on sync. the rest (likes, hints, snippets, examples, similar
+// questions) is only fetched along with the prompt, details_fetched marks
+// rows where that has happened. hints and similar_questions are json arrays
+
+// qid -> what the question list said about it
+pub type ListMetadata = HashMap<i32, ListedMetadata>;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListedMetadata {
+    pub paid_only: bool,
+    pub acceptance_rate: Option<f64>,
+    pub rating: Option<i32>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct QuestionMetadataDetail {
+    pub paid_only: bool,
+    pub acceptance_rate: Option<f64>,
+    pub rating: Option<i32>,
+    pub likes: Option<i32>,
+    pub dislikes: Option<i32>,
+    pub hints: Vec<String>,
+    pub example_testcases: Option<String>,
+    // title slugs of similar questions from the same source
+    pub similar_questions: Vec<String>,
+    pub code_snippets: Vec<QuestionCodeSnippet>,
+    pub details_fetched: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct QuestionCodeSnippet {
+    pub lang: String,
+    pub lang_slug: String,
+    pub code: String,
+}
+
+
+pub fn store_list_metadata(
+    conn: &SqliteConnection,
+    src_id: i32,
+    source_questions: &[SourceQuestion]
+) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema;
+    use schema::question_metadata::dsl::*;
+
+    let src_qids: HashMap<i32, i32> = schema::question::table
+        .filter(schema::question::source.eq(src_id))
+        .filter(schema::question::source_qid.is_not_null())
+        .select((schema::question::source_qid, schema::question::qid))
+        .load::<(Option<i32>, i32)>(conn)?
+        .into_iter()
+        .filter_map(|(src_qid, the_qid)| Some((src_qid?, the_qid)))
+        .collect();
+
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        for sq in source_questions {
+            let the_qid = match src_qids.get(&sq.source_qid) {
+                Some(the_qid) => *the_qid,
+                None => continue,
+            };
+            ensure_metadata_row(conn, the_qid)?;
+            if let Some(paid) = sq.paid_only {
+                diesel::update(question_metadata.filter(qid.eq(the_qid)))
+                    .set(paid_only.eq(paid))
+                    .execute(conn)?;
+            }
+            if sq.acceptance_rate.is_some() {
+                diesel::update(question_metadata.filter(qid.eq(the_qid)))
+                    .set(acceptance_rate.eq(sq.acceptance_rate))
+                    .execute(conn)?;
+            }
+            if sq.rating.is_some() {
+                diesel::update(question_metadata.filter(qid.eq(the_qid)))
+                    .set(rating.eq(sq.rating))
+                    .execute(conn)?;
+            }
+            if sq.frontend_id.is_some() {
+                diesel::update(question_metadata.filter(qid.eq(the_qid)))
+                    .set(frontend_id.eq(&sq.frontend_id))
+                    .execute(conn)?;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+pub fn store_question_details(conn: &SqliteConnection, the_qid: i32, details: &SourceDetails) ->
+Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema;
+    use schema::question_metadata::dsl::*;
+
+    let hints_json = serde_json::to_string(&details.hints)?;
+    let similar_json = serde_json::to_string(&details.similar_questions)?;
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        ensure_metadata_row(conn, the_qid)?;
+        diesel::update(question_metadata.filter(qid.eq(the_qid)))
+            .set((
+                likes.eq(details.likes),
+                dislikes.eq(details.dislikes),
+                hints.eq(&hints_json),
+                example_testcases.eq(&details.example_testcases),
+                similar_questions.eq(&similar_json),
+                details_fetched.eq(true),
+            ))
+            .execute(conn)?;
+        if let Some(paid) = details.paid_only {
+            diesel::update(question_metadata.filter(qid.eq(the_qid)))
+                .set(paid_only.eq(paid))
+                .execute(conn)?;
+        }
+        if details.acceptance_rate.is_some() {
+            diesel::update(question_metadata.filter(qid.eq(the_qid)))
+                .set(acceptance_rate.eq(details.acceptance_rate))
+                .execute(conn)?;
+        }
+
+        diesel::delete(schema::code_snippet::table.filter(schema::code_snippet::qid.eq(the_qid)))
+            .execute(conn)?;
+        let snippets: Vec<NewCodeSnippet> = details.code_snippets.iter()
+            .map(|s| NewCodeSnippet {
+                qid: the_qid,
+                lang: s.lang.clone(),
+                lang_slug: s.lang_slug.clone(),
+                code: s.code.clone(),
+            })
+            .collect();
+        diesel::insert_into(schema::code_snippet::table)
+            .values(&snippets)
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+fn ensure_metadata_row(conn: &SqliteConnection, the_qid: i32) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema::question_metadata::dsl::*;
+
+    diesel::insert_or_ignore_into(question_metadata)
+        .values(qid.eq(the_qid))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+
+pub fn select_question_metadata(conn: &SqliteConnection, the_qid: i32) ->
+Result<Option<QuestionMetadataDetail>, Box<dyn std::error::Error>> {
+    use crate::db::schema;
+
+    let row: Option<QuestionMetadata> = schema::question_metadata::table
+        .filter(schema::question_metadata::qid.eq(the_qid))
+        .first(conn)
+        .optional()?;
+    let row = match row {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let snippets: Vec<CodeSnippet> = schema::code_snippet::table
+        .filter(schema::code_snippet::qid.eq(the_qid))
+        .order_by(schema::code_snippet::csid.asc())
+        .load(conn)?;
+
+    Ok(Some(QuestionMetadataDetail {
+        paid_only: row.paid_only,
+        acceptance_rate: row.acceptance_rate,
+        rating: row.rating,
+        likes: row.likes,
+        dislikes: row.dislikes,
+        hints: serde_json::from_str(&row.hints).unwrap_or_default(),
+        example_testcases: row.example_testcases,
+        similar_questions: serde_json::from_str(&row.similar_questions).unwrap_or_default(),
+        code_snippets: snippets.into_iter()
+            .map(|s| QuestionCodeSnippet { lang: s.lang, lang_slug: s.lang_slug, code: s.code })
+            .collect(),
+        details_fetched: row.details_fetched,
+    }))
+}
+
+pub fn select_list_metadata(conn: &SqliteConnection) ->
+Result<ListMetadata, Box<dyn std::error::Error>> {
+    // list metadata for every question that has a row
+    use crate::db::schema::question_metadata::dsl::*;
+
+    let rows: Vec<(i32, bool, Option<f64>, Option<i32>)> = question_metadata
+        .select((qid, paid_only, acceptance_rate, rating))
+        .load(conn)?;
+
+    Ok(rows.into_iter()
+        .map(|(the_qid, paid, rate, the_rating)| (the_qid, ListedMetadata {
+            paid_only: paid,
+            acceptance_rate: rate,
+            rating: the_rating,
+        }))
+        .collect())
+}
+
+pub fn select_frontend_ids(conn: &SqliteConnection) ->
+Result<HashMap<i32, String>, Box<dyn std::error::Error>> {
+    // qid -> number the source shows, for questions whose source lists one
+    use crate::db::schema::question_metadata::dsl::*;
+
+    let rows: Vec<(i32, Option<String>)> = question_metadata
+        .filter(frontend_id.is_not_null())
+        .select((qid, frontend_id))
+        .load(conn)?;
+
+    Ok(rows.into_iter().filter_map(|(the_qid, number)| Some((the_qid, number?))).collect())
+}
+
+pub fn details_fetched_for_slug(conn: &SqliteConnection, src_id: i32, slug: &str) ->
+Result<bool, Box<dyn std::error::Error>> {
+    use crate::db::schema;
+
+    let fetched: Vec<bool> = schema::question::table
+        .inner_join(schema::question_metadata::table)
+        .filter(schema::question::source.eq(src_id))
+        .filter(schema::question::title_slug.eq(slug))
+        .select(schema::question_metadata::details_fetched)
+        .load(conn)?;
+
+    Ok(fetched.into_iter().any(|f| f))
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::{db_connect_in_memory, LEETCODE_SOURCE_ID};
+    use crate::db::test_util::insert_lc_question;
+    use crate::sources::SourceCodeSnippet;
+
+    fn listed(src_qid: i32, paid_only: Option<bool>, acceptance_rate: Option<f64>) -> SourceQuestion {
+        SourceQuestion {
+            source_qid: src_qid,
+            title: format!("lc {}", src_qid),
+            title_slug: format!("lc-{}", src_qid),
+            difficulty: "EASY".to_string(),
+            topics: vec![],
+            paid_only,
+            acceptance_rate,
+            rating: None,
+            frontend_id: None,
+            prompt: None,
+        }
+    }
+
+    #[test]
+    fn test_store_list_metadata() {
+        let conn = db_connect_in_memory();
+        let q1 = insert_lc_question(&conn, "lc-1", 1, "");
+        let q2 = insert_lc_question(&conn, "lc-2", 2, "");
+
+        store_list_metadata(&conn, LEETCODE_SOURCE_ID, &[
+            listed(1, Some(false), Some(49.5)),
+            SourceQuestion { rating: Some(1800), ..listed(2, Some(true), None) },
+            listed(3, Some(true), Some(10.0)), // not in db, ignored
+        ]).unwrap();
+        let stored = select_list_metadata(&conn).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[&q1], ListedMetadata { paid_only: false, acceptance_rate: Some(49.5), rating: None });
+        assert_eq!(stored[&q2], ListedMetadata { paid_only: true, acceptance_rate: None, rating: Some(1800) });
+
+        // a later list without stats doesn't wipe what is known
+        store_list_metadata(&conn, LEETCODE_SOURCE_ID, &[listed(1, None, None)]).unwrap();
+        assert_eq!(select_list_metadata(&conn).unwrap()[&q1].acceptance_rate, Some(49.5));
+    }
+
+    #[test]
+    fn test_store_question_details() {
+        let conn = db_connect_in_memory();
+        let q1 = insert_lc_question(&conn, "lc-1", 1, "");
+        assert_eq!(select_question_metadata(&conn, q1).unwrap(), None);
+        assert!(!details_fetched_for_slug(&conn, LEETCODE_SOURCE_ID, "lc-1").unwrap());
+
+        let mut details = SourceDetails {
+            prompt: "<p>prompt</p>".to_string(),
+            paid_only: Some(false),
+            acceptance_rate: Some(51.2),
+            likes: Some(10),
+            dislikes: Some(2),
+            hints: vec!["use a map".to_string()],
+            code_snippets: vec![SourceCodeSnippet {
+                lang: "Python3".to_string(), lang_slug: "python3".to_string(), code: "class Solution:".to_string()
+            }],
+            example_testcases: Some("[2,7,11,15]\n9".to_string()),
+            similar_questions: vec!["3sum".to_string()],
+        };
+        store_question_details(&conn, q1, &details).unwrap();
+        details.code_snippets[0].code = "class Solution:\n    pass".to_string();
+        store_question_details(&conn, q1, &details).unwrap();
+
+        let stored = select_question_metadata(&conn, q1).unwrap().unwrap();
+        assert!(stored.details_fetched);
+        assert_eq!(stored.acceptance_rate, Some(51.2));
+        assert_eq!(stored.hints, vec!["use a map".to_string()]);
+        assert_eq!(stored.similar_questions, vec!["3sum".to_string()]);
+        assert_eq!(stored.code_snippets.len(), 1);
+        assert_eq!(stored.code_snippets[0].code, "class Solution:\n    pass");
+        assert!(details_fetched_for_slug(&conn, LEETCODE_SOURCE_ID, "lc-1").unwrap());
+    }
+}
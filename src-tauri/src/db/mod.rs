@@ -1,3 +1,6 @@
 pub mod models;
 pub mod schema;
-pub mod lib;
\ No newline at end of file
+pub mod lib;
+pub mod metadata;
+#[cfg(test)]
+pub mod test_util;
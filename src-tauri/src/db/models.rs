@@ -1,4 +1,4 @@
-use super::schema::{question, question_topic, topic};
+use super::schema::{question, question_topic, topic, star, solution, user, code_snippet, question_relation, question_translation, prompt_render, prompt_asset, question_structure, question_example, question_constraint, test_case, code_template, review_log, review_state, deck, deck_question};
 
 #[derive(Queryable, Debug, Clone)]
 pub struct Question {
@@ -9,6 +9,7 @@ pub struct Question {
     pub difficulty: Option<String>,
     pub source: Option<i32>,
     pub source_qid: Option<i32>,
+    pub archived: bool,
 }
 
 #[derive(Queryable)]
@@ -35,10 +36,18 @@ pub struct Star {
 pub struct User {
     pub uid: i32,
     pub name: String,
-    pub hide_diff: bool,
-    pub hide_cat: bool,
-    pub hide_solved: bool,
-    pub dark_mode: bool,
+    pub hide_diff: Option<bool>,
+    pub hide_cat: Option<bool>,
+    pub hide_solved: Option<bool>,
+    pub dark_mode: Option<bool>,
+    pub prompt_locale: String,
+    pub preferred_language: String,
+}
+
+#[derive(Queryable)]
+pub struct Source {
+    pub sid: i32,
+    pub name: String,
 }
 
 #[derive(Queryable, Debug, Clone)]
@@ -49,6 +58,139 @@ pub struct Solution {
     pub notes: String,
 }
 
+#[derive(Queryable, Debug, Clone)]
+pub struct TestCase {
+    pub tcid: i32,
+    pub uid: i32,
+    pub qid: i32,
+    pub input: String,
+    pub expected: String,
+    pub comparator: Option<String>,
+    pub note: Option<String>,
+    pub caught_bug: bool,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct QuestionMetadata {
+    pub qid: i32,
+    pub paid_only: bool,
+    pub acceptance_rate: Option<f64>,
+    pub likes: Option<i32>,
+    pub dislikes: Option<i32>,
+    pub hints: String,
+    pub example_testcases: Option<String>,
+    pub similar_questions: String,
+    pub details_fetched: bool,
+    pub rating: Option<i32>,
+    pub frontend_id: Option<String>,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct CodeSnippet {
+    pub csid: i32,
+    pub qid: i32,
+    pub lang: String,
+    pub lang_slug: String,
+    pub code: String,
+}
+
+// queried and inserted as is, a render has no generated columns
+#[derive(Queryable, Insertable, Debug, Clone)]
+#[table_name="prompt_render"]
+pub struct PromptRender {
+    pub hash: String,
+    pub html: String,
+    pub markdown: String,
+    pub text: String,
+}
+
+// one row per remote image url, urls with identical bytes share a hash and
+// the file behind it
+#[derive(Queryable, Insertable, Debug, Clone, PartialEq)]
+#[table_name="prompt_asset"]
+pub struct PromptAsset {
+    pub source_url: String,
+    pub hash: String,
+    pub mime: String,
+    pub size: i64,
+    pub last_used: i64,
+}
+
+// what was extracted from which prompt, see prompts::structure
+#[derive(Queryable, Insertable, Debug, Clone, PartialEq)]
+#[table_name="question_structure"]
+pub struct QuestionStructure {
+    pub qid: i32,
+    pub prompt_hash: String,
+    pub max_input_size: Option<i64>,
+    pub expected_complexity: Option<String>,
+}
+
+#[derive(Queryable, Insertable, Debug, Clone, PartialEq)]
+#[table_name="question_example"]
+pub struct QuestionExample {
+    pub qid: i32,
+    pub position: i32,
+    pub input: String,
+    pub output: String,
+    pub explanation: Option<String>,
+}
+
+// bounds are inclusive, "x < 10" is stored as an upper bound of 9
+#[derive(Queryable, Insertable, Debug, Clone, PartialEq)]
+#[table_name="question_constraint"]
+pub struct QuestionConstraint {
+    pub qid: i32,
+    pub position: i32,
+    pub text: String,
+    pub subject: Option<String>,
+    pub lower_bound: Option<i64>,
+    pub upper_bound: Option<i64>,
+    pub is_size: bool,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct Deck {
+    pub did: i32,
+    pub uid: i32,
+    pub name: String,
+}
+
+#[derive(Queryable, Insertable, Debug, Clone, PartialEq)]
+#[table_name="deck_question"]
+pub struct DeckQuestion {
+    pub did: i32,
+    pub qid: i32,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct ReviewLog {
+    pub rlid: i32,
+    pub uid: i32,
+    pub qid: i32,
+    pub reviewed_at: i64,
+    pub grade: i32,
+}
+
+#[derive(Queryable, Insertable, Debug, Clone, PartialEq)]
+#[table_name="review_state"]
+pub struct ReviewState {
+    pub uid: i32,
+    pub qid: i32,
+    pub due_at: i64,
+    pub interval_days: i32,
+    pub ease: f64,
+    pub repetitions: i32,
+}
+
+#[derive(Queryable, Debug, Clone)]
+pub struct QuestionTranslation {
+    pub qid: i32,
+    pub locale: String,
+    pub title: String,
+    pub prompt: Option<String>,
+}
+
 
 
 #[derive(Insertable)]
@@ -74,3 +216,105 @@ pub struct NewQuestionTopic {
     pub qid: i32,
     pub tid: i32,
 }
+
+#[derive(Insertable)]
+#[table_name="star"]
+pub struct NewStar {
+    pub qid: i32,
+    pub uid: i32,
+}
+
+#[derive(Insertable)]
+#[table_name="solution"]
+pub struct NewSolution {
+    pub uid: i32,
+    pub qid: i32,
+    pub notes: String,
+}
+
+#[derive(Insertable)]
+#[table_name="test_case"]
+pub struct NewTestCase {
+    pub uid: i32,
+    pub qid: i32,
+    pub input: String,
+    pub expected: String,
+    pub comparator: Option<String>,
+    pub note: Option<String>,
+    pub caught_bug: bool,
+}
+
+#[derive(Insertable)]
+#[table_name="deck"]
+pub struct NewDeck {
+    pub uid: i32,
+    pub name: String,
+}
+
+#[derive(Insertable)]
+#[table_name="review_log"]
+pub struct NewReviewLog {
+    pub uid: i32,
+    pub qid: i32,
+    pub reviewed_at: i64,
+    pub grade: i32,
+}
+
+#[derive(Insertable)]
+#[table_name="user"]
+pub struct NewUser {
+    pub uid: Option<i32>,
+    pub name: String,
+    pub hide_diff: Option<bool>,
+    pub hide_cat: Option<bool>,
+    pub hide_solved: Option<bool>,
+    pub dark_mode: Option<bool>,
+    pub prompt_locale: String,
+    pub preferred_language: String,
+}
+
+#[derive(Queryable, Debug, Clone, PartialEq)]
+pub struct CodeTemplate {
+    pub ctid: i32,
+    pub uid: i32,
+    pub tid: i32,
+    pub name: String,
+    pub lang_slug: String,
+    pub code: String,
+}
+
+#[derive(Insertable)]
+#[table_name="code_template"]
+pub struct NewCodeTemplate {
+    pub uid: i32,
+    pub tid: i32,
+    pub name: String,
+    pub lang_slug: String,
+    pub code: String,
+}
+
+#[derive(Insertable)]
+#[table_name="code_snippet"]
+pub struct NewCodeSnippet {
+    pub qid: i32,
+    pub lang: String,
+    pub lang_slug: String,
+    pub code: String,
+}
+
+#[derive(Insertable)]
+#[table_name="question_relation"]
+pub struct NewQuestionRelation {
+    pub qid: i32,
+    pub related_qid: i32,
+    pub kind: String,
+}
+
+#[derive(Insertable)]
+#[table_name="question_translation"]
+pub struct NewQuestionTranslation {
+    pub qid: i32,
+    pub locale: String,
+    pub title: String,
+    pub prompt: Option<String>,
+}
@@ -1,3 +1,72 @@
+table! {
+    app_setting (name) {
+        name -> Text,
+        value -> Text,
+    }
+}
+
+table! {
+    code_snippet (csid) {
+        csid -> Integer,
+        qid -> Integer,
+        lang -> Text,
+        lang_slug -> Text,
+        code -> Text,
+    }
+}
+
+table! {
+    code_template (ctid) {
+        ctid -> Integer,
+        uid -> Integer,
+        tid -> Integer,
+        name -> Text,
+        lang_slug -> Text,
+        code -> Text,
+    }
+}
+
+table! {
+    deck (did) {
+        did -> Integer,
+        uid -> Integer,
+        name -> Text,
+    }
+}
+
+table! {
+    deck_question (did, qid) {
+        did -> Integer,
+        qid -> Integer,
+    }
+}
+
+table! {
+    feed_source (sid) {
+        sid -> Integer,
+        location -> Text,
+    }
+}
+
+table! {
+    prompt_asset (source_url) {
+        source_url -> Text,
+        hash -> Text,
+        mime -> Text,
+        size -> BigInt,
+        last_used -> BigInt,
+    }
+}
+
+table! {
+    prompt_render (hash) {
+        hash -> Text,
+        html -> Text,
+        markdown -> Text,
+        text -> Text,
+    }
+}
+
 table! {
     question (qid) {
         qid -> Integer,
@@ -7,6 +76,72 @@ table! {
         difficulty -> Nullable<Text>,
         source -> Nullable<Integer>,
         source_qid -> Nullable<Integer>,
+        archived -> Bool,
+    }
+}
+
+table! {
+    question_constraint (qid, position) {
+        qid -> Integer,
+        position -> Integer,
+        text -> Text,
+        subject -> Nullable<Text>,
+        lower_bound -> Nullable<BigInt>,
+        upper_bound -> Nullable<BigInt>,
+        is_size -> Bool,
+    }
+}
+
+table! {
+    question_example (qid, position) {
+        qid -> Integer,
+        position -> Integer,
+        input -> Text,
+        output -> Text,
+        explanation -> Nullable<Text>,
+    }
+}
+
+table! {
+    question_metadata (qid) {
+        qid -> Integer,
+        paid_only -> Bool,
+        acceptance_rate -> Nullable<Double>,
+        likes -> Nullable<Integer>,
+        dislikes -> Nullable<Integer>,
+        hints -> Text,
+        example_testcases -> Nullable<Text>,
+        similar_questions -> Text,
+        details_fetched -> Bool,
+        rating -> Nullable<Integer>,
+        frontend_id -> Nullable<Text>,
+    }
+}
+
+table! {
+    question_relation (relid) {
+        relid -> Integer,
+        qid -> Integer,
+        related_qid -> Integer,
+        kind -> Text,
+    }
+}
+
+table! {
+    question_structure (qid) {
+        qid -> Integer,
+        prompt_hash -> Text,
+        max_input_size -> Nullable<BigInt>,
+        expected_complexity -> Nullable<Text>,
+    }
+}
+
+table! {
+    question_translation (qid, locale) {
+        qid -> Integer,
+        locale -> Text,
+        title -> Text,
+        prompt -> Nullable<Text>,
     }
 }
 
@@ -18,6 +153,27 @@ table! {
     }
 }
 
+table! {
+    review_log (rlid) {
+        rlid -> Integer,
+        uid -> Integer,
+        qid -> Integer,
+        reviewed_at -> BigInt,
+        grade -> Integer,
+    }
+}
+
+table! {
+    review_state (uid, qid) {
+        uid -> Integer,
+        qid -> Integer,
+        due_at -> BigInt,
+        interval_days -> Integer,
+        ease -> Double,
+        repetitions -> Integer,
+    }
+}
+
 table! {
     solution (sid) {
         sid -> Integer,
@@ -42,6 +198,19 @@ table! {
     }
 }
 
+table! {
+    test_case (tcid) {
+        tcid -> Integer,
+        uid -> Integer,
+        qid -> Integer,
+        input -> Text,
+        expected -> Text,
+        comparator -> Nullable<Text>,
+        note -> Nullable<Text>,
+        caught_bug -> Bool,
+    }
+}
+
 table! {
     topic (tid) {
         tid -> Integer,
@@ -57,23 +226,60 @@ table! {
         hide_cat -> Nullable<Bool>,
         hide_solved -> Nullable<Bool>,
         dark_mode -> Nullable<Bool>,
+        prompt_locale -> Text,
+        preferred_language -> Text,
     }
 }
 
+joinable!(code_snippet -> question (qid));
+joinable!(code_template -> topic (tid));
+joinable!(code_template -> user (uid));
+joinable!(deck -> user (uid));
+joinable!(deck_question -> deck (did));
+joinable!(deck_question -> question (qid));
+joinable!(feed_source -> source (sid));
 joinable!(question -> source (source));
+joinable!(question_constraint -> question (qid));
+joinable!(question_example -> question (qid));
+joinable!(question_metadata -> question (qid));
+joinable!(question_structure -> question (qid));
 joinable!(question_topic -> question (qid));
+joinable!(question_translation -> question (qid));
 joinable!(question_topic -> topic (tid));
+joinable!(review_log -> question (qid));
+joinable!(review_log -> user (uid));
+joinable!(review_state -> question (qid));
+joinable!(review_state -> user (uid));
 joinable!(solution -> question (qid));
 joinable!(solution -> user (uid));
 joinable!(star -> question (qid));
 joinable!(star -> user (uid));
+joinable!(test_case -> question (qid));
+joinable!(test_case -> user (uid));
 
 allow_tables_to_appear_in_same_query!(
+    app_setting,
+    code_snippet,
+    code_template,
+    deck,
+    deck_question,
+    feed_source,
+    prompt_asset,
+    prompt_render,
     question,
+    question_constraint,
+    question_example,
+    question_metadata,
+    question_relation,
+    question_structure,
     question_topic,
+    question_translation,
+    review_log,
+    review_state,
     solution,
     source,
     star,
+    test_case,
     topic,
     user,
 );
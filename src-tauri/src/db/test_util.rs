@@ -0,0 +1,35 @@
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::LEETCODE_SOURCE_ID;
+use crate::db::models::NewQuestion;
+
+// fixtures shared by the unit tests of every module that needs questions in
+// a db_connect_in_memory db
+
+
+pub fn insert_lc_question(conn: &SqliteConnection, slug: &str, src_qid: i32, prompt: &str) -> i32 {
+    // a leetcode question titled after its slug, returns its qid
+    use crate::db::schema::question::dsl;
+
+    diesel::insert_into(dsl::question)
+        .values(&NewQuestion {
+            title: slug.replace('-', " "),
+            title_slug: slug.to_string(),
+            prompt: prompt.to_string(),
+            difficulty: "EASY".to_string(),
+            source: LEETCODE_SOURCE_ID,
+            source_qid: src_qid,
+        })
+        .execute(conn).unwrap();
+
+    dsl::question.order_by(dsl::qid.desc()).select(dsl::qid).first(conn).unwrap()
+}
+
+pub fn set_frontend_id(conn: &SqliteConnection, the_qid: i32, number: &str) {
+    // the number leetcode shows for the question, as a list sync stores it
+    use crate::db::schema::question_metadata::dsl;
+
+    diesel::insert_into(dsl::question_metadata)
+        .values((dsl::qid.eq(the_qid), dsl::frontend_id.eq(number)))
+        .execute(conn).unwrap();
+}
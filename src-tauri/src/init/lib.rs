@@ -1,34 +1,144 @@
+use dotenv::dotenv;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::env;
 use diesel::sqlite::SqliteConnection;
 use diesel::prelude::*;
-use crate::network::fetchers::{fetch_lc_question_prompt, fetch_all_lc_questions};
-use crate::db::models::Question;
+use crate::db::models::{Question, NewQuestion, NewQuestionTopic};
 use crate::db::lib::{
-    insert_all_lc_q_base_info, 
-    count_lc_questions_in_db,
-    LEETCODE_SOURCE_ID
+    db_connect,
+    insert_all_q_base_info,
+    count_source_questions_in_db,
+    select_app_setting,
+    select_or_insert_topic,
+    set_app_setting
 };
+use crate::db::metadata::store_list_metadata;
+use crate::network::fetchers::ListProgress;
+use crate::prompts::assets::{cache_prompt_images, AssetStore};
+use crate::prompts::detail::store_details;
+use crate::prompts::structure::refresh_structures;
+use crate::sources::{
+    registered_sources,
+    register_source,
+    source_for_id,
+    QuestionSource,
+    SourceQuestion
+};
+use crate::sources::leetcode::LEETCODE_SOURCE_NAME;
 
 const INIT_FETCHED_PROMPTS: i32 = 20;
+const SYNC_ON_STARTUP_VAR: &str = "SYNC_SOURCES_ON_STARTUP";
+const IMPORT_ON_STARTUP_VAR: &str = "IMPORT_SOURCES_ON_STARTUP";
+// app_setting set once a source's whole question list was imported. single
+// questions (add by url, deck imports) can exist before that happens
+const CATALOGUE_LOADED_SETTING: &str = "catalogue_loaded";
+pub const CATALOGUE_PROGRESS_EVENT: &str = "catalogue-progress";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionChange {
+    Inserted,
+    Restored,
+    Updated,
+    Unchanged,
+}
+
+// a source's question list coming in, page by page. startup keeps the last
+// one in its status, sync_source sends them as catalogue-progress events
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CatalogueProgress {
+    pub source: String,
+    pub page: usize,
+    pub fetched: usize,
+    pub total: usize,
+}
+
+impl CatalogueProgress {
+    fn new(source_name: &str, progress: ListProgress) -> Self {
+        CatalogueProgress {
+            source: source_name.to_string(),
+            page: progress.page,
+            fetched: progress.fetched,
+            total: progress.total,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct SyncSummary {
+    pub inserted: usize,
+    pub updated: usize,
+    pub archived: usize,
+    pub restored: usize,
+    pub unchanged: usize,
+}
 
-pub async fn get_lc_questions_on_init(
-    conn: &SqliteConnection
+pub async fn get_questions_on_init(
+    conn: &SqliteConnection,
+    on_progress: &(dyn Fn(CatalogueProgress) + Send + Sync)
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // check if db has LC questions inserted already, does fetch + insert if not
+    // make sure every registered question source has a source row, then
+    // fetch + insert questions for sources that have none in the db yet.
+    // sources left out of IMPORT_SOURCES_ON_STARTUP only get a row, their
+    // questions come in the first time they are synced with sync_source.
+    // a source that fails to import doesn't hold up the others, the first
+    // failure is still returned for the startup status
 
-    if count_lc_questions_in_db(conn).await? > 0 { // questions previously inserted
+    let mut first_err: Option<Box<dyn std::error::Error>> = None;
+    for question_source in registered_sources() {
+        let src_id = register_source(conn, question_source.name())?;
+        if !import_on_startup(question_source.name()) {
+            continue;
+        }
+        if let Err(err) = get_source_questions_on_init(conn, question_source.as_ref(), src_id, on_progress).await {
+            println!("could not import questions from {}: {:?}", question_source.name(), err);
+            first_err.get_or_insert(err);
+        }
+    }
+
+    match first_err {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+
+async fn get_source_questions_on_init(
+    conn: &SqliteConnection,
+    question_source: &dyn QuestionSource,
+    src_id: i32,
+    on_progress: &(dyn Fn(CatalogueProgress) + Send + Sync)
+) -> Result<(), Box<dyn std::error::Error>> {
+    let on_page = |progress: ListProgress| on_progress(CatalogueProgress::new(question_source.name(), progress));
+    let loaded_setting = format!("{}_{}", CATALOGUE_LOADED_SETTING, src_id);
+    if select_app_setting(conn, &loaded_setting)?.is_some() {
+        if sync_on_startup() {
+            let remote_questions = question_source.list_questions(&on_page).await?;
+            apply_source_sync(conn, src_id, remote_questions)?;
+        }
         return Ok(());
     }
 
-    let fetched_questions = fetch_all_lc_questions().await?;
-    insert_all_lc_q_base_info(&conn, fetched_questions).await?;
-    get_lc_question_prompts_on_init(&conn, INIT_FETCHED_PROMPTS).await?;
+    // questions added one at a time are matched up by a sync instead of
+    // being inserted a second time
+    let fetched_questions = question_source.list_questions(&on_page).await?;
+    if count_source_questions_in_db(conn, src_id).await? > 0 {
+        apply_source_sync(conn, src_id, fetched_questions)?;
+    } else {
+        insert_all_q_base_info(conn, src_id, fetched_questions).await?;
+    }
+    set_app_setting(conn, &loaded_setting, "true")?;
+    get_question_prompts_on_init(conn, question_source, src_id, INIT_FETCHED_PROMPTS).await?;
 
     Ok(())
 }
 
 
-async fn get_lc_question_prompts_on_init(
+async fn get_question_prompts_on_init(
     conn: &SqliteConnection, 
+    question_source: &dyn QuestionSource,
+    src_id: i32,
     num_prompts: i32
 ) -> Result<(), Box<dyn std::error::Error>> {
     // get some question prompts for cards on top of deck when app starts up
@@ -36,19 +146,241 @@ async fn get_lc_question_prompts_on_init(
     use crate::db::schema::question::dsl::*;
     let need_prompt_rows: Vec<Question> = question
         .filter(source.is_not_null())
-        .filter(source.eq(LEETCODE_SOURCE_ID))
+        .filter(source.eq(src_id))
         .filter(source_qid.is_not_null())
-        .filter(source_qid.lt(num_prompts + 1))
+        .filter(title_slug.is_not_null())
+        .order_by(source_qid.asc())
+        .limit(num_prompts.into())
         .load(conn)?;
 
-    // LC graphql api requires prompt fetching using title slug as graphql variable
+    let asset_store = AssetStore::from_env();
+    // sources like LC graphql api require prompt fetching using title slug
+    // one failed prompt shouldn't cost the rest of them. whatever is missing
+    // gets picked up later by the prefetch worker or on first open
     for q in need_prompt_rows {
-        let prompt_html = fetch_lc_question_prompt(&q.title_slug.unwrap()).await?;
-        diesel::update(question)
-            .filter(qid.eq(q.qid))
-            .set(prompt.eq(prompt_html))
+        let slug = q.title_slug.unwrap();
+        let fetched = question_source.fetch_details(&slug).await;
+        let mut details = match fetched {
+            Ok(details) => details,
+            Err(err) => {
+                println!("could not fetch prompt for {}: {:?}", slug, err);
+                continue;
+            }
+        };
+        details.prompt = cache_prompt_images(&asset_store, &details.prompt).await;
+        store_details(conn, src_id, &slug, &details)?;
+    }
+
+    Ok(())
+}
+
+
+fn import_on_startup(source_name: &str) -> bool {
+    dotenv().ok();
+    imports_source(env::var(IMPORT_ON_STARTUP_VAR).ok().as_deref(), source_name)
+}
+
+fn imports_source(listed: Option<&str>, source_name: &str) -> bool {
+    // comma separated source names, only leetcode when unset
+    match listed {
+        Some(names) => names.split(',').any(|n| n.trim() == source_name),
+        None => source_name == LEETCODE_SOURCE_NAME,
+    }
+}
+
+
+fn sync_on_startup() -> bool {
+    // off unless SYNC_SOURCES_ON_STARTUP is set to true in .env
+    dotenv().ok();
+    env::var(SYNC_ON_STARTUP_VAR)
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false)
+}
+
+
+pub async fn sync_source(
+    src_id: i32,
+    on_progress: &(dyn Fn(CatalogueProgress) + Send + Sync)
+) -> Result<SyncSummary, Box<dyn std::error::Error>> {
+    // diff a source's current question list against what is in the db.
+    // connections are opened around the network call, not across it
+    let question_source = source_for_id(&db_connect(), src_id)?;
+    let source_name = question_source.name().to_string();
+    let on_page = |progress: ListProgress| on_progress(CatalogueProgress::new(&source_name, progress));
+    let remote_questions = question_source.list_questions(&on_page).await?;
+
+    apply_source_sync(&db_connect(), src_id, remote_questions)
+}
+
+
+pub fn apply_source_sync(
+    conn: &SqliteConnection,
+    src_id: i32,
+    remote_questions: Vec<SourceQuestion>
+) -> Result<SyncSummary, Box<dyn std::error::Error>> {
+    // local rows are matched to the remote list by source_qid. new questions
+    // get inserted, changed base info and topics get updated, and questions
+    // the source no longer lists are archived rather than deleted so stars
+    // and solutions on them survive
+    use crate::db::schema::question::dsl::*;
+
+    if remote_questions.is_empty() {
+        // more likely a broken response than a source with no questions
+        return Err("source returned no questions, refusing to archive everything".into());
+    }
+
+    // paid only and acceptance rate move all the time, they are refreshed
+    // but don't count as an update
+    let listed_metadata = remote_questions.clone();
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        let mut summary = SyncSummary::default();
+        let local_rows: Vec<Question> = question
+            .filter(source.eq(src_id))
+            .filter(source_qid.is_not_null())
+            .load(conn)?;
+        let mut local_by_src_qid: HashMap<i32, Question> = local_rows.into_iter()
+            .map(|q| (q.source_qid.unwrap(), q))
+            .collect();
+
+        for rq in remote_questions {
+            let local = local_by_src_qid.remove(&rq.source_qid);
+            match sync_question(conn, src_id, local, &rq)?.1 {
+                QuestionChange::Inserted => summary.inserted += 1,
+                QuestionChange::Restored => summary.restored += 1,
+                QuestionChange::Updated => summary.updated += 1,
+                QuestionChange::Unchanged => summary.unchanged += 1,
+            }
+        }
+
+        // whatever is left is gone upstream
+        let gone: Vec<i32> = local_by_src_qid.values()
+            .filter(|q| !q.archived)
+            .map(|q| q.qid)
+            .collect();
+        summary.archived = diesel::update(question.filter(qid.eq_any(&gone)))
+            .set(archived.eq(true))
             .execute(conn)?;
+        store_list_metadata(conn, src_id, &listed_metadata)?;
+
+        Ok(summary)
+    })
+}
+
+pub fn upsert_source_question(conn: &SqliteConnection, src_id: i32, rq: SourceQuestion) ->
+Result<(i32, QuestionChange), Box<dyn std::error::Error>> {
+    // a single question, matched by source_qid like a sync would. nothing
+    // else from the source is touched
+    use crate::db::schema::question::dsl::*;
+
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        let local: Option<Question> = question
+            .filter(source.eq(src_id))
+            .filter(source_qid.eq(rq.source_qid))
+            .first(conn)
+            .optional()?;
+        let synced = sync_question(conn, src_id, local, &rq)?;
+        store_list_metadata(conn, src_id, &[rq])?;
+
+        Ok(synced)
+    })
+}
+
+fn sync_question(
+    conn: &SqliteConnection,
+    src_id: i32,
+    local: Option<Question>,
+    rq: &SourceQuestion
+) -> Result<(i32, QuestionChange), Box<dyn std::error::Error>> {
+    // brings one local row in line with what the source says about it,
+    // returns its qid and what changed
+    use crate::db::schema::question::dsl::*;
+
+    let local = match local {
+        Some(local) => local,
+        None => {
+            diesel::insert_into(question)
+                .values(&NewQuestion {
+                    title: rq.title.clone(),
+                    title_slug: rq.title_slug.clone(),
+                    prompt: rq.prompt.clone().unwrap_or_default(),
+                    difficulty: rq.difficulty.clone(),
+                    source: src_id,
+                    source_qid: rq.source_qid,
+                })
+                .execute(conn)?;
+            let new_qid: i32 = question.order_by(qid.desc()).select(qid).first(conn)?;
+            replace_question_topics(conn, new_qid, &rq.topics)?;
+            refresh_structures(conn, Some(&[new_qid]))?;
+            return Ok((new_qid, QuestionChange::Inserted));
+        }
+    };
+
+    // a listed prompt replaces the stored one, sources that don't
+    // list prompts leave whatever was fetched alone
+    let prompt_changed = rq.prompt.is_some() && local.prompt != rq.prompt;
+    let base_info_changed = local.title != rq.title ||
+        local.title_slug.as_ref() != Some(&rq.title_slug) ||
+        local.difficulty.as_ref() != Some(&rq.difficulty) ||
+        prompt_changed;
+    let topics_changed = select_question_topic_names(conn, local.qid)? !=
+        rq.topics.iter().cloned().collect::<BTreeSet<String>>();
+    let change = if local.archived {
+        QuestionChange::Restored
+    }
+    else if base_info_changed || topics_changed {
+        QuestionChange::Updated
     }
+    else {
+        return Ok((local.qid, QuestionChange::Unchanged));
+    };
+
+    diesel::update(question.filter(qid.eq(local.qid)))
+        .set((
+            title.eq(&rq.title),
+            title_slug.eq(&rq.title_slug),
+            difficulty.eq(&rq.difficulty),
+            archived.eq(false),
+        ))
+        .execute(conn)?;
+    if let Some(listed_prompt) = rq.prompt.as_ref().filter(|_| prompt_changed) {
+        diesel::update(question.filter(qid.eq(local.qid)))
+            .set(prompt.eq(listed_prompt))
+            .execute(conn)?;
+        refresh_structures(conn, Some(&[local.qid]))?;
+    }
+    if topics_changed {
+        replace_question_topics(conn, local.qid, &rq.topics)?;
+    }
+
+    Ok((local.qid, change))
+}
+
+fn select_question_topic_names(conn: &SqliteConnection, the_qid: i32) ->
+Result<BTreeSet<String>, Box<dyn std::error::Error>> {
+    use crate::db::schema::{question_topic, topic};
+
+    let names: Vec<String> = question_topic::table
+        .inner_join(topic::table)
+        .filter(question_topic::qid.eq(the_qid))
+        .select(topic::name)
+        .load(conn)?;
+
+    Ok(names.into_iter().collect())
+}
+
+fn replace_question_topics(conn: &SqliteConnection, the_qid: i32, topic_names: &[String]) ->
+Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema::question_topic::dsl::*;
+
+    diesel::delete(question_topic.filter(qid.eq(the_qid))).execute(conn)?;
+    let mut new_question_topics: Vec<NewQuestionTopic> = vec![];
+    for topic_name in topic_names {
+        let the_tid = select_or_insert_topic(conn, topic_name)?;
+        new_question_topics.push(NewQuestionTopic { qid: the_qid, tid: the_tid });
+    }
+    diesel::insert_into(question_topic)
+        .values(&new_question_topics)
+        .execute(conn)?;
 
     Ok(())
 }
@@ -59,18 +391,180 @@ async fn get_lc_question_prompts_on_init(
 ///////////////////////////////////////
 #[cfg(test)]
 mod test {
-    use crate::db::lib::db_connect;
+    use crate::db::lib::{db_connect, db_connect_in_memory, LEETCODE_SOURCE_ID};
     use super::*;
 
+    fn remote_question(src_qid: i32, the_title: &str, topics: Vec<&str>) -> SourceQuestion {
+        SourceQuestion {
+            source_qid: src_qid,
+            title: the_title.to_string(),
+            title_slug: the_title.to_lowercase().replace(' ', "-"),
+            difficulty: "MEDIUM".to_string(),
+            topics: topics.into_iter().map(|t| t.to_string()).collect(),
+            paid_only: Some(false),
+            acceptance_rate: None,
+            rating: None,
+            frontend_id: None,
+            prompt: None,
+        }
+    }
+
+    #[test]
+    fn test_imports_source() {
+        assert!(imports_source(None, LEETCODE_SOURCE_NAME));
+        assert!(!imports_source(None, "codeforces"));
+        assert!(imports_source(Some("leetcode, codeforces"), "codeforces"));
+        assert!(!imports_source(Some("codeforces"), LEETCODE_SOURCE_NAME));
+    }
+
+    #[test]
+    fn apply_source_sync_test() {
+        use crate::db::schema::question::dsl::*;
+        let conn = db_connect_in_memory();
+
+        let first = apply_source_sync(&conn, LEETCODE_SOURCE_ID, vec![
+            remote_question(1, "Two Sum", vec!["Array"]),
+            remote_question(2, "Add Two Numbers", vec!["Linked List"]),
+            remote_question(3, "Old Question", vec![]),
+        ]).unwrap();
+        assert_eq!(first.inserted, 3);
+
+        let second = apply_source_sync(&conn, LEETCODE_SOURCE_ID, vec![
+            remote_question(1, "Two Sum", vec!["Array", "Hash Table"]),
+            remote_question(2, "Add Two Numbers", vec!["Linked List"]),
+            remote_question(4, "New Question", vec!["Array"]),
+        ]).unwrap();
+        assert_eq!(second, SyncSummary { inserted: 1, updated: 1, archived: 1, restored: 0, unchanged: 1 });
+        let archived_titles: Vec<String> = question
+            .filter(archived.eq(true))
+            .select(title)
+            .load(&conn).unwrap();
+        assert_eq!(archived_titles, vec!["Old Question".to_string()]);
+        let two_sum_qid: i32 = question.filter(source_qid.eq(1)).select(qid).first(&conn).unwrap();
+        assert_eq!(select_question_topic_names(&conn, two_sum_qid).unwrap().len(), 2);
+
+        let third = apply_source_sync(&conn, LEETCODE_SOURCE_ID, vec![
+            remote_question(1, "Two Sum", vec!["Array", "Hash Table"]),
+            remote_question(2, "Add Two Numbers (renamed)", vec!["Linked List"]),
+            remote_question(3, "Old Question", vec![]),
+            remote_question(4, "New Question", vec!["Array"]),
+        ]).unwrap();
+        assert_eq!(third, SyncSummary { inserted: 0, updated: 1, archived: 0, restored: 1, unchanged: 2 });
+
+        assert!(apply_source_sync(&conn, LEETCODE_SOURCE_ID, vec![]).is_err());
+    }
+
+    #[tokio::test]
+    async fn get_source_questions_on_init_test() {
+        // whole import against the mock LC server: list, insert, prompts.
+        // one fixture question has no recorded prompt and gets skipped
+        use crate::network::mock_leetcode::*;
+        use crate::sources::leetcode::LeetCodeSource;
+
+        let server = start_mock_leetcode(MockFaults::default()).await;
+        let lc = LeetCodeSource::with_endpoint(&server.url);
+        let conn = db_connect_in_memory();
+
+        let progress = std::sync::Mutex::new(vec![]);
+        get_source_questions_on_init(&conn, &lc, LEETCODE_SOURCE_ID,
+            &|p| progress.lock().unwrap().push(p)).await.unwrap();
+        let imported = count_source_questions_in_db(&conn, LEETCODE_SOURCE_ID).await.unwrap();
+        assert_eq!(imported as usize, fixture_question_count());
+        let last = progress.lock().unwrap().last().cloned().unwrap();
+        assert_eq!((last.source.as_str(), last.fetched), ("leetcode", fixture_question_count()));
+        let with_prompts = count_lc_questions_with_prompts_in_db(&conn).await.unwrap();
+        assert_eq!(with_prompts as usize, fixture_prompt_count());
+        let two_sum_qid: i32 = crate::db::schema::question::table
+            .filter(crate::db::schema::question::title_slug.eq("two-sum"))
+            .select(crate::db::schema::question::qid)
+            .first(&conn).unwrap();
+        let metadata = crate::db::metadata::select_question_metadata(&conn, two_sum_qid).unwrap().unwrap();
+        assert!(metadata.details_fetched && !metadata.paid_only);
+        assert_eq!(metadata.acceptance_rate, Some(49.7));
+        assert_eq!(metadata.similar_questions, vec!["3sum".to_string(), "4sum".to_string()]);
+        assert_eq!(metadata.code_snippets.len(), 3);
+
+        // already imported, nothing is fetched again
+        let requests_before = server.requests().len();
+        get_source_questions_on_init(&conn, &lc, LEETCODE_SOURCE_ID, &|_| {}).await.unwrap();
+        assert_eq!(server.requests().len(), requests_before);
+
+        let remote = lc.list_questions(&|_| {}).await.unwrap();
+        let summary = apply_source_sync(&conn, LEETCODE_SOURCE_ID, remote).unwrap();
+        assert_eq!(summary.unchanged, fixture_question_count());
+    }
+
+    #[tokio::test]
+    async fn get_source_questions_on_init_after_single_add_test() {
+        // a question added on its own before the first import doesn't stop
+        // the catalogue from loading, and isn't duplicated by it
+        use crate::network::mock_leetcode::*;
+        use crate::sources::leetcode::LeetCodeSource;
+
+        let server = start_mock_leetcode(MockFaults::default()).await;
+        let lc = LeetCodeSource::with_endpoint(&server.url);
+        let conn = db_connect_in_memory();
+        let remote = lc.list_questions(&|_| {}).await.unwrap();
+        insert_all_q_base_info(&conn, LEETCODE_SOURCE_ID, remote[..1].to_vec()).await.unwrap();
+
+        get_source_questions_on_init(&conn, &lc, LEETCODE_SOURCE_ID, &|_| {}).await.unwrap();
+        let imported = count_source_questions_in_db(&conn, LEETCODE_SOURCE_ID).await.unwrap();
+        assert_eq!(imported as usize, fixture_question_count());
+    }
+
+    #[tokio::test]
+    async fn get_source_questions_on_init_malformed_test() {
+        use crate::network::mock_leetcode::*;
+        use crate::sources::leetcode::LeetCodeSource;
+
+        let server = start_mock_leetcode(MockFaults { malformed_list: true, ..MockFaults::default() }).await;
+        let conn = db_connect_in_memory();
+        let lc = LeetCodeSource::with_endpoint(&server.url);
+        assert!(get_source_questions_on_init(&conn, &lc, LEETCODE_SOURCE_ID, &|_| {}).await.is_err());
+        assert_eq!(count_source_questions_in_db(&conn, LEETCODE_SOURCE_ID).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn get_codeforces_questions_on_init_test() {
+        // codeforces tags land on existing topics and ratings are kept
+        use crate::db::lib::insert_topic;
+        use crate::db::metadata::select_list_metadata;
+        use crate::network::mock_server::{MockResponse, MockServer};
+        use crate::sources::codeforces::CodeforcesSource;
+
+        let server = MockServer::start(|_, _| {
+            MockResponse::json(200, include_str!("../../fixtures/codeforces/problemset.json"))
+        }).await;
+        let cf = CodeforcesSource::with_endpoint(&server.url);
+        let conn = db_connect_in_memory();
+        let greedy_tid = insert_topic(&conn, "Greedy").unwrap();
+        let cf_sid = register_source(&conn, "codeforces").unwrap();
+
+        get_source_questions_on_init(&conn, &cf, cf_sid, &|_| {}).await.unwrap();
+        assert_eq!(count_source_questions_in_db(&conn, cf_sid).await.unwrap(), 6);
+        let taxi: Question = crate::db::schema::question::table
+            .filter(crate::db::schema::question::title_slug.eq("158B"))
+            .first(&conn).unwrap();
+        assert_eq!(taxi.difficulty.as_deref(), Some("EASY"));
+        let taxi_tids: Vec<i32> = crate::db::schema::question_topic::table
+            .filter(crate::db::schema::question_topic::qid.eq(taxi.qid))
+            .select(crate::db::schema::question_topic::tid)
+            .load(&conn).unwrap();
+        assert!(taxi_tids.contains(&greedy_tid));
+        assert_eq!(select_list_metadata(&conn).unwrap()[&taxi.qid].rating, Some(1100));
+    }
+
     #[tokio::test]
-    async fn get_lc_questions_on_init_test() {
+    #[ignore]
+    // hits the live LC api and the real DATABASE_URL db
+    async fn get_questions_on_init_live() {
         let conn = db_connect();
 
-        let pre_call_qs = count_lc_questions_in_db(&conn).await.unwrap();
+        let pre_call_qs = count_source_questions_in_db(&conn, LEETCODE_SOURCE_ID).await.unwrap();
         let pre_call_prompts = count_lc_questions_with_prompts_in_db(&conn).await.unwrap();
-        let call_success = get_lc_questions_on_init(&conn).await;
+        let call_success = get_questions_on_init(&conn, &|_| {}).await;
         assert!(call_success.is_ok());
-        let post_call_qs = count_lc_questions_in_db(&conn).await.unwrap();
+        let post_call_qs = count_source_questions_in_db(&conn, LEETCODE_SOURCE_ID).await.unwrap();
         let post_call_prompts = count_lc_questions_with_prompts_in_db(&conn).await.unwrap();
 
         if pre_call_qs > 0 {
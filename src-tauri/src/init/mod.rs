@@ -1 +1,3 @@
-pub mod lib;
\ No newline at end of file
+pub mod lib;
+pub mod single;
+pub mod startup;
@@ -0,0 +1,162 @@
+use crate::db::lib::{db_connect, query_questions, QuestionOptions, QuestionQueryResult, LEETCODE_SOURCE_ID};
+use crate::init::lib::upsert_source_question;
+use crate::prompts::detail::{fetch_missing_prompt, PromptFetches};
+use crate::sources::leetcode::LeetCodeSource;
+use crate::sources::{QuestionSource, SourceQuestion};
+
+// adds one leetcode question without importing the whole catalogue, e.g. the
+// link someone was handed after a mock interview. accepts problem urls from
+// leetcode.com and leetcode.cn (same slugs), bare slugs and "#123" numbers
+
+const PROBLEM_HOSTS: [&str; 4] = ["leetcode.com", "www.leetcode.com", "leetcode.cn", "www.leetcode.cn"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuestionRef {
+    Slug(String),
+    Number(i32),
+}
+
+
+pub fn parse_question_ref(input: &str) -> Result<QuestionRef, Box<dyn std::error::Error>> {
+    let input = input.trim();
+    if let Some(number) = input.strip_prefix('#') {
+        return match number.trim().parse::<i32>() {
+            Ok(n) if n > 0 => Ok(QuestionRef::Number(n)),
+            _ => Err(format!("not a question number: {}", input).into()),
+        };
+    }
+
+    // urls with or without a scheme, anything after the slug (/description/,
+    // /solutions/..., ?envType=..., #...) is ignored
+    let without_scheme = input.trim_start_matches("https://").trim_start_matches("http://");
+    let mut parts = without_scheme.split(['/', '?', '#']);
+    let first = parts.next().unwrap_or("");
+    if first.contains('.') {
+        if !PROBLEM_HOSTS.contains(&first.to_lowercase().as_str()) {
+            return Err(format!("not a leetcode.com or leetcode.cn url: {}", input).into());
+        }
+        return match (parts.next(), parts.next()) {
+            (Some("problems"), Some(slug)) if is_slug(slug) => Ok(QuestionRef::Slug(slug.to_lowercase())),
+            _ => Err(format!("not a leetcode problem url: {}", input).into()),
+        };
+    }
+
+    if is_slug(input) {
+        return Ok(QuestionRef::Slug(input.to_lowercase()));
+    }
+
+    Err(format!("expected a leetcode problem url, slug or #number, got: {}", input).into())
+}
+
+fn is_slug(s: &str) -> bool {
+    !s.is_empty() &&
+    !s.starts_with('-') &&
+    s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') &&
+    s.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+
+pub async fn add_question_from_url(fetches: &PromptFetches, user: i32, input: &str) ->
+Result<QuestionQueryResult, Box<dyn std::error::Error>> {
+    // connections are opened around the network calls, not across them
+    let question_ref = parse_question_ref(input)?;
+    let fetched = fetch_referenced_question(&LeetCodeSource::default(), &question_ref).await?;
+    let slug = fetched.title_slug.clone();
+    let (the_qid, _) = upsert_source_question(&db_connect(), LEETCODE_SOURCE_ID, fetched)?;
+
+    // the question is in either way, a prompt that can't be fetched now is
+    // fetched again when the card is opened
+    if let Err(err) = fetch_missing_prompt(fetches, LEETCODE_SOURCE_ID, &slug).await.map_err(|err| err.to_string()) {
+        println!("could not fetch prompt for {}: {}", slug, err);
+    }
+
+    let mut results = query_questions(QuestionOptions {
+        user,
+        diff: None,
+        topics: None,
+        solved: None,
+        source_ids: None,
+        starred: None,
+        range: Some(vec![(the_qid, the_qid)]),
+        include_archived: Some(true),
+        ..QuestionOptions::default()
+    }).await?;
+
+    results.remove(&the_qid).ok_or_else(|| format!("question {} missing after upsert", the_qid).into())
+}
+
+pub async fn fetch_referenced_question(
+    lc: &LeetCodeSource,
+    question_ref: &QuestionRef
+) -> Result<SourceQuestion, Box<dyn std::error::Error>> {
+    // numbers always go through LC's search. the db only keeps the internal
+    // questionId, which isn't the number LC shows
+    let slug = match question_ref {
+        QuestionRef::Slug(slug) => slug.clone(),
+        QuestionRef::Number(n) => {
+            return lc.fetch_by_number(*n).await?
+                .ok_or_else(|| format!("no leetcode question #{}", n).into());
+        }
+    };
+
+    lc.fetch_metadata(&slug).await
+        .map_err(|err| format!("could not fetch leetcode question {}: {}", slug, err).into())
+}
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::db_connect_in_memory;
+    use diesel::prelude::*;
+    use crate::db::models::Question;
+    use crate::init::lib::QuestionChange;
+    use crate::network::mock_leetcode::{start_mock_leetcode, MockFaults};
+
+    #[test]
+    fn test_parse_question_ref() {
+        let two_sum = Ok(QuestionRef::Slug("two-sum".to_string()));
+        for input in [
+            "https://leetcode.com/problems/two-sum/",
+            "https://leetcode.com/problems/two-sum/description/?envType=study-plan",
+            "leetcode.com/problems/two-sum",
+            "https://leetcode.cn/problems/two-sum/solutions/",
+            "http://www.leetcode.com/problems/Two-Sum#comments",
+            "  two-sum ",
+        ] {
+            assert_eq!(parse_question_ref(input).map_err(|e| e.to_string()), two_sum, "{}", input);
+        }
+        assert_eq!(parse_question_ref("#123").unwrap(), QuestionRef::Number(123));
+        assert_eq!(parse_question_ref("3sum").unwrap(), QuestionRef::Slug("3sum".to_string()));
+
+        for bad in ["", "#", "#-1", "#abc", "123", "two sum", "https://example.com/problems/two-sum",
+                    "https://leetcode.com/contest/weekly-contest-300/", "https://leetcode.com/problems/"] {
+            assert!(parse_question_ref(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_upsert_referenced_question() {
+        let server = start_mock_leetcode(MockFaults::default()).await;
+        let lc = LeetCodeSource::with_endpoint(&server.url);
+        let conn = db_connect_in_memory();
+
+        let by_slug = fetch_referenced_question(&lc, &QuestionRef::Slug("two-sum".to_string())).await.unwrap();
+        assert_eq!(by_slug.source_qid, 1);
+        let (two_sum, change) = upsert_source_question(&conn, LEETCODE_SOURCE_ID, by_slug.clone()).unwrap();
+        assert_eq!(change, QuestionChange::Inserted);
+        assert_eq!(upsert_source_question(&conn, LEETCODE_SOURCE_ID, by_slug).unwrap(), (two_sum, QuestionChange::Unchanged));
+
+        let by_number = fetch_referenced_question(&lc, &QuestionRef::Number(2)).await.unwrap();
+        assert_eq!(by_number.title_slug, "add-two-numbers");
+        let (add_two, change) = upsert_source_question(&conn, LEETCODE_SOURCE_ID, by_number).unwrap();
+        assert_eq!(change, QuestionChange::Inserted);
+        let stored: Question = crate::db::schema::question::table.find(add_two).first(&conn).unwrap();
+        assert_eq!((stored.source_qid, stored.archived), (Some(2), false));
+
+        assert!(fetch_referenced_question(&lc, &QuestionRef::Number(99999)).await.is_err());
+        assert!(fetch_referenced_question(&lc, &QuestionRef::Slug("no-such-question".to_string())).await.is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, Manager};
+use tokio::runtime::Handle;
+use crate::db::lib::db_connect;
+use crate::init::lib::{get_questions_on_init, CatalogueProgress};
+use crate::prompts::assets::{collect_orphaned_assets, AssetStore};
+use crate::prompts::prefetch::{start_prompt_prefetch, PromptPrefetch};
+use crate::prompts::structure::refresh_structures;
+
+// migrations and the question catalogue preload run in the background so the
+// window opens right away. the frontend can poll get_startup_status or listen
+// for startup-status events to show a "syncing catalogue" state, with the
+// question list's page progress while it comes in
+
+pub const STARTUP_STATUS_EVENT: &str = "startup-status";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupPhase {
+    Pending,
+    Migrating,
+    SyncingCatalogue,
+    Ready,
+    Failed,
+}
+
+// error is set when migrations failed (phase failed) or when the catalogue
+// could not be synced (phase ready, app still usable with what is in the db)
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StartupStatus {
+    pub phase: StartupPhase,
+    pub error: Option<String>,
+    pub catalogue: Option<CatalogueProgress>,
+}
+
+impl StartupStatus {
+    // commands may use the db once migrations are through, the catalogue
+    // sync can still be running
+    pub fn db_ready(&self) -> bool {
+        matches!(self.phase, StartupPhase::SyncingCatalogue | StartupPhase::Ready)
+    }
+}
+
+pub struct StartupTask {
+    status: Mutex<StartupStatus>,
+}
+
+impl Default for StartupTask {
+    fn default() -> Self {
+        StartupTask {
+            status: Mutex::new(StartupStatus { phase: StartupPhase::Pending, error: None, catalogue: None }),
+        }
+    }
+}
+
+impl StartupTask {
+    pub fn status(&self) -> StartupStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn update(&self, phase: StartupPhase, error: Option<String>) -> StartupStatus {
+        let mut status = self.status.lock().unwrap();
+        *status = StartupStatus { phase, error, catalogue: None };
+
+        status.clone()
+    }
+
+    pub fn set_catalogue_progress(&self, progress: CatalogueProgress) -> StartupStatus {
+        let mut status = self.status.lock().unwrap();
+        status.catalogue = Some(progress);
+
+        status.clone()
+    }
+}
+
+
+pub fn start_startup_task(app: AppHandle, runtime: Handle) {
+    // the preload holds a sqlite connection across awaits, so it gets a
+    // thread of its own and is driven to completion there
+    thread::spawn(move || runtime.block_on(run_startup(app)));
+}
+
+async fn run_startup(app: AppHandle) {
+    set_phase(&app, StartupPhase::Migrating, None);
+    let conn = db_connect();
+    if let Err(err) = diesel_migrations::run_pending_migrations(&conn) {
+        println!("migrations failed, {:?}", err);
+        set_phase(&app, StartupPhase::Failed, Some(format!("migrations failed: {}", err)));
+        return;
+    }
+    if let Err(err) = app.state::<PromptPrefetch>().load_settings(&conn) {
+        println!("could not load prompt prefetch settings, {:?}", err);
+    }
+    if let Err(err) = collect_orphaned_assets(&conn, &AssetStore::from_env()) {
+        println!("could not clean up prompt image cache, {:?}", err);
+    }
+    // prompts are extracted as they are stored, this catches ones stored
+    // before that or by an older parser
+    if let Err(err) = refresh_structures(&conn, None) {
+        println!("could not extract prompt structures, {:?}", err);
+    }
+
+    set_phase(&app, StartupPhase::SyncingCatalogue, None);
+    let on_progress = |progress: CatalogueProgress| {
+        emit_status(&app, app.state::<StartupTask>().set_catalogue_progress(progress));
+    };
+    match get_questions_on_init(&conn, &on_progress).await {
+        Ok(_) => set_phase(&app, StartupPhase::Ready, None),
+        Err(err) => {
+            println!("leetcode question preload failed, {:?}", err);
+            set_phase(&app, StartupPhase::Ready, Some(format!("catalogue sync failed: {}", err)));
+        }
+    }
+
+    start_prompt_prefetch(app);
+}
+
+fn set_phase(app: &AppHandle, phase: StartupPhase, error: Option<String>) {
+    emit_status(app, app.state::<StartupTask>().update(phase, error));
+}
+
+fn emit_status(app: &AppHandle, status: StartupStatus) {
+    if let Err(err) = app.emit_all(STARTUP_STATUS_EVENT, status) {
+        println!("could not emit startup status: {:?}", err);
+    }
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_startup_task_status() {
+        let task = StartupTask::default();
+        assert_eq!(task.status().phase, StartupPhase::Pending);
+        assert!(!task.status().db_ready());
+        assert!(!task.update(StartupPhase::Migrating, None).db_ready());
+        assert!(!task.update(StartupPhase::Failed, Some("migrations failed".to_string())).db_ready());
+
+        let updated = task.update(StartupPhase::Ready, Some("catalogue sync failed: offline".to_string()));
+        assert_eq!(task.status(), updated);
+        assert_eq!(serde_json::to_value(&updated).unwrap(), serde_json::json!({
+            "phase": "ready",
+            "error": "catalogue sync failed: offline",
+            "catalogue": null,
+        }));
+
+        assert!(task.update(StartupPhase::SyncingCatalogue, None).db_ready());
+        let progress = CatalogueProgress { source: "leetcode".to_string(), page: 2, fetched: 200, total: 3000 };
+        assert_eq!(task.set_catalogue_progress(progress.clone()).catalogue, Some(progress));
+        assert_eq!(task.update(StartupPhase::Ready, None).catalogue, None);
+        assert_eq!(serde_json::to_value(StartupPhase::SyncingCatalogue).unwrap(), "syncing_catalogue");
+    }
+}
@@ -12,12 +12,49 @@ extern crate dotenv;
 
 mod db;
 mod network;
+mod notebook;
 mod init;
+mod prompts;
+mod relations;
+mod review;
+mod runner;
+mod sources;
+mod transfer;
 
 use std::collections::HashMap;
 use db::lib::{db_connect, QuestionQueryResult, query_questions, QuestionOptions};
-use init::lib::get_lc_questions_on_init;
+use init::lib::{CatalogueProgress, SyncSummary, CATALOGUE_PROGRESS_EVENT};
+use init::startup::{start_startup_task, StartupStatus, StartupTask};
+use notebook::starter::{SolutionEntry, StarterCode};
+use notebook::templates::{CodeTemplateInput, UserCodeTemplate};
+use prompts::assets::{
+    collect_orphaned_assets,
+    load_asset,
+    select_asset_status,
+    set_asset_cap,
+    AssetCacheStatus,
+    AssetStore,
+    ASSET_SCHEME
+};
+use prompts::detail::{PromptFetches, QuestionDetail};
+use prompts::prefetch::{PrefetchStatus, PromptPrefetch};
+use prompts::structure::{select_complexity_cards, select_structured_prompt, ComplexityCard, StructuredPrompt};
+use relations::graph::{RelatedQuestion, UnsolvedNeighbor};
+use review::deck::SavedDeck;
+use review::scheduler::ScheduledCard;
+use runner::cases::{TestCaseInput, UserTestCase};
+use runner::lib::{RunCase, RunReport};
+use sources::feed::FeedImportSummary;
+use transfer::backup::{BackupSummary, RestoreMode};
+use transfer::markdown::DeckSyncSummary;
+use transfer::merge::MergeReport;
+use transfer::obsidian::VaultSummary;
+use transfer::study_sheet::StudySheetSummary;
 use serde::Serialize;
+use tauri::Manager;
+
+// the only command that works before startup migrations are done
+const UNGATED_COMMANDS: [&str; 1] = ["get_startup_status"];
 
 
 
@@ -35,21 +72,31 @@ struct QuestionCommandResult {
 }
 
 
-async fn preload_lc_questions_into_db() -> CommandResult {
-    let conn = db_connect();
-    let q_check = get_lc_questions_on_init(&conn).await;
-    let mut message: String = "Successfully preloaded leetcode questions".to_string();
-    let mut status: i32 = 200;
-    match q_check {
-        Ok(_) => {}
-        Err(err) => {
-            println!("leetcode question preload failed, {:?}", err);
-            message = "failed to preload leetcode questions".to_string();
-            status = 500;
-        }
-    }
+#[derive(Serialize)]
+struct BackupCommandResult {
+    data: Option<BackupSummary>,
+    result: CommandResult,
+}
+
+
+#[derive(Serialize)]
+struct StartupCommandResult {
+    data: StartupStatus,
+    result: CommandResult,
+}
+
 
-    CommandResult { message, status }
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_startup_status(app: tauri::AppHandle) -> StartupCommandResult {
+    // migrations + catalogue preload progress, also sent as startup-status events
+    StartupCommandResult {
+        data: app.state::<StartupTask>().status(),
+        result: CommandResult {
+            message: "startup status query successful".to_string(),
+            status: 200,
+        },
+    }
 }
 
 
@@ -80,14 +127,1117 @@ async fn get_questions(options: QuestionOptions) -> QuestionCommandResult {
 }
 
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+#[derive(Serialize)]
+struct QuestionDetailCommandResult {
+    data: Option<QuestionDetail>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_question_detail(app: tauri::AppHandle, qid: i32, user: Option<i32>) -> QuestionDetailCommandResult {
+    // fills in the prompt on first open if init didn't fetch it, in the
+    // user's prompt language when there is a translation
+    let fetches = app.state::<PromptFetches>();
+    let detail_result = prompts::translation::get_localized_detail(&fetches, user, qid).await;
+    let mut message: String = "question detail query successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<QuestionDetail> = None;
+    match detail_result {
+        Ok(detail) => { data = Some(detail); }
+        Err(err) => {
+            println!("could not load question detail {}: {:?}", qid, err);
+            message = "question detail query failed".to_string();
+            status = 500;
+        }
+    }
+
+    QuestionDetailCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct AddQuestionCommandResult {
+    data: Option<QuestionQueryResult>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn add_question_from_url(app: tauri::AppHandle, user: i32, url: String) -> AddQuestionCommandResult {
+    // url can also be a bare slug or "#123"
+    let fetches = app.state::<PromptFetches>();
+    let add_result = init::single::add_question_from_url(&fetches, user, &url).await
+        .map_err(|err| err.to_string());
+    let mut message: String = "question added".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<QuestionQueryResult> = None;
+    match add_result {
+        Ok(added) => { data = Some(added); }
+        Err(err) => {
+            println!("could not add question from {}: {}", url, err);
+            message = format!("adding question failed: {}", err);
+            status = 500;
+        }
+    }
+
+    AddQuestionCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct PromptLocaleCommandResult {
+    data: Option<String>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_prompt_locale(user: i32) -> PromptLocaleCommandResult {
+    let locale_result = prompts::translation::select_user_locale(&db_connect(), user)
+        .map_err(|err| err.to_string());
+    let mut message: String = "prompt locale query successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<String> = None;
+    match locale_result {
+        Ok(locale) => { data = Some(locale); }
+        Err(err) => {
+            println!("could not load prompt locale for user {}: {}", user, err);
+            message = "prompt locale query failed".to_string();
+            status = 500;
+        }
+    }
+
+    PromptLocaleCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn set_prompt_locale(user: i32, locale: String) -> PromptLocaleCommandResult {
+    let locale_result = prompts::translation::set_user_locale(&db_connect(), user, &locale)
+        .map_err(|err| err.to_string());
+    let mut message: String = "prompt locale update successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<String> = None;
+    match locale_result {
+        Ok(locale) => { data = Some(locale); }
+        Err(err) => {
+            println!("could not set prompt locale for user {}: {}", user, err);
+            message = format!("prompt locale update failed: {}", err);
+            status = 400;
+        }
+    }
+
+    PromptLocaleCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct PrefetchCommandResult {
+    data: Option<PrefetchStatus>,
+    result: CommandResult,
+}
+
+
+fn prefetch_command(
+    app: &tauri::AppHandle,
+    action: &str,
+    apply: impl FnOnce(&PromptPrefetch, &diesel::SqliteConnection) -> Result<(), Box<dyn std::error::Error>>
+) -> PrefetchCommandResult {
+    // shared by the prompt prefetch commands. applies a change, if any, and
+    // answers with the worker's status afterwards
+    let prefetch = app.state::<PromptPrefetch>();
+    let conn = db_connect();
+    let status_result = apply(&prefetch, &conn).and_then(|_| prefetch.status(&conn));
+    let mut message: String = format!("{} successful", action);
+    let mut status: i32 = 200;
+    let mut data: Option<PrefetchStatus> = None;
+    match status_result {
+        Ok(prefetch_status) => { data = Some(prefetch_status); }
+        Err(err) => {
+            println!("could not {}: {:?}", action, err);
+            message = format!("{} failed", action);
+            status = 500;
+        }
+    }
+
+    PrefetchCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_prompt_prefetch_status(app: tauri::AppHandle) -> PrefetchCommandResult {
+    prefetch_command(&app, "prompt prefetch status query", |_, _| Ok(()))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn pause_prompt_prefetch(app: tauri::AppHandle) -> PrefetchCommandResult {
+    prefetch_command(&app, "prompt prefetch pause", |p, conn| p.set_paused(conn, true))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn resume_prompt_prefetch(app: tauri::AppHandle) -> PrefetchCommandResult {
+    prefetch_command(&app, "prompt prefetch resume", |p, conn| p.set_paused(conn, false))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn set_prompt_prefetch_rate(app: tauri::AppHandle, per_minute: u32) -> PrefetchCommandResult {
+    prefetch_command(&app, "prompt prefetch rate change", |p, conn| p.set_rate(conn, per_minute))
+}
+
+
+#[derive(Serialize)]
+struct AssetCacheCommandResult {
+    data: Option<AssetCacheStatus>,
+    result: CommandResult,
+}
+
+
+fn asset_cache_command(
+    action: &str,
+    apply: impl FnOnce(&AssetStore, &diesel::SqliteConnection) -> Result<(), Box<dyn std::error::Error>>
+) -> AssetCacheCommandResult {
+    // shared by the prompt image cache commands, answers with the cache
+    // status after the change
+    let conn = db_connect();
+    let status_result = apply(&AssetStore::from_env(), &conn).and_then(|_| select_asset_status(&conn));
+    let mut message: String = format!("{} successful", action);
+    let mut status: i32 = 200;
+    let mut data: Option<AssetCacheStatus> = None;
+    match status_result {
+        Ok(cache_status) => { data = Some(cache_status); }
+        Err(err) => {
+            println!("could not {}: {:?}", action, err);
+            message = format!("{} failed", action);
+            status = 500;
+        }
+    }
+
+    AssetCacheCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_prompt_asset_status() -> AssetCacheCommandResult {
+    asset_cache_command("prompt image cache status query", |_, _| Ok(()))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn set_prompt_asset_limit(max_bytes: i64) -> AssetCacheCommandResult {
+    asset_cache_command("prompt image cache limit change", |store, conn| {
+        set_asset_cap(conn, store, max_bytes)?;
+        Ok(())
+    })
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn collect_prompt_assets() -> AssetCacheCommandResult {
+    asset_cache_command("prompt image cache cleanup", |store, conn| {
+        collect_orphaned_assets(conn, store)?;
+        Ok(())
+    })
+}
+
+
+fn serve_prompt_asset(app: &tauri::AppHandle, request: &tauri::http::Request) ->
+Result<tauri::http::Response, Box<dyn std::error::Error>> {
+    // answers prompt-asset:// urls in cached prompts from the asset store
+    if !app.state::<StartupTask>().status().db_ready() {
+        return tauri::http::ResponseBuilder::new().status(503).body(vec![]);
+    }
+    match load_asset(&db_connect(), &AssetStore::from_env(), request.uri())? {
+        Some(asset) => tauri::http::ResponseBuilder::new()
+            .status(200)
+            .mimetype(&asset.mime)
+            .header("Cache-Control", "max-age=31536000, immutable")
+            .body(asset.bytes),
+        None => tauri::http::ResponseBuilder::new().status(404).body(vec![]),
+    }
+}
+
+
+#[derive(Serialize)]
+struct StructuredPromptCommandResult {
+    data: Option<StructuredPrompt>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_question_structure(qid: i32) -> StructuredPromptCommandResult {
+    // examples and constraints for the card front, none while the prompt
+    // hasn't been fetched
+    let structure_result = select_structured_prompt(&db_connect(), qid);
+    let mut message: String = "question structure query successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<StructuredPrompt> = None;
+    match structure_result {
+        Ok(structured) => { data = structured; }
+        Err(err) => {
+            println!("could not query question structure: {:?}", err);
+            message = "question structure query failed".to_string();
+            status = 500;
+        }
+    }
+
+    StructuredPromptCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct ComplexityCardsCommandResult {
+    data: Vec<ComplexityCard>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_complexity_cards() -> ComplexityCardsCommandResult {
+    let cards_result = select_complexity_cards(&db_connect());
+    let mut message: String = "complexity cards query successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Vec<ComplexityCard> = vec![];
+    match cards_result {
+        Ok(cards) => { data = cards; }
+        Err(err) => {
+            println!("could not query complexity cards: {:?}", err);
+            message = "complexity cards query failed".to_string();
+            status = 500;
+        }
+    }
+
+    ComplexityCardsCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct RunSolutionCommandResult {
+    data: Option<RunReport>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn run_solution(
+    user: Option<i32>,
+    qid: i32,
+    language: String,
+    code: String,
+    cases: Option<Vec<RunCase>>
+) -> RunSolutionCommandResult {
+    // cases default to the question's examples and the user's test cases. a
+    // compile error still comes back as data, it is the solution that failed
+    // not the command
+    let run_result = runner::lib::run_solution(user, qid, &language, &code, cases).await
+        .map_err(|err| err.to_string());
+    let mut message: String = "solution run successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<RunReport> = None;
+    match run_result {
+        Ok(report) => { data = Some(report); }
+        Err(err) => {
+            println!("could not run solution: {:?}", err);
+            message = format!("solution run failed: {}", err);
+            status = 500;
+        }
+    }
+
+    RunSolutionCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct TestCasesCommandResult {
+    data: Vec<UserTestCase>,
+    result: CommandResult,
+}
+
+
+fn test_case_command(
+    action: &str,
+    user: i32,
+    apply: impl FnOnce(&diesel::SqliteConnection) -> Result<i32, Box<dyn std::error::Error>>
+) -> TestCasesCommandResult {
+    // shared by the test case commands. apply returns the qid it touched,
+    // the answer is that question's test cases afterwards
     let conn = db_connect();
-    diesel_migrations::run_pending_migrations(&conn).expect("migration error");
-    preload_lc_questions_into_db().await;
+    let cases_result = apply(&conn).and_then(|the_qid| runner::cases::select_test_cases(&conn, user, the_qid));
+    let mut message: String = format!("{} successful", action);
+    let mut status: i32 = 200;
+    let mut data: Vec<UserTestCase> = vec![];
+    match cases_result {
+        Ok(cases) => { data = cases; }
+        Err(err) => {
+            println!("could not {}: {:?}", action, err);
+            message = format!("{} failed: {}", action, err);
+            status = 400;
+        }
+    }
+
+    TestCasesCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_test_cases(user: i32, qid: i32) -> TestCasesCommandResult {
+    test_case_command("test case query", user, |_| Ok(qid))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn add_test_case(user: i32, qid: i32, case: TestCaseInput) -> TestCasesCommandResult {
+    test_case_command("test case creation", user, |conn| {
+        runner::cases::add_test_case(conn, user, qid, &case).map(|_| qid)
+    })
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn update_test_case(user: i32, tcid: i32, case: TestCaseInput) -> TestCasesCommandResult {
+    test_case_command("test case update", user, |conn| runner::cases::update_test_case(conn, user, tcid, &case))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn delete_test_case(user: i32, tcid: i32) -> TestCasesCommandResult {
+    test_case_command("test case deletion", user, |conn| runner::cases::delete_test_case(conn, user, tcid))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn mark_test_case_caught_bug(user: i32, tcid: i32, caught_bug: bool) -> TestCasesCommandResult {
+    test_case_command("test case update", user, |conn| runner::cases::mark_caught_bug(conn, user, tcid, caught_bug))
+}
+
+
+#[derive(Serialize)]
+struct PreferredLanguageCommandResult {
+    data: Option<String>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_preferred_language(user: i32) -> PreferredLanguageCommandResult {
+    let language_result = notebook::starter::select_preferred_language(&db_connect(), user)
+        .map_err(|err| err.to_string());
+    let mut message: String = "preferred language query successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<String> = None;
+    match language_result {
+        Ok(language) => { data = Some(language); }
+        Err(err) => {
+            println!("could not load preferred language for user {}: {}", user, err);
+            message = "preferred language query failed".to_string();
+            status = 500;
+        }
+    }
+
+    PreferredLanguageCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn set_preferred_language(user: i32, language: String) -> PreferredLanguageCommandResult {
+    let language_result = notebook::starter::set_preferred_language(&db_connect(), user, &language)
+        .map_err(|err| err.to_string());
+    let mut message: String = "preferred language update successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<String> = None;
+    match language_result {
+        Ok(language) => { data = Some(language); }
+        Err(err) => {
+            println!("could not set preferred language for user {}: {}", user, err);
+            message = format!("preferred language update failed: {}", err);
+            status = 400;
+        }
+    }
+
+    PreferredLanguageCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct StarterCodeCommandResult {
+    data: Option<StarterCode>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_starter_code(app: tauri::AppHandle, user: i32, qid: i32, language: Option<String>) -> StarterCodeCommandResult {
+    // language defaults to the user's preferred one. no data and a 200 means
+    // the question has no snippet in that language
+    let fetches = app.state::<PromptFetches>();
+    let starter_result = notebook::starter::get_starter_code(&fetches, user, qid, language).await
+        .map_err(|err| err.to_string());
+    let mut message: String = "starter code query successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<StarterCode> = None;
+    match starter_result {
+        Ok(starter) => { data = starter; }
+        Err(err) => {
+            println!("could not load starter code for question {}: {}", qid, err);
+            message = format!("starter code query failed: {}", err);
+            status = 500;
+        }
+    }
+
+    StarterCodeCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct SolutionEntryCommandResult {
+    data: Option<SolutionEntry>,
+    result: CommandResult,
+}
+
+
+fn solution_entry_command(action: &str, entry_result: Result<SolutionEntry, String>) -> SolutionEntryCommandResult {
+    let mut message: String = format!("{} successful", action);
+    let mut status: i32 = 200;
+    let mut data: Option<SolutionEntry> = None;
+    match entry_result {
+        Ok(entry) => { data = Some(entry); }
+        Err(err) => {
+            println!("could not {}: {}", action, err);
+            message = format!("{} failed: {}", action, err);
+            status = 400;
+        }
+    }
+
+    SolutionEntryCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn start_solution(app: tauri::AppHandle, user: i32, qid: i32, language: Option<String>) -> SolutionEntryCommandResult {
+    // a new solution entry pre-filled with the question's starter code
+    let fetches = app.state::<PromptFetches>();
+    let entry_result = notebook::starter::start_solution(&fetches, user, qid, language).await
+        .map_err(|err| err.to_string());
+
+    solution_entry_command("solution creation", entry_result)
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn insert_code_template(user: i32, sid: i32, ctid: i32) -> SolutionEntryCommandResult {
+    let entry_result = notebook::templates::insert_template(&db_connect(), user, sid, ctid)
+        .map_err(|err| err.to_string());
+
+    solution_entry_command("code template insertion", entry_result)
+}
+
+
+#[derive(Serialize)]
+struct CodeTemplatesCommandResult {
+    data: Vec<UserCodeTemplate>,
+    result: CommandResult,
+}
+
+
+fn code_template_command(
+    action: &str,
+    apply: impl FnOnce(&diesel::SqliteConnection) -> Result<Vec<UserCodeTemplate>, Box<dyn std::error::Error>>
+) -> CodeTemplatesCommandResult {
+    let templates_result = apply(&db_connect());
+    let mut message: String = format!("{} successful", action);
+    let mut status: i32 = 200;
+    let mut data: Vec<UserCodeTemplate> = vec![];
+    match templates_result {
+        Ok(templates) => { data = templates; }
+        Err(err) => {
+            println!("could not {}: {:?}", action, err);
+            message = format!("{} failed: {}", action, err);
+            status = 400;
+        }
+    }
+
+    CodeTemplatesCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_code_templates(user: i32, tid: Option<i32>) -> CodeTemplatesCommandResult {
+    code_template_command("code template query", |conn| notebook::templates::select_code_templates(conn, user, tid))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_question_templates(user: i32, qid: i32) -> CodeTemplatesCommandResult {
+    // templates filed under the question's topics
+    code_template_command("code template query", |conn| notebook::templates::select_question_templates(conn, user, qid))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn save_code_template(user: i32, template: CodeTemplateInput) -> CodeTemplatesCommandResult {
+    // answers with all of the user's templates
+    code_template_command("code template save", |conn| {
+        notebook::templates::save_code_template(conn, user, &template)?;
+        notebook::templates::select_code_templates(conn, user, None)
+    })
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn delete_code_template(user: i32, ctid: i32) -> CodeTemplatesCommandResult {
+    code_template_command("code template deletion", |conn| {
+        notebook::templates::delete_code_template(conn, user, ctid)?;
+        notebook::templates::select_code_templates(conn, user, None)
+    })
+}
+
+
+#[derive(Serialize)]
+struct RelatedQuestionsCommandResult {
+    data: Vec<RelatedQuestion>,
+    result: CommandResult,
+}
+
+
+fn related_questions_command(
+    action: &str,
+    run: impl FnOnce(&diesel::SqliteConnection) -> Result<Vec<RelatedQuestion>, Box<dyn std::error::Error>>
+) -> RelatedQuestionsCommandResult {
+    // shared by the commands that change or read a question's links. all of
+    // them answer with the question's related questions afterwards
+    let related_result = run(&db_connect());
+    let mut message: String = format!("{} successful", action);
+    let mut status: i32 = 200;
+    let mut data: Vec<RelatedQuestion> = vec![];
+    match related_result {
+        Ok(related) => { data = related; }
+        Err(err) => {
+            println!("could not {}: {:?}", action, err);
+            message = format!("{} failed", action);
+            status = 500;
+        }
+    }
+
+    RelatedQuestionsCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_related_questions(user: i32, qid: i32) -> RelatedQuestionsCommandResult {
+    related_questions_command("related questions query", |conn| {
+        relations::graph::select_related_questions(conn, user, qid)
+    })
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn link_questions(user: i32, qid: i32, related_qid: i32) -> RelatedQuestionsCommandResult {
+    related_questions_command("question link", |conn| {
+        relations::graph::add_manual_link(conn, qid, related_qid)?;
+        relations::graph::select_related_questions(conn, user, qid)
+    })
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn unlink_questions(user: i32, qid: i32, related_qid: i32) -> RelatedQuestionsCommandResult {
+    related_questions_command("question unlink", |conn| {
+        relations::graph::remove_manual_link(conn, qid, related_qid)?;
+        relations::graph::select_related_questions(conn, user, qid)
+    })
+}
+
+
+#[derive(Serialize)]
+struct UnsolvedNeighborsCommandResult {
+    data: Vec<UnsolvedNeighbor>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_unsolved_neighbors(user: i32) -> UnsolvedNeighborsCommandResult {
+    // unsolved questions linked to ones the user solved, closest fits first
+    let neighbors_result = relations::graph::select_unsolved_neighbors(&db_connect(), user);
+    let mut message: String = "unsolved neighbors query successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Vec<UnsolvedNeighbor> = vec![];
+    match neighbors_result {
+        Ok(neighbors) => { data = neighbors; }
+        Err(err) => {
+            println!("could not load unsolved neighbors for user {}: {:?}", user, err);
+            message = "unsolved neighbors query failed".to_string();
+            status = 500;
+        }
+    }
+
+    UnsolvedNeighborsCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct ReviewCommandResult {
+    data: Option<ScheduledCard>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn record_review(user: i32, qid: i32, grade: i32) -> ReviewCommandResult {
+    let review_result = review::scheduler::record_review(user, qid, grade).await;
+    let mut message: String = "review successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<ScheduledCard> = None;
+    match review_result {
+        Ok(state) => { data = Some(state); }
+        Err(err) => {
+            println!("could not record review of {}: {:?}", qid, err);
+            message = format!("review failed: {}", err);
+            status = 400;
+        }
+    }
+
+    ReviewCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct DueQuestionsCommandResult {
+    data: Vec<ScheduledCard>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_due_questions(user: i32) -> DueQuestionsCommandResult {
+    let due_result = review::scheduler::get_due_questions(user).await;
+    let mut message: String = "due questions query successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Vec<ScheduledCard> = vec![];
+    match due_result {
+        Ok(due) => { data = due; }
+        Err(err) => {
+            println!("could not load due questions for user {}: {:?}", user, err);
+            message = "due questions query failed".to_string();
+            status = 500;
+        }
+    }
+
+    DueQuestionsCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct SavedDecksCommandResult {
+    data: Vec<SavedDeck>,
+    result: CommandResult,
+}
+
+
+fn saved_deck_command(
+    action: &str,
+    user: i32,
+    apply: impl FnOnce(&diesel::SqliteConnection) -> Result<(), Box<dyn std::error::Error>>
+) -> SavedDecksCommandResult {
+    // shared by the saved deck commands, the answer is the user's decks
+    // after the change
+    let conn = db_connect();
+    let decks_result = apply(&conn).and_then(|_| review::deck::select_saved_decks(&conn, user));
+    let mut message: String = format!("{} successful", action);
+    let mut status: i32 = 200;
+    let mut data: Vec<SavedDeck> = vec![];
+    match decks_result {
+        Ok(decks) => { data = decks; }
+        Err(err) => {
+            println!("could not {}: {:?}", action, err);
+            message = format!("{} failed: {}", action, err);
+            status = 400;
+        }
+    }
+
+    SavedDecksCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn get_saved_decks(user: i32) -> SavedDecksCommandResult {
+    saved_deck_command("saved deck query", user, |_| Ok(()))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn create_saved_deck(user: i32, name: String) -> SavedDecksCommandResult {
+    saved_deck_command("saved deck creation", user, |conn| {
+        review::deck::create_saved_deck(conn, user, &name).map(|_| ())
+    })
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn delete_saved_deck(user: i32, did: i32) -> SavedDecksCommandResult {
+    saved_deck_command("saved deck deletion", user, |conn| review::deck::delete_saved_deck(conn, user, did))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn add_saved_deck_question(user: i32, did: i32, qid: i32) -> SavedDecksCommandResult {
+    saved_deck_command("saved deck update", user, |conn| review::deck::add_deck_question(conn, user, did, qid))
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn remove_saved_deck_question(user: i32, did: i32, qid: i32) -> SavedDecksCommandResult {
+    saved_deck_command("saved deck update", user, |conn| review::deck::remove_deck_question(conn, user, did, qid))
+}
+
+
+#[derive(Serialize)]
+struct SyncCommandResult {
+    data: Option<SyncSummary>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn sync_source(app: tauri::AppHandle, source_id: i32) -> SyncCommandResult {
+    // question list pages are sent as catalogue-progress events
+    let on_progress = |progress: CatalogueProgress| {
+        if let Err(err) = app.emit_all(CATALOGUE_PROGRESS_EVENT, progress) {
+            println!("could not emit catalogue progress: {:?}", err);
+        }
+    };
+    let sync_result = init::lib::sync_source(source_id, &on_progress).await;
+    let mut message: String = "source sync successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<SyncSummary> = None;
+    match sync_result {
+        Ok(summary) => { data = Some(summary); }
+        Err(err) => {
+            println!("could not sync source {}: {:?}", source_id, err);
+            message = "source sync failed".to_string();
+            status = 500;
+        }
+    }
+
+    SyncCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct FeedImportCommandResult {
+    data: Option<FeedImportSummary>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn import_feed(location: String) -> FeedImportCommandResult {
+    let import_result = sources::feed::import_feed(location.clone()).await;
+    let mut message: String = "feed import successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<FeedImportSummary> = None;
+    match import_result {
+        Ok(summary) => { data = Some(summary); }
+        Err(err) => {
+            println!("could not import feed {}: {:?}", location, err);
+            message = format!("feed import failed: {}", err);
+            status = 500;
+        }
+    }
+
+    FeedImportCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn export_backup(path: String) -> BackupCommandResult {
+    let export_result = transfer::backup::export_backup(path).await;
+    let mut message: String = "backup export successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<BackupSummary> = None;
+    match export_result {
+        Ok(summary) => { data = Some(summary); }
+        Err(err) => {
+            println!("could not export backup: {:?}", err);
+            message = "backup export failed".to_string();
+            status = 500;
+        }
+    }
+
+    BackupCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn import_backup(path: String, mode: RestoreMode) -> BackupCommandResult {
+    let import_result = transfer::backup::import_backup(path, mode).await;
+    let mut message: String = "backup import successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<BackupSummary> = None;
+    match import_result {
+        Ok(summary) => { data = Some(summary); }
+        Err(err) => {
+            println!("could not import backup: {:?}", err);
+            message = "backup import failed".to_string();
+            status = 500;
+        }
+    }
+
+    BackupCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct DeckSyncCommandResult {
+    data: Option<DeckSyncSummary>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn export_markdown_deck(user: i32, dir: String) -> DeckSyncCommandResult {
+    let export_result = transfer::markdown::export_markdown_deck(user, dir).await;
+    let mut message: String = "markdown deck export successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<DeckSyncSummary> = None;
+    match export_result {
+        Ok(summary) => { data = Some(summary); }
+        Err(err) => {
+            println!("could not export markdown deck: {:?}", err);
+            message = "markdown deck export failed".to_string();
+            status = 500;
+        }
+    }
+
+    DeckSyncCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn import_markdown_deck(user: i32, dir: String) -> DeckSyncCommandResult {
+    let import_result = transfer::markdown::import_markdown_deck(user, dir).await;
+    let mut message: String = "markdown deck import successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<DeckSyncSummary> = None;
+    match import_result {
+        Ok(summary) => { data = Some(summary); }
+        Err(err) => {
+            println!("could not import markdown deck: {:?}", err);
+            message = "markdown deck import failed".to_string();
+            status = 500;
+        }
+    }
+
+    DeckSyncCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct VaultCommandResult {
+    data: Option<VaultSummary>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn export_obsidian_vault(options: QuestionOptions, dir: String) -> VaultCommandResult {
+    let export_result = transfer::obsidian::export_obsidian_vault(options, dir).await;
+    let mut message: String = "obsidian vault export successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<VaultSummary> = None;
+    match export_result {
+        Ok(summary) => { data = Some(summary); }
+        Err(err) => {
+            println!("could not export obsidian vault: {:?}", err);
+            message = "obsidian vault export failed".to_string();
+            status = 500;
+        }
+    }
+
+    VaultCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct MergeCommandResult {
+    data: Option<MergeReport>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn merge_database(path: String) -> MergeCommandResult {
+    let merge_result = transfer::merge::merge_database(path).await;
+    let mut message: String = "database merge successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<MergeReport> = None;
+    match merge_result {
+        Ok(report) => { data = Some(report); }
+        Err(err) => {
+            println!("could not merge database: {:?}", err);
+            message = "database merge failed".to_string();
+            status = 500;
+        }
+    }
+
+    MergeCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[derive(Serialize)]
+struct StudySheetCommandResult {
+    data: Option<StudySheetSummary>,
+    result: CommandResult,
+}
+
+
+#[tauri::command]
+#[allow(dead_code)]
+async fn export_study_sheet(options: QuestionOptions, path: String) -> StudySheetCommandResult {
+    let export_result = transfer::study_sheet::export_study_sheet(options, path).await;
+    let mut message: String = "study sheet export successful".to_string();
+    let mut status: i32 = 200;
+    let mut data: Option<StudySheetSummary> = None;
+    match export_result {
+        Ok(summary) => { data = Some(summary); }
+        Err(err) => {
+            println!("could not export study sheet: {:?}", err);
+            message = "study sheet export failed".to_string();
+            status = 500;
+        }
+    }
+
+    StudySheetCommandResult { data, result: CommandResult { message, status } }
+}
+
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // migrations and the catalogue preload run after the window is up
+    let runtime = tokio::runtime::Handle::current();
+    let handler = tauri::generate_handler![
+        get_startup_status,
+        get_questions,
+        get_question_detail,
+        add_question_from_url,
+        get_prompt_locale,
+        set_prompt_locale,
+        get_prompt_prefetch_status,
+        pause_prompt_prefetch,
+        resume_prompt_prefetch,
+        set_prompt_prefetch_rate,
+        get_prompt_asset_status,
+        set_prompt_asset_limit,
+        collect_prompt_assets,
+        get_question_structure,
+        get_complexity_cards,
+        run_solution,
+        get_test_cases,
+        add_test_case,
+        update_test_case,
+        delete_test_case,
+        mark_test_case_caught_bug,
+        get_preferred_language,
+        set_preferred_language,
+        get_starter_code,
+        start_solution,
+        insert_code_template,
+        get_code_templates,
+        get_question_templates,
+        save_code_template,
+        delete_code_template,
+        get_related_questions,
+        link_questions,
+        unlink_questions,
+        get_unsolved_neighbors,
+        record_review,
+        get_due_questions,
+        get_saved_decks,
+        create_saved_deck,
+        delete_saved_deck,
+        add_saved_deck_question,
+        remove_saved_deck_question,
+        sync_source,
+        import_feed,
+        export_backup,
+        import_backup,
+        export_markdown_deck,
+        import_markdown_deck,
+        export_obsidian_vault,
+        merge_database,
+        export_study_sheet,
+    ];
 
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![get_questions])
+        .manage(StartupTask::default())
+        .manage(PromptFetches::default())
+        .manage(PromptPrefetch::default())
+        .register_uri_scheme_protocol(ASSET_SCHEME, serve_prompt_asset)
+        .setup(move |app| {
+            start_startup_task(app.handle(), runtime);
+            Ok(())
+        })
+        .invoke_handler(move |invoke: tauri::Invoke| {
+            // nothing may query the db before startup migrations are done,
+            // the frontend waits for the startup-status event meanwhile
+            let status = invoke.message.window().state::<StartupTask>().status();
+            if !status.db_ready() && !UNGATED_COMMANDS.contains(&invoke.message.command()) {
+                invoke.resolver.reject(format!("app is still starting up ({:?})", status.phase));
+                return;
+            }
+            handler(invoke)
+        })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
     Ok(())
@@ -0,0 +1,312 @@
+use dotenv::dotenv;
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use reqwest::StatusCode;
+
+// every request to a question source goes through one shared client. it
+// keeps connections alive between requests, times out instead of hanging,
+// backs off exponentially on 429 and 5xx, and spaces requests out with a
+// token bucket so a full catalogue import doesn't get the app rate limited.
+// settings come from .env, see HttpSettings::from_env
+
+const USER_AGENT: &str = concat!("dsa-flashcards/", env!("CARGO_PKG_VERSION"));
+const MAX_BACKOFF_MS: u64 = 30_000;
+
+static SHARED_CLIENT: Lazy<HttpClient> = Lazy::new(|| {
+    HttpClient::new(HttpSettings::from_env()).unwrap_or_else(|err| {
+        println!("could not build http client from .env settings, using defaults: {:?}", err);
+        HttpClient::new(HttpSettings::default()).expect("default http client")
+    })
+});
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpSettings {
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    // retries after the first attempt
+    pub max_retries: u32,
+    pub backoff_base: Duration,
+    pub requests_per_second: f64,
+    pub burst: u32,
+    pub user_agent: String,
+    pub proxy: Option<String>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            max_retries: 4,
+            backoff_base: Duration::from_millis(500),
+            requests_per_second: 4.0,
+            burst: 4,
+            user_agent: USER_AGENT.to_string(),
+            proxy: None,
+        }
+    }
+}
+
+impl HttpSettings {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+        let defaults = HttpSettings::default();
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+
+        HttpSettings {
+            timeout: var("HTTP_TIMEOUT_SECS").and_then(|v| v.parse().ok())
+                .map(Duration::from_secs).unwrap_or(defaults.timeout),
+            connect_timeout: var("HTTP_CONNECT_TIMEOUT_SECS").and_then(|v| v.parse().ok())
+                .map(Duration::from_secs).unwrap_or(defaults.connect_timeout),
+            max_retries: var("HTTP_MAX_RETRIES").and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_retries),
+            backoff_base: defaults.backoff_base,
+            requests_per_second: var("HTTP_REQUESTS_PER_SECOND").and_then(|v| v.parse().ok())
+                .filter(|rate: &f64| *rate > 0.0)
+                .unwrap_or(defaults.requests_per_second),
+            burst: defaults.burst,
+            user_agent: var("HTTP_USER_AGENT").unwrap_or(defaults.user_agent),
+            proxy: var("HTTP_PROXY_URL"),
+        }
+    }
+}
+
+
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    state: Mutex<(f64, Instant)>, // tokens left, last refill
+}
+
+impl TokenBucket {
+    pub fn new(refill_per_sec: f64, capacity: u32) -> Self {
+        let capacity = capacity.max(1) as f64;
+        TokenBucket { capacity, refill_per_sec, state: Mutex::new((capacity, Instant::now())) }
+    }
+
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                let refilled = state.0 + now.duration_since(state.1).as_secs_f64() * self.refill_per_sec;
+                *state = (refilled.min(self.capacity), now);
+                if state.0 >= 1.0 {
+                    state.0 -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - state.0) / self.refill_per_sec)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+
+pub struct HttpClient {
+    client: reqwest::Client,
+    settings: HttpSettings,
+    bucket: TokenBucket,
+}
+
+impl HttpClient {
+    pub fn new(settings: HttpSettings) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(settings.timeout)
+            .connect_timeout(settings.connect_timeout)
+            .user_agent(settings.user_agent.clone());
+        if let Some(proxy_url) = &settings.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy_url)?);
+        }
+        let bucket = TokenBucket::new(settings.requests_per_second, settings.burst);
+
+        Ok(HttpClient { client: builder.build()?, settings, bucket })
+    }
+
+    pub async fn post_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        body: &serde_json::Value
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let response = self.send(url, || self.client.post(url).json(body)).await?;
+        Ok(response.json::<T>().await?)
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, Box<dyn std::error::Error>> {
+        let response = self.send(url, || self.client.get(url)).await?;
+        Ok(response.json::<T>().await?)
+    }
+
+    pub async fn get_text(&self, url: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let response = self.send(url, || self.client.get(url)).await?;
+        Ok(response.text().await?)
+    }
+
+    pub async fn get_bytes(&self, url: &str, max_bytes: u64) ->
+    Result<(Option<String>, Vec<u8>), Box<dyn std::error::Error>> {
+        // content type comes along for whoever has to serve the bytes again
+        let response = self.send(url, || self.client.get(url)).await?;
+        if response.content_length().map(|len| len > max_bytes).unwrap_or(false) {
+            return Err(format!("{} is larger than {} bytes", url, max_bytes).into());
+        }
+        let content_type = response.headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let bytes = response.bytes().await?;
+        if bytes.len() as u64 > max_bytes {
+            return Err(format!("{} is larger than {} bytes", url, max_bytes).into());
+        }
+
+        Ok((content_type, bytes.to_vec()))
+    }
+
+    async fn send(
+        &self,
+        url: &str,
+        build: impl Fn() -> reqwest::RequestBuilder
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+        let mut attempt: u32 = 0;
+        loop {
+            self.bucket.acquire().await;
+            let sent = build().send().await;
+
+            let retry_after: Option<Duration> = match &sent {
+                Ok(res) if retryable_status(res.status()) => Some(retry_after(res).unwrap_or_else(|| self.backoff(attempt))),
+                Err(err) if err.is_timeout() || err.is_connect() => Some(self.backoff(attempt)),
+                _ => None,
+            };
+            match retry_after {
+                Some(wait) if attempt < self.settings.max_retries => {
+                    println!("request to {} failed ({}), retrying in {:?}",
+                        url, describe_failure(&sent), wait);
+                    drop(sent);
+                    attempt += 1;
+                    tokio::time::sleep(wait).await;
+                }
+                _ => return Ok(sent?.error_for_status()?),
+            }
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ms = (self.settings.backoff_base.as_millis() as u64)
+            .saturating_mul(1u64 << attempt.min(16))
+            .min(MAX_BACKOFF_MS);
+        Duration::from_millis(ms)
+    }
+}
+
+
+pub fn shared_client() -> &'static HttpClient {
+    &SHARED_CLIENT
+}
+
+fn retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+fn retry_after(res: &reqwest::Response) -> Option<Duration> {
+    // only the delay-seconds form, capped like our own backoff
+    res.headers().get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|secs| Duration::from_millis(secs.saturating_mul(1000).min(MAX_BACKOFF_MS)))
+}
+
+fn describe_failure(sent: &Result<reqwest::Response, reqwest::Error>) -> String {
+    match sent {
+        Ok(res) => res.status().to_string(),
+        Err(err) => err.to_string(),
+    }
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use crate::network::mock_server::{MockResponse, MockServer};
+
+    fn test_settings() -> HttpSettings {
+        HttpSettings {
+            backoff_base: Duration::from_millis(1),
+            requests_per_second: 1000.0,
+            burst: 10,
+            ..HttpSettings::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_post_json_retries_429_and_5xx() {
+        let server = MockServer::start(|_, i| match i {
+            0 => MockResponse::json(503, "{}"),
+            1 => MockResponse::json(429, "{}").with_header("retry-after", "0"),
+            _ => MockResponse::json(200, r#"{"ok": true}"#),
+        }).await;
+        let client = HttpClient::new(test_settings()).unwrap();
+
+        let res: serde_json::Value = client.post_json(&server.url, &json!({"query": "q"})).await.unwrap();
+        assert_eq!(res, json!({"ok": true}));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!((requests[0].method.as_str(), requests[0].path.as_str()), ("POST", "/"));
+        assert_eq!(requests[0].headers["user-agent"], USER_AGENT);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&requests[2].body).unwrap(), json!({"query": "q"}));
+    }
+
+    #[tokio::test]
+    async fn test_post_json_gives_up_and_skips_client_errors() {
+        let server = MockServer::start(|_, _| MockResponse::json(500, "{}")).await;
+        let client = HttpClient::new(HttpSettings { max_retries: 2, ..test_settings() }).unwrap();
+        assert!(client.post_json::<serde_json::Value>(&server.url, &json!({})).await.is_err());
+        assert_eq!(server.requests().len(), 3);
+
+        let not_found = MockServer::start(|_, _| MockResponse::json(404, "{}")).await;
+        assert!(client.post_json::<serde_json::Value>(&not_found.url, &json!({})).await.is_err());
+        assert_eq!(not_found.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_json_retries() {
+        let server = MockServer::start(|_, i| match i {
+            0 => MockResponse::json(502, "{}"),
+            _ => MockResponse::json(200, r#"{"status": "OK"}"#),
+        }).await;
+        let client = HttpClient::new(test_settings()).unwrap();
+
+        let url = format!("{}/problemset.problems", server.url);
+        let res: serde_json::Value = client.get_json(&url).await.unwrap();
+        assert_eq!(res, json!({"status": "OK"}));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!((requests[1].method.as_str(), requests[1].path.as_str()), ("GET", "/problemset.problems"));
+    }
+
+    #[tokio::test]
+    async fn test_token_bucket_spaces_out_requests() {
+        let bucket = TokenBucket::new(20.0, 2);
+        let start = Instant::now();
+        for _ in 0..4 {
+            bucket.acquire().await;
+        }
+        // the burst of 2 is free, the next 2 wait ~50ms each
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let client = HttpClient::new(HttpSettings {
+            backoff_base: Duration::from_millis(500), ..HttpSettings::default()
+        }).unwrap();
+        assert_eq!(client.backoff(0), Duration::from_millis(500));
+        assert_eq!(client.backoff(3), Duration::from_millis(4000));
+        assert_eq!(client.backoff(40), Duration::from_millis(MAX_BACKOFF_MS));
+    }
+}
@@ -1,31 +1,166 @@
+use dotenv::dotenv;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use serde_json::json;
-use crate::network::structs::{QuestionList, QuestionListResponse, PromptResponse};
+use std::env;
+use std::future::Future;
+use crate::network::structs::{
+    QuestionList,
+    QuestionListResponse,
+    PromptResponse,
+    ResponseQuestion,
+    ResponseQuestionDetails,
+    MetadataResponse,
+    DetailsResponse,
+    ResponseTranslation,
+    TranslationResponse,
+    CfResponse,
+    CfProblemset
+};
+use crate::network::client::shared_client;
 use crate::network::lc_graphql::*;
 
-pub async fn fetch_all_lc_questions() -> 
-Result<QuestionList, Box<dyn std::error::Error>> {
+const ENDPOINT_VAR: &str = "LEETCODE_GRAPHQL_ENDPOINT";
+const CN_ENDPOINT_VAR: &str = "LEETCODE_CN_GRAPHQL_ENDPOINT";
+const CF_ENDPOINT_VAR: &str = "CODEFORCES_API_ENDPOINT";
+pub const CF_API_ENDPOINT: &str = "https://codeforces.com/api";
+const NUMBER_SEARCH_LIMIT: i32 = 50;
+
+// reported after every page of a paged question list fetch. total is 0
+// while the provider hasn't said how many questions there are
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ListProgress {
+    pub page: usize,
+    pub fetched: usize,
+    pub total: usize,
+}
+
+
+pub fn lc_graphql_endpoint() -> String {
+    // LEETCODE_GRAPHQL_ENDPOINT in .env points the app somewhere other than
+    // leetcode.com, e.g. a local mock or a proxy
+    dotenv().ok();
+    env::var(ENDPOINT_VAR)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| LC_GRAPHQL_ENDPOINT.to_string())
+}
+
+
+pub fn lc_cn_graphql_endpoint() -> String {
+    dotenv().ok();
+    env::var(CN_ENDPOINT_VAR)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| LC_CN_GRAPHQL_ENDPOINT.to_string())
+}
+
+
+pub fn cf_api_endpoint() -> String {
+    dotenv().ok();
+    env::var(CF_ENDPOINT_VAR)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| CF_API_ENDPOINT.to_string())
+}
+
+
+pub async fn fetch_all_lc_questions(
+    endpoint: &str,
+    on_page: &(dyn Fn(ListProgress) + Send + Sync)
+) -> Result<QuestionList, Box<dyn std::error::Error>> {
+    fetch_question_pages(
+        |skip, limit| fetch_lc_question_page(endpoint, skip, limit),
+        LIST_PAGE_SIZE,
+        on_page
+    ).await
+}
+
+async fn fetch_lc_question_page(
+    endpoint: &str,
+    skip: i32,
+    limit: i32
+) -> Result<QuestionList, Box<dyn std::error::Error>> {
 
     let req_body = json!({
         "query": Q_LIST_QUERY,
         "variables": {
             "categorySlug": "",
-            "limit": LIST_LEN_LIMIT,
+            "skip": skip,
+            "limit": limit,
             "filters": {},
         }
     });
-    let res = reqwest::Client::new()
-        .post(LC_GRAPHQL_ENDPOINT)
-        .json(&req_body)
-        .send()
-        .await?;
-    let parsed = res.json::<QuestionListResponse>().await?;
+    let parsed = post_graphql::<QuestionListResponse>(endpoint, &req_body).await?;
 
     Ok(parsed.data.problemsetQuestionList)
 
 }
 
+pub async fn fetch_lc_question_by_number(
+    endpoint: &str,
+    number: i32
+) -> Result<Option<ResponseQuestion>, Box<dyn std::error::Error>> {
+    // LC has no lookup by frontend id, but searching the list for the number
+    // puts that question among the first results
+    let req_body = json!({
+        "query": Q_LIST_QUERY,
+        "variables": {
+            "categorySlug": "",
+            "skip": 0,
+            "limit": NUMBER_SEARCH_LIMIT,
+            "filters": { "searchKeywords": number.to_string() },
+        }
+    });
+    let parsed = post_graphql::<QuestionListResponse>(endpoint, &req_body).await?;
+
+    Ok(parsed.data.problemsetQuestionList.questions.into_iter()
+        .find(|q| q.frontendQuestionId.as_deref() == Some(number.to_string().as_str())))
+}
+
+pub async fn fetch_question_pages<F, Fut>(
+    fetch_page: F,
+    page_size: i32,
+    on_page: &(dyn Fn(ListProgress) + Send + Sync)
+) -> Result<QuestionList, Box<dyn std::error::Error>>
+where
+    F: Fn(i32, i32) -> Fut,
+    Fut: Future<Output = Result<QuestionList, Box<dyn std::error::Error>>>
+{
+    // pages through a question list with skip/limit until the reported total
+    // is reached, or until a short page if there is no total. retries are
+    // up to the http client, a page that still fails fails the whole fetch
+    let mut questions: Vec<ResponseQuestion> = vec![];
+    let mut total: i32 = 0;
+    let mut page: usize = 0;
+
+    loop {
+        let skip = questions.len() as i32;
+        let fetched = fetch_page(skip, page_size).await
+            .map_err(|err| format!("question list page at {} failed: {}", skip, err))?;
+
+        page += 1;
+        total = total.max(fetched.total);
+        let page_len = fetched.questions.len() as i32;
+        questions.extend(fetched.questions);
+        on_page(ListProgress {
+            page,
+            fetched: questions.len(),
+            total: total as usize,
+        });
+
+        let done = if total > 0 { questions.len() as i32 >= total } else { page_len < page_size };
+        if done || page_len == 0 {
+            break;
+        }
+    }
+
+    Ok(QuestionList { total: questions.len() as i32, questions })
+}
+
 pub async fn fetch_lc_question_prompt(
-    title_slug: &String
+    endpoint: &str,
+    title_slug: &str
 ) -> Result<String, Box<dyn std::error::Error>> {
 
     let query_string = Q_PROMPT_QUERY;
@@ -35,16 +170,89 @@ pub async fn fetch_lc_question_prompt(
             "titleSlug": title_slug,
         }
     });
-    let res = reqwest::Client::new()
-        .post(LC_GRAPHQL_ENDPOINT)
-        .json(&req_body)
-        .send()
-        .await?;
-    let parsed = res.json::<PromptResponse>().await?;
+    let parsed = post_graphql::<PromptResponse>(endpoint, &req_body).await?;
 
     Ok(parsed.data.question.content)
 }
 
+pub async fn fetch_lc_question_metadata(
+    endpoint: &str,
+    title_slug: &str
+) -> Result<ResponseQuestion, Box<dyn std::error::Error>> {
+
+    let req_body = json!({
+        "query": Q_METADATA_QUERY,
+        "variables": {
+            "titleSlug": title_slug,
+        }
+    });
+    let parsed = post_graphql::<MetadataResponse>(endpoint, &req_body).await?;
+
+    Ok(parsed.data.question)
+}
+
+pub async fn fetch_lc_question_details(
+    endpoint: &str,
+    title_slug: &str
+) -> Result<ResponseQuestionDetails, Box<dyn std::error::Error>> {
+
+    let req_body = json!({
+        "query": Q_DETAILS_QUERY,
+        "variables": {
+            "titleSlug": title_slug,
+        }
+    });
+    let parsed = post_graphql::<DetailsResponse>(endpoint, &req_body).await?;
+
+    Ok(parsed.data.question)
+}
+
+pub async fn fetch_lc_cn_translation(
+    endpoint: &str,
+    title_slug: &str
+) -> Result<Option<ResponseTranslation>, Box<dyn std::error::Error>> {
+
+    let req_body = json!({
+        "query": Q_TRANSLATION_QUERY,
+        "variables": {
+            "titleSlug": title_slug,
+        }
+    });
+    let parsed = post_graphql::<TranslationResponse>(endpoint, &req_body).await?;
+
+    Ok(parsed.data.question)
+}
+
+pub async fn fetch_cf_problemset(endpoint: &str) -> Result<CfProblemset, Box<dyn std::error::Error>> {
+    // the whole problemset comes in one response, there is no paging
+    let url = format!("{}/problemset.problems", endpoint.trim_end_matches('/'));
+    let res = shared_client().get_json::<CfResponse<CfProblemset>>(&url).await?;
+    if res.status != "OK" {
+        return Err(format!("codeforces api error: {}", res.comment.unwrap_or(res.status)).into());
+    }
+
+    res.result.ok_or_else(|| "codeforces api answered OK without a result".into())
+}
+
+async fn post_graphql<T: DeserializeOwned>(
+    endpoint: &str,
+    req_body: &serde_json::Value
+) -> Result<T, Box<dyn std::error::Error>> {
+    // graphql reports failures in an errors array next to (usually null)
+    // data, so surface those before trying to parse data
+    let res = shared_client()
+        .post_json::<serde_json::Value>(endpoint, req_body)
+        .await?;
+    if let Some(errors) = res.get("errors").and_then(|e| e.as_array()).filter(|e| !e.is_empty()) {
+        let messages: Vec<&str> = errors.iter()
+            .filter_map(|e| e.get("message").and_then(|m| m.as_str()))
+            .collect();
+        return Err(format!("graphql error: {}", messages.join("; ")).into());
+    }
+
+    Ok(serde_json::from_value::<T>(res)?)
+}
+
 
 
 ///////////////////////////////////////
@@ -52,30 +260,191 @@ pub async fn fetch_lc_question_prompt(
 ///////////////////////////////////////
 #[cfg(test)]
 mod test {
-    // note: these unit tests mainly serve as a quick check for breakage
-    //       from the Leetcode graphql API
+    // note: the *_live tests hit the real Leetcode graphql API and mainly
+    //       serve as a quick check for breakage from it. they are ignored
+    //       by default, run them with `cargo test -- --ignored`
 
     use super::*;
     use regex::Regex;
-    
+    use std::sync::Mutex;
+    use crate::network::mock_leetcode::{
+        start_mock_leetcode,
+        fixture_question_count,
+        MockFaults,
+        MALFORMED_SLUG
+    };
+    use crate::network::mock_server::{MockResponse, MockServer};
+
     #[tokio::test]
     async fn fetch_all_lc_questions_test() {
-        let fetched_question_list_res = fetch_all_lc_questions().await;
-        assert!(fetched_question_list_res.is_ok());
+        let server = start_mock_leetcode(MockFaults::default()).await;
+        let fetched_question_list = fetch_all_lc_questions(&server.url, &|_| {}).await.unwrap();
+        assert_eq!(fetched_question_list.questions.len(), fixture_question_count());
+        assert_eq!(fetched_question_list.questions[0].titleSlug, "two-sum");
+    }
+
+    #[tokio::test]
+    async fn fetch_lc_question_pages_test() {
+        // second page answers 503 once and is retried by the client
+        let server = start_mock_leetcode(MockFaults { flaky_list_pages: vec![2], ..MockFaults::default() }).await;
+        let progress = Mutex::new(vec![]);
+        let fetched = fetch_question_pages(
+            |skip, limit| fetch_lc_question_page(&server.url, skip, limit),
+            2,
+            &|p| progress.lock().unwrap().push(p.fetched)
+        ).await.unwrap();
 
-        let fetched_question_list = fetched_question_list_res.unwrap();
-        assert!(fetched_question_list.questions.len() > 0);
+        assert_eq!(fetched.questions.len(), fixture_question_count());
+        assert_eq!(*progress.lock().unwrap(), vec![2, 4, 5]);
+        let skips: Vec<i64> = server.requests().iter()
+            .map(|r| serde_json::from_str::<serde_json::Value>(&r.body).unwrap()["variables"]["skip"].as_i64().unwrap())
+            .collect();
+        assert_eq!(skips, vec![0, 2, 2, 4]);
+    }
+
+    #[tokio::test]
+    async fn fetch_all_lc_questions_malformed_test() {
+        let server = start_mock_leetcode(MockFaults { malformed_list: true, ..MockFaults::default() }).await;
+        assert!(fetch_all_lc_questions(&server.url, &|_| {}).await.is_err());
     }
 
     #[tokio::test]
     async fn fetch_lc_question_prompt_test() {
-        let mock_slug = "two-sum".to_string();
-        let fetched_prompt_res = fetch_lc_question_prompt(&mock_slug).await;
-        assert!(fetched_prompt_res.is_ok());
-        let fetched_prompt = fetched_prompt_res.unwrap();
+        let server = start_mock_leetcode(MockFaults::default()).await;
+        let fetched_prompt = fetch_lc_question_prompt(&server.url, "two-sum").await.unwrap();
+        assert!(contains_html_tags(fetched_prompt));
+
+        let missing = fetch_lc_question_prompt(&server.url, "no-such-question").await;
+        assert!(missing.unwrap_err().to_string().contains("That question does not exist."));
+        assert!(fetch_lc_question_prompt(&server.url, MALFORMED_SLUG).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn fetch_lc_question_metadata_test() {
+        let server = start_mock_leetcode(MockFaults::default()).await;
+        let fetched_metadata = fetch_lc_question_metadata(&server.url, "two-sum").await.unwrap();
+        assert!(fetched_metadata.questionId == "1");
+        assert!(!fetched_metadata.topicTags.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fetch_lc_question_by_number_test() {
+        let server = start_mock_leetcode(MockFaults::default()).await;
+        let found = fetch_lc_question_by_number(&server.url, 2).await.unwrap().unwrap();
+        assert_eq!(found.titleSlug, "add-two-numbers");
+        assert!(fetch_lc_question_by_number(&server.url, 99999).await.unwrap().is_none());
+
+        // newer questions have an internal id that differs from their number
+        let drifted = MockServer::start(|_, _| {
+            MockResponse::json(200, r#"{"data": {"problemsetQuestionList": {"total": 1, "questions": [
+                {"questionId": "2050", "frontendQuestionId": "1900", "title": "t", "titleSlug": "t",
+                 "difficulty": "Hard", "topicTags": []}
+            ]}}}"#)
+        }).await;
+        assert_eq!(fetch_lc_question_by_number(&drifted.url, 1900).await.unwrap().unwrap().titleSlug, "t");
+        assert!(fetch_lc_question_by_number(&drifted.url, 2050).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn fetch_lc_question_details_test() {
+        let server = start_mock_leetcode(MockFaults::default()).await;
+        let details = fetch_lc_question_details(&server.url, "two-sum").await.unwrap();
+        assert!(contains_html_tags(details.content.unwrap()));
+        assert!(!details.hints.is_empty());
+        assert!(details.codeSnippets.unwrap().iter().any(|s| s.langSlug == "python3"));
+        assert!(details.similarQuestions.unwrap().contains("3sum"));
+    }
+
+    #[tokio::test]
+    async fn fetch_lc_cn_translation_test() {
+        let server = start_mock_leetcode(MockFaults::default()).await;
+        let translation = fetch_lc_cn_translation(&server.url, "two-sum").await.unwrap().unwrap();
+        assert_eq!(translation.translatedTitle.as_deref(), Some("两数之和"));
+        assert!(contains_html_tags(translation.translatedContent.unwrap()));
+        assert!(fetch_lc_cn_translation(&server.url, "no-such-question").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn fetch_all_lc_questions_live() {
+        let fetched_question_list = fetch_all_lc_questions(LC_GRAPHQL_ENDPOINT, &|_| {}).await.unwrap();
+        // more than a single page's worth
+        assert!(fetched_question_list.questions.len() as i32 > LIST_PAGE_SIZE);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn fetch_lc_question_prompt_live() {
+        let fetched_prompt = fetch_lc_question_prompt(LC_GRAPHQL_ENDPOINT, "two-sum").await.unwrap();
         assert!(contains_html_tags(fetched_prompt));
     }
 
+    #[tokio::test]
+    #[ignore]
+    async fn fetch_lc_question_details_live() {
+        let details = fetch_lc_question_details(LC_GRAPHQL_ENDPOINT, "two-sum").await.unwrap();
+        assert!(!details.isPaidOnly);
+        assert!(details.stats.unwrap().contains("acRate"));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn fetch_lc_cn_translation_live() {
+        let translation = fetch_lc_cn_translation(LC_CN_GRAPHQL_ENDPOINT, "two-sum").await.unwrap().unwrap();
+        assert!(contains_html_tags(translation.translatedContent.unwrap()));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn fetch_lc_question_metadata_live() {
+        let fetched_metadata = fetch_lc_question_metadata(LC_GRAPHQL_ENDPOINT, "two-sum").await.unwrap();
+        assert!(fetched_metadata.questionId == "1");
+    }
+
+    #[tokio::test]
+    async fn fetch_question_pages_test() {
+        use std::sync::Mutex;
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let calls = AtomicU32::new(0);
+        let fake_page = |skip: i32, limit: i32| {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if call == 4 { // second page of the second fetch fails
+                    return Err("connection reset".into());
+                }
+                let questions = (skip..(skip + limit).min(7))
+                    .map(|n| ResponseQuestion {
+                        difficulty: "Easy".to_string(),
+                        questionId: n.to_string(),
+                        frontendQuestionId: Some(n.to_string()),
+                        title: format!("q {}", n),
+                        titleSlug: format!("q-{}", n),
+                        topicTags: vec![],
+                        acRate: None,
+                        paidOnly: None,
+                    })
+                    .collect();
+                Ok(QuestionList { total: 7, questions })
+            }
+        };
+        let progress = Mutex::new(vec![]);
+        let fetched = fetch_question_pages(&fake_page, 3, &|p| progress.lock().unwrap().push(p))
+            .await.unwrap();
+
+        let ids: Vec<String> = fetched.questions.iter().map(|q| q.questionId.clone()).collect();
+        assert_eq!(ids, (0..7).map(|n| n.to_string()).collect::<Vec<String>>());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        let fetched_counts: Vec<usize> = progress.lock().unwrap().iter().map(|p| p.fetched).collect();
+        assert_eq!(fetched_counts, vec![3, 6, 7]);
+        assert!(progress.lock().unwrap().iter().all(|p| p.total == 7));
+
+        // a failed page isn't retried on top of the client's retries
+        let failed = fetch_question_pages(&fake_page, 3, &|_| {}).await;
+        assert!(failed.unwrap_err().to_string().contains("page at 3 failed: connection reset"));
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+    }
+
     pub fn contains_html_tags(s: String) -> bool {
         let re = Regex::new(r"</?[a-z][\s\S]*>").unwrap();
         re.is_match(&s)
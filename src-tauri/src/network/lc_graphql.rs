@@ -1,21 +1,29 @@
 pub const LC_GRAPHQL_ENDPOINT: &str = "https://leetcode.com/graphql";
+// leetcode.cn has the same problems under the same slugs, plus translations
+pub const LC_CN_GRAPHQL_ENDPOINT: &str = "https://leetcode.cn/graphql";
 
-pub const LIST_LEN_LIMIT: i32 = 2500;
+pub const LIST_PAGE_SIZE: i32 = 500;
 
 pub const Q_LIST_QUERY: &str = "query \
 problemsetQuestionList(\
     $categorySlug: String, \
+    $skip: Int, \
     $limit: Int, \
     $filters: QuestionListFilterInput\
 ) { \
     problemsetQuestionList: questionList( \
         categorySlug: $categorySlug \
+        skip: $skip \
         limit: $limit \
         filters: $filters \
     ) { \
+        total: totalNum \
         questions: data { \
+            acRate \
             difficulty \
+            paidOnly: isPaidOnly \
             questionId \
+            frontendQuestionId \
             title \
             titleSlug \
             topicTags { name id slug }  \
@@ -29,4 +37,44 @@ questionData($titleSlug: String!) { \
     question(titleSlug: $titleSlug) { \
         content \
     }\
-}";
\ No newline at end of file
+}";
+
+
+pub const Q_METADATA_QUERY: &str = "query \
+questionData($titleSlug: String!) { \
+    question(titleSlug: $titleSlug) { \
+        acRate \
+        difficulty \
+        paidOnly: isPaidOnly \
+        questionId \
+        frontendQuestionId \
+        title \
+        titleSlug \
+        topicTags { name id slug }  \
+    }\
+}";
+
+
+pub const Q_DETAILS_QUERY: &str = "query \
+questionData($titleSlug: String!) { \
+    question(titleSlug: $titleSlug) { \
+        content \
+        isPaidOnly \
+        likes \
+        dislikes \
+        stats \
+        hints \
+        exampleTestcases \
+        similarQuestions \
+        codeSnippets { lang langSlug code } \
+    }\
+}";
+
+
+pub const Q_TRANSLATION_QUERY: &str = "query \
+questionTranslations($titleSlug: String!) { \
+    question(titleSlug: $titleSlug) { \
+        translatedTitle \
+        translatedContent \
+    }\
+}";
@@ -0,0 +1,108 @@
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use crate::network::mock_server::{MockResponse, MockServer, RecordedRequest};
+
+// stands in for the LC graphql endpoint in tests. answers the list, prompt,
+// details, metadata and (leetcode.cn) translation queries from the recorded
+// responses in fixtures/leetcode.
+// the list is paged with skip/limit like the real api, prompts that have no
+// fixture get LC's "does not exist" error, and MockFaults adds transient
+// 5xx pages and malformed bodies on top
+
+const QUESTION_LIST_FIXTURE: &str = include_str!("../../fixtures/leetcode/question_list.json");
+const PROMPTS_FIXTURE: &str = include_str!("../../fixtures/leetcode/prompts.json");
+const DETAILS_FIXTURE: &str = include_str!("../../fixtures/leetcode/details.json");
+const TRANSLATIONS_FIXTURE: &str = include_str!("../../fixtures/leetcode/translations.json");
+const NOT_FOUND_FIXTURE: &str = include_str!("../../fixtures/leetcode/question_not_found.json");
+const MALFORMED_FIXTURE: &str = include_str!("../../fixtures/leetcode/malformed.json");
+
+// prompt requests for this slug get a body that isn't valid json
+pub const MALFORMED_SLUG: &str = "malformed-prompt";
+
+#[derive(Debug, Default, Clone)]
+pub struct MockFaults {
+    // list pages, by skip, that answer 503 the first time they are requested
+    pub flaky_list_pages: Vec<i32>,
+    // every list page answers with a truncated body
+    pub malformed_list: bool,
+}
+
+pub async fn start_mock_leetcode(faults: MockFaults) -> MockServer {
+    let list: Value = serde_json::from_str(QUESTION_LIST_FIXTURE).unwrap();
+    let questions: Vec<Value> = list["data"]["problemsetQuestionList"]["questions"]
+        .as_array().unwrap().clone();
+    let prompts: Value = serde_json::from_str(PROMPTS_FIXTURE).unwrap();
+    let details: Value = serde_json::from_str(DETAILS_FIXTURE).unwrap();
+    let translations: Value = serde_json::from_str(TRANSLATIONS_FIXTURE).unwrap();
+    let failed_once: Mutex<HashSet<i32>> = Mutex::new(HashSet::new());
+
+    MockServer::start(move |request: &RecordedRequest, _| {
+        let body: Value = match serde_json::from_str(&request.body) {
+            Ok(body) => body,
+            Err(_) => return MockResponse::json(400, r#"{"errors": [{"message": "bad request"}]}"#),
+        };
+        let query = body["query"].as_str().unwrap_or("");
+        let variables = &body["variables"];
+
+        if query.contains("questionList") {
+            let skip = variables["skip"].as_i64().unwrap_or(0) as i32;
+            if faults.flaky_list_pages.contains(&skip) && failed_once.lock().unwrap().insert(skip) {
+                return MockResponse::json(503, "{}");
+            }
+            if faults.malformed_list {
+                return MockResponse::json(200, MALFORMED_FIXTURE);
+            }
+            let limit = variables["limit"].as_i64().unwrap_or(questions.len() as i64) as usize;
+            let page: Vec<&Value> = questions.iter().skip(skip as usize).take(limit).collect();
+            let res = json!({
+                "data": { "problemsetQuestionList": { "total": questions.len(), "questions": page } }
+            });
+            return MockResponse::json(200, &res.to_string());
+        }
+
+        let slug = variables["titleSlug"].as_str().unwrap_or("");
+        if slug == MALFORMED_SLUG {
+            return MockResponse::json(200, MALFORMED_FIXTURE);
+        }
+        if query.contains("translatedContent") {
+            // leetcode.cn answers a null question for slugs it doesn't know
+            let question = translations.get(slug).cloned().unwrap_or(Value::Null);
+            return MockResponse::json(200, &json!({ "data": { "question": question } }).to_string());
+        }
+        if query.contains("hints") {
+            // details are the prompt plus whatever details.json has for the
+            // slug. slugs without an entry answer like a sparse LC question
+            let content = match prompts.get(slug) {
+                Some(res) => res["data"]["question"]["content"].clone(),
+                None => return MockResponse::json(200, NOT_FOUND_FIXTURE),
+            };
+            let mut question = details.get(slug).cloned().unwrap_or_else(|| json!({
+                "isPaidOnly": false, "likes": null, "dislikes": null, "stats": null, "hints": [],
+                "exampleTestcases": null, "similarQuestions": "[]", "codeSnippets": []
+            }));
+            question["content"] = content;
+            return MockResponse::json(200, &json!({ "data": { "question": question } }).to_string());
+        }
+        if query.contains("content") {
+            return match prompts.get(slug) {
+                Some(res) => MockResponse::json(200, &res.to_string()),
+                None => MockResponse::json(200, NOT_FOUND_FIXTURE),
+            };
+        }
+        match questions.iter().find(|q| q["titleSlug"] == slug) {
+            Some(q) => MockResponse::json(200, &json!({ "data": { "question": q } }).to_string()),
+            None => MockResponse::json(200, NOT_FOUND_FIXTURE),
+        }
+    }).await
+}
+
+pub fn fixture_question_count() -> usize {
+    let list: Value = serde_json::from_str(QUESTION_LIST_FIXTURE).unwrap();
+    list["data"]["problemsetQuestionList"]["questions"].as_array().unwrap().len()
+}
+
+pub fn fixture_prompt_count() -> usize {
+    let prompts: Value = serde_json::from_str(PROMPTS_FIXTURE).unwrap();
+    prompts.as_object().unwrap().len()
+}
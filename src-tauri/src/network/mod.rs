@@ -1,3 +1,8 @@
+pub mod client;
 pub mod fetchers;
 pub mod structs;
-mod lc_graphql;
\ No newline at end of file
+mod lc_graphql;
+#[cfg(test)]
+pub mod mock_leetcode;
+#[cfg(test)]
+pub mod mock_server;
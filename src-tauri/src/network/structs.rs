@@ -12,13 +12,22 @@ pub struct ResponseTopic {
 pub struct ResponseQuestion {
     pub difficulty: String,
     pub questionId: String,
+    // the number LC shows, questionId is internal and drifts from it
+    #[serde(default)]
+    pub frontendQuestionId: Option<String>,
     pub title: String,
     pub titleSlug: String,
     pub topicTags: Vec<ResponseTopic>,
+    #[serde(default)]
+    pub acRate: Option<f64>,
+    #[serde(default)]
+    pub paidOnly: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QuestionList {
+    #[serde(default)]
+    pub total: i32,
     pub questions: Vec<ResponseQuestion>,
 }
 
@@ -46,3 +55,90 @@ pub struct QuestionPrompt {
 pub struct PromptResponse {
     pub data: QuestionPrompt
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuestionMetadata {
+    pub question: ResponseQuestion
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataResponse {
+    pub data: QuestionMetadata
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResponseCodeSnippet {
+    pub lang: String,
+    pub langSlug: String,
+    pub code: String,
+}
+
+// content and codeSnippets come back null for paid only questions. stats and
+// similarQuestions are json encoded strings
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResponseQuestionDetails {
+    pub content: Option<String>,
+    #[serde(default)]
+    pub isPaidOnly: bool,
+    pub likes: Option<i32>,
+    pub dislikes: Option<i32>,
+    pub stats: Option<String>,
+    #[serde(default)]
+    pub hints: Vec<String>,
+    pub exampleTestcases: Option<String>,
+    pub similarQuestions: Option<String>,
+    pub codeSnippets: Option<Vec<ResponseCodeSnippet>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuestionDetails {
+    pub question: ResponseQuestionDetails
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DetailsResponse {
+    pub data: QuestionDetails
+}
+
+// leetcode.cn answers a null question for slugs it doesn't have, and null
+// translations for problems nobody translated yet
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResponseTranslation {
+    pub translatedTitle: Option<String>,
+    pub translatedContent: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuestionTranslation {
+    pub question: Option<ResponseTranslation>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TranslationResponse {
+    pub data: QuestionTranslation
+}
+
+// codeforces api answers {"status": "OK", "result": ...} or
+// {"status": "FAILED", "comment": "why"}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CfResponse<T> {
+    pub status: String,
+    pub comment: Option<String>,
+    pub result: Option<T>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CfProblemset {
+    pub problems: Vec<CfProblem>,
+}
+
+// problems from gym/acmsguru sets come without a contest id
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CfProblem {
+    pub contestId: Option<i32>,
+    pub index: String,
+    pub name: String,
+    pub rating: Option<i32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
@@ -0,0 +1,2 @@
+pub mod starter;
+pub mod templates;
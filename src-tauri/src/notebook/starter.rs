@@ -0,0 +1,222 @@
+use serde::Serialize;
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::db_connect;
+use crate::db::models::{CodeSnippet, NewSolution, Solution};
+use crate::prompts::detail::{get_question_detail, PromptFetches};
+
+// starter code for new solutions. leetcode's codeSnippets come with the
+// question details and are stored per language in code_snippet. every user
+// has a preferred language (user.preferred_language), a new solution entry
+// starts out as the snippet in that language inside a fenced code block so
+// the signature is there to fill in
+
+pub const DEFAULT_LANGUAGE: &str = "python3";
+// leetcode's lang slugs
+pub const LANGUAGES: [&str; 19] = [
+    "cpp", "java", "python", "python3", "c", "csharp", "javascript", "typescript", "php",
+    "swift", "kotlin", "dart", "golang", "ruby", "scala", "rust", "racket", "erlang", "elixir",
+];
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StarterCode {
+    pub qid: i32,
+    pub lang: String,
+    pub lang_slug: String,
+    pub code: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SolutionEntry {
+    pub sid: i32,
+    pub qid: i32,
+    pub notes: String,
+}
+
+impl From<Solution> for SolutionEntry {
+    fn from(s: Solution) -> Self {
+        SolutionEntry { sid: s.sid, qid: s.qid, notes: s.notes }
+    }
+}
+
+
+pub fn select_preferred_language(conn: &SqliteConnection, the_uid: i32) ->
+Result<String, Box<dyn std::error::Error>> {
+    use crate::db::schema::user::dsl::*;
+
+    Ok(user.filter(uid.eq(the_uid))
+        .select(preferred_language)
+        .first::<String>(conn)
+        .optional()?
+        .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string()))
+}
+
+pub fn set_preferred_language(conn: &SqliteConnection, the_uid: i32, language: &str) ->
+Result<String, Box<dyn std::error::Error>> {
+    use crate::db::schema::user::dsl::*;
+
+    check_language(language)?;
+    let updated = diesel::update(user.filter(uid.eq(the_uid)))
+        .set(preferred_language.eq(language))
+        .execute(conn)?;
+    if updated == 0 {
+        return Err(format!("no user {}", the_uid).into());
+    }
+
+    select_preferred_language(conn, the_uid)
+}
+
+pub fn check_language(language: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if !LANGUAGES.contains(&language) {
+        return Err(format!("unknown language {}, expected one of {}", language, LANGUAGES.join(", ")).into());
+    }
+
+    Ok(())
+}
+
+
+pub async fn get_starter_code(fetches: &PromptFetches, the_uid: i32, the_qid: i32, language: Option<String>) ->
+Result<Option<StarterCode>, Box<dyn std::error::Error>> {
+    // snippets are part of the details fetched on first open, a question
+    // nobody opened yet gets them fetched here
+    if let Some(language) = &language {
+        check_language(language)?;
+    }
+    get_question_detail(fetches, the_qid).await?;
+
+    select_starter_code(&db_connect(), the_uid, the_qid, language.as_deref())
+}
+
+pub async fn start_solution(fetches: &PromptFetches, the_uid: i32, the_qid: i32, language: Option<String>) ->
+Result<SolutionEntry, Box<dyn std::error::Error>> {
+    // the entry is created even without a snippet (custom questions, paid
+    // only ones, offline before the details were fetched), it just starts
+    // out with whatever snippet is stored or empty then
+    if let Some(language) = &language {
+        check_language(language)?;
+    }
+    if let Err(err) = get_question_detail(fetches, the_qid).await.map_err(|err| err.to_string()) {
+        println!("could not fetch snippets for question {}, {}", the_qid, err);
+    }
+
+    let conn = db_connect();
+    let starter = select_starter_code(&conn, the_uid, the_qid, language.as_deref())?;
+    create_solution_entry(&conn, the_uid, the_qid, starter.as_ref())
+}
+
+pub fn select_starter_code(conn: &SqliteConnection, the_uid: i32, the_qid: i32, language: Option<&str>) ->
+Result<Option<StarterCode>, Box<dyn std::error::Error>> {
+    // python and python3 stand in for each other, leetcode has dropped the
+    // python 2 snippet on newer questions
+    use crate::db::schema::code_snippet::dsl::*;
+
+    let wanted = match language {
+        Some(language) => language.to_string(),
+        None => select_preferred_language(conn, the_uid)?,
+    };
+    let mut slugs = vec![wanted.as_str()];
+    match wanted.as_str() {
+        "python" => slugs.push("python3"),
+        "python3" => slugs.push("python"),
+        _ => {}
+    }
+
+    let snippets: Vec<CodeSnippet> = code_snippet
+        .filter(qid.eq(the_qid))
+        .filter(lang_slug.eq_any(&slugs))
+        .load(conn)?;
+    let snippet = slugs.iter().find_map(|slug| snippets.iter().find(|s| s.lang_slug == *slug));
+
+    Ok(snippet.map(|s| StarterCode {
+        qid: the_qid,
+        lang: s.lang.clone(),
+        lang_slug: s.lang_slug.clone(),
+        code: s.code.clone(),
+    }))
+}
+
+pub fn create_solution_entry(conn: &SqliteConnection, the_uid: i32, the_qid: i32, starter: Option<&StarterCode>) ->
+Result<SolutionEntry, Box<dyn std::error::Error>> {
+    use crate::db::schema::solution::dsl::*;
+
+    let found: i64 = crate::db::schema::question::table
+        .filter(crate::db::schema::question::qid.eq(the_qid))
+        .count()
+        .get_result(conn)?;
+    if found == 0 {
+        return Err(format!("no question with qid {}", the_qid).into());
+    }
+
+    diesel::insert_into(solution)
+        .values(&NewSolution {
+            uid: the_uid,
+            qid: the_qid,
+            notes: starter.map(|s| fenced(&s.lang_slug, &s.code)).unwrap_or_default(),
+        })
+        .execute(conn)?;
+    let created: Solution = solution.order_by(sid.desc()).first(conn)?;
+
+    Ok(created.into())
+}
+
+pub fn fenced(language: &str, code: &str) -> String {
+    // fence info strings are what markdown highlighters know the language as
+    let info = match language {
+        "python3" => "python",
+        "golang" => "go",
+        other => other,
+    };
+
+    format!("```{}\n{}\n```\n", info, code.trim_end())
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::db_connect_in_memory;
+    use crate::db::metadata::store_question_details;
+    use crate::db::schema;
+    use crate::sources::{SourceCodeSnippet, SourceDetails};
+
+    fn snippet(lang: &str, slug: &str, code: &str) -> SourceCodeSnippet {
+        SourceCodeSnippet { lang: lang.to_string(), lang_slug: slug.to_string(), code: code.to_string() }
+    }
+
+    #[test]
+    fn test_starter_code_in_preferred_language() {
+        let conn = db_connect_in_memory();
+        diesel::insert_into(schema::question::table)
+            .values(schema::question::title.eq("two sum"))
+            .execute(&conn).unwrap();
+        let the_qid: i32 = schema::question::table.select(schema::question::qid).first(&conn).unwrap();
+        store_question_details(&conn, the_qid, &SourceDetails {
+            code_snippets: vec![
+                snippet("Python3", "python3", "class Solution:\n    def twoSum(self, nums: List[int], target: int) -> List[int]:\n        "),
+                snippet("Go", "golang", "func twoSum(nums []int, target int) []int {\n    \n}"),
+            ],
+            ..SourceDetails::default()
+        }).unwrap();
+
+        assert_eq!(select_preferred_language(&conn, 1).unwrap(), DEFAULT_LANGUAGE);
+        let entry = create_solution_entry(&conn, 1, the_qid,
+            select_starter_code(&conn, 1, the_qid, None).unwrap().as_ref()).unwrap();
+        assert_eq!(entry.notes,
+            "```python\nclass Solution:\n    def twoSum(self, nums: List[int], target: int) -> List[int]:\n```\n");
+
+        assert!(set_preferred_language(&conn, 1, "go").is_err());
+        set_preferred_language(&conn, 1, "golang").unwrap();
+        let go = select_starter_code(&conn, 1, the_qid, None).unwrap().unwrap();
+        assert!(fenced(&go.lang_slug, &go.code).starts_with("```go\nfunc twoSum("));
+        // python 2 falls back on the python3 snippet, rust has none
+        assert_eq!(select_starter_code(&conn, 1, the_qid, Some("python")).unwrap().unwrap().lang_slug, "python3");
+        assert_eq!(select_starter_code(&conn, 1, the_qid, Some("rust")).unwrap(), None);
+
+        let empty = create_solution_entry(&conn, 1, the_qid, None).unwrap();
+        assert_eq!((empty.notes.as_str(), empty.sid > entry.sid), ("", true));
+        assert!(create_solution_entry(&conn, 1, the_qid + 1, None).is_err());
+    }
+}
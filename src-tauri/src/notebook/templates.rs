@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::models::{CodeTemplate, NewCodeTemplate, Solution};
+use crate::notebook::starter::{check_language, fenced, select_preferred_language, SolutionEntry};
+
+// a user's own code templates (a bfs skeleton, a union find class), filed
+// under a topic and a language. the templates offered for a question are the
+// ones filed under its topics, the user's preferred language first. a
+// template is unique per (user, topic, name, language), saving one again
+// under the same name replaces its code
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct CodeTemplateInput {
+    // set to edit an existing template
+    pub ctid: Option<i32>,
+    pub tid: i32,
+    pub name: String,
+    // the user's preferred language when missing
+    pub lang_slug: Option<String>,
+    pub code: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct UserCodeTemplate {
+    pub ctid: i32,
+    pub tid: i32,
+    pub topic: String,
+    pub name: String,
+    pub lang_slug: String,
+    pub code: String,
+}
+
+
+pub fn select_code_templates(conn: &SqliteConnection, the_uid: i32, the_tid: Option<i32>) ->
+Result<Vec<UserCodeTemplate>, Box<dyn std::error::Error>> {
+    use crate::db::schema::{code_template, topic};
+
+    let mut query = code_template::table
+        .inner_join(topic::table)
+        .filter(code_template::uid.eq(the_uid))
+        .into_boxed();
+    if let Some(the_tid) = the_tid {
+        query = query.filter(code_template::tid.eq(the_tid));
+    }
+    let rows: Vec<(CodeTemplate, String)> = query
+        .order_by((topic::name.asc(), code_template::name.asc(), code_template::lang_slug.asc()))
+        .select((code_template::all_columns, topic::name))
+        .load(conn)?;
+
+    Ok(rows.into_iter().map(|(t, topic_name)| UserCodeTemplate {
+        ctid: t.ctid,
+        tid: t.tid,
+        topic: topic_name,
+        name: t.name,
+        lang_slug: t.lang_slug,
+        code: t.code,
+    }).collect())
+}
+
+pub fn select_question_templates(conn: &SqliteConnection, the_uid: i32, the_qid: i32) ->
+Result<Vec<UserCodeTemplate>, Box<dyn std::error::Error>> {
+    use crate::db::schema::question_topic;
+
+    let topics: Vec<i32> = question_topic::table
+        .filter(question_topic::qid.eq(the_qid))
+        .select(question_topic::tid)
+        .load(conn)?;
+    let preferred = select_preferred_language(conn, the_uid)?;
+    let mut templates: Vec<UserCodeTemplate> = select_code_templates(conn, the_uid, None)?
+        .into_iter()
+        .filter(|t| topics.contains(&t.tid))
+        .collect();
+    // stable, so topic and name order holds within each group
+    templates.sort_by_key(|t| t.lang_slug != preferred);
+
+    Ok(templates)
+}
+
+pub fn save_code_template(conn: &SqliteConnection, the_uid: i32, template: &CodeTemplateInput) ->
+Result<i32, Box<dyn std::error::Error>> {
+    // returns the ctid of the saved template
+    use crate::db::schema::code_template::dsl::*;
+
+    let the_name = template.name.trim();
+    if the_name.is_empty() || template.code.trim().is_empty() {
+        return Err("a code template needs a name and some code".into());
+    }
+    let the_lang = match &template.lang_slug {
+        Some(the_lang) => the_lang.clone(),
+        None => select_preferred_language(conn, the_uid)?,
+    };
+    check_language(&the_lang)?;
+    let topic_found: i64 = crate::db::schema::topic::table
+        .filter(crate::db::schema::topic::tid.eq(template.tid))
+        .count()
+        .get_result(conn)?;
+    if topic_found == 0 {
+        return Err(format!("no topic with tid {}", template.tid).into());
+    }
+
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        let same_key: Option<i32> = code_template
+            .filter(uid.eq(the_uid))
+            .filter(tid.eq(template.tid))
+            .filter(name.eq(the_name))
+            .filter(lang_slug.eq(&the_lang))
+            .select(ctid)
+            .first(conn)
+            .optional()?;
+        let target = match template.ctid {
+            Some(the_ctid) => {
+                owned_template(conn, the_uid, the_ctid)?;
+                if same_key.map(|other| other != the_ctid).unwrap_or(false) {
+                    return Err(format!("there already is a {} template named {} in that topic", the_lang, the_name).into());
+                }
+                Some(the_ctid)
+            }
+            None => same_key,
+        };
+
+        match target {
+            Some(the_ctid) => {
+                diesel::update(code_template.find(the_ctid))
+                    .set((
+                        tid.eq(template.tid),
+                        name.eq(the_name),
+                        lang_slug.eq(&the_lang),
+                        code.eq(&template.code),
+                    ))
+                    .execute(conn)?;
+                Ok(the_ctid)
+            }
+            None => {
+                diesel::insert_into(code_template)
+                    .values(&NewCodeTemplate {
+                        uid: the_uid,
+                        tid: template.tid,
+                        name: the_name.to_string(),
+                        lang_slug: the_lang.clone(),
+                        code: template.code.clone(),
+                    })
+                    .execute(conn)?;
+                Ok(code_template.order_by(ctid.desc()).select(ctid).first(conn)?)
+            }
+        }
+    })
+}
+
+pub fn delete_code_template(conn: &SqliteConnection, the_uid: i32, the_ctid: i32) ->
+Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema::code_template::dsl::*;
+
+    owned_template(conn, the_uid, the_ctid)?;
+    diesel::delete(code_template.find(the_ctid)).execute(conn)?;
+
+    Ok(())
+}
+
+pub fn insert_template(conn: &SqliteConnection, the_uid: i32, the_sid: i32, the_ctid: i32) ->
+Result<SolutionEntry, Box<dyn std::error::Error>> {
+    // appends the template to a solution entry as its own code block
+    use crate::db::schema::solution::dsl::*;
+
+    let template = owned_template(conn, the_uid, the_ctid)?;
+    let entry: Solution = solution
+        .filter(sid.eq(the_sid))
+        .filter(uid.eq(the_uid))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| format!("user {} has no solution {}", the_uid, the_sid))?;
+
+    let mut new_notes = entry.notes.trim_end().to_string();
+    if !new_notes.is_empty() {
+        new_notes.push_str("\n\n");
+    }
+    new_notes.push_str(&fenced(&template.lang_slug, &template.code));
+    diesel::update(solution.find(the_sid))
+        .set(notes.eq(&new_notes))
+        .execute(conn)?;
+
+    Ok(SolutionEntry { sid: entry.sid, qid: entry.qid, notes: new_notes })
+}
+
+fn owned_template(conn: &SqliteConnection, the_uid: i32, the_ctid: i32) ->
+Result<CodeTemplate, Box<dyn std::error::Error>> {
+    use crate::db::schema::code_template::dsl::*;
+
+    code_template
+        .filter(ctid.eq(the_ctid))
+        .filter(uid.eq(the_uid))
+        .first::<CodeTemplate>(conn)
+        .optional()?
+        .ok_or_else(|| format!("user {} has no code template {}", the_uid, the_ctid).into())
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::{db_connect_in_memory, insert_topic};
+    use crate::db::models::NewQuestionTopic;
+    use crate::db::schema;
+    use crate::notebook::starter::{create_solution_entry, set_preferred_language};
+
+    #[test]
+    fn test_code_templates() {
+        let conn = db_connect_in_memory();
+        let graphs = insert_topic(&conn, "Graph").unwrap();
+        let unions = insert_topic(&conn, "Union Find").unwrap();
+        diesel::insert_into(schema::question::table)
+            .values(schema::question::title.eq("number of islands"))
+            .execute(&conn).unwrap();
+        let the_qid: i32 = schema::question::table.select(schema::question::qid).first(&conn).unwrap();
+        diesel::insert_into(schema::question_topic::table)
+            .values(&NewQuestionTopic { qid: the_qid, tid: graphs })
+            .execute(&conn).unwrap();
+
+        let bfs = CodeTemplateInput {
+            tid: graphs,
+            name: " bfs ".to_string(),
+            code: "q = deque([start])".to_string(),
+            ..CodeTemplateInput::default()
+        };
+        let bfs_ctid = save_code_template(&conn, 1, &bfs).unwrap();
+        let rust_bfs = CodeTemplateInput { lang_slug: Some("rust".to_string()), code: "let mut q = VecDeque::new();".to_string(), ..bfs.clone() };
+        save_code_template(&conn, 1, &rust_bfs).unwrap();
+        save_code_template(&conn, 1, &CodeTemplateInput { tid: unions, name: "dsu".to_string(), ..bfs.clone() }).unwrap();
+        // same name and language again replaces the code
+        let again = save_code_template(&conn, 1, &CodeTemplateInput { code: "q = deque()".to_string(), ..bfs.clone() }).unwrap();
+        assert_eq!(again, bfs_ctid);
+        assert!(save_code_template(&conn, 1, &CodeTemplateInput { tid: 999, ..bfs.clone() }).is_err());
+        assert!(save_code_template(&conn, 1, &CodeTemplateInput { lang_slug: Some("cobol".to_string()), ..bfs.clone() }).is_err());
+
+        assert_eq!(select_code_templates(&conn, 1, None).unwrap().len(), 3);
+        set_preferred_language(&conn, 1, "rust").unwrap();
+        let offered = select_question_templates(&conn, 1, the_qid).unwrap();
+        assert_eq!(offered.iter().map(|t| (t.topic.as_str(), t.lang_slug.as_str())).collect::<Vec<(&str, &str)>>(),
+            vec![("Graph", "rust"), ("Graph", "python3")]);
+        assert_eq!(offered[1].code, "q = deque()");
+
+        let entry = create_solution_entry(&conn, 1, the_qid, None).unwrap();
+        let entry = insert_template(&conn, 1, entry.sid, bfs_ctid).unwrap();
+        let entry = insert_template(&conn, 1, entry.sid, offered[0].ctid).unwrap();
+        assert_eq!(entry.notes, "```python\nq = deque()\n```\n\n```rust\nlet mut q = VecDeque::new();\n```\n");
+        assert!(insert_template(&conn, 2, entry.sid, bfs_ctid).is_err());
+
+        assert!(delete_code_template(&conn, 2, bfs_ctid).is_err());
+        delete_code_template(&conn, 1, bfs_ctid).unwrap();
+        assert_eq!(select_code_templates(&conn, 1, Some(graphs)).unwrap().len(), 1);
+    }
+}
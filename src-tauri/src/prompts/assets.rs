@@ -0,0 +1,618 @@
+use dotenv::dotenv;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::{db_connect, select_app_setting, set_app_setting};
+use crate::db::models::PromptAsset;
+use crate::network::client::shared_client;
+
+// images in prompts point at remote hosts (mostly assets.leetcode.com), so
+// cards lose them offline. before a prompt is stored its images are
+// downloaded into a content addressed store (a file per sha256 of the bytes)
+// and the html is rewritten to the prompt-asset protocol, which main.rs
+// serves from that store. prompt_asset maps every remote url to its file.
+// the store has a size cap, least recently shown images go first and the
+// prompts using them get their remote urls back
+
+pub const ASSET_SCHEME: &str = "prompt-asset";
+// custom protocols are reached differently by the windows webview
+#[cfg(not(windows))]
+pub const ASSET_URL_PREFIX: &str = "prompt-asset://localhost/";
+#[cfg(windows)]
+pub const ASSET_URL_PREFIX: &str = "https://prompt-asset.localhost/";
+
+const ASSET_DIR_VAR: &str = "PROMPT_ASSET_DIR";
+const DEFAULT_ASSET_DIR: &str = "prompt_assets";
+const MAX_BYTES_SETTING: &str = "prompt_asset_max_bytes";
+const DEFAULT_MAX_BYTES: i64 = 256 * 1024 * 1024;
+const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+// images are downloaded and recorded before the prompt pointing at them is
+// stored, so anything this recent may still be in flight and isn't collected
+const COLLECT_GRACE_SECS: i64 = 10 * 60;
+
+static IMG_SRC: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(<img\b[^>]*?\bsrc\s*=\s*)(?:"(https?://[^"]+)"|'(https?://[^']+)')"#).unwrap()
+});
+static ASSET_REF: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!("{}([0-9a-f]{{64}})", regex::escape(ASSET_URL_PREFIX))).unwrap()
+});
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AssetCacheStatus {
+    pub assets: i64,
+    pub bytes: i64,
+    pub max_bytes: i64,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct AssetCollection {
+    pub removed_assets: usize,
+    pub removed_files: usize,
+    pub evicted_assets: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadedImage {
+    pub source_url: String,
+    pub hash: String,
+    pub mime: String,
+    pub size: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ServedAsset {
+    pub mime: String,
+    pub bytes: Vec<u8>,
+}
+
+pub struct AssetStore {
+    dir: PathBuf,
+}
+
+impl AssetStore {
+    pub fn new(dir: PathBuf) -> Self {
+        AssetStore { dir }
+    }
+
+    pub fn from_env() -> Self {
+        // relative paths land next to the db, like DATABASE_URL
+        dotenv().ok();
+        let dir = env::var(ASSET_DIR_VAR).unwrap_or_else(|_| DEFAULT_ASSET_DIR.to_string());
+
+        AssetStore::new(PathBuf::from(dir))
+    }
+
+    fn path_for(&self, hash: &str) -> PathBuf {
+        // fanned out by the first byte so no directory gets huge
+        self.dir.join(&hash[..2]).join(hash)
+    }
+
+    pub fn read(&self, hash: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(fs::read(self.path_for(hash))?)
+    }
+
+    fn write(&self, hash: &str, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.path_for(hash);
+        if path.exists() {
+            return Ok(());
+        }
+        // written under another name first so a crash never leaves half a file
+        // behind the real one
+        fs::create_dir_all(path.parent().unwrap())?;
+        let partial = path.with_extension("partial");
+        fs::write(&partial, bytes)?;
+        fs::rename(&partial, &path)?;
+
+        Ok(())
+    }
+
+    fn remove(&self, hash: &str) -> Result<(), Box<dyn std::error::Error>> {
+        match fs::remove_file(self.path_for(hash)) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn stored_files(&self) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        let mut files: Vec<PathBuf> = vec![];
+        if !self.dir.exists() {
+            return Ok(files);
+        }
+        for fan_out in fs::read_dir(&self.dir)? {
+            let fan_out = fan_out?.path();
+            if fan_out.is_dir() {
+                for file in fs::read_dir(&fan_out)? {
+                    files.push(file?.path());
+                }
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+
+pub async fn cache_prompt_images(store: &AssetStore, html: &str) -> String {
+    // an image that can't be cached right now keeps its remote url, which
+    // still works online. connections are opened around the downloads
+    let urls = remote_images(html);
+    if urls.is_empty() {
+        return html.to_string();
+    }
+    let mut cached = match select_cached_images(&db_connect(), &urls) {
+        Ok(cached) => cached,
+        Err(err) => {
+            println!("could not look up cached prompt images: {:?}", err);
+            return html.to_string();
+        }
+    };
+
+    let missing: Vec<String> = urls.into_iter().filter(|url| !cached.contains_key(url)).collect();
+    let downloaded = download_images(store, &missing).await;
+    match record_images(&db_connect(), store, &downloaded) {
+        Ok(recorded) => cached.extend(recorded),
+        Err(err) => println!("could not record prompt images: {:?}", err),
+    }
+
+    rewrite_images(html, &cached)
+}
+
+pub fn remote_images(html: &str) -> Vec<String> {
+    let mut seen: HashSet<String> = HashSet::new();
+
+    IMG_SRC.captures_iter(html)
+        .filter_map(|caps| caps.get(2).or_else(|| caps.get(3)))
+        .map(|src| src.as_str().to_string())
+        .filter(|src| seen.insert(src.clone()))
+        .collect()
+}
+
+pub fn select_cached_images(conn: &SqliteConnection, urls: &[String]) ->
+Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    use crate::db::schema::prompt_asset::dsl::*;
+
+    let rows: Vec<(String, String)> = prompt_asset
+        .filter(source_url.eq_any(urls))
+        .select((source_url, hash))
+        .load(conn)?;
+
+    Ok(rows.into_iter().collect())
+}
+
+pub async fn download_images(store: &AssetStore, urls: &[String]) -> Vec<DownloadedImage> {
+    let mut downloaded: Vec<DownloadedImage> = vec![];
+    for url in urls {
+        // src attributes hold html escaped urls
+        let fetched = shared_client().get_bytes(&url.replace("&amp;", "&"), MAX_IMAGE_BYTES).await
+            .map_err(|err| err.to_string());
+        let (content_type, bytes) = match fetched {
+            Ok(fetched) => fetched,
+            Err(err) => {
+                println!("could not download prompt image {}: {}", url, err);
+                continue;
+            }
+        };
+        let mime = content_type.unwrap_or_default()
+            .split(';').next().unwrap_or_default()
+            .trim().to_lowercase();
+        if !mime.starts_with("image/") {
+            println!("prompt image {} is not an image ({})", url, mime);
+            continue;
+        }
+
+        let content_hash = hex_sha256(&bytes);
+        if let Err(err) = store.write(&content_hash, &bytes).map_err(|err| err.to_string()) {
+            println!("could not store prompt image {}: {}", url, err);
+            continue;
+        }
+        downloaded.push(DownloadedImage {
+            source_url: url.clone(),
+            hash: content_hash,
+            mime,
+            size: bytes.len() as i64,
+        });
+    }
+
+    downloaded
+}
+
+pub fn record_images(conn: &SqliteConnection, store: &AssetStore, downloaded: &[DownloadedImage]) ->
+Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    // images that were just downloaded are never the ones evicted to make
+    // room, the prompt about to be stored points at them
+    use crate::db::schema::prompt_asset::dsl::*;
+
+    let now = unix_now();
+    let rows: Vec<PromptAsset> = downloaded.iter().map(|d| PromptAsset {
+        source_url: d.source_url.clone(),
+        hash: d.hash.clone(),
+        mime: d.mime.clone(),
+        size: d.size,
+        last_used: now,
+    }).collect();
+    diesel::replace_into(prompt_asset).values(&rows).execute(conn)?;
+
+    let keep: HashSet<String> = downloaded.iter().map(|d| d.hash.clone()).collect();
+    enforce_asset_cap(conn, store, &keep)?;
+
+    Ok(downloaded.iter().map(|d| (d.source_url.clone(), d.hash.clone())).collect())
+}
+
+pub fn rewrite_images(html: &str, cached: &HashMap<String, String>) -> String {
+    IMG_SRC.replace_all(html, |caps: &regex::Captures| {
+        let src = caps.get(2).or_else(|| caps.get(3)).unwrap().as_str();
+        match cached.get(src) {
+            Some(asset_hash) => format!("{}\"{}{}\"", &caps[1], ASSET_URL_PREFIX, asset_hash),
+            None => caps[0].to_string(),
+        }
+    }).to_string()
+}
+
+
+pub fn load_asset(conn: &SqliteConnection, store: &AssetStore, uri: &str) ->
+Result<Option<ServedAsset>, Box<dyn std::error::Error>> {
+    // the hash is the last path segment of prompt-asset://localhost/<hash>
+    use crate::db::schema::prompt_asset::dsl::*;
+
+    let requested = uri.split(['?', '#']).next().unwrap_or_default()
+        .rsplit('/').next().unwrap_or_default();
+    if requested.len() != 64 || !requested.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(None);
+    }
+    let stored_mime: Option<String> = prompt_asset
+        .filter(hash.eq(requested))
+        .select(mime)
+        .first(conn)
+        .optional()?;
+    let stored_mime = match stored_mime {
+        Some(m) => m,
+        None => return Ok(None),
+    };
+    let bytes = match store.read(requested) {
+        Ok(bytes) => bytes,
+        Err(_) => return Ok(None),
+    };
+    diesel::update(prompt_asset.filter(hash.eq(requested)))
+        .set(last_used.eq(unix_now()))
+        .execute(conn)?;
+
+    Ok(Some(ServedAsset { mime: stored_mime, bytes }))
+}
+
+
+pub fn select_asset_status(conn: &SqliteConnection) ->
+Result<AssetCacheStatus, Box<dyn std::error::Error>> {
+    let sizes = select_asset_sizes(conn)?;
+
+    Ok(AssetCacheStatus {
+        assets: sizes.len() as i64,
+        bytes: sizes.values().map(|(s, _)| s).sum(),
+        max_bytes: select_max_bytes(conn)?,
+    })
+}
+
+pub fn set_asset_cap(conn: &SqliteConnection, store: &AssetStore, max_bytes: i64) ->
+Result<usize, Box<dyn std::error::Error>> {
+    if max_bytes < 0 {
+        return Err("prompt image cache size can't be negative".into());
+    }
+    set_app_setting(conn, MAX_BYTES_SETTING, &max_bytes.to_string())?;
+
+    enforce_asset_cap(conn, store, &HashSet::new())
+}
+
+fn select_max_bytes(conn: &SqliteConnection) -> Result<i64, Box<dyn std::error::Error>> {
+    Ok(select_app_setting(conn, MAX_BYTES_SETTING)?
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_MAX_BYTES))
+}
+
+fn select_asset_sizes(conn: &SqliteConnection) ->
+Result<HashMap<String, (i64, i64)>, Box<dyn std::error::Error>> {
+    // size and last use per file, whichever of its urls was shown last
+    use crate::db::schema::prompt_asset::dsl::*;
+
+    let rows: Vec<(String, i64, i64)> = prompt_asset.select((hash, size, last_used)).load(conn)?;
+    let mut sizes: HashMap<String, (i64, i64)> = HashMap::new();
+    for (asset_hash, asset_size, used) in rows {
+        let entry = sizes.entry(asset_hash).or_insert((asset_size, used));
+        entry.1 = entry.1.max(used);
+    }
+
+    Ok(sizes)
+}
+
+pub fn enforce_asset_cap(conn: &SqliteConnection, store: &AssetStore, keep: &HashSet<String>) ->
+Result<usize, Box<dyn std::error::Error>> {
+    let max_bytes = select_max_bytes(conn)?;
+    let sizes = select_asset_sizes(conn)?;
+    let mut total: i64 = sizes.values().map(|(s, _)| s).sum();
+    let mut by_use: Vec<(&String, &(i64, i64))> = sizes.iter().filter(|(h, _)| !keep.contains(*h)).collect();
+    by_use.sort_by_key(|(h, (_, used))| (*used, (*h).clone()));
+
+    let mut evicted = 0;
+    for (asset_hash, (asset_size, _)) in by_use {
+        if total <= max_bytes {
+            break;
+        }
+        evict_asset(conn, store, asset_hash)?;
+        total -= asset_size;
+        evicted += 1;
+    }
+
+    Ok(evicted)
+}
+
+fn evict_asset(conn: &SqliteConnection, store: &AssetStore, asset_hash: &str) ->
+Result<(), Box<dyn std::error::Error>> {
+    // prompts go back to the remote url first, the file goes last
+    use crate::db::schema::prompt_asset::dsl::*;
+
+    // source_url is kept as it was written in the src attribute
+    let remote_url: String = prompt_asset.filter(hash.eq(asset_hash)).select(source_url).first(conn)?;
+    let asset_url = format!("{}{}", ASSET_URL_PREFIX, asset_hash);
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        replace_in_prompts(conn, &asset_url, &remote_url)?;
+        diesel::delete(prompt_asset.filter(hash.eq(asset_hash))).execute(conn)?;
+        Ok(())
+    })?;
+
+    store.remove(asset_hash)
+}
+
+fn replace_in_prompts(conn: &SqliteConnection, from: &str, to: &str) ->
+Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema::{question, question_translation};
+
+    let pattern = format!("%{}%", from);
+    let questions: Vec<(i32, Option<String>)> = question::table
+        .filter(question::prompt.like(&pattern))
+        .select((question::qid, question::prompt))
+        .load(conn)?;
+    for (the_qid, the_prompt) in questions {
+        diesel::update(question::table.find(the_qid))
+            .set(question::prompt.eq(the_prompt.map(|p| p.replace(from, to))))
+            .execute(conn)?;
+    }
+
+    let translations: Vec<(i32, String, Option<String>)> = question_translation::table
+        .filter(question_translation::prompt.like(&pattern))
+        .select((question_translation::qid, question_translation::locale, question_translation::prompt))
+        .load(conn)?;
+    for (the_qid, the_locale, the_prompt) in translations {
+        diesel::update(question_translation::table.find((the_qid, the_locale)))
+            .set(question_translation::prompt.eq(the_prompt.map(|p| p.replace(from, to))))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+
+pub fn collect_orphaned_assets(conn: &SqliteConnection, store: &AssetStore) ->
+Result<AssetCollection, Box<dyn std::error::Error>> {
+    collect_assets_older_than(conn, store, unix_now() - COLLECT_GRACE_SECS)
+}
+
+fn collect_assets_older_than(conn: &SqliteConnection, store: &AssetStore, cutoff: i64) ->
+Result<AssetCollection, Box<dyn std::error::Error>> {
+    // assets no stored prompt points at anymore (prompt refetched, question
+    // deleted, db restored from a backup) and files without a row, e.g. left
+    // by a crash between download and insert. rows used and files written
+    // since the cutoff are left alone, a prompt fetch may be about to use them
+    use crate::db::schema::{prompt_asset, question, question_translation};
+
+    let pattern = format!("%{}%", ASSET_URL_PREFIX);
+    let mut prompts: Vec<Option<String>> = question::table
+        .filter(question::prompt.like(&pattern))
+        .select(question::prompt)
+        .load(conn)?;
+    prompts.extend(question_translation::table
+        .filter(question_translation::prompt.like(&pattern))
+        .select(question_translation::prompt)
+        .load::<Option<String>>(conn)?);
+    let referenced: HashSet<String> = prompts.iter().flatten()
+        .flat_map(|p| ASSET_REF.captures_iter(p).map(|caps| caps[1].to_string()))
+        .collect();
+
+    let mut collection = AssetCollection::default();
+    let stored: Vec<(String, i64)> = prompt_asset::table
+        .select((prompt_asset::hash, prompt_asset::last_used))
+        .load(conn)?;
+    let mut kept: HashSet<String> = stored.iter()
+        .filter(|(h, used)| referenced.contains(h) || *used >= cutoff)
+        .map(|(h, _)| h.clone())
+        .collect();
+    let orphans: HashSet<&String> = stored.iter().map(|(h, _)| h).filter(|h| !kept.contains(*h)).collect();
+    for orphan in orphans {
+        diesel::delete(prompt_asset::table.filter(prompt_asset::hash.eq(orphan))).execute(conn)?;
+        collection.removed_assets += 1;
+    }
+
+    for file in store.stored_files()? {
+        let name = file.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+        let modified = fs::metadata(&file)?.modified()?
+            .duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+        if modified >= cutoff {
+            kept.insert(name);
+        } else if !kept.contains(&name) {
+            fs::remove_file(&file)?;
+            collection.removed_files += 1;
+        }
+    }
+    collection.evicted_assets = enforce_asset_cap(conn, store, &HashSet::new())?;
+
+    Ok(collection)
+}
+
+
+fn hex_sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::{db_connect_in_memory, LEETCODE_SOURCE_ID};
+    use crate::db::models::NewQuestion;
+    use crate::network::mock_server::{MockResponse, MockServer};
+
+    const PIXEL: &str = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"1\" height=\"1\"/>";
+
+    fn temp_store(name: &str) -> AssetStore {
+        let dir = std::env::temp_dir().join(format!("dsa-flashcards-assets-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        AssetStore::new(dir)
+    }
+
+    fn served(content_type: &str, body: &str) -> MockResponse {
+        MockResponse {
+            status: 200,
+            headers: vec![("content-type".to_string(), content_type.to_string())],
+            body: body.to_string(),
+        }
+    }
+
+    async fn image_server() -> MockServer {
+        MockServer::start(|req, _| match req.path.as_str() {
+            p if p.starts_with("/img/") => served("image/svg+xml", PIXEL),
+            "/page.html" => served("text/html", "<p>not an image</p>"),
+            _ => MockResponse::json(404, "{}"),
+        }).await
+    }
+
+    fn insert_prompt(conn: &SqliteConnection, slug: &str, html: &str) -> i32 {
+        use crate::db::schema::question::dsl::*;
+        diesel::insert_into(question)
+            .values(&NewQuestion {
+                title: slug.to_string(),
+                title_slug: slug.to_string(),
+                prompt: html.to_string(),
+                difficulty: "EASY".to_string(),
+                source: LEETCODE_SOURCE_ID,
+                source_qid: 1,
+            })
+            .execute(conn).unwrap();
+        question.select(qid).order_by(qid.desc()).first(conn).unwrap()
+    }
+
+    #[test]
+    fn test_remote_images_and_rewrite() {
+        let html = "<p>x</p><img alt=\"\" src=\"https://assets.leetcode.com/a.jpg\" style=\"width: 4px\" />\
+            <IMG src='http://example.com/b.png?x=1&amp;y=2'><img src=\"https://assets.leetcode.com/a.jpg\">\
+            <img src=\"/relative.png\"><a href=\"https://example.com/c.png\">c</a>";
+        assert_eq!(remote_images(html), vec![
+            "https://assets.leetcode.com/a.jpg".to_string(),
+            "http://example.com/b.png?x=1&amp;y=2".to_string(),
+        ]);
+
+        let hash = "ab".repeat(32);
+        let cached = HashMap::from([("https://assets.leetcode.com/a.jpg".to_string(), hash.clone())]);
+        let rewritten = rewrite_images(html, &cached);
+        assert_eq!(rewritten.matches(&format!("src=\"{}{}\"", ASSET_URL_PREFIX, hash)).count(), 2);
+        assert!(rewritten.contains("style=\"width: 4px\""));
+        assert!(rewritten.contains("<IMG src='http://example.com/b.png?x=1&amp;y=2'>"));
+        assert_eq!(ASSET_REF.captures(&rewritten).unwrap()[1], hash);
+    }
+
+    #[tokio::test]
+    async fn test_download_record_and_load_images() {
+        let server = image_server().await;
+        let store = temp_store("download");
+        let conn = db_connect_in_memory();
+        let urls: Vec<String> = ["/img/a.svg", "/img/same-bytes.svg", "/page.html", "/missing.png"].iter()
+            .map(|p| format!("{}{}", server.url, p))
+            .collect();
+
+        let downloaded = download_images(&store, &urls).await;
+        assert_eq!(downloaded.len(), 2);
+        assert_eq!(downloaded[0].hash, downloaded[1].hash);
+        assert_eq!((downloaded[0].mime.as_str(), downloaded[0].size), ("image/svg+xml", PIXEL.len() as i64));
+
+        let recorded = record_images(&conn, &store, &downloaded).unwrap();
+        assert_eq!(select_cached_images(&conn, &urls).unwrap(), recorded);
+        assert_eq!(select_asset_status(&conn).unwrap(),
+            AssetCacheStatus { assets: 1, bytes: PIXEL.len() as i64, max_bytes: DEFAULT_MAX_BYTES });
+
+        let uri = format!("{}{}", ASSET_URL_PREFIX, downloaded[0].hash);
+        assert_eq!(load_asset(&conn, &store, &uri).unwrap(), Some(ServedAsset {
+            mime: "image/svg+xml".to_string(),
+            bytes: PIXEL.as_bytes().to_vec(),
+        }));
+        assert_eq!(load_asset(&conn, &store, &format!("{}{}", ASSET_URL_PREFIX, "0".repeat(64))).unwrap(), None);
+        assert_eq!(load_asset(&conn, &store, &format!("{}../../etc/passwd", ASSET_URL_PREFIX)).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_cap_evicts_least_recently_used_and_restores_urls() {
+        use crate::db::schema::prompt_asset::dsl::*;
+        let server = MockServer::start(|req, _| served("image/svg+xml", &format!("<svg id=\"{}\"/>", req.path))).await;
+        let store = temp_store("cap");
+        let conn = db_connect_in_memory();
+        // urls as written in src attributes, escaped
+        let old_url = format!("{}/img/old.svg?a=1&amp;b=2", server.url);
+        let new_url = format!("{}/img/new.svg", server.url);
+        let html = format!("<img src=\"{}\"><img src=\"{}\">", old_url, new_url);
+
+        let downloaded = download_images(&store, &remote_images(&html)).await;
+        assert_eq!(server.requests()[0].path, "/img/old.svg?a=1&b=2");
+        let recorded = record_images(&conn, &store, &downloaded).unwrap();
+        let the_qid = insert_prompt(&conn, "two-sum", &rewrite_images(&html, &recorded));
+        diesel::update(prompt_asset.filter(source_url.eq(&old_url))).set(last_used.eq(1)).execute(&conn).unwrap();
+
+        // room for one of the two
+        let evicted = set_asset_cap(&conn, &store, downloaded[1].size).unwrap();
+        assert_eq!(evicted, 1);
+        let stored_prompt: Option<String> = crate::db::schema::question::table.find(the_qid)
+            .select(crate::db::schema::question::prompt)
+            .first(&conn).unwrap();
+        assert_eq!(stored_prompt.unwrap(), format!("<img src=\"{}\"><img src=\"{}{}\">",
+            old_url, ASSET_URL_PREFIX, recorded[&new_url]));
+        assert!(store.read(&recorded[&old_url]).is_err());
+        assert!(store.read(&recorded[&new_url]).is_ok());
+        assert_eq!(select_asset_status(&conn).unwrap().assets, 1);
+        assert!(set_asset_cap(&conn, &store, -1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_collect_orphaned_assets() {
+        let server = image_server().await;
+        let store = temp_store("gc");
+        let conn = db_connect_in_memory();
+        let urls = vec![format!("{}/img/kept.svg", server.url)];
+        let downloaded = download_images(&store, &urls).await;
+        let recorded = record_images(&conn, &store, &downloaded).unwrap();
+
+        // nothing points at the asset yet, and a stray file sits next to it.
+        // both are fresh, a prompt fetch could still be about to use them
+        store.write(&"cd".repeat(32), b"stray").unwrap();
+        assert_eq!(collect_orphaned_assets(&conn, &store).unwrap(), AssetCollection::default());
+        assert_eq!(store.stored_files().unwrap().len(), 2);
+        let collection = collect_assets_older_than(&conn, &store, unix_now() + 1).unwrap();
+        assert_eq!(collection, AssetCollection { removed_assets: 1, removed_files: 2, evicted_assets: 0 });
+        assert!(store.stored_files().unwrap().is_empty());
+
+        let downloaded = download_images(&store, &urls).await;
+        let recorded_again = record_images(&conn, &store, &downloaded).unwrap();
+        assert_eq!(recorded, recorded_again);
+        insert_prompt(&conn, "kept", &rewrite_images(&format!("<img src=\"{}\">", urls[0]), &recorded_again));
+        assert_eq!(collect_assets_older_than(&conn, &store, unix_now() + 1).unwrap(), AssetCollection::default());
+        assert_eq!(store.stored_files().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,308 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::{db_connect, TOPICLESS_QUESTION_TOPIC_ID};
+use crate::db::metadata::{
+    details_fetched_for_slug,
+    select_question_metadata,
+    store_question_details,
+    QuestionMetadataDetail
+};
+use crate::db::models::Question;
+use crate::prompts::assets::{cache_prompt_images, AssetStore};
+use crate::prompts::structure::refresh_structures;
+use crate::prompts::translation::DEFAULT_LOCALE;
+use crate::relations::graph::replace_similar_relations;
+use crate::sources::{source_for_id, SourceDetails};
+
+// only the first few prompts of a source are fetched at init. everything else
+// is filled in here the first time a card is opened, and written back so it
+// is only ever fetched once. the prompt comes with the rest of the question
+// details (hints, snippets, ...), which are stored alongside it
+
+#[derive(Serialize, Debug)]
+pub struct QuestionDetail {
+    pub qid: i32,
+    pub title: String,
+    pub title_slug: Option<String>,
+    pub prompt: Option<String>,
+    // prompt is sanitized html by the time it leaves a command, see prompts::render
+    pub prompt_markdown: Option<String>,
+    pub prompt_text: Option<String>,
+    pub difficulty: Option<String>,
+    pub source: Option<i32>,
+    pub source_qid: Option<i32>,
+    // page on the provider's site, shown when a source has no prompts
+    pub problem_url: Option<String>,
+    pub archived: bool,
+    pub topics: Vec<i32>,
+    pub metadata: Option<QuestionMetadataDetail>,
+    // language title and prompt are in, see prompts::translation
+    pub locale: String,
+}
+
+// prompt fetches in progress, keyed by title slug. a second request for a
+// slug waits on the first one and then finds the stored prompt instead of
+// hitting the network again
+#[derive(Default)]
+pub struct PromptFetches {
+    in_flight: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl PromptFetches {
+    pub async fn run_once<L, F, Fut>(
+        &self,
+        title_slug: &str,
+        lookup: L,
+        fetch: F
+    ) -> Result<String, Box<dyn std::error::Error>>
+    where
+        L: Fn() -> Result<Option<String>, Box<dyn std::error::Error>>,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<String, Box<dyn std::error::Error>>>
+    {
+        let slug_lock = self.in_flight.lock().unwrap()
+            .entry(title_slug.to_string())
+            .or_default()
+            .clone();
+        let _guard = slug_lock.lock().await;
+
+        let stored = lookup().map_err(|err| err.to_string());
+        let result = match stored {
+            Ok(Some(prompt)) => Ok(prompt),
+            Ok(None) => fetch().await,
+            Err(err) => Err(err.into()),
+        };
+        self.in_flight.lock().unwrap().remove(title_slug);
+
+        result
+    }
+}
+
+
+pub async fn get_question_detail(fetches: &PromptFetches, the_qid: i32) ->
+Result<QuestionDetail, Box<dyn std::error::Error>> {
+    let detail = select_question_detail(&db_connect(), the_qid)?;
+    let details_missing = detail.metadata.as_ref().map(|m| !m.details_fetched).unwrap_or(true);
+    let (src_id, slug) = match (detail.source, detail.title_slug.clone()) {
+        (Some(src_id), Some(slug)) if prompt_missing(&detail.prompt) || details_missing => (src_id, slug),
+        _ => return Ok(detail),
+    };
+    fetch_missing_prompt(fetches, src_id, &slug).await?;
+
+    select_question_detail(&db_connect(), the_qid)
+}
+
+
+pub async fn fetch_missing_prompt(fetches: &PromptFetches, src_id: i32, slug: &str) ->
+Result<String, Box<dyn std::error::Error>> {
+    // fetches and stores the prompt and details for a slug unless another
+    // request already did. connections are opened around the fetch, not
+    // across it. paid only questions come back with an empty prompt
+    let question_source = source_for_id(&db_connect(), src_id)?;
+
+    fetches.run_once(
+        slug,
+        || select_fetched_prompt(&db_connect(), src_id, slug),
+        || async {
+            let mut fetched = question_source.fetch_details(slug).await?;
+            fetched.prompt = cache_prompt_images(&AssetStore::from_env(), &fetched.prompt).await;
+            store_details(&db_connect(), src_id, slug, &fetched)?;
+            Ok(fetched.prompt)
+        }
+    ).await
+}
+
+
+pub fn select_question_detail(conn: &SqliteConnection, the_qid: i32) ->
+Result<QuestionDetail, Box<dyn std::error::Error>> {
+    use crate::db::schema;
+
+    let q: Question = schema::question::table
+        .filter(schema::question::qid.eq(the_qid))
+        .first(conn)?;
+    let mut topics: Vec<i32> = schema::question_topic::table
+        .filter(schema::question_topic::qid.eq(the_qid))
+        .select(schema::question_topic::tid)
+        .load(conn)?;
+    if topics.is_empty() {
+        topics.push(TOPICLESS_QUESTION_TOPIC_ID);
+    }
+
+    let problem_url = select_problem_url(conn, q.source, q.title_slug.as_deref());
+    Ok(QuestionDetail {
+        qid: q.qid,
+        title: q.title,
+        title_slug: q.title_slug,
+        prompt: q.prompt,
+        prompt_markdown: None,
+        prompt_text: None,
+        difficulty: q.difficulty,
+        source: q.source,
+        source_qid: q.source_qid,
+        problem_url,
+        archived: q.archived,
+        topics,
+        metadata: select_question_metadata(conn, the_qid)?,
+        locale: DEFAULT_LOCALE.to_string(),
+    })
+}
+
+fn select_fetched_prompt(conn: &SqliteConnection, src_id: i32, slug: &str) ->
+Result<Option<String>, Box<dyn std::error::Error>> {
+    // a slug counts as fetched once its details are in, prompt or not
+    if !details_fetched_for_slug(conn, src_id, slug)? {
+        return Ok(None);
+    }
+
+    Ok(Some(select_stored_prompt(conn, src_id, slug)?.unwrap_or_default()))
+}
+
+pub fn select_stored_prompt(conn: &SqliteConnection, src_id: i32, slug: &str) ->
+Result<Option<String>, Box<dyn std::error::Error>> {
+    use crate::db::schema::question::dsl::*;
+
+    let stored: Vec<Option<String>> = question
+        .filter(source.eq(src_id))
+        .filter(title_slug.eq(slug))
+        .select(prompt)
+        .load(conn)?;
+
+    Ok(stored.into_iter().flatten().find(|p| !prompt_missing(&Some(p.to_string()))))
+}
+
+pub fn store_prompt(conn: &SqliteConnection, src_id: i32, slug: &str, prompt_html: &str) ->
+Result<(), Box<dyn std::error::Error>> {
+    // examples and constraints are extracted right away, see prompts::structure
+    use crate::db::schema::question::dsl::*;
+
+    diesel::update(question)
+        .filter(source.eq(src_id))
+        .filter(title_slug.eq(slug))
+        .set(prompt.eq(prompt_html))
+        .execute(conn)?;
+    let qids: Vec<i32> = question
+        .filter(source.eq(src_id))
+        .filter(title_slug.eq(slug))
+        .select(qid)
+        .load(conn)?;
+    refresh_structures(conn, Some(&qids))?;
+
+    Ok(())
+}
+
+pub fn store_details(conn: &SqliteConnection, src_id: i32, slug: &str, details: &SourceDetails) ->
+Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema::question::dsl::*;
+
+    if !prompt_missing(&Some(details.prompt.clone())) {
+        store_prompt(conn, src_id, slug, &details.prompt)?;
+    }
+    let qids: Vec<i32> = question
+        .filter(source.eq(src_id))
+        .filter(title_slug.eq(slug))
+        .select(qid)
+        .load(conn)?;
+    for the_qid in qids {
+        store_question_details(conn, the_qid, details)?;
+        replace_similar_relations(conn, the_qid, src_id, &details.similar_questions)?;
+    }
+
+    Ok(())
+}
+
+fn select_problem_url(conn: &SqliteConnection, src_id: Option<i32>, slug: Option<&str>) -> Option<String> {
+    let question_source = source_for_id(conn, src_id?).ok()?;
+    question_source.problem_url(slug?)
+}
+
+pub fn prompt_missing(stored: &Option<String>) -> bool {
+    stored.as_ref().map(|p| p.trim().is_empty()).unwrap_or(true)
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use crate::db::lib::{db_connect_in_memory, LEETCODE_SOURCE_ID};
+    use crate::db::models::NewQuestion;
+
+    #[test]
+    fn test_store_and_select_prompt() {
+        let conn = db_connect_in_memory();
+        diesel::insert_into(crate::db::schema::question::table)
+            .values(&NewQuestion {
+                title: "Two Sum".to_string(),
+                title_slug: "two-sum".to_string(),
+                prompt: "".to_string(),
+                difficulty: "EASY".to_string(),
+                source: LEETCODE_SOURCE_ID,
+                source_qid: 1,
+            })
+            .execute(&conn).unwrap();
+
+        assert_eq!(select_stored_prompt(&conn, LEETCODE_SOURCE_ID, "two-sum").unwrap(), None);
+        store_prompt(&conn, LEETCODE_SOURCE_ID, "two-sum", "<p>Given an array</p>").unwrap();
+        assert_eq!(select_stored_prompt(&conn, LEETCODE_SOURCE_ID, "two-sum").unwrap().as_deref(),
+            Some("<p>Given an array</p>"));
+
+        let the_qid: i32 = crate::db::schema::question::table
+            .select(crate::db::schema::question::qid)
+            .first(&conn).unwrap();
+        let detail = select_question_detail(&conn, the_qid).unwrap();
+        assert_eq!(detail.prompt.as_deref(), Some("<p>Given an array</p>"));
+        assert_eq!(detail.topics, vec![TOPICLESS_QUESTION_TOPIC_ID]);
+        assert_eq!(detail.metadata, None);
+        // a prompt stored without details still counts as not fetched
+        assert_eq!(select_fetched_prompt(&conn, LEETCODE_SOURCE_ID, "two-sum").unwrap(), None);
+
+        store_details(&conn, LEETCODE_SOURCE_ID, "two-sum", &SourceDetails {
+            prompt: "".to_string(),
+            hints: vec!["use a map".to_string()],
+            ..SourceDetails::default()
+        }).unwrap();
+        assert_eq!(select_fetched_prompt(&conn, LEETCODE_SOURCE_ID, "two-sum").unwrap().as_deref(),
+            Some("<p>Given an array</p>"));
+        let metadata = select_question_detail(&conn, the_qid).unwrap().metadata.unwrap();
+        assert_eq!(metadata.hints, vec!["use a map".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_run_once_dedupes_concurrent_fetches() {
+        let fetches = PromptFetches::default();
+        let stored: Mutex<Option<String>> = Mutex::new(None);
+        let network_calls = AtomicU32::new(0);
+
+        let request = || fetches.run_once(
+            "two-sum",
+            || Ok(stored.lock().unwrap().clone()),
+            || async {
+                network_calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                *stored.lock().unwrap() = Some("<p>prompt</p>".to_string());
+                Ok("<p>prompt</p>".to_string())
+            }
+        );
+        let (a, b, c) = tokio::join!(request(), request(), request());
+
+        assert_eq!(network_calls.load(Ordering::SeqCst), 1);
+        for prompt in [a, b, c] {
+            assert_eq!(prompt.unwrap(), "<p>prompt</p>");
+        }
+        assert!(fetches.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prompt_missing() {
+        assert!(prompt_missing(&None));
+        assert!(prompt_missing(&Some(" \n".to_string())));
+        assert!(!prompt_missing(&Some("<p>x</p>".to_string())));
+    }
+}
@@ -0,0 +1,6 @@
+pub mod assets;
+pub mod detail;
+pub mod prefetch;
+pub mod render;
+pub mod structure;
+pub mod translation;
@@ -0,0 +1,482 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use diesel::sql_types::Integer;
+use diesel::sqlite::{Sqlite, SqliteConnection};
+use diesel::prelude::*;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+use crate::db::lib::{db_connect, select_app_setting, set_app_setting};
+use crate::db::models::Question;
+use crate::db::schema::{deck_question, question, question_metadata, review_state, star};
+use crate::prompts::detail::{fetch_missing_prompt, PromptFetches};
+use crate::sources::source_for_id;
+
+// works through every question that still has no prompt so the whole
+// catalogue ends up available offline. paid only questions are left out,
+// sources don't hand out their prompts, as are sources without prompts to
+// fetch and questions whose details already came back without one. starred
+// questions go first, then ones due for review, then ones in saved decks,
+// across every user. the pause flag and rate live in app_setting, and the
+// queue itself is just "questions with an empty prompt", so a restart picks
+// up where the last run stopped
+
+pub const PREFETCH_PROGRESS_EVENT: &str = "prompt-prefetch-progress";
+const PAUSED_SETTING: &str = "prompt_prefetch_paused";
+const RATE_SETTING: &str = "prompt_prefetch_per_minute";
+const DEFAULT_PER_MINUTE: u32 = 20;
+const MAX_PER_MINUTE: u32 = 600;
+const IDLE_RECHECK_SECS: u64 = 600;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PrefetchStatus {
+    pub paused: bool,
+    pub per_minute: u32,
+    pub fetched: usize,
+    pub failed: usize,
+    pub remaining: i64,
+    pub last_qid: Option<i32>,
+}
+
+// the queue is worked through tier by tier, each in catalogue order. a
+// question is only in the first tier it qualifies for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrefetchTier {
+    Starred,
+    Due,
+    Deck,
+    Rest,
+}
+
+const TIERS: [PrefetchTier; 4] = [PrefetchTier::Starred, PrefetchTier::Due, PrefetchTier::Deck, PrefetchTier::Rest];
+
+// (source, source_qid, qid), the order questions are fetched in
+pub type QueueKey = (i32, i32, i32);
+
+#[derive(Debug, PartialEq)]
+pub struct PrefetchTarget {
+    pub qid: i32,
+    pub source: i32,
+    pub source_qid: i32,
+    pub title_slug: String,
+    pub tier: PrefetchTier,
+}
+
+impl PrefetchTarget {
+    pub fn key(&self) -> QueueKey {
+        (self.source, self.source_qid, self.qid)
+    }
+}
+
+pub struct PromptPrefetch {
+    paused: AtomicBool,
+    per_minute: AtomicU32,
+    fetched: AtomicUsize,
+    failed: AtomicUsize,
+    // per tier, the last question whose fetch failed this session. the tier
+    // carries on after it so one broken question doesn't stall the queue,
+    // the next restart tries it again
+    cursors: Mutex<HashMap<PrefetchTier, QueueKey>>,
+    last_qid: Mutex<Option<i32>>,
+    wake: Notify,
+}
+
+impl Default for PromptPrefetch {
+    fn default() -> Self {
+        PromptPrefetch {
+            paused: AtomicBool::new(false),
+            per_minute: AtomicU32::new(DEFAULT_PER_MINUTE),
+            fetched: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            cursors: Mutex::new(HashMap::new()),
+            last_qid: Mutex::new(None),
+            wake: Notify::new(),
+        }
+    }
+}
+
+impl PromptPrefetch {
+    pub fn load_settings(&self, conn: &SqliteConnection) -> Result<(), Box<dyn std::error::Error>> {
+        // app_setting only exists once migrations ran, so this happens at
+        // startup rather than when the state is first managed
+        if let Some(paused) = select_app_setting(conn, PAUSED_SETTING)? {
+            self.paused.store(paused == "true", Ordering::SeqCst);
+        }
+        if let Some(per_minute) = select_app_setting(conn, RATE_SETTING)?.and_then(|v| v.parse::<u32>().ok()) {
+            self.per_minute.store(per_minute, Ordering::SeqCst);
+        }
+
+        Ok(())
+    }
+
+    pub fn status(&self, conn: &SqliteConnection) -> Result<PrefetchStatus, Box<dyn std::error::Error>> {
+        Ok(PrefetchStatus {
+            paused: self.paused.load(Ordering::SeqCst),
+            per_minute: self.per_minute.load(Ordering::SeqCst),
+            fetched: self.fetched.load(Ordering::SeqCst),
+            failed: self.failed.load(Ordering::SeqCst),
+            remaining: count_missing_prompts(conn)?,
+            last_qid: *self.last_qid.lock().unwrap(),
+        })
+    }
+
+    pub fn set_paused(&self, conn: &SqliteConnection, paused: bool) ->
+    Result<(), Box<dyn std::error::Error>> {
+        set_app_setting(conn, PAUSED_SETTING, if paused { "true" } else { "false" })?;
+        self.paused.store(paused, Ordering::SeqCst);
+        self.wake.notify_one();
+
+        Ok(())
+    }
+
+    pub fn set_rate(&self, conn: &SqliteConnection, per_minute: u32) ->
+    Result<(), Box<dyn std::error::Error>> {
+        if per_minute == 0 || per_minute > MAX_PER_MINUTE {
+            return Err(format!("prefetch rate must be between 1 and {} per minute", MAX_PER_MINUTE).into());
+        }
+        set_app_setting(conn, RATE_SETTING, &per_minute.to_string())?;
+        self.per_minute.store(per_minute, Ordering::SeqCst);
+        self.wake.notify_one();
+
+        Ok(())
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_millis(60_000 / self.per_minute.load(Ordering::SeqCst).max(1) as u64)
+    }
+
+    fn mark_failed(&self, target: &PrefetchTarget) {
+        self.failed.fetch_add(1, Ordering::SeqCst);
+        self.cursors.lock().unwrap().insert(target.tier, target.key());
+    }
+}
+
+
+pub fn start_prompt_prefetch(app: AppHandle) {
+    // runs on the async runtime, never on the thread driving the window
+    tauri::async_runtime::spawn(run_prompt_prefetch(app));
+}
+
+async fn run_prompt_prefetch(app: AppHandle) {
+    let prefetch = app.state::<PromptPrefetch>();
+    let fetches = app.state::<PromptFetches>();
+
+    loop {
+        if prefetch.paused.load(Ordering::SeqCst) {
+            emit_progress(&app, &prefetch);
+            prefetch.wake.notified().await;
+            continue;
+        }
+
+        let cursors = prefetch.cursors.lock().unwrap().clone();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default();
+        let next = select_next_missing_prompt(&db_connect(), &cursors, now).map_err(|err| err.to_string());
+        let target = match next {
+            Ok(Some(target)) => target,
+            Ok(None) => {
+                // nothing left. a later source sync can add questions, so
+                // look again every so often or when woken
+                emit_progress(&app, &prefetch);
+                wait_or_wake(&prefetch, Duration::from_secs(IDLE_RECHECK_SECS)).await;
+                continue;
+            }
+            Err(err) => {
+                println!("prompt prefetch could not read the queue: {}", err);
+                wait_or_wake(&prefetch, Duration::from_secs(IDLE_RECHECK_SECS)).await;
+                continue;
+            }
+        };
+
+        let fetched = fetch_missing_prompt(&fetches, target.source, &target.title_slug).await
+            .map_err(|err| err.to_string());
+        match fetched {
+            Ok(prompt) if !prompt.trim().is_empty() => { prefetch.fetched.fetch_add(1, Ordering::SeqCst); }
+            Ok(_) => {
+                println!("prompt prefetch got an empty prompt for question {}", target.qid);
+                prefetch.mark_failed(&target);
+            }
+            Err(err) => {
+                println!("prompt prefetch failed for question {}: {}", target.qid, err);
+                prefetch.mark_failed(&target);
+            }
+        }
+        *prefetch.last_qid.lock().unwrap() = Some(target.qid);
+        emit_progress(&app, &prefetch);
+
+        wait_or_wake(&prefetch, prefetch.interval()).await;
+    }
+}
+
+async fn wait_or_wake(prefetch: &PromptPrefetch, timeout: Duration) {
+    // pausing, resuming or a rate change cuts the wait short
+    tokio::select! {
+        _ = tokio::time::sleep(timeout) => {}
+        _ = prefetch.wake.notified() => {}
+    }
+}
+
+fn emit_progress(app: &AppHandle, prefetch: &PromptPrefetch) {
+    let status = prefetch.status(&db_connect()).map_err(|err| err.to_string());
+    match status {
+        Ok(status) => {
+            if let Err(err) = app.emit_all(PREFETCH_PROGRESS_EVENT, status) {
+                println!("could not emit prompt prefetch progress: {:?}", err);
+            }
+        }
+        Err(err) => println!("could not read prompt prefetch status: {}", err),
+    }
+}
+
+
+pub fn select_next_missing_prompt(conn: &SqliteConnection, cursors: &HashMap<PrefetchTier, QueueKey>, now: i64) ->
+Result<Option<PrefetchTarget>, Box<dyn std::error::Error>> {
+    // the first question of the first tier with any left, past that tier's
+    // cursor. the cursor is compared as a tuple so the query stays one
+    // indexed range scan however many questions failed
+    use crate::db::schema::question::dsl::*;
+
+    let prompt_less = prompt_less_sources(conn)?;
+    for tier in TIERS {
+        let mut next = in_tier(missing_prompts(&prompt_less), tier, now);
+        if let Some((c_source, c_source_qid, c_qid)) = cursors.get(&tier).copied() {
+            next = next.filter(source.gt(c_source)
+                .or(source.eq(c_source).and(source_qid.gt(c_source_qid)))
+                .or(source.eq(c_source).and(source_qid.eq(c_source_qid)).and(qid.gt(c_qid))));
+        }
+        let row: Option<Question> = next
+            .order_by((source.asc(), source_qid.asc(), qid.asc()))
+            .first(conn)
+            .optional()?;
+        if let Some(Question { qid: the_qid, source: Some(the_source), source_qid: Some(the_source_qid), title_slug: Some(the_slug), .. }) = row {
+            return Ok(Some(PrefetchTarget {
+                qid: the_qid,
+                source: the_source,
+                source_qid: the_source_qid,
+                title_slug: the_slug,
+                tier,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+pub fn count_missing_prompts(conn: &SqliteConnection) -> Result<i64, Box<dyn std::error::Error>> {
+    Ok(missing_prompts(&prompt_less_sources(conn)?)
+        .count()
+        .first::<i64>(conn)?)
+}
+
+fn missing_prompts(prompt_less: &[i32]) -> question::BoxedQuery<'_, Sqlite> {
+    use crate::db::schema::question::dsl::*;
+
+    question
+        .filter(prompt.is_null().or(prompt.eq("")))
+        .filter(archived.eq(false))
+        .filter(source.is_not_null())
+        .filter(source_qid.is_not_null())
+        .filter(title_slug.is_not_null())
+        .filter(source.ne_all(prompt_less))
+        .filter(qid.ne_all(settled_qids()))
+        .into_boxed()
+}
+
+fn in_tier(query: question::BoxedQuery<'_, Sqlite>, tier: PrefetchTier, now: i64) -> question::BoxedQuery<'_, Sqlite> {
+    // each tier leaves out the questions of the tiers before it
+    use crate::db::schema::question::dsl::*;
+
+    match tier {
+        PrefetchTier::Starred => query.filter(qid.eq_any(starred_qids())),
+        PrefetchTier::Due => query
+            .filter(qid.ne_all(starred_qids()))
+            .filter(qid.eq_any(due_qids(now))),
+        PrefetchTier::Deck => query
+            .filter(qid.ne_all(starred_qids()))
+            .filter(qid.ne_all(due_qids(now)))
+            .filter(qid.eq_any(deck_qids())),
+        PrefetchTier::Rest => query
+            .filter(qid.ne_all(starred_qids()))
+            .filter(qid.ne_all(due_qids(now)))
+            .filter(qid.ne_all(deck_qids())),
+    }
+}
+
+fn prompt_less_sources(conn: &SqliteConnection) -> Result<Vec<i32>, Box<dyn std::error::Error>> {
+    // a source that can't be resolved anymore has nothing to fetch either
+    use crate::db::schema::source::dsl::*;
+
+    let src_ids: Vec<i32> = source.select(sid).load(conn)?;
+
+    Ok(src_ids.into_iter()
+        .filter(|src_id| !source_for_id(conn, *src_id).map(|s| s.has_prompts()).unwrap_or(false))
+        .collect())
+}
+
+fn settled_qids() -> question_metadata::BoxedQuery<'static, Sqlite, Integer> {
+    // subqueries, a catalogue has thousands of these. paid only questions
+    // never get a prompt, fetched details came back with whatever there was
+    use crate::db::schema::question_metadata::dsl::*;
+
+    question_metadata
+        .filter(paid_only.eq(true).or(details_fetched.eq(true)))
+        .select(qid)
+        .into_boxed()
+}
+
+fn starred_qids() -> star::BoxedQuery<'static, Sqlite, Integer> {
+    use crate::db::schema::star::dsl::*;
+
+    star.select(qid).into_boxed()
+}
+
+fn due_qids(now: i64) -> review_state::BoxedQuery<'static, Sqlite, Integer> {
+    use crate::db::schema::review_state::dsl::*;
+
+    review_state.filter(due_at.le(now)).select(qid).into_boxed()
+}
+
+fn deck_qids() -> deck_question::BoxedQuery<'static, Sqlite, Integer> {
+    use crate::db::schema::deck_question::dsl::*;
+
+    deck_question.select(qid).into_boxed()
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::{db_connect_in_memory, LEETCODE_SOURCE_ID};
+    use crate::db::models::{NewQuestion, NewStar};
+    use crate::db::schema;
+    use crate::db::test_util::insert_lc_question;
+    use crate::review::deck::{add_deck_question, create_saved_deck};
+    use crate::review::scheduler::insert_review;
+
+    fn after(targets: &[&PrefetchTarget]) -> HashMap<PrefetchTier, QueueKey> {
+        // cursors as the worker leaves them once these targets failed
+        targets.iter().map(|t| (t.tier, t.key())).collect()
+    }
+
+    #[test]
+    fn test_select_next_missing_prompt() {
+        let conn = db_connect_in_memory();
+        insert_lc_question(&conn, "lc-1", 1, "<p>have it</p>");
+        let q2 = insert_lc_question(&conn, "lc-2", 2, "");
+        let q3 = insert_lc_question(&conn, "lc-3", 3, "");
+        let q4 = insert_lc_question(&conn, "lc-4", 4, "");
+        diesel::insert_into(schema::star::table)
+            .values(&NewStar { qid: q4, uid: 1 })
+            .execute(&conn).unwrap();
+
+        let starred = select_next_missing_prompt(&conn, &HashMap::new(), 0).unwrap().unwrap();
+        assert_eq!(starred, PrefetchTarget {
+            qid: q4,
+            source: LEETCODE_SOURCE_ID,
+            source_qid: 4,
+            title_slug: "lc-4".to_string(),
+            tier: PrefetchTier::Starred,
+        });
+        let second = select_next_missing_prompt(&conn, &after(&[&starred]), 0).unwrap().unwrap();
+        assert_eq!((second.qid, second.tier), (q2, PrefetchTier::Rest));
+        let third = select_next_missing_prompt(&conn, &after(&[&starred, &second]), 0).unwrap().unwrap();
+        assert_eq!(third.qid, q3);
+        assert_eq!(select_next_missing_prompt(&conn, &after(&[&starred, &third]), 0).unwrap(), None);
+        assert_eq!(count_missing_prompts(&conn).unwrap(), 3);
+
+        diesel::insert_into(schema::question_metadata::table)
+            .values((schema::question_metadata::qid.eq(q2), schema::question_metadata::paid_only.eq(true)))
+            .execute(&conn).unwrap();
+        assert_eq!(select_next_missing_prompt(&conn, &after(&[&starred]), 0).unwrap().unwrap().qid, q3);
+        assert_eq!(count_missing_prompts(&conn).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_due_and_deck_questions_come_before_the_rest() {
+        let conn = db_connect_in_memory();
+        let plain = insert_lc_question(&conn, "lc-1", 1, "");
+        let in_deck = insert_lc_question(&conn, "lc-2", 2, "");
+        let due = insert_lc_question(&conn, "lc-3", 3, "");
+        let starred = insert_lc_question(&conn, "lc-4", 4, "");
+        diesel::insert_into(schema::star::table)
+            .values(&NewStar { qid: starred, uid: 1 })
+            .execute(&conn).unwrap();
+        insert_review(&conn, 1, due, 0, 4).unwrap();
+        let the_did = create_saved_deck(&conn, 1, "warm up").unwrap();
+        add_deck_question(&conn, 1, the_did, in_deck).unwrap();
+        add_deck_question(&conn, 1, the_did, due).unwrap();
+
+        // the order the worker goes through them in when every fetch fails
+        let drain = |now: i64| {
+            let mut cursors: HashMap<PrefetchTier, QueueKey> = HashMap::new();
+            let mut order: Vec<(i32, PrefetchTier)> = vec![];
+            while let Some(target) = select_next_missing_prompt(&conn, &cursors, now).unwrap() {
+                cursors.insert(target.tier, target.key());
+                order.push((target.qid, target.tier));
+            }
+            order
+        };
+        // a day in, the reviewed card is due. before then it is just in a deck
+        assert_eq!(drain(24 * 60 * 60), vec![
+            (starred, PrefetchTier::Starred),
+            (due, PrefetchTier::Due),
+            (in_deck, PrefetchTier::Deck),
+            (plain, PrefetchTier::Rest),
+        ]);
+        assert_eq!(drain(0), vec![
+            (starred, PrefetchTier::Starred),
+            (in_deck, PrefetchTier::Deck),
+            (due, PrefetchTier::Deck),
+            (plain, PrefetchTier::Rest),
+        ]);
+    }
+
+    #[test]
+    fn test_prefetch_leaves_questions_without_prompts_alone() {
+        let conn = db_connect_in_memory();
+        let fetched = insert_lc_question(&conn, "lc-1", 1, "");
+        let cf = crate::sources::register_source(&conn, "codeforces").unwrap();
+        diesel::insert_into(schema::question::table)
+            .values(&NewQuestion {
+                title: "Guess the K-th Zero".to_string(),
+                title_slug: "1520F2".to_string(),
+                prompt: "".to_string(),
+                difficulty: "HARD".to_string(),
+                source: cf,
+                source_qid: 1520006,
+            })
+            .execute(&conn).unwrap();
+        assert_eq!(count_missing_prompts(&conn).unwrap(), 1);
+
+        // details came back once already, with no prompt in them
+        diesel::insert_into(schema::question_metadata::table)
+            .values((schema::question_metadata::qid.eq(fetched), schema::question_metadata::details_fetched.eq(true)))
+            .execute(&conn).unwrap();
+        assert_eq!(count_missing_prompts(&conn).unwrap(), 0);
+        assert_eq!(select_next_missing_prompt(&conn, &HashMap::new(), 0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_prefetch_settings_survive_reload() {
+        let conn = db_connect_in_memory();
+        let prefetch = PromptPrefetch::default();
+        prefetch.load_settings(&conn).unwrap();
+        assert!(!prefetch.paused.load(Ordering::SeqCst));
+        assert_eq!(prefetch.interval(), Duration::from_secs(60 / DEFAULT_PER_MINUTE as u64));
+
+        prefetch.set_paused(&conn, true).unwrap();
+        prefetch.set_rate(&conn, 120).unwrap();
+        assert!(prefetch.set_rate(&conn, 0).is_err());
+
+        let reloaded = PromptPrefetch::default();
+        reloaded.load_settings(&conn).unwrap();
+        let status = reloaded.status(&conn).unwrap();
+        assert!(status.paused);
+        assert_eq!(status.per_minute, 120);
+        assert_eq!(reloaded.interval(), Duration::from_millis(500));
+    }
+}
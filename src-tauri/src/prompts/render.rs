@@ -0,0 +1,240 @@
+use ammonia::{Builder, UrlRelative};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::QuestionQueryResult;
+use crate::db::models::PromptRender;
+use crate::prompts::assets::ASSET_SCHEME;
+use crate::prompts::detail::{prompt_missing, QuestionDetail};
+
+// question.prompt keeps the html a source sent, which goes nowhere as is.
+// what commands return is that html cleaned down to the tags prompts
+// actually use (no scripts, handlers, styles or non http links) plus
+// commonmark and plain text versions for exports and search. renders are
+// cached in prompt_render by a hash of the raw prompt, so a prompt that
+// changes upstream is simply rendered again
+
+// bump when the whitelist or conversions change so old renders are redone
+const RENDER_VERSION: &str = "2";
+// sqlite caps the number of bound parameters per statement
+const HASH_CHUNK: usize = 500;
+
+const PROMPT_TAGS: [&str; 32] = [
+    "a", "b", "blockquote", "br", "code", "div", "em", "h1", "h2", "h3", "h4", "hr", "i", "img",
+    "kbd", "li", "ol", "p", "pre", "s", "samp", "span", "strong", "sub", "sup", "table", "tbody",
+    "td", "th", "thead", "tr", "ul",
+];
+
+static SUP: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<sup>(.*?)</sup>").unwrap());
+static SUB: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<sub>(.*?)</sub>").unwrap());
+static BLANK_LINES: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n{3,}").unwrap());
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RenderedPrompt {
+    pub html: String,
+    pub markdown: String,
+    pub text: String,
+}
+
+
+pub fn sanitize_prompt(raw: &str) -> String {
+    // tags outside the whitelist are unwrapped, script and style are dropped
+    // with their content. relative urls have nothing to resolve against in
+    // the webview and are dropped too. cached images keep their
+    // prompt-asset urls, see prompts::assets
+    Builder::default()
+        .tags(PROMPT_TAGS.iter().copied().collect())
+        .generic_attributes(HashSet::new())
+        .tag_attributes(HashMap::from([
+            ("a", HashSet::from(["href"])),
+            ("img", HashSet::from(["src", "alt", "width", "height"])),
+        ]))
+        .url_schemes(HashSet::from(["http", "https", ASSET_SCHEME]))
+        .url_relative(UrlRelative::Deny)
+        .link_rel(Some("noopener noreferrer"))
+        .clean(raw)
+        .to_string()
+}
+
+pub fn render_prompt(raw: &str) -> RenderedPrompt {
+    let html = sanitize_prompt(raw);
+    // code spans can't hold html
+    let markdown = tidy(&html2md::parse_html(&flatten_scripts(&html)));
+    let text = plain_text(&html);
+
+    RenderedPrompt { html, markdown, text }
+}
+
+pub fn flatten_scripts(html: &str) -> String {
+    // 10<sup>4</sup> becomes 10^4, x<sub>i</sub> becomes x_i
+    SUB.replace_all(&SUP.replace_all(html, "^$1"), "_$1").to_string()
+}
+
+pub fn plain_text(html: &str) -> String {
+    tidy(&html2text::from_read_with_decorator(
+        html.as_bytes(),
+        usize::MAX,
+        html2text::render::text_renderer::TrivialDecorator::new()
+    ))
+}
+
+fn tidy(converted: &str) -> String {
+    let trimmed_lines: Vec<&str> = converted.lines().map(|line| line.trim_end()).collect();
+
+    BLANK_LINES.replace_all(&trimmed_lines.join("\n"), "\n\n").trim().to_string()
+}
+
+pub fn prompt_hash(raw: &str) -> String {
+    let digest = Sha256::digest(format!("{}\n{}", RENDER_VERSION, raw).as_bytes());
+
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+
+pub fn rendered_prompts(conn: &SqliteConnection, raws: &[&str]) ->
+Result<Vec<RenderedPrompt>, Box<dyn std::error::Error>> {
+    // renders in the same order as raws, from the cache where possible
+    use crate::db::schema::prompt_render::dsl::*;
+
+    let hashes: Vec<String> = raws.iter().map(|raw| prompt_hash(raw)).collect();
+    let unique: Vec<String> = hashes.iter().cloned().collect::<HashSet<String>>().into_iter().collect();
+    let mut cached: HashMap<String, RenderedPrompt> = HashMap::new();
+    for chunk in unique.chunks(HASH_CHUNK) {
+        let rows: Vec<PromptRender> = prompt_render.filter(hash.eq_any(chunk)).load(conn)?;
+        for row in rows {
+            cached.insert(row.hash, RenderedPrompt { html: row.html, markdown: row.markdown, text: row.text });
+        }
+    }
+
+    let mut new_renders: Vec<PromptRender> = vec![];
+    for (raw, raw_hash) in raws.iter().zip(&hashes) {
+        if cached.contains_key(raw_hash) {
+            continue;
+        }
+        let rendered = render_prompt(raw);
+        new_renders.push(PromptRender {
+            hash: raw_hash.clone(),
+            html: rendered.html.clone(),
+            markdown: rendered.markdown.clone(),
+            text: rendered.text.clone(),
+        });
+        cached.insert(raw_hash.clone(), rendered);
+    }
+    for chunk in new_renders.chunks(HASH_CHUNK) {
+        diesel::replace_into(prompt_render).values(chunk).execute(conn)?;
+    }
+
+    Ok(hashes.iter().map(|raw_hash| cached[raw_hash].clone()).collect())
+}
+
+pub fn render_query_results(
+    conn: &SqliteConnection,
+    mut results: HashMap<i32, QuestionQueryResult>
+) -> Result<HashMap<i32, QuestionQueryResult>, Box<dyn std::error::Error>> {
+    let with_prompt: Vec<i32> = results.values()
+        .filter(|q| !prompt_missing(&q.prompt))
+        .map(|q| q.qid)
+        .collect();
+    let raws: Vec<&str> = with_prompt.iter()
+        .map(|the_qid| results[the_qid].prompt.as_deref().unwrap_or_default())
+        .collect();
+    let rendered = rendered_prompts(conn, &raws)?;
+
+    for (the_qid, r) in with_prompt.into_iter().zip(rendered) {
+        if let Some(q) = results.get_mut(&the_qid) {
+            q.prompt = Some(r.html);
+            q.prompt_markdown = Some(r.markdown);
+            q.prompt_text = Some(r.text);
+        }
+    }
+
+    Ok(results)
+}
+
+pub fn render_detail(conn: &SqliteConnection, mut detail: QuestionDetail) ->
+Result<QuestionDetail, Box<dyn std::error::Error>> {
+    if prompt_missing(&detail.prompt) {
+        return Ok(detail);
+    }
+    let raw = detail.prompt.take().unwrap_or_default();
+    let rendered = rendered_prompts(conn, &[&raw])?.remove(0);
+    detail.prompt = Some(rendered.html);
+    detail.prompt_markdown = Some(rendered.markdown);
+    detail.prompt_text = Some(rendered.text);
+
+    Ok(detail)
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::db_connect_in_memory;
+
+    const PROMPTS_FIXTURE: &str = include_str!("../../fixtures/leetcode/prompts.json");
+
+    fn two_sum_prompt() -> String {
+        let prompts: serde_json::Value = serde_json::from_str(PROMPTS_FIXTURE).unwrap();
+        prompts["two-sum"]["data"]["question"]["content"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_sanitize_prompt() {
+        let dirty = "<p onclick=\"steal()\" class=\"x\" style=\"color:red\">hi <script>alert(1)</script></p>\
+            <img src=\"https://assets.leetcode.com/a.png\" onerror=\"alert(1)\">\
+            <img src=\"/relative.png\"><a href=\"javascript:alert(1)\">js</a>\
+            <iframe src=\"https://evil.example\"></iframe><font face=\"monospace\">mono</font>\
+            <a href=\"https://leetcode.com/problems/two-sum/\">link</a>";
+        let clean = sanitize_prompt(dirty);
+        for gone in ["onclick", "class=", "style", "script", "alert", "onerror", "relative.png", "javascript", "iframe", "<font"] {
+            assert!(!clean.contains(gone), "{} left in {}", gone, clean);
+        }
+        assert!(clean.contains("<p>hi </p>"));
+        assert!(clean.contains("<img src=\"https://assets.leetcode.com/a.png\">"));
+        assert!(clean.contains("mono"));
+        assert!(clean.contains("href=\"https://leetcode.com/problems/two-sum/\" rel=\"noopener noreferrer\""));
+    }
+
+    #[test]
+    fn test_render_prompt_markdown_and_text() {
+        let rendered = render_prompt(&two_sum_prompt());
+        assert!(rendered.markdown.starts_with("Given an array of integers `nums`"));
+        assert!(rendered.markdown.contains("**Example 1:**"));
+        assert!(rendered.markdown.contains("```\nInput: nums = [2,7,11,15], target = 9"));
+        assert!(rendered.markdown.contains("* `2 <= nums.length <= 10^4`"));
+        assert!(!rendered.markdown.contains("\n\n\n"));
+
+        assert!(rendered.text.starts_with("Given an array of integers nums and an integer target"));
+        assert!(rendered.text.contains("Output: [0,1]"));
+        assert!(!rendered.text.contains("<code>") && rendered.text.contains("2 <= nums.length <= 10"));
+        assert!(!rendered.text.contains("**"));
+    }
+
+    #[test]
+    fn test_rendered_prompts_are_cached() {
+        use crate::db::schema::prompt_render::dsl::*;
+        let conn = db_connect_in_memory();
+        let raw = two_sum_prompt();
+
+        let first = rendered_prompts(&conn, &[&raw, "<p>other</p>", &raw]).unwrap();
+        assert_eq!(first.len(), 3);
+        assert_eq!(first[0], first[2]);
+        assert_eq!(first[1].text, "other");
+        let cached_rows: i64 = prompt_render.count().first(&conn).unwrap();
+        assert_eq!(cached_rows, 2);
+
+        // a cached render is returned as stored
+        diesel::update(prompt_render.filter(hash.eq(prompt_hash("<p>other</p>"))))
+            .set(text.eq("from cache"))
+            .execute(&conn).unwrap();
+        assert_eq!(rendered_prompts(&conn, &["<p>other</p>"]).unwrap()[0].text, "from cache");
+        assert_ne!(prompt_hash("<p>other</p>"), prompt_hash("<p>other </p>"));
+    }
+}
@@ -0,0 +1,562 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::models::{QuestionConstraint, QuestionExample, QuestionStructure};
+use crate::prompts::detail::prompt_missing;
+use crate::prompts::render::{flatten_scripts, plain_text, sanitize_prompt};
+
+// leetcode prompts are laid out the same way every time: the statement,
+// "Example N:" blocks of Input/Output/Explanation and a "Constraints:" list.
+// examples and constraints are pulled out of the plain text version of the
+// prompt into question_example and question_constraint, so cards can show
+// examples up front and constraint sizes (n <= 10^5) can be turned into
+// "expected complexity" cards. question_structure remembers which prompt
+// the rows came from, a refetched prompt is extracted again
+
+// bump when the parser changes so stored extractions are redone
+const STRUCTURE_VERSION: &str = "1";
+
+// a bound: 10^4, -10^9, 5 * 10^4, 2^31 - 1, 1000
+const VALUE: &str = r"-?\s*\d+(?:\s*\^\s*\d+)?(?:\s*\*\s*\d+(?:\s*\^\s*\d+)?)*(?:\s*[-+]\s*\d+(?:\s*\^\s*\d+)?)*";
+
+static EXAMPLE_HEADING: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^example\s*\d*\s*:?$").unwrap());
+static CONSTRAINTS_HEADING: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^constraints\s*:?$").unwrap());
+static SECTION_END: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^(follow[- ]?up|note)\b").unwrap());
+static RANGE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"^({v})\s*(<=|<)\s*(.+?)\s*(<=|<)\s*({v})\s*\.?$", v = VALUE)).unwrap()
+});
+static UPPER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"^(.+?)\s*(<=|<)\s*({v})\s*\.?$", v = VALUE)).unwrap()
+});
+static LOWER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"^(.+?)\s*(>=|>)\s*({v})\s*\.?$", v = VALUE)).unwrap()
+});
+static COUNT_RANGE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"(?i)^the (number of .+?) is in the range \[\s*({v})\s*,\s*({v})\s*\]\s*\.?$", v = VALUE)).unwrap()
+});
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PromptExample {
+    pub input: String,
+    pub output: String,
+    pub explanation: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PromptConstraint {
+    pub text: String,
+    pub subject: Option<String>,
+    pub lower_bound: Option<i64>,
+    pub upper_bound: Option<i64>,
+    // bounds an input's size (nums.length, n, number of nodes) rather than
+    // the values in it
+    pub is_size: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StructuredPrompt {
+    pub qid: i32,
+    pub examples: Vec<PromptExample>,
+    pub constraints: Vec<PromptConstraint>,
+    pub max_input_size: Option<i64>,
+    pub expected_complexity: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ComplexityCard {
+    pub qid: i32,
+    pub title: String,
+    pub size_constraints: Vec<String>,
+    pub max_input_size: i64,
+    pub expected_complexity: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Statement,
+    Example,
+    Constraints,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExampleField {
+    Input,
+    Output,
+    Explanation,
+}
+
+#[derive(Default)]
+struct PartialExample {
+    input: Vec<String>,
+    output: Vec<String>,
+    explanation: Vec<String>,
+}
+
+impl PartialExample {
+    fn push(&mut self, field: ExampleField, line: &str) {
+        if line.is_empty() {
+            return;
+        }
+        match field {
+            ExampleField::Input => self.input.push(line.to_string()),
+            ExampleField::Output => self.output.push(line.to_string()),
+            ExampleField::Explanation => self.explanation.push(line.to_string()),
+        }
+    }
+
+    fn finish(self) -> Option<PromptExample> {
+        // an example without both sides is a picture or a note, not an example
+        if self.input.is_empty() || self.output.is_empty() {
+            return None;
+        }
+        Some(PromptExample {
+            input: self.input.join("\n"),
+            output: self.output.join("\n"),
+            explanation: if self.explanation.is_empty() { None } else { Some(self.explanation.join("\n")) },
+        })
+    }
+}
+
+
+pub fn extract_structure(raw_prompt: &str) -> (Vec<PromptExample>, Vec<PromptConstraint>) {
+    let text = plain_text(&flatten_scripts(&sanitize_prompt(raw_prompt)));
+    let mut examples: Vec<PromptExample> = vec![];
+    let mut constraints: Vec<PromptConstraint> = vec![];
+    let mut section = Section::Statement;
+    let mut example = PartialExample::default();
+    let mut field: Option<ExampleField> = None;
+
+    for line in text.lines().map(|l| l.trim()) {
+        let heading = if EXAMPLE_HEADING.is_match(line) {
+            Some(Section::Example)
+        } else if CONSTRAINTS_HEADING.is_match(line) {
+            Some(Section::Constraints)
+        } else if SECTION_END.is_match(line) {
+            Some(Section::Statement)
+        } else {
+            None
+        };
+        if let Some(next) = heading {
+            examples.extend(std::mem::take(&mut example).finish());
+            field = None;
+            section = next;
+            continue;
+        }
+
+        match section {
+            Section::Statement => {}
+            Section::Constraints => {
+                if !line.is_empty() {
+                    constraints.push(parse_constraint(line));
+                }
+            }
+            Section::Example => {
+                let labelled = [
+                    ("Input:", ExampleField::Input),
+                    ("Output:", ExampleField::Output),
+                    ("Explanation:", ExampleField::Explanation),
+                ].into_iter().find_map(|(label, f)| line.strip_prefix(label).map(|rest| (f, rest.trim())));
+                match (labelled, field) {
+                    (Some((f, rest)), _) => {
+                        field = Some(f);
+                        example.push(f, rest);
+                    }
+                    (None, Some(f)) => example.push(f, line),
+                    (None, None) => {}
+                }
+            }
+        }
+    }
+    examples.extend(example.finish());
+
+    (examples, constraints)
+}
+
+pub fn parse_constraint(line: &str) -> PromptConstraint {
+    let text = line.replace('≤', "<=").replace('≥', ">=").replace('−', "-");
+    let mut constraint = PromptConstraint {
+        text: line.to_string(),
+        subject: None,
+        lower_bound: None,
+        upper_bound: None,
+        is_size: false,
+    };
+
+    if let Some(caps) = COUNT_RANGE.captures(&text) {
+        constraint.subject = Some(caps[1].to_string());
+        constraint.lower_bound = eval_bound(&caps[2]);
+        constraint.upper_bound = eval_bound(&caps[3]);
+    } else if let Some(caps) = RANGE.captures(&text) {
+        constraint.subject = Some(caps[3].to_string());
+        constraint.lower_bound = eval_bound(&caps[1]).map(|lo| if &caps[2] == "<" { lo + 1 } else { lo });
+        constraint.upper_bound = eval_bound(&caps[5]).map(|hi| if &caps[4] == "<" { hi - 1 } else { hi });
+    } else if let Some(caps) = UPPER.captures(&text) {
+        constraint.subject = Some(caps[1].to_string());
+        constraint.upper_bound = eval_bound(&caps[3]).map(|hi| if &caps[2] == "<" { hi - 1 } else { hi });
+    } else if let Some(caps) = LOWER.captures(&text) {
+        constraint.subject = Some(caps[1].to_string());
+        constraint.lower_bound = eval_bound(&caps[3]).map(|lo| if &caps[2] == ">" { lo + 1 } else { lo });
+    }
+    constraint.is_size = constraint.subject.as_deref().map(is_size_subject).unwrap_or(false);
+
+    constraint
+}
+
+fn is_size_subject(subject: &str) -> bool {
+    // "1 <= m, n <= 200" bounds two sizes at once
+    subject.split(',').map(|s| s.trim()).any(|name| {
+        name.ends_with(".length") || name.ends_with(".length()") ||
+        name.ends_with(".size") || name.ends_with(".size()") ||
+        name == "n" || name == "m" ||
+        name.to_lowercase().starts_with("number of ")
+    })
+}
+
+pub fn eval_bound(value: &str) -> Option<i64> {
+    // sums of products of powers, e.g. "2^31 - 1" or "5 * 10^4"
+    let compact: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    let (negative, rest) = match compact.strip_prefix('-') {
+        Some(rest) => (true, rest.to_string()),
+        None => (false, compact),
+    };
+
+    let mut total: i64 = 0;
+    let mut sign: i64 = if negative { -1 } else { 1 };
+    let mut term_start = 0;
+    let chars: Vec<char> = rest.chars().collect();
+    for i in 0..=chars.len() {
+        if i == chars.len() || chars[i] == '+' || chars[i] == '-' {
+            let term: String = chars[term_start..i].iter().collect();
+            total = total.checked_add(sign.checked_mul(eval_product(&term)?)?)?;
+            if i < chars.len() {
+                sign = if chars[i] == '-' { -1 } else { 1 };
+            }
+            term_start = i + 1;
+        }
+    }
+
+    Some(total)
+}
+
+fn eval_product(term: &str) -> Option<i64> {
+    term.split('*').try_fold(1i64, |product, factor| {
+        let value = match factor.split_once('^') {
+            Some((base, exp)) => base.parse::<i64>().ok()?.checked_pow(exp.parse::<u32>().ok()?)?,
+            None => factor.parse::<i64>().ok()?,
+        };
+        product.checked_mul(value)
+    })
+}
+
+pub fn expected_complexity(max_input_size: i64) -> &'static str {
+    // the usual rule of thumb, about 10^8 simple steps fit in the time limit
+    match max_input_size {
+        n if n <= 12 => "O(n!)",
+        n if n <= 25 => "O(2^n)",
+        n if n <= 100 => "O(n^4)",
+        n if n <= 500 => "O(n^3)",
+        n if n <= 10_000 => "O(n^2)",
+        n if n <= 1_000_000 => "O(n log n)",
+        n if n <= 100_000_000 => "O(n)",
+        _ => "O(log n)",
+    }
+}
+
+fn prompt_structure_hash(raw_prompt: &str) -> String {
+    let digest = Sha256::digest(format!("{}\n{}", STRUCTURE_VERSION, raw_prompt).as_bytes());
+
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+
+pub fn store_structure(conn: &SqliteConnection, the_qid: i32, raw_prompt: &str) ->
+Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema::{question_constraint, question_example, question_structure};
+
+    let (examples, constraints) = extract_structure(raw_prompt);
+    let max_input_size = constraints.iter()
+        .filter(|c| c.is_size)
+        .filter_map(|c| c.upper_bound)
+        .max();
+    let example_rows: Vec<QuestionExample> = examples.into_iter().enumerate().map(|(i, e)| QuestionExample {
+        qid: the_qid,
+        position: i as i32,
+        input: e.input,
+        output: e.output,
+        explanation: e.explanation,
+    }).collect();
+    let constraint_rows: Vec<QuestionConstraint> = constraints.into_iter().enumerate().map(|(i, c)| QuestionConstraint {
+        qid: the_qid,
+        position: i as i32,
+        text: c.text,
+        subject: c.subject,
+        lower_bound: c.lower_bound,
+        upper_bound: c.upper_bound,
+        is_size: c.is_size,
+    }).collect();
+
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        diesel::delete(question_example::table.filter(question_example::qid.eq(the_qid))).execute(conn)?;
+        diesel::delete(question_constraint::table.filter(question_constraint::qid.eq(the_qid))).execute(conn)?;
+        diesel::insert_into(question_example::table).values(&example_rows).execute(conn)?;
+        diesel::insert_into(question_constraint::table).values(&constraint_rows).execute(conn)?;
+        diesel::replace_into(question_structure::table)
+            .values(&QuestionStructure {
+                qid: the_qid,
+                prompt_hash: prompt_structure_hash(raw_prompt),
+                max_input_size,
+                expected_complexity: max_input_size.map(|n| expected_complexity(n).to_string()),
+            })
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+pub fn refresh_structures(conn: &SqliteConnection, qids: Option<&[i32]>) ->
+Result<usize, Box<dyn std::error::Error>> {
+    // extracts every prompt that changed since it was last extracted, or was
+    // never extracted. returns how many were
+    use crate::db::schema::{question, question_structure};
+
+    let mut query = question::table
+        .left_join(question_structure::table)
+        .select((question::qid, question::prompt, question_structure::prompt_hash.nullable()))
+        .into_boxed();
+    if let Some(qids) = qids {
+        query = query.filter(question::qid.eq_any(qids));
+    }
+    let rows: Vec<(i32, Option<String>, Option<String>)> = query.load(conn)?;
+
+    let mut refreshed = 0;
+    for (the_qid, the_prompt, stored_hash) in rows {
+        if prompt_missing(&the_prompt) {
+            continue;
+        }
+        let raw_prompt = the_prompt.unwrap_or_default();
+        if stored_hash.as_deref() == Some(prompt_structure_hash(&raw_prompt).as_str()) {
+            continue;
+        }
+        store_structure(conn, the_qid, &raw_prompt)?;
+        refreshed += 1;
+    }
+
+    Ok(refreshed)
+}
+
+pub fn select_structured_prompt(conn: &SqliteConnection, the_qid: i32) ->
+Result<Option<StructuredPrompt>, Box<dyn std::error::Error>> {
+    // none until the question's prompt has been fetched
+    use crate::db::schema::{question, question_constraint, question_example, question_structure};
+
+    question::table.find(the_qid).select(question::qid).first::<i32>(conn)
+        .map_err(|_| format!("no question with qid {}", the_qid))?;
+    refresh_structures(conn, Some(&[the_qid]))?;
+    let structure: Option<QuestionStructure> = question_structure::table.find(the_qid).first(conn).optional()?;
+    let structure = match structure {
+        Some(structure) => structure,
+        None => return Ok(None),
+    };
+
+    let examples: Vec<QuestionExample> = question_example::table
+        .filter(question_example::qid.eq(the_qid))
+        .order_by(question_example::position.asc())
+        .load(conn)?;
+    let constraints: Vec<QuestionConstraint> = question_constraint::table
+        .filter(question_constraint::qid.eq(the_qid))
+        .order_by(question_constraint::position.asc())
+        .load(conn)?;
+
+    Ok(Some(StructuredPrompt {
+        qid: the_qid,
+        examples: examples.into_iter().map(|e| PromptExample {
+            input: e.input,
+            output: e.output,
+            explanation: e.explanation,
+        }).collect(),
+        constraints: constraints.into_iter().map(|c| PromptConstraint {
+            text: c.text,
+            subject: c.subject,
+            lower_bound: c.lower_bound,
+            upper_bound: c.upper_bound,
+            is_size: c.is_size,
+        }).collect(),
+        max_input_size: structure.max_input_size,
+        expected_complexity: structure.expected_complexity,
+    }))
+}
+
+pub fn select_complexity_cards(conn: &SqliteConnection) ->
+Result<Vec<ComplexityCard>, Box<dyn std::error::Error>> {
+    // every question with a prompt whose constraints bound an input size.
+    // structures are extracted when prompts are stored, not here
+    use crate::db::schema::{question, question_constraint, question_structure};
+
+    let rows: Vec<(i32, String, Option<i64>, Option<String>)> = question_structure::table
+        .inner_join(question::table)
+        .filter(question::archived.eq(false))
+        .filter(question_structure::expected_complexity.is_not_null())
+        .select((question::qid, question::title, question_structure::max_input_size, question_structure::expected_complexity))
+        .order_by(question::qid.asc())
+        .load(conn)?;
+    let mut size_constraints: HashMap<i32, Vec<String>> = HashMap::new();
+    for (the_qid, the_text) in question_constraint::table
+        .filter(question_constraint::is_size.eq(true))
+        .order_by((question_constraint::qid.asc(), question_constraint::position.asc()))
+        .select((question_constraint::qid, question_constraint::text))
+        .load::<(i32, String)>(conn)? {
+        size_constraints.entry(the_qid).or_default().push(the_text);
+    }
+
+    Ok(rows.into_iter().filter_map(|(the_qid, the_title, max_size, complexity)| {
+        Some(ComplexityCard {
+            qid: the_qid,
+            title: the_title,
+            size_constraints: size_constraints.remove(&the_qid).unwrap_or_default(),
+            max_input_size: max_size?,
+            expected_complexity: complexity?,
+        })
+    }).collect())
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::{db_connect_in_memory, LEETCODE_SOURCE_ID};
+    use crate::db::models::NewQuestion;
+    use crate::prompts::detail::store_prompt;
+
+    const PROMPTS_FIXTURE: &str = include_str!("../../fixtures/leetcode/prompts.json");
+
+    fn fixture_prompt(slug: &str) -> String {
+        let prompts: serde_json::Value = serde_json::from_str(PROMPTS_FIXTURE).unwrap();
+        prompts[slug]["data"]["question"]["content"].as_str().unwrap().to_string()
+    }
+
+    fn size(text: &str, subject: &str, lower_bound: i64, upper_bound: i64) -> PromptConstraint {
+        PromptConstraint {
+            text: text.to_string(),
+            subject: Some(subject.to_string()),
+            lower_bound: Some(lower_bound),
+            upper_bound: Some(upper_bound),
+            is_size: true,
+        }
+    }
+
+    #[test]
+    fn test_extract_two_sum() {
+        let (examples, constraints) = extract_structure(&fixture_prompt("two-sum"));
+        assert_eq!(examples, vec![PromptExample {
+            input: "nums = [2,7,11,15], target = 9".to_string(),
+            output: "[0,1]".to_string(),
+            explanation: Some("Because nums[0] + nums[1] == 9, we return [0, 1].".to_string()),
+        }]);
+        assert_eq!(constraints, vec![
+            size("2 <= nums.length <= 10^4", "nums.length", 2, 10_000),
+            PromptConstraint {
+                text: "-10^9 <= nums[i] <= 10^9".to_string(),
+                subject: Some("nums[i]".to_string()),
+                lower_bound: Some(-1_000_000_000),
+                upper_bound: Some(1_000_000_000),
+                is_size: false,
+            },
+            PromptConstraint {
+                text: "Only one valid answer exists.".to_string(),
+                subject: None, lower_bound: None, upper_bound: None, is_size: false,
+            },
+        ]);
+
+        let (_, node_constraints) = extract_structure(&fixture_prompt("add-two-numbers"));
+        assert_eq!(node_constraints[0], size(
+            "The number of nodes in each linked list is in the range [1, 100].",
+            "number of nodes in each linked list", 1, 100));
+    }
+
+    #[test]
+    fn test_extract_new_layout_and_follow_up() {
+        let prompt = "<p>Statement with Input: in it.</p>\
+            <p><strong class=\"example\">Example 1:</strong></p>\
+            <div class=\"example-block\"><p><strong>Input:</strong> <span class=\"example-io\">grid = [[1,2]]</span></p>\
+            <p><strong>Output:</strong> <span class=\"example-io\">3</span></p>\
+            <p><strong>Explanation:</strong></p><p>First line.</p><ul><li>Second line.</li></ul></div>\
+            <p><strong class=\"example\">Example 2:</strong></p><img src=\"https://example.com/a.png\">\
+            <p><strong>Constraints:</strong></p><ul><li><code>1 &lt;= m, n &lt;= 200</code></li>\
+            <li><code>0 &lt;= k &lt; n</code></li><li><code>x &gt; 0</code></li></ul>\
+            <p><strong>Follow-up:</strong> can you do it in O(n) time?</p>";
+        let (examples, constraints) = extract_structure(prompt);
+        assert_eq!(examples, vec![PromptExample {
+            input: "grid = [[1,2]]".to_string(),
+            output: "3".to_string(),
+            explanation: Some("First line.\nSecond line.".to_string()),
+        }]);
+        assert_eq!(constraints.len(), 3);
+        assert_eq!(constraints[0], size("1 <= m, n <= 200", "m, n", 1, 200));
+        assert_eq!((constraints[1].subject.as_deref(), constraints[1].upper_bound), (None, None));
+        assert_eq!((constraints[2].lower_bound, constraints[2].is_size), (Some(1), false));
+    }
+
+    #[test]
+    fn test_eval_bound_and_complexity() {
+        assert_eq!(eval_bound("10^5"), Some(100_000));
+        assert_eq!(eval_bound("- 10^9"), Some(-1_000_000_000));
+        assert_eq!(eval_bound("5 * 10^4"), Some(50_000));
+        assert_eq!(eval_bound("2^31 - 1"), Some(2_147_483_647));
+        assert_eq!(eval_bound("-2^31"), Some(-2_147_483_648));
+        assert_eq!(eval_bound("10^9 + 7"), Some(1_000_000_007));
+        assert_eq!(eval_bound("10^30"), None);
+        assert_eq!(eval_bound("n"), None);
+
+        assert_eq!(expected_complexity(8), "O(n!)");
+        assert_eq!(expected_complexity(200), "O(n^3)");
+        assert_eq!(expected_complexity(10_000), "O(n^2)");
+        assert_eq!(expected_complexity(100_000), "O(n log n)");
+        assert_eq!(expected_complexity(1_000_000_000), "O(log n)");
+    }
+
+    #[test]
+    fn test_structures_are_stored_and_refreshed() {
+        use crate::db::schema::question::dsl::*;
+        let conn = db_connect_in_memory();
+        for (slug, number, the_prompt) in [("two-sum", 1, fixture_prompt("two-sum")), ("no-prompt", 2, "".to_string())] {
+            diesel::insert_into(question)
+                .values(&NewQuestion {
+                    title: slug.to_string(),
+                    title_slug: slug.to_string(),
+                    prompt: the_prompt,
+                    difficulty: "EASY".to_string(),
+                    source: LEETCODE_SOURCE_ID,
+                    source_qid: number,
+                })
+                .execute(&conn).unwrap();
+        }
+        let qids: Vec<i32> = question.select(qid).order_by(qid.asc()).load(&conn).unwrap();
+
+        let structured = select_structured_prompt(&conn, qids[0]).unwrap().unwrap();
+        assert_eq!(structured.examples.len(), 1);
+        assert_eq!(structured.constraints.len(), 3);
+        assert_eq!((structured.max_input_size, structured.expected_complexity.as_deref()), (Some(10_000), Some("O(n^2)")));
+        assert_eq!(select_structured_prompt(&conn, qids[1]).unwrap(), None);
+        assert!(select_structured_prompt(&conn, 9999).is_err());
+        assert_eq!(refresh_structures(&conn, None).unwrap(), 0);
+
+        // a refetched prompt replaces what was extracted from the old one
+        store_prompt(&conn, LEETCODE_SOURCE_ID, "two-sum",
+            "<p><strong>Constraints:</strong></p><ul><li>1 &lt;= n &lt;= 20</li></ul>").unwrap();
+        let cards = select_complexity_cards(&conn).unwrap();
+        assert_eq!(cards, vec![ComplexityCard {
+            qid: qids[0],
+            title: "two-sum".to_string(),
+            size_constraints: vec!["1 <= n <= 20".to_string()],
+            max_input_size: 20,
+            expected_complexity: "O(2^n)".to_string(),
+        }]);
+        assert!(select_structured_prompt(&conn, qids[0]).unwrap().unwrap().examples.is_empty());
+    }
+}
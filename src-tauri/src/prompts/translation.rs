@@ -0,0 +1,312 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::{db_connect, QuestionQueryResult, LEETCODE_SOURCE_ID};
+use crate::db::models::{NewQuestionTranslation, QuestionTranslation};
+use crate::network::fetchers::{fetch_lc_cn_translation, lc_cn_graphql_endpoint};
+use crate::prompts::assets::{cache_prompt_images, AssetStore};
+use crate::prompts::detail::{get_question_detail, prompt_missing, PromptFetches, QuestionDetail};
+use crate::prompts::render::render_detail;
+
+// prompts in other languages. leetcode.cn has the leetcode.com problems under
+// the same title slugs, with translated titles and content. it isn't imported
+// as a second source, that would split stars and solutions between two copies
+// of every problem. translations are stored next to the leetcode.com question
+// with the same title_slug instead, so progress is the same whichever
+// language a card is read in. each user picks the language prompts are shown
+// in (user.prompt_locale), questions without a translation stay in english
+
+pub const DEFAULT_LOCALE: &str = "en";
+pub const ZH_CN_LOCALE: &str = "zh-CN";
+pub const PROMPT_LOCALES: [&str; 2] = [DEFAULT_LOCALE, ZH_CN_LOCALE];
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PromptTranslation {
+    pub locale: String,
+    pub title: String,
+    pub prompt: Option<String>,
+}
+
+
+pub fn select_user_locale(conn: &SqliteConnection, the_uid: i32) ->
+Result<String, Box<dyn std::error::Error>> {
+    // users that don't exist read in english like everyone else
+    use crate::db::schema::user::dsl::*;
+
+    Ok(user.filter(uid.eq(the_uid))
+        .select(prompt_locale)
+        .first::<String>(conn)
+        .optional()?
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_string()))
+}
+
+pub fn set_user_locale(conn: &SqliteConnection, the_uid: i32, new_locale: &str) ->
+Result<String, Box<dyn std::error::Error>> {
+    use crate::db::schema::user::dsl::*;
+
+    if !PROMPT_LOCALES.contains(&new_locale) {
+        return Err(format!("unsupported prompt locale {}, expected one of {}",
+            new_locale, PROMPT_LOCALES.join(", ")).into());
+    }
+    let updated = diesel::update(user.filter(uid.eq(the_uid)))
+        .set(prompt_locale.eq(new_locale))
+        .execute(conn)?;
+    if updated == 0 {
+        return Err(format!("no user {}", the_uid).into());
+    }
+
+    select_user_locale(conn, the_uid)
+}
+
+
+pub fn select_translation(conn: &SqliteConnection, the_qid: i32, the_locale: &str) ->
+Result<Option<PromptTranslation>, Box<dyn std::error::Error>> {
+    use crate::db::schema::question_translation::dsl::*;
+
+    let row: Option<QuestionTranslation> = question_translation
+        .filter(qid.eq(the_qid))
+        .filter(locale.eq(the_locale))
+        .first(conn)
+        .optional()?;
+
+    Ok(row.map(|t| PromptTranslation { locale: t.locale, title: t.title, prompt: t.prompt }))
+}
+
+pub fn store_translation(
+    conn: &SqliteConnection,
+    the_locale: &str,
+    slug: &str,
+    translated_title: Option<&str>,
+    translated_prompt: Option<&str>
+) -> Result<usize, Box<dyn std::error::Error>> {
+    // every leetcode question with the slug gets the translation. a row
+    // without a prompt records that there is nothing to fetch, questions
+    // without a translated title keep their own
+    use crate::db::schema;
+
+    let questions: Vec<(i32, String)> = schema::question::table
+        .filter(schema::question::source.eq(LEETCODE_SOURCE_ID))
+        .filter(schema::question::title_slug.eq(slug))
+        .select((schema::question::qid, schema::question::title))
+        .load(conn)?;
+    let rows: Vec<NewQuestionTranslation> = questions.into_iter()
+        .map(|(the_qid, own_title)| NewQuestionTranslation {
+            qid: the_qid,
+            locale: the_locale.to_string(),
+            title: translated_title.filter(|t| !t.trim().is_empty())
+                .map(|t| t.to_string())
+                .unwrap_or(own_title),
+            prompt: translated_prompt.filter(|p| !prompt_missing(&Some(p.to_string())))
+                .map(|p| p.to_string()),
+        })
+        .collect();
+
+    Ok(diesel::replace_into(schema::question_translation::table)
+        .values(&rows)
+        .execute(conn)?)
+}
+
+fn select_fetched_translation(conn: &SqliteConnection, the_locale: &str, slug: &str) ->
+Result<Option<String>, Box<dyn std::error::Error>> {
+    // a slug counts as fetched once it has a row, translated or not
+    use crate::db::schema;
+
+    let stored: Vec<Option<String>> = schema::question_translation::table
+        .inner_join(schema::question::table)
+        .filter(schema::question::source.eq(LEETCODE_SOURCE_ID))
+        .filter(schema::question::title_slug.eq(slug))
+        .filter(schema::question_translation::locale.eq(the_locale))
+        .select(schema::question_translation::prompt)
+        .load(conn)?;
+    if stored.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(stored.into_iter().flatten().next().unwrap_or_default()))
+}
+
+
+pub async fn get_localized_detail(fetches: &PromptFetches, the_uid: Option<i32>, the_qid: i32) ->
+Result<QuestionDetail, Box<dyn std::error::Error>> {
+    let detail = get_question_detail(fetches, the_qid).await?;
+    let user_locale = match the_uid {
+        Some(the_uid) => select_user_locale(&db_connect(), the_uid)?,
+        None => DEFAULT_LOCALE.to_string(),
+    };
+
+    let localized = localize_detail(fetches, &lc_cn_graphql_endpoint(), detail, &user_locale).await
+        .map_err(|err| err.to_string())?;
+
+    render_detail(&db_connect(), localized)
+}
+
+pub async fn localize_detail(
+    fetches: &PromptFetches,
+    endpoint: &str,
+    mut detail: QuestionDetail,
+    user_locale: &str
+) -> Result<QuestionDetail, Box<dyn std::error::Error>> {
+    // only leetcode questions have translations. one that can't be fetched
+    // right now is shown in english rather than failing the whole card
+    let slug = match (detail.source, detail.title_slug.clone()) {
+        (Some(LEETCODE_SOURCE_ID), Some(slug)) if user_locale != DEFAULT_LOCALE => slug,
+        _ => return Ok(detail),
+    };
+
+    let fetched = fetches.run_once(
+        &format!("{}:{}", user_locale, slug),
+        || select_fetched_translation(&db_connect(), user_locale, &slug),
+        || async {
+            let translation = fetch_lc_cn_translation(endpoint, &slug).await?;
+            let (translated_title, translated_prompt) = match translation {
+                Some(t) => (t.translatedTitle, t.translatedContent),
+                None => (None, None),
+            };
+            let translated_prompt = match translated_prompt {
+                Some(p) => Some(cache_prompt_images(&AssetStore::from_env(), &p).await),
+                None => None,
+            };
+            store_translation(&db_connect(), user_locale, &slug,
+                translated_title.as_deref(), translated_prompt.as_deref())?;
+            Ok(translated_prompt.unwrap_or_default())
+        }
+    ).await.map_err(|err| err.to_string());
+    if let Err(err) = fetched {
+        println!("could not fetch {} translation of {}: {}", user_locale, slug, err);
+        return Ok(detail);
+    }
+
+    if let Some(translation) = select_translation(&db_connect(), detail.qid, user_locale)? {
+        if !prompt_missing(&translation.prompt) {
+            detail.title = translation.title;
+            detail.prompt = translation.prompt;
+            detail.locale = translation.locale;
+        }
+    }
+
+    Ok(detail)
+}
+
+pub fn localize_query_results(
+    conn: &SqliteConnection,
+    the_uid: i32,
+    mut results: HashMap<i32, QuestionQueryResult>
+) -> Result<HashMap<i32, QuestionQueryResult>, Box<dyn std::error::Error>> {
+    // question lists only use translations that are already stored, fetching
+    // one per listed question would hammer leetcode.cn
+    use crate::db::schema::question_translation::dsl::*;
+
+    let user_locale = select_user_locale(conn, the_uid)?;
+    if user_locale == DEFAULT_LOCALE {
+        return Ok(results);
+    }
+    let translations: Vec<QuestionTranslation> = question_translation
+        .filter(locale.eq(&user_locale))
+        .filter(prompt.is_not_null())
+        .load(conn)?;
+    for t in translations {
+        if let Some(result) = results.get_mut(&t.qid) {
+            result.title = t.title;
+            result.prompt = t.prompt;
+        }
+    }
+
+    Ok(results)
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::db_connect_in_memory;
+    use crate::db::test_util::insert_lc_question;
+    use crate::network::mock_leetcode::{start_mock_leetcode, MockFaults};
+    use crate::prompts::detail::select_question_detail;
+
+    #[test]
+    fn test_user_locale() {
+        let conn = db_connect_in_memory();
+        // the dev user from the initial migrations
+        assert_eq!(select_user_locale(&conn, 1).unwrap(), DEFAULT_LOCALE);
+        assert_eq!(set_user_locale(&conn, 1, ZH_CN_LOCALE).unwrap(), ZH_CN_LOCALE);
+        assert!(set_user_locale(&conn, 1, "klingon").is_err());
+        assert!(set_user_locale(&conn, 999, ZH_CN_LOCALE).is_err());
+        assert_eq!(select_user_locale(&conn, 1).unwrap(), ZH_CN_LOCALE);
+    }
+
+    #[test]
+    fn test_store_translation_by_slug() {
+        let conn = db_connect_in_memory();
+        let two_sum = insert_lc_question(&conn, "two-sum", 1, "<p>english</p>");
+        let add_two = insert_lc_question(&conn, "add-two-numbers", 2, "<p>english</p>");
+
+        assert_eq!(select_fetched_translation(&conn, ZH_CN_LOCALE, "two-sum").unwrap(), None);
+        store_translation(&conn, ZH_CN_LOCALE, "two-sum", Some("两数之和"), Some("<p>中文</p>")).unwrap();
+        store_translation(&conn, ZH_CN_LOCALE, "add-two-numbers", None, None).unwrap();
+        assert_eq!(store_translation(&conn, ZH_CN_LOCALE, "not-imported", Some("x"), None).unwrap(), 0);
+
+        assert_eq!(select_translation(&conn, two_sum, ZH_CN_LOCALE).unwrap(), Some(PromptTranslation {
+            locale: ZH_CN_LOCALE.to_string(),
+            title: "两数之和".to_string(),
+            prompt: Some("<p>中文</p>".to_string()),
+        }));
+        // untranslated questions keep their title and count as fetched
+        let untranslated = select_translation(&conn, add_two, ZH_CN_LOCALE).unwrap().unwrap();
+        assert_eq!((untranslated.title.as_str(), untranslated.prompt), ("add two numbers", None));
+        assert_eq!(select_fetched_translation(&conn, ZH_CN_LOCALE, "add-two-numbers").unwrap().as_deref(), Some(""));
+
+        let listed = || -> HashMap<i32, QuestionQueryResult> {
+            [two_sum, add_two].iter().map(|the_qid| {
+                let detail = select_question_detail(&conn, *the_qid).unwrap();
+                (*the_qid, QuestionQueryResult {
+                    qid: detail.qid, starred: false, solved: false, topics: detail.topics,
+                    title: detail.title, title_slug: detail.title_slug, prompt: detail.prompt,
+                    difficulty: detail.difficulty, source: detail.source, source_qid: detail.source_qid,
+                    archived: false, paid_only: false, acceptance_rate: None, rating: None,
+                    prompt_markdown: None, prompt_text: None,
+                })
+            }).collect()
+        };
+        let english = localize_query_results(&conn, 1, listed()).unwrap();
+        assert_eq!(english[&two_sum].title, "two sum");
+        set_user_locale(&conn, 1, ZH_CN_LOCALE).unwrap();
+        let localized = localize_query_results(&conn, 1, listed()).unwrap();
+        assert_eq!(localized[&two_sum].title, "两数之和");
+        assert_eq!(localized[&add_two].prompt.as_deref(), Some("<p>english</p>"));
+    }
+
+    #[tokio::test]
+    async fn test_localize_detail_skips_english_and_other_sources() {
+        let server = start_mock_leetcode(MockFaults::default()).await;
+        let fetches = PromptFetches::default();
+        let detail = QuestionDetail {
+            qid: 1,
+            title: "Two Sum".to_string(),
+            title_slug: Some("two-sum".to_string()),
+            prompt: Some("<p>english</p>".to_string()),
+            prompt_markdown: None,
+            prompt_text: None,
+            difficulty: Some("EASY".to_string()),
+            source: Some(LEETCODE_SOURCE_ID),
+            source_qid: Some(1),
+            problem_url: None,
+            archived: false,
+            topics: vec![],
+            metadata: None,
+            locale: DEFAULT_LOCALE.to_string(),
+        };
+
+        // english readers never hit leetcode.cn, and neither do questions
+        // from sources that have no translations
+        let english = localize_detail(&fetches, &server.url, detail, DEFAULT_LOCALE).await.unwrap();
+        assert_eq!(english.locale, DEFAULT_LOCALE);
+        let codeforces = QuestionDetail { source: Some(LEETCODE_SOURCE_ID + 1), ..english };
+        let untranslated = localize_detail(&fetches, &server.url, codeforces, ZH_CN_LOCALE).await.unwrap();
+        assert_eq!((untranslated.title.as_str(), untranslated.locale.as_str()), ("Two Sum", DEFAULT_LOCALE));
+        assert!(server.requests().is_empty());
+    }
+}
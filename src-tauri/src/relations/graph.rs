@@ -0,0 +1,316 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::models::{NewQuestionRelation, Question};
+
+// question to question links, for working through a pattern family instead
+// of going by question number. "similar" rows come from a source's similar
+// questions list and are rewritten whenever its details are fetched,
+// "manual" rows are links the user made. links are stored one way but read
+// both ways, a link from a to b makes each a neighbor of the other
+
+pub const SIMILAR_RELATION: &str = "similar";
+pub const MANUAL_RELATION: &str = "manual";
+
+// neighbor qid -> kinds of link to it
+pub type Neighbors = BTreeMap<i32, BTreeSet<String>>;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RelatedQuestion {
+    pub qid: i32,
+    pub title: String,
+    pub title_slug: Option<String>,
+    pub difficulty: Option<String>,
+    pub kinds: Vec<String>,
+    pub solved: bool,
+    pub starred: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct UnsolvedNeighbor {
+    pub question: RelatedQuestion,
+    // solved questions linking here, more of them means a closer fit
+    pub solved_neighbors: Vec<i32>,
+}
+
+
+pub fn replace_similar_relations(
+    conn: &SqliteConnection,
+    the_qid: i32,
+    src_id: i32,
+    similar_slugs: &[String]
+) -> Result<usize, Box<dyn std::error::Error>> {
+    // similar questions are title slugs from the same source. ones that
+    // aren't in the db (yet) are left out until the next details fetch
+    use crate::db::schema;
+    use schema::question_relation::dsl::*;
+
+    let similar_qids: Vec<i32> = schema::question::table
+        .filter(schema::question::source.eq(src_id))
+        .filter(schema::question::title_slug.eq_any(similar_slugs))
+        .filter(schema::question::qid.ne(the_qid))
+        .select(schema::question::qid)
+        .load(conn)?;
+
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        diesel::delete(question_relation
+            .filter(qid.eq(the_qid))
+            .filter(kind.eq(SIMILAR_RELATION)))
+            .execute(conn)?;
+        let rows: Vec<NewQuestionRelation> = similar_qids.iter()
+            .map(|similar_qid| NewQuestionRelation {
+                qid: the_qid,
+                related_qid: *similar_qid,
+                kind: SIMILAR_RELATION.to_string(),
+            })
+            .collect();
+
+        Ok(diesel::insert_or_ignore_into(question_relation).values(&rows).execute(conn)?)
+    })
+}
+
+pub fn add_manual_link(conn: &SqliteConnection, the_qid: i32, other_qid: i32) ->
+Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema;
+    use schema::question_relation::dsl::*;
+
+    if the_qid == other_qid {
+        return Err("a question can't be linked to itself".into());
+    }
+    let found: i64 = schema::question::table
+        .filter(schema::question::qid.eq_any(vec![the_qid, other_qid]))
+        .count()
+        .first(conn)?;
+    if found != 2 {
+        return Err(format!("no question with qid {} or {}", the_qid, other_qid).into());
+    }
+    if select_neighbors(conn, the_qid)?.get(&other_qid)
+        .map(|kinds| kinds.contains(MANUAL_RELATION))
+        .unwrap_or(false) {
+        return Ok(());
+    }
+
+    diesel::insert_into(question_relation)
+        .values(&NewQuestionRelation {
+            qid: the_qid,
+            related_qid: other_qid,
+            kind: MANUAL_RELATION.to_string(),
+        })
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn remove_manual_link(conn: &SqliteConnection, the_qid: i32, other_qid: i32) ->
+Result<usize, Box<dyn std::error::Error>> {
+    // whichever way the link was made. similar links belong to the source
+    // and come back on the next details fetch, so they aren't touched
+    use crate::db::schema::question_relation::dsl::*;
+
+    Ok(diesel::delete(question_relation
+        .filter(kind.eq(MANUAL_RELATION))
+        .filter(qid.eq(the_qid).and(related_qid.eq(other_qid))
+            .or(qid.eq(other_qid).and(related_qid.eq(the_qid)))))
+        .execute(conn)?)
+}
+
+
+pub fn select_neighbors(conn: &SqliteConnection, the_qid: i32) ->
+Result<Neighbors, Box<dyn std::error::Error>> {
+    use crate::db::schema::question_relation::dsl::*;
+
+    let rows: Vec<(i32, i32, String)> = question_relation
+        .filter(qid.eq(the_qid).or(related_qid.eq(the_qid)))
+        .select((qid, related_qid, kind))
+        .load(conn)?;
+
+    Ok(neighbors_from_rows(rows).remove(&the_qid).unwrap_or_default())
+}
+
+pub fn select_all_neighbors(conn: &SqliteConnection) ->
+Result<HashMap<i32, Neighbors>, Box<dyn std::error::Error>> {
+    use crate::db::schema::question_relation::dsl::*;
+
+    let rows: Vec<(i32, i32, String)> = question_relation
+        .select((qid, related_qid, kind))
+        .load(conn)?;
+
+    Ok(neighbors_from_rows(rows))
+}
+
+fn neighbors_from_rows(rows: Vec<(i32, i32, String)>) -> HashMap<i32, Neighbors> {
+    let mut neighbors: HashMap<i32, Neighbors> = HashMap::new();
+    for (a, b, the_kind) in rows {
+        neighbors.entry(a).or_default().entry(b).or_default().insert(the_kind.clone());
+        neighbors.entry(b).or_default().entry(a).or_default().insert(the_kind);
+    }
+
+    neighbors
+}
+
+
+pub fn select_related_questions(conn: &SqliteConnection, the_uid: i32, the_qid: i32) ->
+Result<Vec<RelatedQuestion>, Box<dyn std::error::Error>> {
+    let neighbors = select_neighbors(conn, the_qid)?;
+    let progress = select_user_progress(conn, the_uid)?;
+    let questions = select_live_questions(conn, neighbors.keys().copied().collect())?;
+
+    Ok(questions.into_iter()
+        .map(|q| related_question(q, &neighbors, &progress))
+        .collect())
+}
+
+pub fn select_unsolved_neighbors(conn: &SqliteConnection, the_uid: i32) ->
+Result<Vec<UnsolvedNeighbor>, Box<dyn std::error::Error>> {
+    // questions one link away from something the user solved, that they
+    // haven't solved themselves. closest fits first, then by qid
+    let all_neighbors = select_all_neighbors(conn)?;
+    let progress = select_user_progress(conn, the_uid)?;
+
+    let mut via: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+    for solved_qid in &progress.solved {
+        let neighbors = match all_neighbors.get(solved_qid) {
+            Some(neighbors) => neighbors,
+            None => continue,
+        };
+        for neighbor in neighbors.keys().filter(|n| !progress.solved.contains(n)) {
+            via.entry(*neighbor).or_default().push(*solved_qid);
+        }
+    }
+    let questions = select_live_questions(conn, via.keys().copied().collect())?;
+
+    let mut unsolved: Vec<UnsolvedNeighbor> = questions.into_iter()
+        .map(|q| {
+            let mut solved_neighbors = via.remove(&q.qid).unwrap_or_default();
+            solved_neighbors.sort_unstable();
+            UnsolvedNeighbor {
+                question: related_question(q, &Neighbors::new(), &progress),
+                solved_neighbors,
+            }
+        })
+        .collect();
+    unsolved.sort_by(|a, b| b.solved_neighbors.len().cmp(&a.solved_neighbors.len())
+        .then(a.question.qid.cmp(&b.question.qid)));
+
+    Ok(unsolved)
+}
+
+
+struct UserProgress {
+    solved: HashSet<i32>,
+    starred: HashSet<i32>,
+}
+
+fn select_user_progress(conn: &SqliteConnection, the_uid: i32) ->
+Result<UserProgress, Box<dyn std::error::Error>> {
+    use crate::db::schema;
+
+    let solved: Vec<i32> = schema::solution::table
+        .filter(schema::solution::uid.eq(the_uid))
+        .select(schema::solution::qid)
+        .load(conn)?;
+    let starred: Vec<i32> = schema::star::table
+        .filter(schema::star::uid.eq(the_uid))
+        .select(schema::star::qid)
+        .load(conn)?;
+
+    Ok(UserProgress { solved: solved.into_iter().collect(), starred: starred.into_iter().collect() })
+}
+
+fn select_live_questions(conn: &SqliteConnection, qids: Vec<i32>) ->
+Result<Vec<Question>, Box<dyn std::error::Error>> {
+    use crate::db::schema::question::dsl::*;
+
+    Ok(question
+        .filter(qid.eq_any(qids))
+        .filter(archived.eq(false))
+        .order_by(qid.asc())
+        .load(conn)?)
+}
+
+fn related_question(q: Question, neighbors: &Neighbors, progress: &UserProgress) -> RelatedQuestion {
+    RelatedQuestion {
+        kinds: neighbors.get(&q.qid).map(|kinds| kinds.iter().cloned().collect()).unwrap_or_default(),
+        solved: progress.solved.contains(&q.qid),
+        starred: progress.starred.contains(&q.qid),
+        qid: q.qid,
+        title: q.title,
+        title_slug: q.title_slug,
+        difficulty: q.difficulty,
+    }
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::{db_connect_in_memory, LEETCODE_SOURCE_ID};
+    use crate::db::models::{NewSolution, NewStar};
+    use crate::db::schema;
+    use crate::db::test_util::insert_lc_question;
+
+    #[test]
+    fn test_similar_and_manual_links() {
+        let conn = db_connect_in_memory();
+        let two_sum = insert_lc_question(&conn, "two-sum", 1, "");
+        let three_sum = insert_lc_question(&conn, "3sum", 15, "");
+        let four_sum = insert_lc_question(&conn, "4sum", 18, "");
+
+        let stored = replace_similar_relations(&conn, two_sum, LEETCODE_SOURCE_ID,
+            &["3sum".to_string(), "not-imported".to_string()]).unwrap();
+        assert_eq!(stored, 1);
+        // rewriting replaces rather than adds
+        replace_similar_relations(&conn, two_sum, LEETCODE_SOURCE_ID,
+            &["3sum".to_string(), "4sum".to_string()]).unwrap();
+        add_manual_link(&conn, three_sum, two_sum).unwrap();
+        add_manual_link(&conn, two_sum, three_sum).unwrap();
+        assert!(add_manual_link(&conn, two_sum, two_sum).is_err());
+        assert!(add_manual_link(&conn, two_sum, 999).is_err());
+
+        let neighbors = select_neighbors(&conn, two_sum).unwrap();
+        assert_eq!(neighbors.keys().copied().collect::<Vec<i32>>(), vec![three_sum, four_sum]);
+        assert_eq!(neighbors[&three_sum].iter().cloned().collect::<Vec<String>>(),
+            vec![MANUAL_RELATION.to_string(), SIMILAR_RELATION.to_string()]);
+        assert!(select_neighbors(&conn, four_sum).unwrap().contains_key(&two_sum));
+
+        assert_eq!(remove_manual_link(&conn, two_sum, three_sum).unwrap(), 1);
+        assert_eq!(select_neighbors(&conn, two_sum).unwrap()[&three_sum].len(), 1);
+    }
+
+    #[test]
+    fn test_related_and_unsolved_neighbors() {
+        let conn = db_connect_in_memory();
+        let a = insert_lc_question(&conn, "a", 1, "");
+        let b = insert_lc_question(&conn, "b", 2, "");
+        let c = insert_lc_question(&conn, "c", 3, "");
+        let d = insert_lc_question(&conn, "d", 4, "");
+        add_manual_link(&conn, a, b).unwrap();
+        add_manual_link(&conn, a, c).unwrap();
+        add_manual_link(&conn, d, c).unwrap();
+        for solved in [a, d] {
+            diesel::insert_into(schema::solution::table)
+                .values(&NewSolution { uid: 1, qid: solved, notes: "".to_string() })
+                .execute(&conn).unwrap();
+        }
+        diesel::insert_into(schema::star::table)
+            .values(&NewStar { uid: 1, qid: b })
+            .execute(&conn).unwrap();
+
+        let related = select_related_questions(&conn, 1, c).unwrap();
+        assert_eq!(related.iter().map(|q| (q.qid, q.solved)).collect::<Vec<(i32, bool)>>(),
+            vec![(a, true), (d, true)]);
+        assert_eq!(related[0].kinds, vec![MANUAL_RELATION.to_string()]);
+
+        let unsolved = select_unsolved_neighbors(&conn, 1).unwrap();
+        assert_eq!(unsolved.len(), 2);
+        assert_eq!((unsolved[0].question.qid, unsolved[0].solved_neighbors.clone()), (c, vec![a, d]));
+        assert_eq!(unsolved[1].question.qid, b);
+        assert!(unsolved[1].question.starred);
+        // another user has solved nothing
+        assert!(select_unsolved_neighbors(&conn, 2).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+use serde::Serialize;
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::models::{Deck, DeckQuestion, NewDeck};
+
+// saved decks, named sets of questions a user puts together to study as a
+// group ("graphs before the interview"). a question can be in any number
+// of a user's decks. not to be confused with the markdown decks in
+// transfer::markdown, which are files on disk
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SavedDeck {
+    pub did: i32,
+    pub name: String,
+    pub qids: Vec<i32>,
+}
+
+
+pub fn select_saved_decks(conn: &SqliteConnection, the_uid: i32) ->
+Result<Vec<SavedDeck>, Box<dyn std::error::Error>> {
+    use crate::db::schema::{deck, deck_question};
+
+    let decks: Vec<Deck> = deck::table
+        .filter(deck::uid.eq(the_uid))
+        .order_by(deck::name.asc())
+        .load(conn)?;
+    let members: Vec<DeckQuestion> = deck_question::table
+        .filter(deck_question::did.eq_any(decks.iter().map(|d| d.did).collect::<Vec<i32>>()))
+        .order_by(deck_question::qid.asc())
+        .load(conn)?;
+
+    Ok(decks.into_iter()
+        .map(|d| SavedDeck {
+            did: d.did,
+            qids: members.iter().filter(|m| m.did == d.did).map(|m| m.qid).collect(),
+            name: d.name,
+        })
+        .collect())
+}
+
+pub fn create_saved_deck(conn: &SqliteConnection, the_uid: i32, deck_name: &str) ->
+Result<i32, Box<dyn std::error::Error>> {
+    use crate::db::schema::deck::dsl::*;
+
+    let trimmed = deck_name.trim();
+    if trimmed.is_empty() {
+        return Err("a deck needs a name".into());
+    }
+    let taken: i64 = deck
+        .filter(uid.eq(the_uid))
+        .filter(name.eq(trimmed))
+        .count()
+        .get_result(conn)?;
+    if taken > 0 {
+        return Err(format!("there already is a deck named {}", trimmed).into());
+    }
+
+    diesel::insert_into(deck)
+        .values(&NewDeck { uid: the_uid, name: trimmed.to_string() })
+        .execute(conn)?;
+
+    Ok(deck.order_by(did.desc()).select(did).first(conn)?)
+}
+
+pub fn delete_saved_deck(conn: &SqliteConnection, the_uid: i32, the_did: i32) ->
+Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema::{deck, deck_question};
+
+    owned_deck(conn, the_uid, the_did)?;
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        diesel::delete(deck_question::table.filter(deck_question::did.eq(the_did))).execute(conn)?;
+        diesel::delete(deck::table.find(the_did)).execute(conn)?;
+        Ok(())
+    })
+}
+
+pub fn add_deck_question(conn: &SqliteConnection, the_uid: i32, the_did: i32, the_qid: i32) ->
+Result<(), Box<dyn std::error::Error>> {
+    // adding a question that is already in the deck does nothing
+    use crate::db::schema::deck_question::dsl::*;
+
+    owned_deck(conn, the_uid, the_did)?;
+    let found: i64 = crate::db::schema::question::table
+        .filter(crate::db::schema::question::qid.eq(the_qid))
+        .count()
+        .get_result(conn)?;
+    if found == 0 {
+        return Err(format!("no question with qid {}", the_qid).into());
+    }
+
+    diesel::insert_or_ignore_into(deck_question)
+        .values(&DeckQuestion { did: the_did, qid: the_qid })
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn remove_deck_question(conn: &SqliteConnection, the_uid: i32, the_did: i32, the_qid: i32) ->
+Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema::deck_question::dsl::*;
+
+    owned_deck(conn, the_uid, the_did)?;
+    diesel::delete(deck_question.filter(did.eq(the_did)).filter(qid.eq(the_qid))).execute(conn)?;
+
+    Ok(())
+}
+
+fn owned_deck(conn: &SqliteConnection, the_uid: i32, the_did: i32) ->
+Result<Deck, Box<dyn std::error::Error>> {
+    use crate::db::schema::deck::dsl::*;
+
+    deck
+        .filter(did.eq(the_did))
+        .filter(uid.eq(the_uid))
+        .first::<Deck>(conn)
+        .optional()?
+        .ok_or_else(|| format!("user {} has no deck {}", the_uid, the_did).into())
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::db_connect_in_memory;
+    use crate::db::test_util::insert_lc_question;
+
+    #[test]
+    fn test_saved_decks() {
+        let conn = db_connect_in_memory();
+        let q1 = insert_lc_question(&conn, "lc-1", 1, "");
+        let q2 = insert_lc_question(&conn, "lc-2", 2, "");
+        let graphs = create_saved_deck(&conn, 1, " graphs ").unwrap();
+        let arrays = create_saved_deck(&conn, 1, "arrays").unwrap();
+        assert!(create_saved_deck(&conn, 1, "graphs").is_err());
+        assert!(create_saved_deck(&conn, 1, "  ").is_err());
+
+        add_deck_question(&conn, 1, graphs, q2).unwrap();
+        add_deck_question(&conn, 1, graphs, q1).unwrap();
+        add_deck_question(&conn, 1, graphs, q1).unwrap();
+        add_deck_question(&conn, 1, arrays, q1).unwrap();
+        assert!(add_deck_question(&conn, 1, graphs, 999).is_err());
+        assert!(add_deck_question(&conn, 2, graphs, q1).is_err());
+        remove_deck_question(&conn, 1, arrays, q1).unwrap();
+
+        assert_eq!(select_saved_decks(&conn, 1).unwrap(), vec![
+            SavedDeck { did: arrays, name: "arrays".to_string(), qids: vec![] },
+            SavedDeck { did: graphs, name: "graphs".to_string(), qids: vec![q1, q2] },
+        ]);
+        assert!(delete_saved_deck(&conn, 2, graphs).is_err());
+        delete_saved_deck(&conn, 1, graphs).unwrap();
+        assert_eq!(select_saved_decks(&conn, 1).unwrap().len(), 1);
+        let left: i64 = crate::db::schema::deck_question::table.count().first(&conn).unwrap();
+        assert_eq!(left, 0);
+    }
+}
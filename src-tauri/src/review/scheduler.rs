@@ -0,0 +1,214 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::db_connect;
+use crate::db::models::{NewReviewLog, ReviewLog, ReviewState};
+
+// spaced repetition. every review is kept in review_log, review_state is
+// only ever derived from it: replaying a card's log in order of reviewed_at
+// through the sm-2 steps below gives its current interval, ease and due
+// time. that is what lets two installs' logs be unioned and the state
+// recomputed instead of picking one machine's state over the other's.
+// grades are sm-2's 0 (blackout) to 5 (perfect), below 3 starts the card
+// over. times are unix seconds
+
+pub const MIN_GRADE: i32 = 0;
+pub const MAX_GRADE: i32 = 5;
+const PASSING_GRADE: i32 = 3;
+const INITIAL_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ScheduledCard {
+    pub qid: i32,
+    pub due_at: i64,
+    pub interval_days: i32,
+    pub repetitions: i32,
+}
+
+impl From<ReviewState> for ScheduledCard {
+    fn from(state: ReviewState) -> Self {
+        ScheduledCard {
+            qid: state.qid,
+            due_at: state.due_at,
+            interval_days: state.interval_days,
+            repetitions: state.repetitions,
+        }
+    }
+}
+
+
+pub fn next_state(previous: Option<&ReviewState>, the_uid: i32, the_qid: i32, reviewed_at: i64, the_grade: i32) -> ReviewState {
+    let (mut interval, mut ease, mut reps) = previous
+        .map(|s| (s.interval_days, s.ease, s.repetitions))
+        .unwrap_or((0, INITIAL_EASE, 0));
+    if the_grade < PASSING_GRADE {
+        reps = 0;
+        interval = 1;
+    }
+    else {
+        reps += 1;
+        interval = match reps {
+            1 => 1,
+            2 => 6,
+            _ => (interval as f64 * ease).round() as i32,
+        };
+    }
+    let miss = (MAX_GRADE - the_grade) as f64;
+    ease = (ease + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE);
+
+    ReviewState {
+        uid: the_uid,
+        qid: the_qid,
+        due_at: reviewed_at + interval as i64 * SECONDS_PER_DAY,
+        interval_days: interval,
+        ease,
+        repetitions: reps,
+    }
+}
+
+pub fn replay(logs: &[ReviewLog]) -> Option<ReviewState> {
+    // logs of one card in any order, ties keep the order they were logged in
+    let mut ordered: Vec<&ReviewLog> = logs.iter().collect();
+    ordered.sort_by_key(|l| (l.reviewed_at, l.rlid));
+
+    ordered.into_iter().fold(None, |state, l| {
+        Some(next_state(state.as_ref(), l.uid, l.qid, l.reviewed_at, l.grade))
+    })
+}
+
+
+pub async fn record_review(uid: i32, qid: i32, grade: i32) ->
+Result<ScheduledCard, Box<dyn std::error::Error>> {
+    let conn = db_connect();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    Ok(insert_review(&conn, uid, qid, now, grade)?.into())
+}
+
+pub async fn get_due_questions(uid: i32) ->
+Result<Vec<ScheduledCard>, Box<dyn std::error::Error>> {
+    let conn = db_connect();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    select_due_questions(&conn, uid, now)
+}
+
+
+pub fn insert_review(conn: &SqliteConnection, the_uid: i32, the_qid: i32, at: i64, the_grade: i32) ->
+Result<ReviewState, Box<dyn std::error::Error>> {
+    if !(MIN_GRADE..=MAX_GRADE).contains(&the_grade) {
+        return Err(format!("grade {} is not between {} and {}", the_grade, MIN_GRADE, MAX_GRADE).into());
+    }
+
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        diesel::insert_into(crate::db::schema::review_log::table)
+            .values(&NewReviewLog { uid: the_uid, qid: the_qid, reviewed_at: at, grade: the_grade })
+            .execute(conn)?;
+        recompute_review_state(conn, the_uid, the_qid)?
+            .ok_or_else(|| "review was not logged".into())
+    })
+}
+
+pub fn recompute_review_state(conn: &SqliteConnection, the_uid: i32, the_qid: i32) ->
+Result<Option<ReviewState>, Box<dyn std::error::Error>> {
+    // rewrites the card's state from its whole log, none when it has no log
+    use crate::db::schema::review_log;
+    use crate::db::schema::review_state::dsl::*;
+
+    let logs: Vec<ReviewLog> = review_log::table
+        .filter(review_log::uid.eq(the_uid))
+        .filter(review_log::qid.eq(the_qid))
+        .load(conn)?;
+    diesel::delete(review_state.filter(uid.eq(the_uid)).filter(qid.eq(the_qid)))
+        .execute(conn)?;
+    let state = replay(&logs);
+    if let Some(state) = &state {
+        diesel::insert_into(review_state)
+            .values(state)
+            .execute(conn)?;
+    }
+
+    Ok(state)
+}
+
+pub fn recompute_review_states(conn: &SqliteConnection, cards: &HashSet<(i32, i32)>) ->
+Result<(), Box<dyn std::error::Error>> {
+    for (the_uid, the_qid) in cards {
+        recompute_review_state(conn, *the_uid, *the_qid)?;
+    }
+
+    Ok(())
+}
+
+pub fn select_due_questions(conn: &SqliteConnection, the_uid: i32, now: i64) ->
+Result<Vec<ScheduledCard>, Box<dyn std::error::Error>> {
+    // most overdue first
+    use crate::db::schema::review_state::dsl::*;
+
+    let states: Vec<ReviewState> = review_state
+        .filter(uid.eq(the_uid))
+        .filter(due_at.le(now))
+        .order_by((due_at.asc(), qid.asc()))
+        .load(conn)?;
+
+    Ok(states.into_iter().map(ScheduledCard::from).collect())
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::db_connect_in_memory;
+    use crate::db::test_util::insert_lc_question;
+
+    fn log(rlid: i32, reviewed_at: i64, grade: i32) -> ReviewLog {
+        ReviewLog { rlid, uid: 1, qid: 1, reviewed_at, grade }
+    }
+
+    #[test]
+    fn test_next_state_intervals() {
+        let first = next_state(None, 1, 1, 0, 4);
+        assert_eq!((first.interval_days, first.repetitions, first.due_at), (1, 1, SECONDS_PER_DAY));
+        let second = next_state(Some(&first), 1, 1, 0, 4);
+        assert_eq!(second.interval_days, 6);
+        let third = next_state(Some(&second), 1, 1, 0, 5);
+        assert_eq!(third.interval_days, (6.0 * second.ease).round() as i32);
+
+        let lapse = next_state(Some(&third), 1, 1, 0, 1);
+        assert_eq!((lapse.interval_days, lapse.repetitions), (1, 0));
+        assert!(lapse.ease < third.ease);
+        let floor = (0..10).fold(lapse, |s, _| next_state(Some(&s), 1, 1, 0, 0));
+        assert_eq!(floor.ease, MIN_EASE);
+    }
+
+    #[test]
+    fn test_replay_is_chronological() {
+        let in_order = replay(&[log(1, 0, 5), log(2, 100, 5), log(3, 200, 1)]).unwrap();
+        let shuffled = replay(&[log(3, 200, 1), log(1, 0, 5), log(2, 100, 5)]).unwrap();
+        assert_eq!(in_order, shuffled);
+        assert_eq!(in_order.repetitions, 0);
+        assert_eq!(replay(&[]), None);
+    }
+
+    #[test]
+    fn test_insert_review_and_due_questions() {
+        let conn = db_connect_in_memory();
+        let q1 = insert_lc_question(&conn, "lc-1", 1, "");
+        let q2 = insert_lc_question(&conn, "lc-2", 2, "");
+        insert_review(&conn, 1, q1, 0, 4).unwrap();
+        insert_review(&conn, 1, q2, 0, 4).unwrap();
+        let q2_state = insert_review(&conn, 1, q2, SECONDS_PER_DAY, 5).unwrap();
+        assert_eq!(q2_state.interval_days, 6);
+        assert!(insert_review(&conn, 1, q1, 0, 6).is_err());
+
+        let due: Vec<i32> = select_due_questions(&conn, 1, 2 * SECONDS_PER_DAY).unwrap()
+            .into_iter().map(|d| d.qid).collect();
+        assert_eq!(due, vec![q1]);
+        assert!(select_due_questions(&conn, 1, 0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::models::{NewTestCase, TestCase};
+use crate::runner::lib::{Comparator, RunCase};
+
+// a user's own test cases for a question, the edge cases worth keeping
+// around ("empty input", "all duplicates"). runs without explicit cases
+// judge the examples and then these. caught_bug marks cases that once
+// failed a solution that passed everything else
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct TestCaseInput {
+    pub input: String,
+    pub expected: String,
+    pub comparator: Option<Comparator>,
+    pub note: Option<String>,
+    pub caught_bug: Option<bool>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct UserTestCase {
+    pub tcid: i32,
+    pub qid: i32,
+    pub input: String,
+    pub expected: String,
+    // none runs the case with the question's default, see runner::lib
+    pub comparator: Option<Comparator>,
+    pub note: Option<String>,
+    pub caught_bug: bool,
+}
+
+impl UserTestCase {
+    pub fn run_case(&self) -> RunCase {
+        RunCase {
+            input: self.input.clone(),
+            expected: Some(self.expected.clone()),
+            comparator: self.comparator,
+            tcid: Some(self.tcid),
+        }
+    }
+}
+
+impl TryFrom<TestCase> for UserTestCase {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(row: TestCase) -> Result<Self, Self::Error> {
+        Ok(UserTestCase {
+            tcid: row.tcid,
+            qid: row.qid,
+            input: row.input,
+            expected: row.expected,
+            comparator: row.comparator.as_deref().map(Comparator::from_name).transpose()?,
+            note: row.note,
+            caught_bug: row.caught_bug,
+        })
+    }
+}
+
+
+pub fn select_test_cases(conn: &SqliteConnection, the_uid: i32, the_qid: i32) ->
+Result<Vec<UserTestCase>, Box<dyn std::error::Error>> {
+    use crate::db::schema::test_case::dsl::*;
+
+    let rows: Vec<TestCase> = test_case
+        .filter(uid.eq(the_uid))
+        .filter(qid.eq(the_qid))
+        .order_by(tcid.asc())
+        .load(conn)?;
+
+    rows.into_iter().map(UserTestCase::try_from).collect()
+}
+
+pub fn select_test_cases_by_qid(conn: &SqliteConnection, the_uid: i32) ->
+Result<HashMap<i32, Vec<UserTestCase>>, Box<dyn std::error::Error>> {
+    use crate::db::schema::test_case::dsl::*;
+
+    let rows: Vec<TestCase> = test_case
+        .filter(uid.eq(the_uid))
+        .order_by(tcid.asc())
+        .load(conn)?;
+    let mut cases_by_qid: HashMap<i32, Vec<UserTestCase>> = HashMap::new();
+    for row in rows {
+        let case = UserTestCase::try_from(row)?;
+        cases_by_qid.entry(case.qid).or_default().push(case);
+    }
+
+    Ok(cases_by_qid)
+}
+
+pub fn add_test_case(conn: &SqliteConnection, the_uid: i32, the_qid: i32, case: &TestCaseInput) ->
+Result<i32, Box<dyn std::error::Error>> {
+    use crate::db::schema::test_case::dsl::*;
+
+    let found: i64 = crate::db::schema::question::table
+        .filter(crate::db::schema::question::qid.eq(the_qid))
+        .count()
+        .get_result(conn)?;
+    if found == 0 {
+        return Err(format!("no question with qid {}", the_qid).into());
+    }
+
+    diesel::insert_into(test_case)
+        .values(&new_test_case(the_uid, the_qid, case)?)
+        .execute(conn)?;
+    let the_tcid: i32 = test_case.order_by(tcid.desc()).select(tcid).first(conn)?;
+
+    Ok(the_tcid)
+}
+
+pub fn update_test_case(conn: &SqliteConnection, the_uid: i32, the_tcid: i32, case: &TestCaseInput) ->
+Result<i32, Box<dyn std::error::Error>> {
+    // returns the qid of the updated case. caught_bug is left alone unless
+    // the update says otherwise
+    use crate::db::schema::test_case::dsl::*;
+
+    let existing = owned_test_case(conn, the_uid, the_tcid)?;
+    let updated = new_test_case(the_uid, existing.qid, case)?;
+    diesel::update(test_case.find(the_tcid))
+        .set((
+            input.eq(updated.input),
+            expected.eq(updated.expected),
+            comparator.eq(updated.comparator),
+            note.eq(updated.note),
+            caught_bug.eq(case.caught_bug.unwrap_or(existing.caught_bug)),
+        ))
+        .execute(conn)?;
+
+    Ok(existing.qid)
+}
+
+pub fn delete_test_case(conn: &SqliteConnection, the_uid: i32, the_tcid: i32) ->
+Result<i32, Box<dyn std::error::Error>> {
+    use crate::db::schema::test_case::dsl::*;
+
+    let existing = owned_test_case(conn, the_uid, the_tcid)?;
+    diesel::delete(test_case.find(the_tcid)).execute(conn)?;
+
+    Ok(existing.qid)
+}
+
+pub fn mark_caught_bug(conn: &SqliteConnection, the_uid: i32, the_tcid: i32, caught: bool) ->
+Result<i32, Box<dyn std::error::Error>> {
+    use crate::db::schema::test_case::dsl::*;
+
+    let existing = owned_test_case(conn, the_uid, the_tcid)?;
+    diesel::update(test_case.find(the_tcid))
+        .set(caught_bug.eq(caught))
+        .execute(conn)?;
+
+    Ok(existing.qid)
+}
+
+fn owned_test_case(conn: &SqliteConnection, the_uid: i32, the_tcid: i32) ->
+Result<TestCase, Box<dyn std::error::Error>> {
+    use crate::db::schema::test_case::dsl::*;
+
+    test_case
+        .filter(tcid.eq(the_tcid))
+        .filter(uid.eq(the_uid))
+        .first::<TestCase>(conn)
+        .optional()?
+        .ok_or_else(|| format!("user {} has no test case {}", the_uid, the_tcid).into())
+}
+
+pub fn new_test_case(the_uid: i32, the_qid: i32, case: &TestCaseInput) ->
+Result<NewTestCase, Box<dyn std::error::Error>> {
+    // inputs are stored the way the harness reads them, one argument per
+    // line without blank lines in between
+    let the_input: Vec<&str> = case.input.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect();
+    if the_input.is_empty() {
+        return Err("a test case needs an input".into());
+    }
+    if case.expected.trim().is_empty() {
+        return Err("a test case needs an expected output".into());
+    }
+
+    Ok(NewTestCase {
+        uid: the_uid,
+        qid: the_qid,
+        input: the_input.join("\n"),
+        expected: case.expected.trim().to_string(),
+        comparator: case.comparator.map(|c| c.as_str().to_string()),
+        note: case.note.as_ref().map(|n| n.trim().to_string()).filter(|n| !n.is_empty()),
+        caught_bug: case.caught_bug.unwrap_or(false),
+    })
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::db_connect_in_memory;
+    use crate::db::schema;
+
+    fn insert_question(conn: &SqliteConnection) -> i32 {
+        diesel::insert_into(schema::question::table)
+            .values(schema::question::title.eq("contains duplicate"))
+            .execute(conn).unwrap();
+        schema::question::table
+            .order_by(schema::question::qid.desc())
+            .select(schema::question::qid)
+            .first(conn).unwrap()
+    }
+
+    #[test]
+    fn test_test_case_crud() {
+        let conn = db_connect_in_memory();
+        let the_qid = insert_question(&conn);
+        let empty = TestCaseInput {
+            input: "[]\r\n\r\n0\n".to_string(),
+            expected: " false ".to_string(),
+            note: Some("empty input".to_string()),
+            ..TestCaseInput::default()
+        };
+        let tcid = add_test_case(&conn, 1, the_qid, &empty).unwrap();
+        let cases = select_test_cases(&conn, 1, the_qid).unwrap();
+        assert_eq!(cases, vec![UserTestCase {
+            tcid,
+            qid: the_qid,
+            input: "[]\n0".to_string(),
+            expected: "false".to_string(),
+            comparator: None,
+            note: Some("empty input".to_string()),
+            caught_bug: false,
+        }]);
+        assert_eq!((cases[0].run_case().tcid, cases[0].run_case().comparator), (Some(tcid), None));
+
+        assert_eq!(mark_caught_bug(&conn, 1, tcid, true).unwrap(), the_qid);
+        let unordered = TestCaseInput {
+            comparator: Some(Comparator::UnorderedList),
+            ..empty.clone()
+        };
+        update_test_case(&conn, 1, tcid, &unordered).unwrap();
+        let updated = &select_test_cases(&conn, 1, the_qid).unwrap()[0];
+        assert_eq!((updated.comparator, updated.caught_bug), (Some(Comparator::UnorderedList), true));
+
+        // other users can't touch it and bad input is refused
+        assert!(delete_test_case(&conn, 2, tcid).is_err());
+        assert!(add_test_case(&conn, 1, the_qid, &TestCaseInput { input: " \n".to_string(), ..empty.clone() }).is_err());
+        assert!(add_test_case(&conn, 1, the_qid + 1, &empty).is_err());
+
+        assert_eq!(delete_test_case(&conn, 1, tcid).unwrap(), the_qid);
+        assert!(select_test_cases(&conn, 1, the_qid).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,279 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// turns a leetcode style solution (a Solution class or impl with one entry
+// method) into a program that reads one case from stdin, calls the method
+// and prints what it returned. a case is leetcode's testcase format, one
+// json value per line per argument. results are printed after a marker line
+// so whatever the solution prints itself can be told apart
+
+pub const RESULT_MARKER: &str = "@@dsa-flashcards-result@@";
+pub const TIME_MARKER: &str = "@@dsa-flashcards-time@@";
+
+const PYTHON_TEMPLATE: &str = include_str!("templates/python_harness.py.tmpl");
+const RUST_TEMPLATE: &str = include_str!("templates/rust_harness.rs.tmpl");
+
+// argument types the rust harness can read, possibly nested in Vec
+const RUST_ARG_TYPES: [&str; 10] = ["i32", "i64", "u32", "u64", "usize", "f32", "f64", "bool", "String", "char"];
+// linked lists, trees and graphs need leetcode's own (de)serializers
+const UNSUPPORTED_TYPES: [&str; 3] = ["ListNode", "TreeNode", "Node"];
+
+static PYTHON_DEF: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bdef\s+([A-Za-z_]\w*)\s*\(").unwrap());
+static RUST_FN: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bpub\s+fn\s+([A-Za-z_]\w*)\s*\(").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    Python,
+    Rust,
+}
+
+impl Language {
+    pub fn from_slug(slug: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match slug.to_lowercase().as_str() {
+            "python" | "python3" => Ok(Language::Python),
+            "rust" => Ok(Language::Rust),
+            other => Err(format!("solutions in {} can't be run locally, only python3 and rust", other).into()),
+        }
+    }
+
+    // code_snippet.lang_slug of the snippet for this language
+    pub fn snippet_slug(&self) -> &'static str {
+        match self {
+            Language::Python => "python3",
+            Language::Rust => "rust",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub ty: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub method: String,
+    pub params: Vec<Param>,
+    pub returns: Option<String>,
+}
+
+
+pub fn parse_signature(language: Language, source: &str) -> Result<Signature, Box<dyn std::error::Error>> {
+    // the first public method of Solution is the entry point, helpers are
+    // expected to be private (_helper in python, plain fn in rust)
+    let signature = match language {
+        Language::Python => {
+            let found = PYTHON_DEF.captures_iter(source)
+                .find(|caps| !caps[1].starts_with('_'))
+                .ok_or("no Solution method found in python code")?;
+            let open = found.get(0).unwrap().end();
+            let (args, after) = balanced_args(source, open).ok_or("unbalanced parentheses in method signature")?;
+            let mut params = split_top_level(&args);
+            if params.first().map(|p| p.trim()) != Some("self") {
+                return Err(format!("{} is not a Solution method", &found[1]).into());
+            }
+            params.remove(0);
+            let returns = source[after..].split(':').next()
+                .and_then(|r| r.trim().strip_prefix("->"))
+                .map(|r| r.trim().to_string());
+            Signature {
+                method: found[1].to_string(),
+                params: params.iter().map(|p| python_param(p)).collect(),
+                returns,
+            }
+        }
+        Language::Rust => {
+            let found = RUST_FN.captures(source).ok_or("no pub fn found in rust code")?;
+            let open = found.get(0).unwrap().end();
+            let (args, after) = balanced_args(source, open).ok_or("unbalanced parentheses in method signature")?;
+            let params: Vec<Param> = split_top_level(&args).iter()
+                .filter(|p| !p.trim().is_empty())
+                .map(|p| {
+                    let (name, ty) = p.split_once(':').unwrap_or((p.as_str(), ""));
+                    Param { name: name.trim().to_string(), ty: Some(ty.trim().to_string()) }
+                })
+                .collect();
+            let returns = source[after..].split('{').next()
+                .and_then(|r| r.trim().strip_prefix("->"))
+                .map(|r| r.trim().to_string());
+            Signature { method: found[1].to_string(), params, returns }
+        }
+    };
+
+    let mentioned = signature.params.iter().filter_map(|p| p.ty.clone())
+        .chain(signature.returns.clone())
+        .collect::<Vec<String>>()
+        .join(" ");
+    if let Some(unsupported) = UNSUPPORTED_TYPES.iter().find(|t| Regex::new(&format!(r"\b{}\b", t)).unwrap().is_match(&mentioned)) {
+        return Err(format!("{} arguments and results can't be run locally yet", unsupported).into());
+    }
+
+    Ok(signature)
+}
+
+fn python_param(param: &str) -> Param {
+    let (name, ty) = match param.split_once(':') {
+        Some((name, ty)) => (name, Some(ty.split('=').next().unwrap_or_default().trim().to_string())),
+        None => (param.split('=').next().unwrap_or_default(), None),
+    };
+
+    Param { name: name.trim().to_string(), ty }
+}
+
+fn balanced_args(source: &str, open: usize) -> Option<(String, usize)> {
+    // text between the "(" ending just before open and its ")", and the
+    // index just past that ")"
+    let mut depth = 1;
+    for (i, ch) in source[open..].char_indices() {
+        match ch {
+            '(' | '[' | '<' => depth += 1,
+            ')' | ']' | '>' if depth > 1 => depth -= 1,
+            ')' => return Some((source[open..open + i].to_string(), open + i + 1)),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn split_top_level(args: &str) -> Vec<String> {
+    // "grid: List[List[int]], k: int" -> two params, commas in [] <> () kept
+    let mut parts: Vec<String> = vec![];
+    let mut depth = 0;
+    let mut current = String::new();
+    for ch in args.chars() {
+        match ch {
+            '(' | '[' | '<' => depth += 1,
+            ')' | ']' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(ch);
+    }
+    if !current.trim().is_empty() {
+        parts.push(current);
+    }
+
+    parts.into_iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect()
+}
+
+
+pub fn build_program(language: Language, code: &str, signature: &Signature) ->
+Result<String, Box<dyn std::error::Error>> {
+    // the solution's code goes in last so nothing in it is taken for a
+    // placeholder
+    let program = match language {
+        Language::Python => PYTHON_TEMPLATE
+            .replace("__RESULT_MARKER__", RESULT_MARKER)
+            .replace("__TIME_MARKER__", TIME_MARKER)
+            .replace("__METHOD__", &signature.method),
+        Language::Rust => {
+            let mut args = String::new();
+            let mut call_args: Vec<String> = vec![];
+            for param in &signature.params {
+                let ty = param.ty.clone().unwrap_or_default();
+                let (by_mut_ref, owned_ty) = match ty.strip_prefix("&mut") {
+                    Some(inner) => (true, inner.trim().to_string()),
+                    None => (false, ty.clone()),
+                };
+                if !rust_type_supported(&owned_ty) {
+                    return Err(format!("{} arguments can't be run locally yet", ty).into());
+                }
+                args.push_str(&format!(
+                    "    let mut {name}: {ty} = __harness::parse(__lines.next().expect(\"missing argument {name}\"));\n",
+                    name = param.name, ty = owned_ty));
+                call_args.push(if by_mut_ref { format!("&mut {}", param.name) } else { param.name.clone() });
+            }
+            // in place methods return (), leetcode shows the first argument then
+            let returns_unit = signature.returns.as_deref().map(|r| r == "()").unwrap_or(true);
+            let output = match (returns_unit, signature.params.first()) {
+                (true, Some(first)) => first.name.clone(),
+                _ => "__result".to_string(),
+            };
+            if let Some(returns) = signature.returns.as_deref().filter(|r| *r != "()") {
+                if !rust_type_supported(returns) {
+                    return Err(format!("{} results can't be run locally yet", returns).into());
+                }
+            }
+
+            RUST_TEMPLATE
+                .replace("__RESULT_MARKER__", RESULT_MARKER)
+                .replace("__TIME_MARKER__", TIME_MARKER)
+                .replace("__ARGS__", args.trim_end_matches('\n'))
+                .replace("__CALL_ARGS__", &call_args.join(", "))
+                .replace("__OUTPUT__", &output)
+                .replace("__METHOD__", &signature.method)
+        }
+    };
+
+    Ok(program.replace("__CODE__", code))
+}
+
+fn rust_type_supported(ty: &str) -> bool {
+    let ty = ty.trim();
+    match ty.strip_prefix("Vec<").and_then(|inner| inner.strip_suffix('>')) {
+        Some(inner) => rust_type_supported(inner),
+        None => RUST_ARG_TYPES.contains(&ty),
+    }
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_python_signature() {
+        let code = "class Solution:\n    def _helper(self, x): pass\n\n    def twoSum(self, nums: List[int], target: int) -> List[int]:\n        return []";
+        assert_eq!(parse_signature(Language::Python, code).unwrap(), Signature {
+            method: "twoSum".to_string(),
+            params: vec![
+                Param { name: "nums".to_string(), ty: Some("List[int]".to_string()) },
+                Param { name: "target".to_string(), ty: Some("int".to_string()) },
+            ],
+            returns: Some("List[int]".to_string()),
+        });
+
+        let nested = "class Solution:\n    def f(self, grid: Dict[str, List[int]], k=3):\n        pass";
+        let signature = parse_signature(Language::Python, nested).unwrap();
+        assert_eq!(signature.params.len(), 2);
+        assert_eq!((signature.params[1].name.as_str(), signature.returns), ("k", None));
+
+        let linked = "class Solution:\n    def addTwoNumbers(self, l1: Optional[ListNode], l2: Optional[ListNode]) -> Optional[ListNode]:\n        ";
+        assert!(parse_signature(Language::Python, linked).is_err());
+        assert!(parse_signature(Language::Python, "def solve(x): pass").is_err());
+    }
+
+    #[test]
+    fn test_parse_rust_signature_and_program() {
+        let code = "impl Solution {\n    pub fn rotate(nums: &mut Vec<i32>, k: i32) {\n    }\n}";
+        let signature = parse_signature(Language::Rust, code).unwrap();
+        assert_eq!(signature, Signature {
+            method: "rotate".to_string(),
+            params: vec![
+                Param { name: "nums".to_string(), ty: Some("&mut Vec<i32>".to_string()) },
+                Param { name: "k".to_string(), ty: Some("i32".to_string()) },
+            ],
+            returns: None,
+        });
+
+        let program = build_program(Language::Rust, code, &signature).unwrap();
+        assert!(program.contains("let mut nums: Vec<i32> = __harness::parse("));
+        assert!(program.contains("Solution::rotate(&mut nums, k)"));
+        assert!(program.contains("ToJson::to_json(&nums)"));
+        assert!(!program.contains("__CODE__") && !program.contains("__ARGS__"));
+
+        let tree = "impl Solution {\n    pub fn max_depth(root: Option<Rc<RefCell<TreeNode>>>) -> i32 {\n    }\n}";
+        assert!(parse_signature(Language::Rust, tree).is_err());
+        let map_arg = "impl Solution {\n    pub fn f(m: HashMap<i32, i32>) -> i32 {\n    }\n}";
+        let signature = parse_signature(Language::Rust, map_arg).unwrap();
+        assert!(build_program(Language::Rust, map_arg, &signature).is_err());
+    }
+}
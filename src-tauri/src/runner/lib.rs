@@ -0,0 +1,521 @@
+use dotenv::dotenv;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::db_connect;
+use crate::prompts::structure::refresh_structures;
+use crate::runner::cases::select_test_cases;
+use crate::runner::harness::{build_program, parse_signature, Language, Signature, RESULT_MARKER, TIME_MARKER};
+use crate::runner::sandbox::{run_limited, Limits, ProcessOutcome};
+
+// runs a solution against test cases on this machine, with whatever python
+// and rustc are installed (PYTHON_BIN and RUSTC_BIN, python3 and rustc by
+// default). rust solutions are compiled once per run, then every case runs
+// in its own process with the time and memory limits. without cases of its
+// own a run uses the question's example test cases, with the outputs of the
+// prompt's examples as expected results
+
+const PYTHON_BIN_VAR: &str = "PYTHON_BIN";
+const RUSTC_BIN_VAR: &str = "RUSTC_BIN";
+const CASE_TIME_LIMIT_MS: u64 = 3_000;
+const CASE_MEMORY_LIMIT_MB: u64 = 512;
+const COMPILE_TIME_LIMIT_MS: u64 = 60_000;
+// float_tolerance compares the way leetcode does, to 5 decimal places
+const FLOAT_TOLERANCE: f64 = 1e-5;
+
+static RUN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparator {
+    // same json value, spacing aside
+    Exact,
+    // a list whose order doesn't matter ("return the answer in any order")
+    UnorderedList,
+    // numbers within FLOAT_TOLERANCE, also inside lists
+    FloatTolerance,
+}
+
+impl Comparator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Comparator::Exact => "exact",
+            Comparator::UnorderedList => "unordered_list",
+            Comparator::FloatTolerance => "float_tolerance",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match name {
+            "exact" => Ok(Comparator::Exact),
+            "unordered_list" => Ok(Comparator::UnorderedList),
+            "float_tolerance" => Ok(Comparator::FloatTolerance),
+            other => Err(format!("unknown comparator {}", other).into()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RunCase {
+    // one json value per line per argument, like leetcode's testcases
+    pub input: String,
+    pub expected: Option<String>,
+    // picked from the method's return type when missing
+    #[serde(default)]
+    pub comparator: Option<Comparator>,
+    // set for the user's stored test cases, see runner::cases
+    #[serde(default)]
+    pub tcid: Option<i32>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseStatus {
+    Passed,
+    Failed,
+    // ran fine, there was nothing to compare the output with
+    Ran,
+    RuntimeError,
+    TimeLimitExceeded,
+    MemoryLimitExceeded,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CaseResult {
+    pub tcid: Option<i32>,
+    pub input: String,
+    pub expected: Option<String>,
+    pub output: Option<String>,
+    pub status: CaseStatus,
+    pub stdout: String,
+    pub stderr: String,
+    // time spent in the solution's method, or the whole process when the
+    // method never returned
+    pub time_ms: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RunReport {
+    pub qid: i32,
+    pub language: String,
+    pub compile_error: Option<String>,
+    pub cases: Vec<CaseResult>,
+    pub passed: usize,
+    pub total: usize,
+}
+
+
+pub async fn run_solution(
+    user: Option<i32>,
+    qid: i32,
+    language: &str,
+    code: &str,
+    cases: Option<Vec<RunCase>>
+) -> Result<RunReport, Box<dyn std::error::Error>> {
+    // the db is only needed to pick the signature and cases, it is closed
+    // before anything runs. without cases of its own a run gets the examples
+    // followed by the user's stored test cases
+    let the_language = Language::from_slug(language)?;
+    let (signature, mut run_cases) = {
+        let conn = db_connect();
+        let signature = solution_signature(&conn, qid, the_language, code)?;
+        let run_cases = match cases.filter(|c| !c.is_empty()) {
+            Some(cases) => cases,
+            None => {
+                let mut run_cases = select_example_cases(&conn, qid, signature.params.len())?;
+                if let Some(the_uid) = user {
+                    run_cases.extend(select_test_cases(&conn, the_uid, qid)?.into_iter().map(|c| c.run_case()));
+                }
+                run_cases
+            }
+        };
+        (signature, run_cases)
+    };
+    let comparator = default_comparator(&signature);
+    for case in run_cases.iter_mut() {
+        case.comparator.get_or_insert(comparator);
+    }
+    if run_cases.is_empty() {
+        return Err(format!("question {} has no example test cases to run against", qid).into());
+    }
+
+    run_cases_locally(qid, the_language, code, &signature, run_cases, case_limits()).await
+}
+
+pub fn solution_signature(conn: &SqliteConnection, the_qid: i32, language: Language, solution: &str) ->
+Result<Signature, Box<dyn std::error::Error>> {
+    // the stored snippet says which method leetcode calls, the code itself
+    // is used for questions without one
+    use crate::db::schema::code_snippet::dsl::*;
+
+    let snippet: Option<String> = code_snippet
+        .filter(qid.eq(the_qid))
+        .filter(lang_slug.eq(language.snippet_slug()))
+        .select(code)
+        .first(conn)
+        .optional()?;
+
+    match snippet {
+        Some(snippet) => parse_signature(language, &snippet),
+        None => parse_signature(language, solution),
+    }
+}
+
+pub fn select_example_cases(conn: &SqliteConnection, the_qid: i32, num_params: usize) ->
+Result<Vec<RunCase>, Box<dyn std::error::Error>> {
+    // exampleTestcases holds every example's arguments back to back, the
+    // outputs come from the examples extracted from the prompt
+    use crate::db::schema::{question_example, question_metadata};
+
+    let testcases: Option<String> = question_metadata::table
+        .filter(question_metadata::qid.eq(the_qid))
+        .select(question_metadata::example_testcases)
+        .first::<Option<String>>(conn)
+        .optional()?
+        .flatten();
+    let lines: Vec<&str> = testcases.as_deref().unwrap_or_default()
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .collect();
+    if lines.is_empty() || num_params == 0 {
+        return Ok(vec![]);
+    }
+
+    refresh_structures(conn, Some(&[the_qid]))?;
+    let outputs: Vec<String> = question_example::table
+        .filter(question_example::qid.eq(the_qid))
+        .order_by(question_example::position.asc())
+        .select(question_example::output)
+        .load(conn)?;
+
+    Ok(lines.chunks(num_params).enumerate().map(|(i, args)| RunCase {
+        input: args.join("\n"),
+        expected: outputs.get(i).cloned(),
+        ..RunCase::default()
+    }).collect())
+}
+
+pub fn default_comparator(signature: &Signature) -> Comparator {
+    // float results are judged with a tolerance, everything else exactly
+    let returns = signature.returns.as_deref().unwrap_or_default();
+    if ["float", "f64", "f32"].iter().any(|t| returns.contains(t)) {
+        return Comparator::FloatTolerance;
+    }
+
+    Comparator::Exact
+}
+
+fn case_limits() -> Limits {
+    Limits { time_ms: CASE_TIME_LIMIT_MS, memory_mb: Some(CASE_MEMORY_LIMIT_MB) }
+}
+
+fn tool(var: &str, default: &str) -> String {
+    dotenv().ok();
+    env::var(var).ok().filter(|v| !v.trim().is_empty()).unwrap_or_else(|| default.to_string())
+}
+
+
+pub async fn run_cases_locally(
+    qid: i32,
+    language: Language,
+    code: &str,
+    signature: &Signature,
+    cases: Vec<RunCase>,
+    limits: Limits
+) -> Result<RunReport, Box<dyn std::error::Error>> {
+    let program = build_program(language, code, signature)?;
+    let workdir = run_dir()?;
+    let report = run_in_dir(&workdir, qid, language, &program, cases, limits).await
+        .map_err(|err| err.to_string());
+    let _ = fs::remove_dir_all(&workdir);
+
+    Ok(report?)
+}
+
+async fn run_in_dir(
+    workdir: &Path,
+    qid: i32,
+    language: Language,
+    program: &str,
+    cases: Vec<RunCase>,
+    limits: Limits
+) -> Result<RunReport, Box<dyn std::error::Error>> {
+    let mut report = RunReport {
+        qid,
+        language: language.snippet_slug().to_string(),
+        compile_error: None,
+        cases: vec![],
+        passed: 0,
+        total: cases.len(),
+    };
+
+    let (runner, runner_args) = match language {
+        Language::Python => {
+            fs::write(workdir.join("solution.py"), program)?;
+            // -I keeps PYTHONPATH and user site packages out of the run
+            (tool(PYTHON_BIN_VAR, "python3"), vec!["-I".to_string(), "solution.py".to_string()])
+        }
+        Language::Rust => {
+            fs::write(workdir.join("solution.rs"), program)?;
+            let rustc = tool(RUSTC_BIN_VAR, "rustc");
+            let compile_args: Vec<String> = ["--edition", "2021", "-O", "-o", "solution", "solution.rs"]
+                .iter().map(|a| a.to_string()).collect();
+            let compiled = run_limited(&rustc, &compile_args, workdir, "",
+                Limits { time_ms: COMPILE_TIME_LIMIT_MS, memory_mb: None }).await?;
+            if !compiled.success() {
+                report.compile_error = Some(if compiled.timed_out {
+                    "compilation timed out".to_string()
+                } else {
+                    compiled.stderr
+                });
+                return Ok(report);
+            }
+            (workdir.join("solution").to_string_lossy().to_string(), vec![])
+        }
+    };
+
+    for case in cases {
+        let outcome = run_limited(&runner, &runner_args, workdir, &format!("{}\n", case.input), limits).await?;
+        let result = case_result(case, outcome);
+        if result.status == CaseStatus::Passed {
+            report.passed += 1;
+        }
+        report.cases.push(result);
+    }
+
+    Ok(report)
+}
+
+fn run_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = env::temp_dir().join(format!("dsa-flashcards-run-{}-{}",
+        std::process::id(), RUN_COUNTER.fetch_add(1, Ordering::SeqCst)));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+
+    Ok(dir)
+}
+
+fn case_result(case: RunCase, outcome: ProcessOutcome) -> CaseResult {
+    // whatever the solution printed comes before the marker line the harness
+    // adds, the result and its timing after it
+    let (stdout, output, method_ms) = match outcome.stdout.rfind(&format!("\n{}", RESULT_MARKER)) {
+        Some(at) => {
+            let mut harness_lines = outcome.stdout[at + 1..].lines();
+            let output = harness_lines.next().and_then(|l| l.strip_prefix(RESULT_MARKER)).map(|o| o.to_string());
+            let method_ms = harness_lines.next()
+                .and_then(|l| l.strip_prefix(TIME_MARKER))
+                .and_then(|t| t.trim().parse::<f64>().ok());
+            (outcome.stdout[..at].to_string(), output, method_ms)
+        }
+        None => (outcome.stdout.clone(), None, None),
+    };
+
+    let out_of_memory = outcome.stderr.contains("MemoryError") ||
+        outcome.stderr.contains("memory allocation of");
+    let status = if outcome.timed_out {
+        CaseStatus::TimeLimitExceeded
+    } else if out_of_memory {
+        CaseStatus::MemoryLimitExceeded
+    } else if !outcome.success() || output.is_none() {
+        CaseStatus::RuntimeError
+    } else {
+        let comparator = case.comparator.unwrap_or(Comparator::Exact);
+        match &case.expected {
+            Some(expected) if outputs_match(expected, output.as_deref().unwrap_or_default(), comparator) => CaseStatus::Passed,
+            Some(_) => CaseStatus::Failed,
+            None => CaseStatus::Ran,
+        }
+    };
+
+    CaseResult {
+        tcid: case.tcid,
+        input: case.input,
+        expected: case.expected,
+        output,
+        status,
+        stdout,
+        stderr: outcome.stderr,
+        time_ms: method_ms.unwrap_or(outcome.wall_ms),
+    }
+}
+
+pub fn outputs_match(expected: &str, actual: &str, comparator: Comparator) -> bool {
+    // json aware, so spacing doesn't matter and 2.00000 equals 2.0. output
+    // that isn't json is compared as trimmed text
+    use serde_json::Value;
+
+    let (expected_json, actual_json) = match (serde_json::from_str::<Value>(expected.trim()), serde_json::from_str::<Value>(actual.trim())) {
+        (Ok(e), Ok(a)) => (e, a),
+        _ => return expected.trim() == actual.trim(),
+    };
+    match (comparator, &expected_json, &actual_json) {
+        (Comparator::FloatTolerance, e, a) => within_tolerance(e, a),
+        (Comparator::UnorderedList, Value::Array(e), Value::Array(a)) => {
+            let mut e: Vec<String> = e.iter().map(|v| v.to_string()).collect();
+            let mut a: Vec<String> = a.iter().map(|v| v.to_string()).collect();
+            e.sort();
+            a.sort();
+            e == a
+        }
+        _ => expected_json == actual_json,
+    }
+}
+
+fn within_tolerance(expected: &serde_json::Value, actual: &serde_json::Value) -> bool {
+    use serde_json::Value;
+
+    match (expected, actual) {
+        (Value::Number(e), Value::Number(a)) => match (e.as_i64(), a.as_i64()) {
+            (Some(e), Some(a)) => e == a,
+            _ => (e.as_f64().unwrap_or(f64::NAN) - a.as_f64().unwrap_or(f64::NAN)).abs() <= FLOAT_TOLERANCE,
+        },
+        (Value::Array(e), Value::Array(a)) => e.len() == a.len() && e.iter().zip(a).all(|(e, a)| within_tolerance(e, a)),
+        _ => expected == actual,
+    }
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::{db_connect_in_memory, LEETCODE_SOURCE_ID};
+    use crate::db::metadata::store_question_details;
+    use crate::db::models::NewQuestion;
+    use crate::sources::{SourceCodeSnippet, SourceDetails};
+
+    const TWO_SUM_PY: &str = "class Solution:\n    def twoSum(self, nums: List[int], target: int) -> List[int]:\n        print('looking', target)\n        seen = {}\n        for i, n in enumerate(nums):\n            if target - n in seen:\n                return [seen[target - n], i]\n            seen[n] = i\n";
+    const TWO_SUM_RS: &str = "impl Solution {\n    pub fn two_sum(nums: Vec<i32>, target: i32) -> Vec<i32> {\n        let mut seen = HashMap::new();\n        for (i, n) in nums.iter().enumerate() {\n            if let Some(&j) = seen.get(&(target - n)) {\n                return vec![j as i32, i as i32];\n            }\n            seen.insert(*n, i);\n        }\n        vec![]\n    }\n}";
+
+    fn installed(program: &str) -> bool {
+        let found = std::process::Command::new(program).arg("--version").output().map(|o| o.status.success()).unwrap_or(false);
+        if !found {
+            println!("{} is not installed, skipping", program);
+        }
+        found
+    }
+
+    fn cases() -> Vec<RunCase> {
+        vec![
+            RunCase { input: "[2,7,11,15]\n9".to_string(), expected: Some("[0,1]".to_string()), ..RunCase::default() },
+            RunCase { input: "[3,2,4]\n6".to_string(), expected: Some("[1, 2]".to_string()), ..RunCase::default() },
+            RunCase { input: "[3,3]\n6".to_string(), expected: None, ..RunCase::default() },
+        ]
+    }
+
+    #[test]
+    fn test_outputs_match() {
+        use Comparator::*;
+        assert!(outputs_match("[0,1]", "[0, 1]", Exact));
+        assert!(outputs_match("2.00000", "2.0", Exact));
+        assert!(outputs_match("\"bab\"", "\"bab\"", Exact));
+        assert!(!outputs_match("[0,1]", "[1,0]", Exact));
+        assert!(outputs_match("not json", " not json", Exact));
+
+        assert!(outputs_match("[[1,2],[3]]", "[[3], [1,2]]", UnorderedList));
+        assert!(!outputs_match("[[1,2],[3]]", "[[3], [2,1]]", UnorderedList));
+        assert!(!outputs_match("[1,1,2]", "[1,2,2]", UnorderedList));
+
+        assert!(outputs_match("[2.50000]", "[2.4999999]", FloatTolerance));
+        assert!(!outputs_match("[2.50000]", "[2.4999999]", Exact));
+        assert!(!outputs_match("2.00000", "2.1", FloatTolerance));
+    }
+
+    #[test]
+    fn test_select_example_cases() {
+        use crate::db::schema::question::dsl::*;
+        let conn = db_connect_in_memory();
+        let prompts: serde_json::Value = serde_json::from_str(include_str!("../../fixtures/leetcode/prompts.json")).unwrap();
+        diesel::insert_into(question)
+            .values(&NewQuestion {
+                title: "Two Sum".to_string(),
+                title_slug: "two-sum".to_string(),
+                prompt: prompts["two-sum"]["data"]["question"]["content"].as_str().unwrap().to_string(),
+                difficulty: "EASY".to_string(),
+                source: LEETCODE_SOURCE_ID,
+                source_qid: 1,
+            })
+            .execute(&conn).unwrap();
+        let the_qid: i32 = question.select(qid).first(&conn).unwrap();
+        store_question_details(&conn, the_qid, &SourceDetails {
+            example_testcases: Some("[2,7,11,15]\n9\n[3,2,4]\n6".to_string()),
+            code_snippets: vec![SourceCodeSnippet {
+                lang: "Python3".to_string(),
+                lang_slug: "python3".to_string(),
+                code: "class Solution:\n    def twoSum(self, nums: List[int], target: int) -> List[int]:\n        ".to_string(),
+            }],
+            ..SourceDetails::default()
+        }).unwrap();
+
+        // the snippet decides the entry point even with helpers in front
+        let helper_first = format!("class Solution:\n    def helper(self, x):\n        return x\n{}", &TWO_SUM_PY[16..]);
+        assert_eq!(solution_signature(&conn, the_qid, Language::Python, &helper_first).unwrap().method, "twoSum");
+        assert_eq!(solution_signature(&conn, the_qid, Language::Rust, TWO_SUM_RS).unwrap().method, "two_sum");
+
+        // only the first example has an output in the prompt fixture
+        assert_eq!(select_example_cases(&conn, the_qid, 2).unwrap(), vec![
+            RunCase { input: "[2,7,11,15]\n9".to_string(), expected: Some("[0,1]".to_string()), ..RunCase::default() },
+            RunCase { input: "[3,2,4]\n6".to_string(), expected: None, ..RunCase::default() },
+        ]);
+        assert!(select_example_cases(&conn, the_qid + 1, 2).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_python_solution() {
+        if !installed("python3") {
+            return;
+        }
+        let signature = parse_signature(Language::Python, TWO_SUM_PY).unwrap();
+        let report = run_cases_locally(1, Language::Python, TWO_SUM_PY, &signature, cases(), case_limits()).await.unwrap();
+        let statuses: Vec<CaseStatus> = report.cases.iter().map(|c| c.status).collect();
+        assert_eq!(statuses, vec![CaseStatus::Passed, CaseStatus::Passed, CaseStatus::Ran]);
+        assert_eq!((report.passed, report.total), (2, 3));
+        assert_eq!(report.cases[0].stdout, "looking 9\n");
+        assert_eq!(report.cases[2].output.as_deref(), Some("[0,1]"));
+
+        let broken = TWO_SUM_PY.replace("seen[n] = i", "seen[n] = i\n            raise ValueError('boom')");
+        let report = run_cases_locally(1, Language::Python, &broken, &signature, cases(), case_limits()).await.unwrap();
+        assert_eq!(report.cases[0].status, CaseStatus::RuntimeError);
+        assert!(report.cases[0].stderr.contains("boom"));
+
+        let slow = "class Solution:\n    def twoSum(self, nums, target):\n        while True:\n            pass\n";
+        let limits = Limits { time_ms: 500, memory_mb: Some(CASE_MEMORY_LIMIT_MB) };
+        let report = run_cases_locally(1, Language::Python, slow, &signature, cases()[..1].to_vec(), limits).await.unwrap();
+        assert_eq!(report.cases[0].status, CaseStatus::TimeLimitExceeded);
+
+        let greedy = "class Solution:\n    def twoSum(self, nums, target):\n        return [0] * (10 ** 10)\n";
+        let report = run_cases_locally(1, Language::Python, greedy, &signature, cases()[..1].to_vec(), case_limits()).await.unwrap();
+        assert_eq!(report.cases[0].status, CaseStatus::MemoryLimitExceeded);
+    }
+
+    #[tokio::test]
+    async fn test_run_rust_solution() {
+        if !installed("rustc") {
+            return;
+        }
+        let signature = parse_signature(Language::Rust, TWO_SUM_RS).unwrap();
+        let report = run_cases_locally(1, Language::Rust, TWO_SUM_RS, &signature, cases(), case_limits()).await.unwrap();
+        assert_eq!(report.compile_error, None);
+        let statuses: Vec<CaseStatus> = report.cases.iter().map(|c| c.status).collect();
+        assert_eq!(statuses, vec![CaseStatus::Passed, CaseStatus::Passed, CaseStatus::Ran]);
+
+        let in_place = "impl Solution {\n    pub fn rotate(nums: &mut Vec<i32>, k: i32) {\n        let k = k as usize % nums.len();\n        nums.rotate_right(k);\n    }\n}";
+        let rotate = parse_signature(Language::Rust, in_place).unwrap();
+        let report = run_cases_locally(1, Language::Rust, in_place, &rotate, vec![RunCase {
+            input: "[1,2,3,4,5,6,7]\n3".to_string(),
+            expected: Some("[5,6,7,1,2,3,4]".to_string()),
+            ..RunCase::default()
+        }], case_limits()).await.unwrap();
+        assert_eq!(report.cases[0].status, CaseStatus::Passed);
+
+        let report = run_cases_locally(1, Language::Rust, &TWO_SUM_RS.replace("vec![]", "vec![] oops"), &signature, cases(), case_limits()).await.unwrap();
+        assert!(report.compile_error.unwrap().contains("error"));
+        assert!(report.cases.is_empty());
+    }
+}
@@ -0,0 +1,4 @@
+pub mod cases;
+pub mod harness;
+pub mod lib;
+pub mod sandbox;
@@ -0,0 +1,164 @@
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+// runs interpreters, compilers and compiled solutions as child processes
+// with a wall clock limit, and on unix a cpu time and address space limit
+// set with ulimit by a sh wrapper right before exec. a child that runs over
+// its time is killed. output beyond MAX_OUTPUT_BYTES is cut off
+
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub time_ms: u64,
+    // none for compilers, rustc needs more than any solution should
+    pub memory_mb: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessOutcome {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub wall_ms: f64,
+}
+
+impl ProcessOutcome {
+    pub fn success(&self) -> bool {
+        !self.timed_out && self.exit_code == Some(0)
+    }
+}
+
+
+pub async fn run_limited(
+    program: &str,
+    args: &[String],
+    dir: &Path,
+    stdin: &str,
+    limits: Limits
+) -> Result<ProcessOutcome, Box<dyn std::error::Error>> {
+    let mut command = limited_command(program, args, limits);
+    command
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let started = Instant::now();
+    let mut child = command.spawn()
+        .map_err(|err| format!("could not start {}: {}", program, err))?;
+    let child_stdin = child.stdin.take();
+    let feed = async move {
+        // a solution that never reads its input closes the pipe early, one
+        // that never reads and never exits is cut off by the timeout below.
+        // the pipe closes when child_stdin drops
+        if let Some(mut child_stdin) = child_stdin {
+            let _ = child_stdin.write_all(stdin.as_bytes()).await;
+        }
+    };
+
+    // input is fed while the output is read, both within the time limit.
+    // dropping the wait on timeout kills the child (kill_on_drop)
+    let run = async { tokio::join!(feed, child.wait_with_output()).1 };
+    let waited = tokio::time::timeout(Duration::from_millis(limits.time_ms), run).await;
+    let wall_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let output = match waited {
+        Ok(output) => output?,
+        Err(_) => {
+            return Ok(ProcessOutcome {
+                stdout: String::new(),
+                stderr: String::new(),
+                exit_code: None,
+                timed_out: true,
+                wall_ms,
+            });
+        }
+    };
+
+    Ok(ProcessOutcome {
+        stdout: truncated(&output.stdout),
+        stderr: truncated(&output.stderr),
+        exit_code: output.status.code(),
+        timed_out: false,
+        wall_ms,
+    })
+}
+
+#[cfg(unix)]
+fn limited_command(program: &str, args: &[String], limits: Limits) -> Command {
+    // ulimit -t is a backstop for the wall clock limit and counts whole
+    // seconds. limits the platform doesn't support are skipped quietly
+    let mut script = format!("ulimit -t {} 2>/dev/null; ", limits.time_ms / 1000 + 1);
+    if let Some(memory_mb) = limits.memory_mb {
+        script.push_str(&format!("ulimit -v {} 2>/dev/null; ", memory_mb * 1024));
+    }
+    script.push_str("exec \"$@\"");
+
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(script).arg("sh").arg(program).args(args);
+    command
+}
+
+#[cfg(not(unix))]
+fn limited_command(program: &str, args: &[String], _limits: Limits) -> Command {
+    // only the wall clock limit applies here
+    let mut command = Command::new(program);
+    command.args(args);
+    command
+}
+
+fn truncated(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_OUTPUT_BYTES)]).to_string();
+    if bytes.len() > MAX_OUTPUT_BYTES {
+        return format!("{}\n... output cut off after {} bytes", text, MAX_OUTPUT_BYTES);
+    }
+
+    text
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    fn sh(script: &str) -> Vec<String> {
+        vec!["-c".to_string(), script.to_string()]
+    }
+
+    #[tokio::test]
+    async fn test_run_limited_captures_output_and_exit_code() {
+        let limits = Limits { time_ms: 5000, memory_mb: None };
+        let outcome = run_limited("sh", &sh("read line; echo \"got $line\"; echo oops >&2; exit 3"),
+            &std::env::temp_dir(), "hello\n", limits).await.unwrap();
+        assert_eq!((outcome.stdout.as_str(), outcome.stderr.as_str()), ("got hello\n", "oops\n"));
+        assert_eq!(outcome.exit_code, Some(3));
+        assert!(!outcome.success());
+
+        assert!(run_limited("no-such-program-here", &[], &std::env::temp_dir(), "", limits).await
+            .map(|o| o.exit_code != Some(0)).unwrap_or(true));
+    }
+
+    #[tokio::test]
+    async fn test_run_limited_kills_on_timeout() {
+        let limits = Limits { time_ms: 200, memory_mb: Some(64) };
+        let started = Instant::now();
+        let outcome = run_limited("sh", &sh("sleep 5"), &std::env::temp_dir(), "", limits).await.unwrap();
+        assert!(outcome.timed_out);
+        assert!(started.elapsed() < Duration::from_secs(3));
+
+        // more input than the pipe holds, for a child that never reads it
+        let started = Instant::now();
+        let input = "1\n".repeat(512 * 1024);
+        let outcome = run_limited("sh", &sh("sleep 5"), &std::env::temp_dir(), &input, limits).await.unwrap();
+        assert!(outcome.timed_out);
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,245 @@
+use async_trait::async_trait;
+use crate::network::fetchers::{cf_api_endpoint, fetch_cf_problemset, ListProgress};
+use crate::network::structs::CfProblem;
+use crate::sources::{QuestionSource, SourceQuestion};
+
+// codeforces problemset. problems are identified by contest id + index
+// ("1520F2"), which is the title slug here and is packed into source_qid
+// since that column is an integer. ratings (800-3500) map onto the
+// EASY/MEDIUM/HARD the question table allows, the rating itself is kept in
+// question_metadata. the api has no problem statements, so prompts stay
+// empty and the ui links to problem_url instead
+
+pub const CODEFORCES_SOURCE_NAME: &str = "codeforces";
+const CF_PROBLEM_URL: &str = "https://codeforces.com/problemset/problem/";
+// ratings below these are EASY and MEDIUM, anything from HARD_RATING up is
+// HARD. unrated (usually brand new) problems count as MEDIUM
+const MEDIUM_RATING: i32 = 1400;
+const HARD_RATING: i32 = 1900;
+
+// codeforces tags whose LC topic is named differently. every other tag is
+// title cased, which lines it up with LC names like "Binary Search"
+const TOPIC_ALIASES: [(&str, &str); 16] = [
+    ("dp", "Dynamic Programming"),
+    ("graphs", "Graph"),
+    ("strings", "String"),
+    ("sortings", "Sorting"),
+    ("trees", "Tree"),
+    ("dfs and similar", "Depth-First Search"),
+    ("bitmasks", "Bit Manipulation"),
+    ("hashing", "Hash Function"),
+    ("shortest paths", "Shortest Path"),
+    ("dsu", "Union Find"),
+    ("divide and conquer", "Divide and Conquer"),
+    ("games", "Game Theory"),
+    ("probabilities", "Probability and Statistics"),
+    ("matrices", "Matrix"),
+    ("brute force", "Enumeration"),
+    ("implementation", "Simulation"),
+];
+
+pub struct CodeforcesSource {
+    endpoint: String,
+}
+
+impl Default for CodeforcesSource {
+    fn default() -> Self {
+        CodeforcesSource { endpoint: cf_api_endpoint() }
+    }
+}
+
+#[cfg(test)]
+impl CodeforcesSource {
+    pub fn with_endpoint(endpoint: &str) -> Self {
+        CodeforcesSource { endpoint: endpoint.to_string() }
+    }
+}
+
+#[async_trait]
+impl QuestionSource for CodeforcesSource {
+    fn name(&self) -> &str {
+        CODEFORCES_SOURCE_NAME
+    }
+
+    fn label(&self) -> String {
+        "Codeforces".to_string()
+    }
+
+    fn question_number(&self, _source_qid: i32, title_slug: &str, _frontend_id: Option<&str>) -> Option<String> {
+        // the packed source_qid means nothing to people, "1520F2" does
+        Some(title_slug.to_uppercase())
+    }
+
+    fn problem_url(&self, title_slug: &str) -> Option<String> {
+        let (contest_id, index) = split_problem_id(title_slug)?;
+        Some(format!("{}{}/{}", CF_PROBLEM_URL, contest_id, index))
+    }
+
+    fn has_prompts(&self) -> bool {
+        // the problemset api hands out no statements
+        false
+    }
+
+    async fn list_questions(&self, on_page: &(dyn Fn(ListProgress) + Send + Sync)) ->
+    Result<Vec<SourceQuestion>, Box<dyn std::error::Error>> {
+        let problemset = fetch_cf_problemset(&self.endpoint).await?;
+        let questions: Vec<SourceQuestion> = problemset.problems.into_iter()
+            .filter_map(source_question)
+            .collect();
+        on_page(ListProgress { page: 1, fetched: questions.len(), total: questions.len() });
+
+        Ok(questions)
+    }
+
+    async fn fetch_prompt(&self, title_slug: &str) -> Result<String, Box<dyn std::error::Error>> {
+        split_problem_id(title_slug)
+            .ok_or(format!("not a codeforces problem id: {}", title_slug))?;
+
+        Ok(String::new())
+    }
+
+    async fn fetch_metadata(&self, title_slug: &str) ->
+    Result<SourceQuestion, Box<dyn std::error::Error>> {
+        let problemset = fetch_cf_problemset(&self.endpoint).await?;
+        problemset.problems.into_iter()
+            .filter_map(source_question)
+            .find(|q| q.title_slug.eq_ignore_ascii_case(title_slug))
+            .ok_or_else(|| format!("no codeforces problem {}", title_slug).into())
+    }
+}
+
+
+pub fn source_question(problem: CfProblem) -> Option<SourceQuestion> {
+    // problems outside of contests (acmsguru, gyms) can't be addressed by
+    // contest id + index and are left out
+    let contest_id = problem.contestId?;
+    let source_qid = encode_problem_id(contest_id, &problem.index)?;
+    let topics: Vec<String> = problem.tags.iter()
+        .filter(|tag| !tag.starts_with('*'))
+        .map(|tag| topic_name(tag))
+        .collect();
+
+    Some(SourceQuestion {
+        source_qid,
+        title: problem.name,
+        title_slug: format!("{}{}", contest_id, problem.index),
+        difficulty: rating_difficulty(problem.rating).to_string(),
+        topics,
+        paid_only: Some(false),
+        acceptance_rate: None,
+        rating: problem.rating,
+        frontend_id: None,
+        prompt: None,
+    })
+}
+
+pub fn rating_difficulty(rating: Option<i32>) -> &'static str {
+    match rating {
+        Some(r) if r < MEDIUM_RATING => "EASY",
+        Some(r) if r >= HARD_RATING => "HARD",
+        _ => "MEDIUM",
+    }
+}
+
+pub fn encode_problem_id(contest_id: i32, index: &str) -> Option<i32> {
+    // contest * 1000 + letter * 10 + part, so 1520F2 -> 1520052 and 4A -> 4000
+    let mut chars = index.chars();
+    let letter = chars.next().filter(|c| c.is_ascii_uppercase())?;
+    let part = match chars.as_str() {
+        "" => 0,
+        digit if digit.len() == 1 => digit.parse::<i32>().ok()?,
+        _ => return None,
+    };
+    if contest_id <= 0 || contest_id > 2_000_000 {
+        return None;
+    }
+
+    Some(contest_id * 1000 + (letter as i32 - 'A' as i32) * 10 + part)
+}
+
+fn split_problem_id(title_slug: &str) -> Option<(i32, String)> {
+    let split_at = title_slug.find(|c: char| !c.is_ascii_digit())?;
+    let (contest, index) = title_slug.split_at(split_at);
+
+    Some((contest.parse().ok()?, index.to_uppercase()))
+}
+
+fn topic_name(tag: &str) -> String {
+    if let Some((_, alias)) = TOPIC_ALIASES.iter().find(|(cf_tag, _)| *cf_tag == tag) {
+        return alias.to_string();
+    }
+
+    tag.split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::mock_server::{MockResponse, MockServer};
+
+    const PROBLEMSET_FIXTURE: &str = include_str!("../../fixtures/codeforces/problemset.json");
+
+    #[test]
+    fn test_encode_problem_id() {
+        assert_eq!(encode_problem_id(4, "A"), Some(4000));
+        assert_eq!(encode_problem_id(1520, "F2"), Some(1520052));
+        assert_eq!(encode_problem_id(1520, "F1"), Some(1520051));
+        assert_eq!(encode_problem_id(1, "100"), None);
+        assert_eq!(encode_problem_id(1, "a"), None);
+        assert_eq!(split_problem_id("1520f2"), Some((1520, "F2".to_string())));
+    }
+
+    #[test]
+    fn test_rating_difficulty_and_topics() {
+        assert_eq!(rating_difficulty(Some(800)), "EASY");
+        assert_eq!(rating_difficulty(Some(1400)), "MEDIUM");
+        assert_eq!(rating_difficulty(Some(1900)), "HARD");
+        assert_eq!(rating_difficulty(None), "MEDIUM");
+        assert_eq!(topic_name("binary search"), "Binary Search");
+        assert_eq!(topic_name("dp"), "Dynamic Programming");
+    }
+
+    #[tokio::test]
+    async fn test_list_questions_from_fixture() {
+        let server = MockServer::start(|request, _| match request.path.as_str() {
+            "/problemset.problems" => MockResponse::json(200, PROBLEMSET_FIXTURE),
+            _ => MockResponse::json(400, r#"{"status": "FAILED", "comment": "method not found"}"#),
+        }).await;
+        let cf = CodeforcesSource::with_endpoint(&server.url);
+
+        let questions = cf.list_questions(&|_| {}).await.unwrap();
+        // the acmsguru problem has no contest id
+        assert_eq!(questions.len(), 6);
+        let taxi = questions.iter().find(|q| q.title == "Taxi").unwrap();
+        assert_eq!((taxi.source_qid, taxi.title_slug.as_str()), (158010, "158B"));
+        assert_eq!((taxi.difficulty.as_str(), taxi.rating), ("EASY", Some(1100)));
+        assert_eq!(taxi.topics, vec!["Greedy".to_string(), "Simulation".to_string()]);
+        let unrated = questions.iter().find(|q| q.title_slug == "1948G").unwrap();
+        assert_eq!((unrated.difficulty.as_str(), unrated.rating), ("MEDIUM", None));
+
+        let hard = cf.fetch_metadata("1520F2").await.unwrap();
+        assert_eq!(hard.difficulty, "HARD");
+        assert_eq!(cf.fetch_prompt("1520F2").await.unwrap(), "");
+        assert!(cf.fetch_prompt("taxi").await.is_err());
+        assert_eq!(cf.problem_url("1520F2").unwrap(), "https://codeforces.com/problemset/problem/1520/F2");
+
+        let failing = MockServer::start(|_, _| {
+            MockResponse::json(200, r#"{"status": "FAILED", "comment": "Call limit exceeded"}"#)
+        }).await;
+        let err = CodeforcesSource::with_endpoint(&failing.url).list_questions(&|_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("Call limit exceeded"));
+    }
+}
@@ -0,0 +1,294 @@
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+use std::collections::HashSet;
+use std::fs;
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::db_connect;
+use crate::init::lib::{apply_source_sync, SyncSummary};
+use crate::network::client::shared_client;
+use crate::network::fetchers::ListProgress;
+use crate::sources::{register_source, QuestionSource, SourceQuestion};
+
+// question feeds let a question bank that isn't one of the built in
+// providers into the app. a feed is a json or yaml document, read from a
+// local file or an http(s) url:
+//
+//   name: acme-interview-bank        # required, the feed's source is "feed:<name>"
+//   questions:
+//     - id: 101                      # required, integer, unique within the feed
+//       title: Merge Meeting Rooms   # required
+//       slug: merge-meeting-rooms    # optional, derived from the title
+//       difficulty: medium           # required, easy / medium / hard
+//       prompt: "<p>Given ...</p>"   # optional, html
+//       topics: [Array, Sorting]     # optional, matched to topics by name
+//
+// every feed gets its own source row, and its location is remembered so
+// sync_source can re-read it. importing again updates questions in place,
+// keyed by (source, id), and archives questions the feed dropped
+
+pub const FEED_SOURCE_PREFIX: &str = "feed:";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Feed {
+    pub name: String,
+    pub questions: Vec<FeedQuestion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeedQuestion {
+    pub id: i32,
+    pub title: String,
+    pub slug: Option<String>,
+    pub difficulty: String,
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub topics: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FeedImportSummary {
+    pub source_id: i32,
+    pub source_name: String,
+    pub sync: SyncSummary,
+}
+
+pub struct FeedSource {
+    name: String,
+    location: String,
+}
+
+impl FeedSource {
+    pub fn new(name: &str, location: &str) -> Self {
+        FeedSource { name: name.to_string(), location: location.to_string() }
+    }
+}
+
+#[async_trait]
+impl QuestionSource for FeedSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn label(&self) -> String {
+        self.name.trim_start_matches(FEED_SOURCE_PREFIX).to_string()
+    }
+
+    fn has_prompts(&self) -> bool {
+        // prompts are stored when the feed is imported, there is nothing
+        // more to fetch later
+        false
+    }
+
+    async fn list_questions(&self, on_page: &(dyn Fn(ListProgress) + Send + Sync)) ->
+    Result<Vec<SourceQuestion>, Box<dyn std::error::Error>> {
+        let feed = read_feed(&self.location).await?;
+        if feed_source_name(&feed) != self.name {
+            return Err(format!("feed at {} is now named {}, import it as a new feed",
+                self.location, feed.name).into());
+        }
+        let questions = feed_questions(feed)?;
+        on_page(ListProgress { page: 1, fetched: questions.len(), total: questions.len() });
+
+        Ok(questions)
+    }
+
+    async fn fetch_prompt(&self, title_slug: &str) -> Result<String, Box<dyn std::error::Error>> {
+        // prompts come with the feed. one that had none still has none
+        let feed = feed_questions(read_feed(&self.location).await?)?;
+        feed.into_iter()
+            .find(|q| q.title_slug == title_slug)
+            .map(|q| q.prompt.unwrap_or_default())
+            .ok_or_else(|| format!("no question {} in feed {}", title_slug, self.name).into())
+    }
+
+    async fn fetch_metadata(&self, title_slug: &str) ->
+    Result<SourceQuestion, Box<dyn std::error::Error>> {
+        let feed = feed_questions(read_feed(&self.location).await?)?;
+        feed.into_iter()
+            .find(|q| q.title_slug == title_slug)
+            .ok_or_else(|| format!("no question {} in feed {}", title_slug, self.name).into())
+    }
+}
+
+
+pub async fn import_feed(location: String) -> Result<FeedImportSummary, Box<dyn std::error::Error>> {
+    // connections are opened after the feed is read, not across the read
+    let feed = read_feed(&location).await?;
+
+    apply_feed(&db_connect(), &location, feed)
+}
+
+pub fn apply_feed(conn: &SqliteConnection, feed_location: &str, feed: Feed) ->
+Result<FeedImportSummary, Box<dyn std::error::Error>> {
+    use crate::db::schema::feed_source::dsl::*;
+
+    let source_name = feed_source_name(&feed);
+    let questions = feed_questions(feed)?;
+    let src_id = register_source(conn, &source_name)?;
+    diesel::replace_into(feed_source)
+        .values((sid.eq(src_id), location.eq(feed_location)))
+        .execute(conn)?;
+    let sync = apply_source_sync(conn, src_id, questions)?;
+
+    Ok(FeedImportSummary { source_id: src_id, source_name, sync })
+}
+
+pub fn select_feed_location(conn: &SqliteConnection, src_id: i32) ->
+Result<Option<String>, Box<dyn std::error::Error>> {
+    use crate::db::schema::feed_source::dsl::*;
+
+    Ok(feed_source.filter(sid.eq(src_id)).select(location).first(conn).optional()?)
+}
+
+
+pub async fn read_feed(location: &str) -> Result<Feed, Box<dyn std::error::Error>> {
+    let text = if location.starts_with("http://") || location.starts_with("https://") {
+        shared_client().get_text(location).await?
+    }
+    else {
+        fs::read_to_string(location)?
+    };
+
+    parse_feed(&text)
+}
+
+pub fn parse_feed(text: &str) -> Result<Feed, Box<dyn std::error::Error>> {
+    // json is tried first for its clearer errors, yaml otherwise
+    if text.trim_start().starts_with('{') {
+        return Ok(serde_json::from_str(text)?);
+    }
+
+    Ok(serde_yaml::from_str(text)?)
+}
+
+fn feed_source_name(feed: &Feed) -> String {
+    format!("{}{}", FEED_SOURCE_PREFIX, feed.name.trim())
+}
+
+pub fn feed_questions(feed: Feed) -> Result<Vec<SourceQuestion>, Box<dyn std::error::Error>> {
+    // the whole feed is checked up front so a bad entry can't leave half a
+    // feed imported
+    if feed.name.trim().is_empty() {
+        return Err("feed has no name".into());
+    }
+    let mut problems: Vec<String> = vec![];
+    let mut seen: HashSet<i32> = HashSet::new();
+    let mut questions: Vec<SourceQuestion> = vec![];
+    for (i, fq) in feed.questions.into_iter().enumerate() {
+        let difficulty = fq.difficulty.trim().to_uppercase();
+        if !["EASY", "MEDIUM", "HARD"].contains(&difficulty.as_str()) {
+            problems.push(format!("question {} (id {}): difficulty must be easy, medium or hard", i + 1, fq.id));
+        }
+        if fq.title.trim().is_empty() {
+            problems.push(format!("question {} (id {}): title is empty", i + 1, fq.id));
+        }
+        if !seen.insert(fq.id) {
+            problems.push(format!("question {} (id {}): id is used more than once", i + 1, fq.id));
+        }
+        let title_slug = fq.slug.filter(|s| !s.trim().is_empty()).unwrap_or_else(|| slugify(&fq.title));
+
+        questions.push(SourceQuestion {
+            source_qid: fq.id,
+            title: fq.title.trim().to_string(),
+            title_slug,
+            difficulty,
+            topics: fq.topics,
+            paid_only: None,
+            acceptance_rate: None,
+            rating: None,
+            frontend_id: None,
+            prompt: fq.prompt,
+        });
+    }
+    if !problems.is_empty() {
+        return Err(format!("invalid feed: {}", problems.join("; ")).into());
+    }
+
+    Ok(questions)
+}
+
+fn slugify(title: &str) -> String {
+    title.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::db_connect_in_memory;
+    use crate::db::models::Question;
+    use crate::network::mock_server::{MockResponse, MockServer};
+
+    const YAML_FEED: &str = include_str!("../../fixtures/feeds/interview_bank.yaml");
+    const JSON_FEED: &str = include_str!("../../fixtures/feeds/interview_bank.json");
+
+    #[test]
+    fn test_parse_feed_json_and_yaml() {
+        let from_yaml = parse_feed(YAML_FEED).unwrap();
+        let from_json = parse_feed(JSON_FEED).unwrap();
+        assert_eq!(from_yaml, from_json);
+
+        let questions = feed_questions(from_yaml).unwrap();
+        assert_eq!(questions.len(), 3);
+        assert_eq!(questions[0].title_slug, "merge-meeting-rooms");
+        assert_eq!(questions[1].title_slug, "lru-cache-v2");
+        assert_eq!(questions[0].difficulty, "MEDIUM");
+    }
+
+    #[test]
+    fn test_feed_questions_rejects_bad_entries() {
+        let mut feed = parse_feed(YAML_FEED).unwrap();
+        feed.questions[1].difficulty = "brutal".to_string();
+        feed.questions[2].id = feed.questions[0].id;
+        let err = feed_questions(feed).unwrap_err().to_string();
+        assert!(err.contains("question 2 (id 102): difficulty"));
+        assert!(err.contains("id is used more than once"));
+    }
+
+    #[test]
+    fn test_apply_feed_is_idempotent() {
+        use crate::db::schema::question::dsl::*;
+        let conn = db_connect_in_memory();
+        let feed = parse_feed(YAML_FEED).unwrap();
+
+        let first = apply_feed(&conn, "bank.yaml", feed.clone()).unwrap();
+        assert_eq!(first.source_name, "feed:acme-interview-bank");
+        assert_eq!(first.sync.inserted, 3);
+        let again = apply_feed(&conn, "bank.yaml", feed.clone()).unwrap();
+        assert_eq!(again.source_id, first.source_id);
+        assert_eq!(again.sync, SyncSummary { unchanged: 3, ..SyncSummary::default() });
+        assert_eq!(select_feed_location(&conn, first.source_id).unwrap().as_deref(), Some("bank.yaml"));
+
+        let mut edited = feed;
+        edited.questions[0].prompt = Some("<p>rooms, but sorted</p>".to_string());
+        edited.questions.pop();
+        let third = apply_feed(&conn, "bank.json", edited).unwrap();
+        assert_eq!(third.sync, SyncSummary { updated: 1, archived: 1, unchanged: 1, ..SyncSummary::default() });
+        let rooms: Question = question
+            .filter(source.eq(first.source_id))
+            .filter(source_qid.eq(101))
+            .first(&conn).unwrap();
+        assert_eq!(rooms.prompt.as_deref(), Some("<p>rooms, but sorted</p>"));
+        assert_eq!(select_feed_location(&conn, first.source_id).unwrap().as_deref(), Some("bank.json"));
+    }
+
+    #[tokio::test]
+    async fn test_feed_source_reads_url() {
+        let server = MockServer::start(|_, _| MockResponse::json(200, JSON_FEED)).await;
+        let url = format!("{}/bank.json", server.url);
+        let feed_source = FeedSource::new("feed:acme-interview-bank", &url);
+
+        let questions = feed_source.list_questions(&|_| {}).await.unwrap();
+        assert_eq!(questions.len(), 3);
+        assert!(feed_source.fetch_prompt("merge-meeting-rooms").await.unwrap().contains("meetings"));
+        assert!(FeedSource::new("feed:renamed", &url).list_questions(&|_| {}).await.is_err());
+    }
+}
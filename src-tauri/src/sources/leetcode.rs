@@ -0,0 +1,188 @@
+use async_trait::async_trait;
+use crate::network::fetchers::{
+    fetch_all_lc_questions,
+    fetch_lc_question_prompt,
+    fetch_lc_question_details,
+    fetch_lc_question_metadata,
+    fetch_lc_question_by_number,
+    lc_graphql_endpoint,
+    ListProgress
+};
+use crate::network::structs::{ResponseQuestion, ResponseQuestionDetails};
+use crate::sources::{QuestionSource, SourceCodeSnippet, SourceDetails, SourceQuestion};
+
+pub const LEETCODE_SOURCE_NAME: &str = "leetcode";
+const LC_PROBLEM_URL: &str = "https://leetcode.com/problems/";
+
+pub struct LeetCodeSource {
+    endpoint: String,
+}
+
+impl Default for LeetCodeSource {
+    fn default() -> Self {
+        LeetCodeSource { endpoint: lc_graphql_endpoint() }
+    }
+}
+
+#[cfg(test)]
+impl LeetCodeSource {
+    pub fn with_endpoint(endpoint: &str) -> Self {
+        LeetCodeSource { endpoint: endpoint.to_string() }
+    }
+}
+
+impl LeetCodeSource {
+    // base info for the question LC shows as #`number`
+    pub async fn fetch_by_number(&self, number: i32) ->
+    Result<Option<SourceQuestion>, Box<dyn std::error::Error>> {
+        match fetch_lc_question_by_number(&self.endpoint, number).await? {
+            Some(rq) => Ok(Some(source_question(rq)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl QuestionSource for LeetCodeSource {
+    fn name(&self) -> &str {
+        LEETCODE_SOURCE_NAME
+    }
+
+    fn label(&self) -> String {
+        "LeetCode".to_string()
+    }
+
+    fn question_number(&self, _source_qid: i32, _title_slug: &str, frontend_id: Option<&str>) -> Option<String> {
+        // source_qid is LC's internal questionId, not the number it shows.
+        // questions stored before frontend ids were kept have none until
+        // the next sync
+        frontend_id.map(|n| n.to_string())
+    }
+
+    fn problem_url(&self, title_slug: &str) -> Option<String> {
+        Some(format!("{}{}/", LC_PROBLEM_URL, title_slug))
+    }
+
+    async fn list_questions(&self, on_page: &(dyn Fn(ListProgress) + Send + Sync)) ->
+    Result<Vec<SourceQuestion>, Box<dyn std::error::Error>> {
+        let fetched = fetch_all_lc_questions(&self.endpoint, on_page).await?;
+        let mut questions: Vec<SourceQuestion> = vec![];
+        for rq in fetched.questions {
+            questions.push(source_question(rq)?);
+        }
+
+        Ok(questions)
+    }
+
+    async fn fetch_prompt(&self, title_slug: &str) -> Result<String, Box<dyn std::error::Error>> {
+        fetch_lc_question_prompt(&self.endpoint, title_slug).await
+    }
+
+    async fn fetch_details(&self, title_slug: &str) ->
+    Result<SourceDetails, Box<dyn std::error::Error>> {
+        Ok(source_details(fetch_lc_question_details(&self.endpoint, title_slug).await?))
+    }
+
+    async fn fetch_metadata(&self, title_slug: &str) ->
+    Result<SourceQuestion, Box<dyn std::error::Error>> {
+        source_question(fetch_lc_question_metadata(&self.endpoint, title_slug).await?)
+    }
+}
+
+pub fn source_question(rq: ResponseQuestion) -> Result<SourceQuestion, Box<dyn std::error::Error>> {
+    // LC graphql gives ids as strings and difficulty capitalized
+    Ok(SourceQuestion {
+        source_qid: rq.questionId.parse::<i32>()?,
+        title: rq.title,
+        title_slug: rq.titleSlug,
+        difficulty: rq.difficulty.to_uppercase(),
+        topics: rq.topicTags.into_iter().map(|t| t.name).collect(),
+        paid_only: rq.paidOnly,
+        acceptance_rate: rq.acRate,
+        rating: None,
+        frontend_id: rq.frontendQuestionId,
+        prompt: None,
+    })
+}
+
+pub fn source_details(rd: ResponseQuestionDetails) -> SourceDetails {
+    // stats is a json string like {"acRate": "49.7%", ...} and
+    // similarQuestions a json string holding a list of {titleSlug, ...}.
+    // paid only questions come back without content or snippets
+    let acceptance_rate = rd.stats.as_deref()
+        .and_then(|stats| serde_json::from_str::<serde_json::Value>(stats).ok())
+        .and_then(|stats| stats["acRate"].as_str().map(|rate| rate.trim_end_matches('%').to_string()))
+        .and_then(|rate| rate.trim().parse::<f64>().ok());
+    let similar_questions: Vec<String> = rd.similarQuestions.as_deref()
+        .and_then(|similar| serde_json::from_str::<Vec<serde_json::Value>>(similar).ok())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|q| q["titleSlug"].as_str().map(|slug| slug.to_string()))
+        .collect();
+
+    SourceDetails {
+        prompt: rd.content.unwrap_or_default(),
+        paid_only: Some(rd.isPaidOnly),
+        acceptance_rate,
+        likes: rd.likes,
+        dislikes: rd.dislikes,
+        hints: rd.hints,
+        code_snippets: rd.codeSnippets.unwrap_or_default().into_iter()
+            .map(|s| SourceCodeSnippet { lang: s.lang, lang_slug: s.langSlug, code: s.code })
+            .collect(),
+        example_testcases: rd.exampleTestcases,
+        similar_questions,
+    }
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::structs::ResponseTopic;
+
+    #[test]
+    fn test_source_question() {
+        let rq = ResponseQuestion {
+            difficulty: "Easy".to_string(),
+            questionId: "2047".to_string(),
+            frontendQuestionId: Some("1901".to_string()),
+            title: "Two Sum".to_string(),
+            titleSlug: "two-sum".to_string(),
+            topicTags: vec![ResponseTopic {
+                name: "Array".to_string(), id: "VG9waWNUYWdOb2RlOjU=".to_string(), slug: "array".to_string()
+            }],
+            acRate: Some(49.71),
+            paidOnly: Some(false),
+        };
+        let sq = source_question(rq).unwrap();
+        assert_eq!((sq.source_qid, sq.frontend_id.as_deref()), (2047, Some("1901")));
+        assert_eq!(sq.difficulty, "EASY");
+        assert_eq!(sq.topics, vec!["Array".to_string()]);
+        assert_eq!((sq.paid_only, sq.acceptance_rate), (Some(false), Some(49.71)));
+    }
+
+    #[test]
+    fn test_source_details() {
+        let rd = ResponseQuestionDetails {
+            content: None,
+            isPaidOnly: true,
+            likes: Some(120),
+            dislikes: None,
+            stats: Some(r#"{"totalAccepted": "1.2K", "acRate": "38.5%"}"#.to_string()),
+            hints: vec!["sort first".to_string()],
+            exampleTestcases: None,
+            similarQuestions: Some(r#"[{"title": "3Sum", "titleSlug": "3sum", "difficulty": "Medium"}]"#.to_string()),
+            codeSnippets: None,
+        };
+        let details = source_details(rd);
+        assert_eq!(details.prompt, "");
+        assert_eq!(details.paid_only, Some(true));
+        assert_eq!(details.acceptance_rate, Some(38.5));
+        assert_eq!(details.similar_questions, vec!["3sum".to_string()]);
+        assert!(details.code_snippets.is_empty());
+    }
+}
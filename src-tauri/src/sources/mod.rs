@@ -0,0 +1,285 @@
+pub mod codeforces;
+pub mod feed;
+pub mod leetcode;
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::QuestionQueryResult;
+use crate::db::metadata::select_frontend_ids;
+use crate::network::fetchers::ListProgress;
+use crate::sources::codeforces::CodeforcesSource;
+use crate::sources::feed::{select_feed_location, FeedSource};
+use crate::sources::leetcode::LeetCodeSource;
+
+
+// provider neutral shape of a question coming from a question source.
+// difficulty is already normalized to EASY, MEDIUM or HARD. paid_only,
+// acceptance_rate (a percentage) and rating (the provider's own numeric
+// difficulty) are None when the provider doesn't say. frontend_id is the
+// number the provider shows when that isn't source_qid (leetcode). prompt is
+// only set by sources whose list carries prompts, like question feeds
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceQuestion {
+    pub source_qid: i32,
+    pub title: String,
+    pub title_slug: String,
+    pub difficulty: String,
+    pub topics: Vec<String>,
+    pub paid_only: Option<bool>,
+    pub acceptance_rate: Option<f64>,
+    pub rating: Option<i32>,
+    pub frontend_id: Option<String>,
+    pub prompt: Option<String>,
+}
+
+// everything a source knows about a single question beyond its base info.
+// similar_questions holds title slugs of questions from the same source
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceDetails {
+    pub prompt: String,
+    pub paid_only: Option<bool>,
+    pub acceptance_rate: Option<f64>,
+    pub likes: Option<i32>,
+    pub dislikes: Option<i32>,
+    pub hints: Vec<String>,
+    pub code_snippets: Vec<SourceCodeSnippet>,
+    pub example_testcases: Option<String>,
+    pub similar_questions: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceCodeSnippet {
+    pub lang: String,
+    pub lang_slug: String,
+    pub code: String,
+}
+
+// how an exported question points back at where it came from: the source
+// row's name, a label for people, the number the provider shows for it and
+// the problem page when the provider has one
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceRef {
+    pub source_name: String,
+    pub label: String,
+    // none when the number isn't known yet, see question_number
+    pub number: Option<String>,
+    pub url: Option<String>,
+}
+
+#[async_trait]
+pub trait QuestionSource: Send + Sync {
+    // name of this source's row in the source table
+    fn name(&self) -> &str;
+
+    // the provider's name as shown in exports
+    fn label(&self) -> String {
+        self.name().to_string()
+    }
+
+    // the number the provider lists the question under. frontend_id is
+    // what the question list said it is, for providers that list one
+    fn question_number(&self, source_qid: i32, _title_slug: &str, _frontend_id: Option<&str>) -> Option<String> {
+        Some(source_qid.to_string())
+    }
+
+    // the question's page on the provider's site
+    fn problem_url(&self, _title_slug: &str) -> Option<String> {
+        None
+    }
+
+    // whether fetch_prompt can come back with a prompt at all. the prompt
+    // prefetch worker leaves sources without any alone
+    fn has_prompts(&self) -> bool {
+        true
+    }
+
+    // base info (no prompts) for every question the provider has. on_page
+    // is called as each page of the list comes in
+    async fn list_questions(&self, on_page: &(dyn Fn(ListProgress) + Send + Sync)) ->
+        Result<Vec<SourceQuestion>, Box<dyn std::error::Error>>;
+
+    // prompt html for a single question
+    async fn fetch_prompt(&self, title_slug: &str) -> Result<String, Box<dyn std::error::Error>>;
+
+    // prompt plus whatever else the provider has on a single question.
+    // sources without extra metadata only need fetch_prompt
+    async fn fetch_details(&self, title_slug: &str) ->
+    Result<SourceDetails, Box<dyn std::error::Error>> {
+        Ok(SourceDetails { prompt: self.fetch_prompt(title_slug).await?, ..SourceDetails::default() })
+    }
+
+    // base info for a single question
+    async fn fetch_metadata(&self, title_slug: &str) ->
+        Result<SourceQuestion, Box<dyn std::error::Error>>;
+}
+
+
+pub fn registered_sources() -> Vec<Box<dyn QuestionSource>> {
+    // every provider the app imports questions from. adding a provider only
+    // takes an impl of QuestionSource and an entry here
+    vec![
+        Box::new(LeetCodeSource::default()),
+        Box::new(CodeforcesSource::default()),
+    ]
+}
+
+pub fn source_by_name(source_name: &str) -> Option<Box<dyn QuestionSource>> {
+    registered_sources().into_iter().find(|s| s.name() == source_name)
+}
+
+pub fn source_for_id(conn: &SqliteConnection, src_id: i32) ->
+Result<Box<dyn QuestionSource>, Box<dyn std::error::Error>> {
+    // built in providers by name, imported feeds by their stored location
+    let source_name = select_source_name(conn, src_id)?;
+    if let Some(registered) = source_by_name(&source_name) {
+        return Ok(registered);
+    }
+    match select_feed_location(conn, src_id)? {
+        Some(location) => Ok(Box::new(FeedSource::new(&source_name, &location))),
+        None => Err(format!("no question source named {}", source_name).into()),
+    }
+}
+
+// every source row resolved once, for exports that refer to many questions.
+// sources that can't be resolved (a provider since removed) give no refs
+pub struct SourceRefs {
+    sources: HashMap<i32, Box<dyn QuestionSource>>,
+    frontend_ids: HashMap<i32, String>,
+}
+
+impl SourceRefs {
+    pub fn load(conn: &SqliteConnection) -> Result<Self, Box<dyn std::error::Error>> {
+        use crate::db::schema::source::dsl::*;
+
+        let src_ids: Vec<i32> = source.select(sid).load(conn)?;
+        let mut sources: HashMap<i32, Box<dyn QuestionSource>> = HashMap::new();
+        for src_id in src_ids {
+            if let Ok(question_source) = source_for_id(conn, src_id) {
+                sources.insert(src_id, question_source);
+            }
+        }
+
+        Ok(SourceRefs { sources, frontend_ids: select_frontend_ids(conn)? })
+    }
+
+    pub fn get(&self, the_qid: i32, src_id: Option<i32>, source_qid: Option<i32>, title_slug: Option<&str>) -> Option<SourceRef> {
+        let question_source = self.sources.get(&src_id?)?;
+        let source_qid = source_qid?;
+        let title_slug = title_slug.unwrap_or("");
+        let frontend_id = self.frontend_ids.get(&the_qid).map(|n| n.as_str());
+
+        Some(SourceRef {
+            source_name: question_source.name().to_string(),
+            label: question_source.label(),
+            number: question_source.question_number(source_qid, title_slug, frontend_id),
+            url: Some(title_slug).filter(|s| !s.is_empty()).and_then(|s| question_source.problem_url(s)),
+        })
+    }
+}
+
+pub fn select_source_refs(conn: &SqliteConnection, questions: &[QuestionQueryResult]) ->
+Result<HashMap<i32, SourceRef>, Box<dyn std::error::Error>> {
+    // qid -> where the question came from, custom questions have none
+    let refs = SourceRefs::load(conn)?;
+
+    Ok(questions.iter()
+        .filter_map(|q| refs.get(q.qid, q.source, q.source_qid, q.title_slug.as_deref()).map(|r| (q.qid, r)))
+        .collect())
+}
+
+pub fn register_source(conn: &SqliteConnection, source_name: &str) ->
+Result<i32, Box<dyn std::error::Error>> {
+    // finds the source row for a provider, inserting it the first time the
+    // provider is seen. returns the source id questions are stored under
+    use crate::db::schema::source::dsl::*;
+
+    let existing: Option<i32> = source
+        .filter(name.eq(source_name))
+        .select(sid)
+        .first::<i32>(conn)
+        .optional()?;
+    if let Some(existing_sid) = existing {
+        return Ok(existing_sid);
+    }
+
+    diesel::insert_into(source)
+        .values(name.eq(source_name))
+        .execute(conn)?;
+    let inserted: i32 = source.order_by(sid.desc()).select(sid).first(conn)?;
+
+    Ok(inserted)
+}
+
+pub fn select_source_name(conn: &SqliteConnection, src_id: i32) ->
+Result<String, Box<dyn std::error::Error>> {
+    use crate::db::schema::source::dsl::*;
+
+    Ok(source.filter(sid.eq(src_id)).select(name).first::<String>(conn)?)
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::{db_connect_in_memory, LEETCODE_SOURCE_ID};
+    use crate::db::test_util::{insert_lc_question, set_frontend_id};
+
+    #[test]
+    fn test_register_source() {
+        let conn = db_connect_in_memory();
+        // leetcode row comes from the initial migrations
+        assert_eq!(register_source(&conn, "leetcode").unwrap(), LEETCODE_SOURCE_ID);
+        let new_sid = register_source(&conn, "some provider").unwrap();
+        assert!(new_sid != LEETCODE_SOURCE_ID);
+        assert_eq!(register_source(&conn, "some provider").unwrap(), new_sid);
+        assert_eq!(select_source_name(&conn, new_sid).unwrap(), "some provider");
+    }
+
+    #[test]
+    fn test_registered_source_names_are_unique() {
+        let sources = registered_sources();
+        for (i, s) in sources.iter().enumerate() {
+            assert!(sources[i + 1..].iter().all(|other| other.name() != s.name()));
+        }
+        assert!(source_by_name("leetcode").is_some());
+        assert!(source_by_name("codeforces").is_some());
+    }
+
+    #[test]
+    fn test_source_refs() {
+        let conn = db_connect_in_memory();
+        let cf = register_source(&conn, "codeforces").unwrap();
+        let gone = register_source(&conn, "some provider").unwrap();
+        // leetcode's numbers are its frontend ids, not the internal questionId
+        let drifted = insert_lc_question(&conn, "find-a-peak-element-ii", 2047, "");
+        let unsynced = insert_lc_question(&conn, "two-sum", 1, "");
+        set_frontend_id(&conn, drifted, "1901");
+        let refs = SourceRefs::load(&conn).unwrap();
+
+        assert_eq!(refs.get(drifted, Some(LEETCODE_SOURCE_ID), Some(2047), Some("find-a-peak-element-ii")), Some(SourceRef {
+            source_name: "leetcode".to_string(),
+            label: "LeetCode".to_string(),
+            number: Some("1901".to_string()),
+            url: Some("https://leetcode.com/problems/find-a-peak-element-ii/".to_string()),
+        }));
+        assert_eq!(refs.get(unsynced, Some(LEETCODE_SOURCE_ID), Some(1), Some("two-sum")).unwrap().number, None);
+        let cf_ref = refs.get(0, Some(cf), Some(1520006), Some("1520F2")).unwrap();
+        assert_eq!((cf_ref.number.as_deref(), cf_ref.url.as_deref()),
+            (Some("1520F2"), Some("https://codeforces.com/problemset/problem/1520/F2")));
+        assert_eq!(refs.get(0, Some(gone), Some(1), Some("a")), None);
+        assert_eq!(refs.get(0, None, None, Some("custom")), None);
+    }
+
+    #[test]
+    fn test_source_for_id() {
+        let conn = db_connect_in_memory();
+        assert_eq!(source_for_id(&conn, LEETCODE_SOURCE_ID).unwrap().name(), "leetcode");
+        let unknown = register_source(&conn, "some provider").unwrap();
+        assert!(source_for_id(&conn, unknown).is_err());
+    }
+}
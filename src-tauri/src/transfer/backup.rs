@@ -0,0 +1,1108 @@
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::{db_connect, insert_topic};
+use crate::db::models::*;
+use crate::notebook::starter::{check_language, DEFAULT_LANGUAGE};
+use crate::prompts::structure::refresh_structures;
+use crate::runner::lib::Comparator;
+use crate::prompts::translation::DEFAULT_LOCALE;
+use crate::review::scheduler::{recompute_review_states, MAX_GRADE, MIN_GRADE};
+
+// bump whenever the document shape changes. restore refuses documents
+// written by a newer version of the app
+pub const BACKUP_FORMAT_VERSION: i32 = 5;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Backup {
+    pub version: i32,
+    pub created_at: u64,
+    pub sources: Vec<BackupSource>,
+    pub users: Vec<BackupUser>,
+    pub topics: Vec<String>,
+    pub custom_questions: Vec<BackupCustomQuestion>,
+    pub stars: Vec<BackupStar>,
+    pub solutions: Vec<BackupSolution>,
+    // added in version 3
+    #[serde(default)]
+    pub test_cases: Vec<BackupTestCase>,
+    // added in version 4
+    #[serde(default)]
+    pub code_templates: Vec<BackupCodeTemplate>,
+    // added in version 5
+    #[serde(default)]
+    pub review_logs: Vec<BackupReviewLog>,
+    #[serde(default)]
+    pub saved_decks: Vec<BackupSavedDeck>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BackupSource {
+    pub sid: i32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BackupUser {
+    pub uid: i32,
+    pub name: String,
+    pub hide_diff: Option<bool>,
+    pub hide_cat: Option<bool>,
+    pub hide_solved: Option<bool>,
+    pub dark_mode: Option<bool>,
+    // added in version 2, older backups restore users in english
+    #[serde(default)]
+    pub prompt_locale: Option<String>,
+    // added in version 4, older backups restore users with python3
+    #[serde(default)]
+    pub preferred_language: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupQuestionRef {
+    // questions from a source are matched on (source name, source_qid) when
+    // restoring since local qids differ between installs. custom questions
+    // have no source and are matched on their qid within the backup
+    pub source: Option<i32>,
+    pub source_qid: Option<i32>,
+    pub qid: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BackupCustomQuestion {
+    pub qid: i32,
+    pub title: String,
+    pub title_slug: Option<String>,
+    pub prompt: Option<String>,
+    pub difficulty: Option<String>,
+    pub topics: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BackupStar {
+    pub uid: i32,
+    pub question: BackupQuestionRef,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BackupSolution {
+    pub uid: i32,
+    pub question: BackupQuestionRef,
+    pub notes: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupTestCase {
+    pub uid: i32,
+    pub question: BackupQuestionRef,
+    pub input: String,
+    pub expected: String,
+    #[serde(default)]
+    pub comparator: Option<String>,
+    pub note: Option<String>,
+    pub caught_bug: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BackupCodeTemplate {
+    pub uid: i32,
+    // topics are matched by name like custom question topics
+    pub topic: String,
+    pub name: String,
+    pub lang_slug: String,
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BackupReviewLog {
+    // review state is not backed up, it is recomputed from the log
+    pub uid: i32,
+    pub question: BackupQuestionRef,
+    pub reviewed_at: i64,
+    pub grade: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BackupSavedDeck {
+    // decks are matched by name per user
+    pub uid: i32,
+    pub name: String,
+    pub questions: Vec<BackupQuestionRef>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreMode {
+    // wipe local user data first, then load the backup
+    Replace,
+    // keep local user data and add whatever the backup has that is missing
+    Merge,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct BackupSummary {
+    pub users: usize,
+    pub topics: usize,
+    pub custom_questions: usize,
+    pub stars: usize,
+    pub solutions: usize,
+    pub test_cases: usize,
+    pub code_templates: usize,
+    pub review_logs: usize,
+    pub saved_decks: usize,
+    pub skipped: Vec<String>,
+}
+
+
+pub async fn export_backup(path: String) ->
+Result<BackupSummary, Box<dyn std::error::Error>> {
+    // serialize all user data in the db to a json document at path
+    let conn = db_connect();
+    let backup = build_backup(&conn)?;
+    let summary = BackupSummary {
+        users: backup.users.len(),
+        topics: backup.topics.len(),
+        custom_questions: backup.custom_questions.len(),
+        stars: backup.stars.len(),
+        solutions: backup.solutions.len(),
+        test_cases: backup.test_cases.len(),
+        code_templates: backup.code_templates.len(),
+        review_logs: backup.review_logs.len(),
+        saved_decks: backup.saved_decks.len(),
+        skipped: vec![],
+    };
+    fs::write(&path, serde_json::to_string_pretty(&backup)?)?;
+
+    Ok(summary)
+}
+
+pub async fn import_backup(path: String, mode: RestoreMode) ->
+Result<BackupSummary, Box<dyn std::error::Error>> {
+    // read a json document written by export_backup and load it into the db
+    let conn = db_connect();
+    let backup: Backup = serde_json::from_str(&fs::read_to_string(&path)?)?;
+
+    restore_backup(&conn, backup, mode)
+}
+
+
+pub fn build_backup(conn: &SqliteConnection) ->
+Result<Backup, Box<dyn std::error::Error>> {
+    use crate::db::schema;
+
+    let source_rows: Vec<Source> = schema::source::table.load(conn)?;
+    let user_rows: Vec<User> = schema::user::table.load(conn)?;
+    let topic_rows: Vec<Topic> = schema::topic::table.load(conn)?;
+    let question_rows: Vec<Question> = schema::question::table.load(conn)?;
+    let star_rows: Vec<Star> = schema::star::table.load(conn)?;
+    let solution_rows: Vec<Solution> = schema::solution::table.load(conn)?;
+    let question_topic_rows: Vec<QuestionTopic> = schema::question_topic::table.load(conn)?;
+    let test_case_rows: Vec<TestCase> = schema::test_case::table
+        .order_by(schema::test_case::tcid.asc())
+        .load(conn)?;
+    let code_template_rows: Vec<CodeTemplate> = schema::code_template::table
+        .order_by(schema::code_template::ctid.asc())
+        .load(conn)?;
+    let review_log_rows: Vec<ReviewLog> = schema::review_log::table
+        .order_by(schema::review_log::rlid.asc())
+        .load(conn)?;
+    let deck_rows: Vec<Deck> = schema::deck::table
+        .order_by(schema::deck::did.asc())
+        .load(conn)?;
+    let deck_question_rows: Vec<DeckQuestion> = schema::deck_question::table
+        .order_by(schema::deck_question::qid.asc())
+        .load(conn)?;
+
+    let topic_names: HashMap<i32, String> = topic_rows.iter()
+        .map(|t| (t.tid, t.name.clone())).collect();
+    let questions: HashMap<i32, &Question> = question_rows.iter()
+        .map(|q| (q.qid, q)).collect();
+
+    let mut custom_questions: Vec<BackupCustomQuestion> = vec![];
+    for q in question_rows.iter().filter(|q| is_custom_question(q)) {
+        let topics: Vec<String> = question_topic_rows.iter()
+            .filter(|qt| qt.qid == q.qid)
+            .filter_map(|qt| topic_names.get(&qt.tid).cloned())
+            .collect();
+        custom_questions.push(BackupCustomQuestion {
+            qid: q.qid,
+            title: q.title.clone(),
+            title_slug: q.title_slug.clone(),
+            prompt: q.prompt.clone(),
+            difficulty: q.difficulty.clone(),
+            topics,
+        });
+    }
+
+    let mut stars: Vec<BackupStar> = vec![];
+    for s in star_rows {
+        if let Some(q) = questions.get(&s.qid) {
+            stars.push(BackupStar { uid: s.uid, question: question_ref(q) });
+        }
+    }
+    let mut solutions: Vec<BackupSolution> = vec![];
+    for s in solution_rows {
+        if let Some(q) = questions.get(&s.qid) {
+            solutions.push(BackupSolution {
+                uid: s.uid, question: question_ref(q), notes: s.notes
+            });
+        }
+    }
+
+    let mut test_cases: Vec<BackupTestCase> = vec![];
+    for t in test_case_rows {
+        if let Some(q) = questions.get(&t.qid) {
+            test_cases.push(BackupTestCase {
+                uid: t.uid,
+                question: question_ref(q),
+                input: t.input,
+                expected: t.expected,
+                comparator: t.comparator,
+                note: t.note,
+                caught_bug: t.caught_bug,
+            });
+        }
+    }
+
+    let code_templates: Vec<BackupCodeTemplate> = code_template_rows.into_iter()
+        .filter_map(|t| topic_names.get(&t.tid).map(|topic_name| BackupCodeTemplate {
+            uid: t.uid,
+            topic: topic_name.clone(),
+            name: t.name,
+            lang_slug: t.lang_slug,
+            code: t.code,
+        }))
+        .collect();
+
+    let mut review_logs: Vec<BackupReviewLog> = vec![];
+    for l in review_log_rows {
+        if let Some(q) = questions.get(&l.qid) {
+            review_logs.push(BackupReviewLog {
+                uid: l.uid, question: question_ref(q), reviewed_at: l.reviewed_at, grade: l.grade
+            });
+        }
+    }
+
+    let saved_decks: Vec<BackupSavedDeck> = deck_rows.into_iter()
+        .map(|d| BackupSavedDeck {
+            uid: d.uid,
+            questions: deck_question_rows.iter()
+                .filter(|m| m.did == d.did)
+                .filter_map(|m| questions.get(&m.qid).map(|q| question_ref(q)))
+                .collect(),
+            name: d.name,
+        })
+        .collect();
+
+    Ok(Backup {
+        version: BACKUP_FORMAT_VERSION,
+        created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        sources: source_rows.into_iter()
+            .map(|s| BackupSource { sid: s.sid, name: s.name }).collect(),
+        users: user_rows.into_iter().map(|u| BackupUser {
+            uid: u.uid, name: u.name, hide_diff: u.hide_diff,
+            hide_cat: u.hide_cat, hide_solved: u.hide_solved, dark_mode: u.dark_mode,
+            prompt_locale: Some(u.prompt_locale),
+            preferred_language: Some(u.preferred_language),
+        }).collect(),
+        topics: topic_rows.into_iter().map(|t| t.name).collect(),
+        custom_questions,
+        stars,
+        solutions,
+        test_cases,
+        code_templates,
+        review_logs,
+        saved_decks,
+    })
+}
+
+fn is_custom_question(q: &Question) -> bool {
+    q.source.is_none() || q.source_qid.is_none()
+}
+
+fn question_ref(q: &Question) -> BackupQuestionRef {
+    BackupQuestionRef { source: q.source, source_qid: q.source_qid, qid: q.qid }
+}
+
+
+pub fn restore_backup(
+    conn: &SqliteConnection,
+    backup: Backup,
+    mode: RestoreMode
+) -> Result<BackupSummary, Box<dyn std::error::Error>> {
+    if backup.version < 1 || backup.version > BACKUP_FORMAT_VERSION {
+        return Err(format!("unsupported backup version {}", backup.version).into());
+    }
+
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        let mut summary = BackupSummary::default();
+        if mode == RestoreMode::Replace {
+            clear_user_data(conn)?;
+        }
+
+        let uid_map = restore_users(conn, &backup.users, mode, &mut summary)?;
+        let mut tid_map = restore_topics(conn, &backup.topics, &mut summary)?;
+        let sid_map = map_sources(conn, &backup.sources)?;
+        let custom_qid_map = restore_custom_questions(
+            conn, &backup.custom_questions, &mut tid_map, &mut summary)?;
+
+        let mut existing_stars: HashSet<(i32, i32)> = HashSet::new();
+        let mut existing_solutions: HashSet<(i32, i32, String)> = HashSet::new();
+        if mode == RestoreMode::Merge {
+            let star_rows: Vec<Star> = crate::db::schema::star::table.load(conn)?;
+            existing_stars.extend(star_rows.into_iter().map(|s| (s.uid, s.qid)));
+            let solution_rows: Vec<Solution> = crate::db::schema::solution::table.load(conn)?;
+            existing_solutions.extend(solution_rows.into_iter().map(|s| (s.uid, s.qid, s.notes)));
+        }
+
+        let mut new_stars: Vec<NewStar> = vec![];
+        for s in &backup.stars {
+            let resolved = resolve_question_ref(conn, &s.question, &sid_map, &custom_qid_map)?;
+            let (the_uid, the_qid) = match (uid_map.get(&s.uid), resolved) {
+                (Some(u), Some(q)) => (*u, q),
+                _ => {
+                    summary.skipped.push(format!("star on {}", describe_ref(&s.question)));
+                    continue;
+                }
+            };
+            if existing_stars.insert((the_uid, the_qid)) {
+                new_stars.push(NewStar { qid: the_qid, uid: the_uid });
+            }
+        }
+        summary.stars = new_stars.len();
+        diesel::insert_into(crate::db::schema::star::table)
+            .values(&new_stars)
+            .execute(conn)?;
+
+        let mut new_solutions: Vec<NewSolution> = vec![];
+        for s in &backup.solutions {
+            let resolved = resolve_question_ref(conn, &s.question, &sid_map, &custom_qid_map)?;
+            let (the_uid, the_qid) = match (uid_map.get(&s.uid), resolved) {
+                (Some(u), Some(q)) => (*u, q),
+                _ => {
+                    summary.skipped.push(format!("solution on {}", describe_ref(&s.question)));
+                    continue;
+                }
+            };
+            if existing_solutions.insert((the_uid, the_qid, s.notes.clone())) {
+                new_solutions.push(NewSolution { uid: the_uid, qid: the_qid, notes: s.notes.clone() });
+            }
+        }
+        summary.solutions = new_solutions.len();
+        diesel::insert_into(crate::db::schema::solution::table)
+            .values(&new_solutions)
+            .execute(conn)?;
+
+        restore_test_cases(conn, &backup.test_cases, &uid_map, &sid_map, &custom_qid_map, &mut summary)?;
+        restore_code_templates(conn, &backup.code_templates, &uid_map, &mut tid_map, &mut summary)?;
+        restore_review_logs(conn, &backup.review_logs, &uid_map, &sid_map, &custom_qid_map, &mut summary)?;
+        restore_saved_decks(conn, &backup.saved_decks, &uid_map, &sid_map, &custom_qid_map, &mut summary)?;
+
+        Ok(summary)
+    })
+}
+
+fn clear_user_data(conn: &SqliteConnection) -> Result<(), Box<dyn std::error::Error>> {
+    // everything a backup restores. catalogue questions and topics stay put,
+    // custom questions go with everything hanging off their qids so nothing
+    // is left pointing at a qid sqlite hands out again
+    use crate::db::schema;
+
+    let custom_qids: Vec<i32> = schema::question::table
+        .filter(schema::question::source.is_null()
+            .or(schema::question::source_qid.is_null()))
+        .select(schema::question::qid)
+        .load(conn)?;
+
+    diesel::delete(schema::star::table).execute(conn)?;
+    diesel::delete(schema::solution::table).execute(conn)?;
+    diesel::delete(schema::test_case::table).execute(conn)?;
+    diesel::delete(schema::code_template::table).execute(conn)?;
+    diesel::delete(schema::review_log::table).execute(conn)?;
+    diesel::delete(schema::review_state::table).execute(conn)?;
+    diesel::delete(schema::deck_question::table).execute(conn)?;
+    diesel::delete(schema::deck::table).execute(conn)?;
+    diesel::delete(schema::question_topic::table
+        .filter(schema::question_topic::qid.eq_any(&custom_qids)))
+        .execute(conn)?;
+    diesel::delete(schema::question_relation::table
+        .filter(schema::question_relation::qid.eq_any(&custom_qids)
+            .or(schema::question_relation::related_qid.eq_any(&custom_qids))))
+        .execute(conn)?;
+    diesel::delete(schema::question_metadata::table
+        .filter(schema::question_metadata::qid.eq_any(&custom_qids)))
+        .execute(conn)?;
+    diesel::delete(schema::question_example::table
+        .filter(schema::question_example::qid.eq_any(&custom_qids)))
+        .execute(conn)?;
+    diesel::delete(schema::question_constraint::table
+        .filter(schema::question_constraint::qid.eq_any(&custom_qids)))
+        .execute(conn)?;
+    diesel::delete(schema::question_structure::table
+        .filter(schema::question_structure::qid.eq_any(&custom_qids)))
+        .execute(conn)?;
+    diesel::delete(schema::question_translation::table
+        .filter(schema::question_translation::qid.eq_any(&custom_qids)))
+        .execute(conn)?;
+    diesel::delete(schema::code_snippet::table
+        .filter(schema::code_snippet::qid.eq_any(&custom_qids)))
+        .execute(conn)?;
+    diesel::delete(schema::question::table
+        .filter(schema::question::qid.eq_any(&custom_qids)))
+        .execute(conn)?;
+    diesel::delete(schema::user::table).execute(conn)?;
+
+    Ok(())
+}
+
+pub fn restore_users(
+    conn: &SqliteConnection,
+    users: &[BackupUser],
+    mode: RestoreMode,
+    summary: &mut BackupSummary
+) -> Result<HashMap<i32, i32>, Box<dyn std::error::Error>> {
+    // returns backup uid -> local uid. replace keeps the backup's uids so
+    // the frontend's notion of the current user survives a restore, merge
+    // matches users by name. a preferred language this version doesn't know
+    // falls back to the default
+    use crate::db::schema::user::dsl::*;
+
+    let mut uid_map: HashMap<i32, i32> = HashMap::new();
+    for u in users {
+        if mode == RestoreMode::Merge {
+            let existing: Option<i32> = user
+                .filter(name.eq(&u.name))
+                .select(uid)
+                .first::<i32>(conn)
+                .optional()?;
+            if let Some(local_uid) = existing {
+                uid_map.insert(u.uid, local_uid);
+                continue;
+            }
+        }
+
+        let new_user = NewUser {
+            uid: if mode == RestoreMode::Replace { Some(u.uid) } else { None },
+            name: u.name.clone(),
+            hide_diff: u.hide_diff,
+            hide_cat: u.hide_cat,
+            hide_solved: u.hide_solved,
+            dark_mode: u.dark_mode,
+            prompt_locale: u.prompt_locale.clone().unwrap_or_else(|| DEFAULT_LOCALE.to_string()),
+            preferred_language: u.preferred_language.clone()
+                .filter(|l| check_language(l).is_ok())
+                .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string()),
+        };
+        diesel::insert_into(user).values(&new_user).execute(conn)?;
+        let local_uid: i32 = match new_user.uid {
+            Some(kept_uid) => kept_uid,
+            None => user.order_by(uid.desc()).select(uid).first(conn)?,
+        };
+        uid_map.insert(u.uid, local_uid);
+        summary.users += 1;
+    }
+
+    Ok(uid_map)
+}
+
+pub fn restore_topics(
+    conn: &SqliteConnection,
+    topics: &[String],
+    summary: &mut BackupSummary
+) -> Result<HashMap<String, i32>, Box<dyn std::error::Error>> {
+    // topics are matched by name, missing ones get inserted
+    use crate::db::schema::topic::dsl::*;
+
+    let topic_rows: Vec<Topic> = topic.load(conn)?;
+    let mut tid_map: HashMap<String, i32> = topic_rows.into_iter()
+        .map(|t| (t.name, t.tid)).collect();
+    for t in topics {
+        if !tid_map.contains_key(t) {
+            tid_map.insert(t.clone(), insert_topic(conn, t)?);
+            summary.topics += 1;
+        }
+    }
+
+    Ok(tid_map)
+}
+
+pub fn map_sources(conn: &SqliteConnection, sources: &[BackupSource]) ->
+Result<HashMap<i32, i32>, Box<dyn std::error::Error>> {
+    // backup sid -> local sid, matched by source name
+    use crate::db::schema::source::dsl::*;
+
+    let source_rows: Vec<Source> = source.load(conn)?;
+    let mut sid_map: HashMap<i32, i32> = HashMap::new();
+    for s in sources {
+        if let Some(local) = source_rows.iter().find(|r| r.name == s.name) {
+            sid_map.insert(s.sid, local.sid);
+        }
+    }
+
+    Ok(sid_map)
+}
+
+pub fn restore_custom_questions(
+    conn: &SqliteConnection,
+    custom_questions: &[BackupCustomQuestion],
+    tid_map: &mut HashMap<String, i32>,
+    summary: &mut BackupSummary
+) -> Result<HashMap<i32, i32>, Box<dyn std::error::Error>> {
+    // returns backup qid -> local qid. a custom question with the same
+    // title already in the db is reused instead of duplicated
+    use crate::db::schema::question::dsl::*;
+
+    let mut qid_map: HashMap<i32, i32> = HashMap::new();
+    for cq in custom_questions {
+        let existing: Option<i32> = question
+            .filter(source.is_null())
+            .filter(title.eq(&cq.title))
+            .select(qid)
+            .first::<i32>(conn)
+            .optional()?;
+        if let Some(local_qid) = existing {
+            qid_map.insert(cq.qid, local_qid);
+            continue;
+        }
+
+        diesel::insert_into(question)
+            .values((
+                title.eq(&cq.title),
+                title_slug.eq(&cq.title_slug),
+                prompt.eq(&cq.prompt),
+                difficulty.eq(&cq.difficulty),
+            ))
+            .execute(conn)?;
+        let local_qid: i32 = question.order_by(qid.desc()).select(qid).first(conn)?;
+        refresh_structures(conn, Some(&[local_qid]))?;
+        qid_map.insert(cq.qid, local_qid);
+        summary.custom_questions += 1;
+
+        let mut new_question_topics: Vec<NewQuestionTopic> = vec![];
+        for t in &cq.topics {
+            let the_tid = match tid_map.get(t) {
+                Some(existing_tid) => *existing_tid,
+                None => {
+                    let inserted = insert_topic(conn, t)?;
+                    tid_map.insert(t.clone(), inserted);
+                    summary.topics += 1;
+                    inserted
+                }
+            };
+            new_question_topics.push(NewQuestionTopic { qid: local_qid, tid: the_tid });
+        }
+        diesel::insert_into(crate::db::schema::question_topic::table)
+            .values(&new_question_topics)
+            .execute(conn)?;
+    }
+
+    Ok(qid_map)
+}
+
+pub fn restore_test_cases(
+    conn: &SqliteConnection,
+    test_cases: &[BackupTestCase],
+    uid_map: &HashMap<i32, i32>,
+    sid_map: &HashMap<i32, i32>,
+    custom_qid_map: &HashMap<i32, i32>,
+    summary: &mut BackupSummary
+) -> Result<(), Box<dyn std::error::Error>> {
+    // a case already held for the same question with the same input and
+    // expected output is not added again, but does pick up caught_bug.
+    // cases with a comparator this version doesn't know are skipped
+    use crate::db::schema::test_case::dsl::*;
+
+    let test_case_rows: Vec<TestCase> = test_case.load(conn)?;
+    let mut existing: HashMap<(i32, i32, String, String), (i32, bool)> = test_case_rows.into_iter()
+        .map(|t| ((t.uid, t.qid, t.input, t.expected), (t.tcid, t.caught_bug)))
+        .collect();
+    for t in test_cases {
+        let resolved = resolve_question_ref(conn, &t.question, sid_map, custom_qid_map)?;
+        let known_comparator = t.comparator.as_deref().map(Comparator::from_name).transpose().is_ok();
+        let (the_uid, the_qid) = match (uid_map.get(&t.uid), resolved) {
+            (Some(u), Some(q)) if known_comparator => (*u, q),
+            _ => {
+                summary.skipped.push(format!("test case on {}", describe_ref(&t.question)));
+                continue;
+            }
+        };
+        let key = (the_uid, the_qid, t.input.clone(), t.expected.clone());
+        if let Some((the_tcid, caught)) = existing.get_mut(&key) {
+            if t.caught_bug && !*caught {
+                diesel::update(test_case.find(*the_tcid))
+                    .set(caught_bug.eq(true))
+                    .execute(conn)?;
+                *caught = true;
+            }
+            continue;
+        }
+
+        diesel::insert_into(test_case)
+            .values(&NewTestCase {
+                uid: the_uid,
+                qid: the_qid,
+                input: t.input.clone(),
+                expected: t.expected.clone(),
+                comparator: t.comparator.clone(),
+                note: t.note.clone(),
+                caught_bug: t.caught_bug,
+            })
+            .execute(conn)?;
+        let the_tcid: i32 = test_case.order_by(tcid.desc()).select(tcid).first(conn)?;
+        existing.insert(key, (the_tcid, t.caught_bug));
+        summary.test_cases += 1;
+    }
+
+    Ok(())
+}
+
+pub fn restore_code_templates(
+    conn: &SqliteConnection,
+    code_templates: &[BackupCodeTemplate],
+    uid_map: &HashMap<i32, i32>,
+    tid_map: &mut HashMap<String, i32>,
+    summary: &mut BackupSummary
+) -> Result<(), Box<dyn std::error::Error>> {
+    // a template the user already has under the same topic, name and
+    // language keeps its local code. templates in a language this version
+    // doesn't know are skipped
+    use crate::db::schema::code_template::dsl::*;
+
+    let code_template_rows: Vec<CodeTemplate> = code_template.load(conn)?;
+    let mut existing: HashSet<(i32, i32, String, String)> = code_template_rows.into_iter()
+        .map(|t| (t.uid, t.tid, t.name, t.lang_slug))
+        .collect();
+    let mut new_templates: Vec<NewCodeTemplate> = vec![];
+    for t in code_templates {
+        let the_uid = match uid_map.get(&t.uid) {
+            Some(u) if check_language(&t.lang_slug).is_ok() => *u,
+            _ => {
+                summary.skipped.push(format!("code template {}", t.name));
+                continue;
+            }
+        };
+        let the_tid = match tid_map.get(&t.topic) {
+            Some(existing_tid) => *existing_tid,
+            None => {
+                let inserted = insert_topic(conn, &t.topic)?;
+                tid_map.insert(t.topic.clone(), inserted);
+                summary.topics += 1;
+                inserted
+            }
+        };
+        if existing.insert((the_uid, the_tid, t.name.clone(), t.lang_slug.clone())) {
+            new_templates.push(NewCodeTemplate {
+                uid: the_uid,
+                tid: the_tid,
+                name: t.name.clone(),
+                lang_slug: t.lang_slug.clone(),
+                code: t.code.clone(),
+            });
+        }
+    }
+    summary.code_templates = new_templates.len();
+    diesel::insert_into(code_template)
+        .values(&new_templates)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+pub fn restore_review_logs(
+    conn: &SqliteConnection,
+    review_logs: &[BackupReviewLog],
+    uid_map: &HashMap<i32, i32>,
+    sid_map: &HashMap<i32, i32>,
+    custom_qid_map: &HashMap<i32, i32>,
+    summary: &mut BackupSummary
+) -> Result<(), Box<dyn std::error::Error>> {
+    // reviews are a union on (user, question, time, grade). every card that
+    // gained a review has its state replayed from the combined log, so two
+    // machines' reviews interleave by time rather than one side winning
+    use crate::db::schema::review_log::dsl::*;
+
+    let review_log_rows: Vec<ReviewLog> = review_log.load(conn)?;
+    let mut existing: HashSet<(i32, i32, i64, i32)> = review_log_rows.into_iter()
+        .map(|l| (l.uid, l.qid, l.reviewed_at, l.grade))
+        .collect();
+    let mut new_logs: Vec<NewReviewLog> = vec![];
+    let mut touched: HashSet<(i32, i32)> = HashSet::new();
+    for l in review_logs {
+        let resolved = resolve_question_ref(conn, &l.question, sid_map, custom_qid_map)?;
+        let valid_grade = (MIN_GRADE..=MAX_GRADE).contains(&l.grade);
+        let (the_uid, the_qid) = match (uid_map.get(&l.uid), resolved) {
+            (Some(u), Some(q)) if valid_grade => (*u, q),
+            _ => {
+                summary.skipped.push(format!("review of {}", describe_ref(&l.question)));
+                continue;
+            }
+        };
+        if existing.insert((the_uid, the_qid, l.reviewed_at, l.grade)) {
+            new_logs.push(NewReviewLog { uid: the_uid, qid: the_qid, reviewed_at: l.reviewed_at, grade: l.grade });
+            touched.insert((the_uid, the_qid));
+        }
+    }
+    summary.review_logs = new_logs.len();
+    diesel::insert_into(review_log)
+        .values(&new_logs)
+        .execute(conn)?;
+
+    recompute_review_states(conn, &touched)
+}
+
+pub fn restore_saved_decks(
+    conn: &SqliteConnection,
+    saved_decks: &[BackupSavedDeck],
+    uid_map: &HashMap<i32, i32>,
+    sid_map: &HashMap<i32, i32>,
+    custom_qid_map: &HashMap<i32, i32>,
+    summary: &mut BackupSummary
+) -> Result<(), Box<dyn std::error::Error>> {
+    // a deck the user already has under the same name gets the backup's
+    // questions added to it
+    use crate::db::schema::{deck, deck_question};
+
+    for d in saved_decks {
+        let the_uid = match uid_map.get(&d.uid) {
+            Some(u) => *u,
+            None => {
+                summary.skipped.push(format!("saved deck {}", d.name));
+                continue;
+            }
+        };
+        let existing: Option<i32> = deck::table
+            .filter(deck::uid.eq(the_uid))
+            .filter(deck::name.eq(&d.name))
+            .select(deck::did)
+            .first(conn)
+            .optional()?;
+        let the_did = match existing {
+            Some(the_did) => the_did,
+            None => {
+                diesel::insert_into(deck::table)
+                    .values(&NewDeck { uid: the_uid, name: d.name.clone() })
+                    .execute(conn)?;
+                summary.saved_decks += 1;
+                deck::table.order_by(deck::did.desc()).select(deck::did).first(conn)?
+            }
+        };
+        for q_ref in &d.questions {
+            match resolve_question_ref(conn, q_ref, sid_map, custom_qid_map)? {
+                Some(the_qid) => {
+                    diesel::insert_or_ignore_into(deck_question::table)
+                        .values(&DeckQuestion { did: the_did, qid: the_qid })
+                        .execute(conn)?;
+                }
+                None => summary.skipped.push(format!("{} in saved deck {}", describe_ref(q_ref), d.name)),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn resolve_question_ref(
+    conn: &SqliteConnection,
+    q_ref: &BackupQuestionRef,
+    sid_map: &HashMap<i32, i32>,
+    custom_qid_map: &HashMap<i32, i32>
+) -> Result<Option<i32>, Box<dyn std::error::Error>> {
+    use crate::db::schema::question::dsl::*;
+
+    match (q_ref.source, q_ref.source_qid) {
+        (Some(backup_sid), Some(src_qid)) => {
+            let local_sid = match sid_map.get(&backup_sid) {
+                Some(s) => *s,
+                None => return Ok(None),
+            };
+            let local_qid = question
+                .filter(source.eq(local_sid))
+                .filter(source_qid.eq(src_qid))
+                .select(qid)
+                .first::<i32>(conn)
+                .optional()?;
+            Ok(local_qid)
+        }
+        _ => Ok(custom_qid_map.get(&q_ref.qid).copied()),
+    }
+}
+
+pub fn describe_ref(q_ref: &BackupQuestionRef) -> String {
+    match (q_ref.source, q_ref.source_qid) {
+        (Some(s), Some(n)) => format!("question {} from source {}", n, s),
+        _ => format!("custom question {}", q_ref.qid),
+    }
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::db_connect_in_memory;
+    use crate::db::schema;
+    use crate::db::test_util::insert_lc_question;
+    use crate::notebook::starter::{select_preferred_language, set_preferred_language};
+    use crate::notebook::templates::select_code_templates;
+    use crate::prompts::translation::{select_user_locale, set_user_locale, ZH_CN_LOCALE};
+    use crate::review::deck::{add_deck_question, create_saved_deck, select_saved_decks};
+    use crate::review::scheduler::insert_review;
+
+    fn seed_user_data(conn: &SqliteConnection) {
+        let two_sum = insert_lc_question(conn, "lc-1", 1, "");
+        insert_lc_question(conn, "lc-2", 2, "");
+        diesel::insert_into(schema::question::table)
+            .values((
+                schema::question::title.eq("my custom question"),
+                schema::question::difficulty.eq("HARD"),
+            ))
+            .execute(conn).unwrap();
+        let custom_qid: i32 = schema::question::table
+            .order_by(schema::question::qid.desc())
+            .select(schema::question::qid)
+            .first(conn).unwrap();
+        let custom_tid = insert_topic(conn, "my topic").unwrap();
+        diesel::insert_into(schema::question_topic::table)
+            .values(&NewQuestionTopic { qid: custom_qid, tid: custom_tid })
+            .execute(conn).unwrap();
+        diesel::insert_into(schema::star::table)
+            .values(&vec![
+                NewStar { qid: two_sum, uid: 1 },
+                NewStar { qid: custom_qid, uid: 1 }
+            ])
+            .execute(conn).unwrap();
+        diesel::insert_into(schema::solution::table)
+            .values(&NewSolution { uid: 1, qid: two_sum, notes: "hashmap".to_string() })
+            .execute(conn).unwrap();
+        diesel::insert_into(schema::test_case::table)
+            .values(&NewTestCase {
+                uid: 1,
+                qid: two_sum,
+                input: "[3,3]\n6".to_string(),
+                expected: "[0,1]".to_string(),
+                comparator: Some("unordered_list".to_string()),
+                note: Some("duplicates".to_string()),
+                caught_bug: true,
+            })
+            .execute(conn).unwrap();
+        diesel::insert_into(schema::code_template::table)
+            .values(&NewCodeTemplate {
+                uid: 1,
+                tid: custom_tid,
+                name: "bfs".to_string(),
+                lang_slug: "python3".to_string(),
+                code: "q = deque()".to_string(),
+            })
+            .execute(conn).unwrap();
+        insert_review(conn, 1, two_sum, 0, 4).unwrap();
+        insert_review(conn, 1, custom_qid, 0, 2).unwrap();
+        let the_did = create_saved_deck(conn, 1, "warm up").unwrap();
+        add_deck_question(conn, 1, the_did, two_sum).unwrap();
+        add_deck_question(conn, 1, the_did, custom_qid).unwrap();
+    }
+
+    #[test]
+    fn test_backup_round_trip_across_installs() {
+        let conn = db_connect_in_memory();
+        seed_user_data(&conn);
+        set_user_locale(&conn, 1, ZH_CN_LOCALE).unwrap();
+        set_preferred_language(&conn, 1, "rust").unwrap();
+        let backup = build_backup(&conn).unwrap();
+        assert_eq!(backup.custom_questions.len(), 1);
+        assert_eq!(backup.custom_questions[0].topics, vec!["my topic".to_string()]);
+        let json = serde_json::to_string(&backup).unwrap();
+
+        // other install has the lc catalogue in a different order
+        let other = db_connect_in_memory();
+        insert_lc_question(&other, "lc-2", 2, "");
+        let other_two_sum = insert_lc_question(&other, "lc-1", 1, "");
+        let summary = restore_backup(
+            &other, serde_json::from_str(&json).unwrap(), RestoreMode::Replace).unwrap();
+        assert_eq!(summary.stars, 2);
+        assert_eq!(summary.solutions, 1);
+        assert_eq!(summary.test_cases, 1);
+        assert_eq!(summary.code_templates, 1);
+        assert_eq!(summary.custom_questions, 1);
+        assert_eq!(summary.review_logs, 2);
+        assert_eq!(summary.saved_decks, 1);
+        assert!(summary.skipped.is_empty());
+
+        let restored_solution: Solution = schema::solution::table.first(&other).unwrap();
+        assert_eq!(restored_solution.qid, other_two_sum);
+        assert_eq!(restored_solution.notes, "hashmap");
+        let restored_case: TestCase = schema::test_case::table.first(&other).unwrap();
+        assert_eq!((restored_case.qid, restored_case.comparator.as_deref()), (other_two_sum, Some("unordered_list")));
+        assert!(restored_case.caught_bug);
+        assert_eq!(select_user_locale(&other, 1).unwrap(), ZH_CN_LOCALE);
+        assert_eq!(select_preferred_language(&other, 1).unwrap(), "rust");
+        let restored_template = &select_code_templates(&other, 1, None).unwrap()[0];
+        assert_eq!((restored_template.topic.as_str(), restored_template.code.as_str()), ("my topic", "q = deque()"));
+        let restored_state: ReviewState = schema::review_state::table
+            .filter(schema::review_state::qid.eq(other_two_sum))
+            .first(&other).unwrap();
+        assert_eq!(restored_state.repetitions, 1);
+        let restored_deck = &select_saved_decks(&other, 1).unwrap()[0];
+        assert_eq!(restored_deck.name, "warm up");
+        assert!(restored_deck.qids.contains(&other_two_sum));
+        assert_eq!(restored_deck.qids.len(), 2);
+    }
+
+    #[test]
+    fn test_restore_skips_unknown_comparators() {
+        let conn = db_connect_in_memory();
+        seed_user_data(&conn);
+        let mut backup = build_backup(&conn).unwrap();
+        let case = &backup.test_cases[0];
+        let default_case = BackupTestCase {
+            uid: case.uid,
+            question: case.question.clone(),
+            input: "[]\n0".to_string(),
+            expected: "[]".to_string(),
+            comparator: None,
+            note: None,
+            caught_bug: false,
+        };
+        let fuzzy_case = BackupTestCase { input: "[1]\n1".to_string(), comparator: Some("fuzzy".to_string()), ..default_case.clone() };
+        backup.test_cases.extend([default_case, fuzzy_case]);
+
+        let other = db_connect_in_memory();
+        insert_lc_question(&other, "lc-1", 1, "");
+        let summary = restore_backup(&other, backup, RestoreMode::Replace).unwrap();
+        assert_eq!(summary.test_cases, 2);
+        assert_eq!(summary.skipped.len(), 1);
+        let comparators: Vec<Option<String>> = schema::test_case::table
+            .order_by(schema::test_case::tcid.asc())
+            .select(schema::test_case::comparator)
+            .load(&other).unwrap();
+        assert_eq!(comparators, vec![Some("unordered_list".to_string()), None]);
+    }
+
+    #[test]
+    fn test_restore_version_1_users() {
+        // version 1 backups have no prompt_locale
+        let conn = db_connect_in_memory();
+        let backup: Backup = serde_json::from_str(r#"{
+            "version": 1, "created_at": 0, "sources": [], "topics": [],
+            "users": [{"uid": 7, "name": "old", "hide_diff": null, "hide_cat": null,
+                "hide_solved": null, "dark_mode": true}],
+            "custom_questions": [], "stars": [], "solutions": []
+        }"#).unwrap();
+        restore_backup(&conn, backup, RestoreMode::Replace).unwrap();
+        assert_eq!(select_user_locale(&conn, 7).unwrap(), DEFAULT_LOCALE);
+        assert_eq!(select_preferred_language(&conn, 7).unwrap(), DEFAULT_LANGUAGE);
+    }
+
+    #[test]
+    fn test_restore_checks_languages() {
+        let conn = db_connect_in_memory();
+        seed_user_data(&conn);
+        let mut backup = build_backup(&conn).unwrap();
+        backup.users[0].preferred_language = Some("cobol".to_string());
+        backup.code_templates[0].lang_slug = "cobol".to_string();
+
+        let other = db_connect_in_memory();
+        insert_lc_question(&other, "lc-1", 1, "");
+        let summary = restore_backup(&other, backup, RestoreMode::Replace).unwrap();
+        assert_eq!(select_preferred_language(&other, 1).unwrap(), DEFAULT_LANGUAGE);
+        assert_eq!(summary.code_templates, 0);
+        assert_eq!(summary.skipped, vec!["code template bfs".to_string()]);
+    }
+
+    #[test]
+    fn test_replace_leaves_nothing_on_removed_custom_questions() {
+        use crate::db::metadata::store_question_details;
+        use crate::prompts::structure::store_structure;
+        use crate::relations::graph::add_manual_link;
+        use crate::sources::{SourceCodeSnippet, SourceDetails};
+
+        let conn = db_connect_in_memory();
+        seed_user_data(&conn);
+        let custom_qid: i32 = schema::question::table
+            .filter(schema::question::source.is_null())
+            .select(schema::question::qid)
+            .first(&conn).unwrap();
+        let two_sum = insert_lc_question(&conn, "two-sum", 1, "");
+        store_question_details(&conn, custom_qid, &SourceDetails {
+            code_snippets: vec![SourceCodeSnippet {
+                lang: "Python3".to_string(),
+                lang_slug: "python3".to_string(),
+                code: "class Solution:".to_string(),
+            }],
+            ..SourceDetails::default()
+        }).unwrap();
+        store_structure(&conn, custom_qid,
+            "<p><strong>Constraints:</strong></p><ul><li>1 &lt;= n &lt;= 20</li></ul>").unwrap();
+        add_manual_link(&conn, two_sum, custom_qid).unwrap();
+        diesel::insert_into(schema::question_translation::table)
+            .values((
+                schema::question_translation::qid.eq(custom_qid),
+                schema::question_translation::locale.eq(ZH_CN_LOCALE),
+                schema::question_translation::title.eq("自定义"),
+            ))
+            .execute(&conn).unwrap();
+
+        let empty_install = build_backup(&db_connect_in_memory()).unwrap();
+        restore_backup(&conn, empty_install, RestoreMode::Replace).unwrap();
+        let left: Vec<i64> = vec![
+            schema::question_metadata::table.filter(schema::question_metadata::qid.eq(custom_qid)).count().first(&conn).unwrap(),
+            schema::code_snippet::table.filter(schema::code_snippet::qid.eq(custom_qid)).count().first(&conn).unwrap(),
+            schema::question_structure::table.filter(schema::question_structure::qid.eq(custom_qid)).count().first(&conn).unwrap(),
+            schema::question_constraint::table.filter(schema::question_constraint::qid.eq(custom_qid)).count().first(&conn).unwrap(),
+            schema::question_example::table.filter(schema::question_example::qid.eq(custom_qid)).count().first(&conn).unwrap(),
+            schema::question_translation::table.filter(schema::question_translation::qid.eq(custom_qid)).count().first(&conn).unwrap(),
+            schema::question_relation::table.count().first(&conn).unwrap(),
+            schema::review_log::table.count().first(&conn).unwrap(),
+            schema::review_state::table.count().first(&conn).unwrap(),
+            schema::deck_question::table.count().first(&conn).unwrap(),
+        ];
+        assert_eq!(left, vec![0; 10]);
+        assert_eq!(schema::question::table.count().first::<i64>(&conn).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_restore_merge_does_not_duplicate() {
+        let conn = db_connect_in_memory();
+        seed_user_data(&conn);
+        let backup_json = serde_json::to_string(&build_backup(&conn).unwrap()).unwrap();
+
+        let summary = restore_backup(
+            &conn, serde_json::from_str(&backup_json).unwrap(), RestoreMode::Merge).unwrap();
+        assert_eq!(summary.users, 0);
+        assert_eq!(summary.stars, 0);
+        assert_eq!(summary.solutions, 0);
+        assert_eq!(summary.test_cases, 0);
+        assert_eq!(summary.code_templates, 0);
+        assert_eq!(summary.custom_questions, 0);
+        let num_stars: i64 = schema::star::table.count().first(&conn).unwrap();
+        assert_eq!(num_stars, 2);
+    }
+
+    #[test]
+    fn test_restore_skips_questions_missing_locally() {
+        let conn = db_connect_in_memory();
+        seed_user_data(&conn);
+        let backup = build_backup(&conn).unwrap();
+
+        let empty = db_connect_in_memory();
+        let summary = restore_backup(&empty, backup, RestoreMode::Replace).unwrap();
+        assert_eq!(summary.stars, 1); // only the custom question star
+        assert_eq!(summary.solutions, 0);
+        assert_eq!(summary.test_cases, 0);
+        assert_eq!(summary.review_logs, 1); // and its review
+        assert_eq!(summary.saved_decks, 1); // and the deck, with the custom question only
+        assert_eq!(summary.skipped.len(), 5);
+    }
+
+    #[test]
+    fn test_restore_rejects_newer_version() {
+        let conn = db_connect_in_memory();
+        let mut backup = build_backup(&conn).unwrap();
+        backup.version = BACKUP_FORMAT_VERSION + 1;
+        assert!(restore_backup(&conn, backup, RestoreMode::Merge).is_err());
+    }
+}
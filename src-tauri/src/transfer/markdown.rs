@@ -0,0 +1,675 @@
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use crate::db::lib::{
+    db_connect,
+    select_or_insert_topic,
+    select_solution_notes_by_qid
+};
+use crate::db::models::*;
+use crate::sources::SourceRefs;
+use crate::sources::leetcode::LEETCODE_SOURCE_NAME;
+
+// one markdown file per problem:
+//
+//   ---
+//   title: Two Sum
+//   source: leetcode
+//   id: 1
+//   slug: two-sum
+//   difficulty: EASY
+//   topics:
+//   - Array
+//   - Hash Table
+//   ---
+//
+//   # Two Sum
+//
+//   ## Solution 1
+//
+//   notes, usually with a ```lang fenced code block
+//
+// files without any "## Solution" heading are read as a single solution.
+// source and id are the question's source and the number it lists the
+// question under, custom questions have neither. older decks wrote leetcode
+// questions as `leetcode: <id>`, which is still read.
+
+const FRONT_MATTER_DELIM: &str = "---";
+const SOLUTION_HEADING: &str = "## Solution";
+const CODE_FENCE: &str = "```";
+const DIFFICULTIES: [&str; 3] = ["EASY", "MEDIUM", "HARD"];
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct FrontMatter {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_number", deserialize_with = "deserialize_number")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub leetcode: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<String>,
+    #[serde(default)]
+    pub topics: Vec<String>,
+}
+
+fn serialize_number<S: serde::Serializer>(number: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    // plain numbers stay yaml ints so `id: 1` reads the way it always has
+    match number.as_deref().map(|n| (n, n.parse::<u64>())) {
+        Some((_, Ok(n))) => serializer.serialize_u64(n),
+        Some((n, Err(_))) => serializer.serialize_str(n),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_number<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    match Option::<serde_yaml::Value>::deserialize(deserializer)? {
+        Some(serde_yaml::Value::Number(n)) => Ok(Some(n.to_string())),
+        Some(serde_yaml::Value::String(s)) => Ok(Some(s)),
+        Some(serde_yaml::Value::Null) | None => Ok(None),
+        Some(_) => Err(serde::de::Error::custom("id should be a number or a string")),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DeckNote {
+    pub front_matter: FrontMatter,
+    pub solutions: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct DeckSyncSummary {
+    pub files: usize,
+    pub written: usize,
+    pub questions_created: usize,
+    pub solutions_created: usize,
+    pub solutions_updated: usize,
+    pub skipped: Vec<String>,
+}
+
+
+pub async fn export_markdown_deck(uid: i32, dir: String) ->
+Result<DeckSyncSummary, Box<dyn std::error::Error>> {
+    let conn = db_connect();
+    write_deck(&conn, uid, Path::new(&dir))
+}
+
+pub async fn import_markdown_deck(uid: i32, dir: String) ->
+Result<DeckSyncSummary, Box<dyn std::error::Error>> {
+    let conn = db_connect();
+    read_deck(&conn, uid, Path::new(&dir))
+}
+
+
+pub fn write_deck(conn: &SqliteConnection, the_uid: i32, dir: &Path) ->
+Result<DeckSyncSummary, Box<dyn std::error::Error>> {
+    // writes a file for every question the user has a solution for plus
+    // every custom question. files whose content would not change are left
+    // alone so the deck directory's git diffs only show real edits
+    use crate::db::schema;
+
+    fs::create_dir_all(dir)?;
+    let mut solutions_by_qid = select_solution_notes_by_qid(conn, the_uid)?;
+
+    let question_rows: Vec<Question> = schema::question::table
+        .filter(schema::question::qid.eq_any(solutions_by_qid.keys().copied().collect::<Vec<i32>>())
+            .or(schema::question::source.is_null()))
+        .order_by(schema::question::qid.asc())
+        .load(conn)?;
+
+    let source_names: HashMap<i32, String> = schema::source::table
+        .select((schema::source::sid, schema::source::name))
+        .load::<(i32, String)>(conn)?
+        .into_iter()
+        .collect();
+    let source_refs = SourceRefs::load(conn)?;
+
+    let mut summary = DeckSyncSummary::default();
+    let mut file_names: HashSet<String> = HashSet::new();
+    for q in question_rows {
+        let topics: BTreeSet<String> = schema::question_topic::table
+            .inner_join(schema::topic::table)
+            .filter(schema::question_topic::qid.eq(q.qid))
+            .select(schema::topic::name)
+            .load::<String>(conn)?
+            .into_iter()
+            .collect();
+        let source_name = q.source.and_then(|s| source_names.get(&s)).cloned();
+        let number = source_refs.get(q.qid, q.source, q.source_qid, q.title_slug.as_deref())
+            .and_then(|r| r.number);
+        let note = DeckNote {
+            front_matter: FrontMatter {
+                title: q.title.clone(),
+                id: number.clone(),
+                source: source_name,
+                leetcode: None,
+                slug: q.title_slug.clone(),
+                difficulty: q.difficulty.clone(),
+                topics: topics.into_iter().collect(),
+            },
+            solutions: solutions_by_qid.remove(&q.qid).unwrap_or_default(),
+        };
+
+        // custom questions can share a title, later ones get their qid added
+        let mut file_name = note_file_name(&note.front_matter, number.as_deref());
+        if !file_names.insert(file_name.clone()) {
+            file_name = format!("{}-{}.md", file_name.trim_end_matches(".md"), q.qid);
+            file_names.insert(file_name.clone());
+        }
+        let path = dir.join(file_name);
+        let rendered = render_note(&note)?;
+        summary.files += 1;
+        if fs::read_to_string(&path).ok().as_deref() != Some(rendered.as_str()) {
+            fs::write(&path, rendered)?;
+            summary.written += 1;
+        }
+    }
+
+    Ok(summary)
+}
+
+pub fn note_file_name(front_matter: &FrontMatter, number: Option<&str>) -> String {
+    // source questions are prefixed with the number their source lists them
+    // under, plain numbers padded to four digits. codeforces style numbers
+    // already are the slug
+    let slug = match &front_matter.slug {
+        Some(s) if !s.is_empty() => s.clone(),
+        _ => slugify(&front_matter.title),
+    };
+    match number {
+        Some(n) if !n.eq_ignore_ascii_case(&slug) => format!("{:0>4}-{}.md", n, slug),
+        _ => format!("{}.md", slug),
+    }
+}
+
+fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        }
+        else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.trim_end_matches('-').to_string()
+}
+
+pub fn render_note(note: &DeckNote) -> Result<String, Box<dyn std::error::Error>> {
+    let mut out = String::new();
+    out.push_str(FRONT_MATTER_DELIM);
+    out.push('\n');
+    out.push_str(&serde_yaml::to_string(&note.front_matter)?);
+    out.push_str(FRONT_MATTER_DELIM);
+    out.push_str("\n\n# ");
+    out.push_str(&note.front_matter.title);
+    out.push('\n');
+    for (i, notes) in note.solutions.iter().enumerate() {
+        out.push_str(&format!("\n{} {}\n\n", SOLUTION_HEADING, i + 1));
+        out.push_str(notes.trim());
+        out.push('\n');
+    }
+
+    Ok(out)
+}
+
+pub fn parse_note(text: &str) -> Result<DeckNote, Box<dyn std::error::Error>> {
+    let text = text.replace("\r\n", "\n");
+    let mut lines = text.lines();
+    if lines.next().map(|l| l.trim_end()) != Some(FRONT_MATTER_DELIM) {
+        return Err("missing front matter".into());
+    }
+
+    let mut yaml = String::new();
+    let mut closed = false;
+    for line in lines.by_ref() {
+        if line.trim_end() == FRONT_MATTER_DELIM {
+            closed = true;
+            break;
+        }
+        yaml.push_str(line);
+        yaml.push('\n');
+    }
+    if !closed {
+        return Err("unterminated front matter".into());
+    }
+    let mut front_matter: FrontMatter = serde_yaml::from_str(&yaml)?;
+    if let Some(n) = front_matter.leetcode.take() {
+        front_matter.source.get_or_insert_with(|| LEETCODE_SOURCE_NAME.to_string());
+        front_matter.id.get_or_insert(n.to_string());
+    }
+
+    // split the body on solution headings, ignoring anything that looks like
+    // a heading inside a fenced code block
+    let mut preface: Vec<&str> = vec![];
+    let mut solutions: Vec<Vec<&str>> = vec![];
+    let mut in_fence = false;
+    for line in lines {
+        if line.trim_start().starts_with(CODE_FENCE) {
+            in_fence = !in_fence;
+        }
+        if !in_fence && line.starts_with(SOLUTION_HEADING) {
+            solutions.push(vec![]);
+            continue;
+        }
+        match solutions.last_mut() {
+            Some(current) => current.push(line),
+            None => preface.push(line),
+        }
+    }
+
+    let mut solution_notes: Vec<String> = solutions.into_iter()
+        .map(|s| s.join("\n").trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if solution_notes.is_empty() {
+        let title_heading = format!("# {}", front_matter.title);
+        let body = preface.into_iter()
+            .filter(|l| l.trim_end() != title_heading)
+            .collect::<Vec<&str>>()
+            .join("\n");
+        if !body.trim().is_empty() {
+            solution_notes.push(body.trim().to_string());
+        }
+    }
+
+    Ok(DeckNote { front_matter, solutions: solution_notes })
+}
+
+
+pub fn read_deck(conn: &SqliteConnection, the_uid: i32, dir: &Path) ->
+Result<DeckSyncSummary, Box<dyn std::error::Error>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().map(|ext| ext == "md").unwrap_or(false))
+        .collect();
+    paths.sort();
+
+    let numbers = select_question_numbers(conn)?;
+    let mut summary = DeckSyncSummary::default();
+    for path in paths {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let note = match parse_note(&fs::read_to_string(&path)?) {
+            Ok(n) => n,
+            Err(err) => {
+                summary.skipped.push(format!("{}: {}", file_name, err));
+                continue;
+            }
+        };
+        summary.files += 1;
+        conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+            import_note(conn, the_uid, note, &numbers, &mut summary)
+        })?;
+    }
+
+    Ok(summary)
+}
+
+fn import_note(
+    conn: &SqliteConnection,
+    the_uid: i32,
+    note: DeckNote,
+    numbers: &HashMap<(i32, String), i32>,
+    summary: &mut DeckSyncSummary
+) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::db::schema;
+
+    let fm = note.front_matter;
+    let diff = fm.difficulty.as_ref()
+        .map(|d| d.to_uppercase())
+        .filter(|d| DIFFICULTIES.contains(&d.as_str()));
+    let the_qid = match find_question(conn, &fm, numbers)? {
+        Some(existing) => {
+            // catalogue questions keep their source's metadata
+            diesel::update(schema::question::table
+                .filter(schema::question::qid.eq(existing))
+                .filter(schema::question::source.is_null()))
+                .set((
+                    schema::question::title_slug.eq(&fm.slug),
+                    schema::question::difficulty.eq(&diff),
+                ))
+                .execute(conn)?;
+            existing
+        }
+        None if fm.source.is_some() => {
+            // source rows only come from the source itself, a note can wait
+            // for the catalogue to be loaded or synced
+            summary.skipped.push(format!("{}: {} {} is not in the question list yet",
+                fm.title, fm.source.as_deref().unwrap_or_default(),
+                fm.id.clone().or_else(|| fm.slug.clone()).unwrap_or_default()));
+            return Ok(());
+        }
+        None => {
+            diesel::insert_into(schema::question::table)
+                .values((
+                    schema::question::title.eq(&fm.title),
+                    schema::question::title_slug.eq(&fm.slug),
+                    schema::question::difficulty.eq(&diff),
+                ))
+                .execute(conn)?;
+            summary.questions_created += 1;
+            schema::question::table
+                .order_by(schema::question::qid.desc())
+                .select(schema::question::qid)
+                .first::<i32>(conn)?
+        }
+    };
+
+    let linked: Vec<i32> = schema::question_topic::table
+        .filter(schema::question_topic::qid.eq(the_qid))
+        .select(schema::question_topic::tid)
+        .load(conn)?;
+    for topic_name in &fm.topics {
+        let the_tid = select_or_insert_topic(conn, topic_name)?;
+        if !linked.contains(&the_tid) {
+            diesel::insert_into(schema::question_topic::table)
+                .values(&NewQuestionTopic { qid: the_qid, tid: the_tid })
+                .execute(conn)?;
+        }
+    }
+
+    // the nth solution in the file overwrites the user's nth solution for the
+    // question. local solutions past the end of the file are kept
+    let existing_solutions: Vec<Solution> = schema::solution::table
+        .filter(schema::solution::uid.eq(the_uid))
+        .filter(schema::solution::qid.eq(the_qid))
+        .order_by(schema::solution::sid.asc())
+        .load(conn)?;
+    for (i, notes) in note.solutions.into_iter().enumerate() {
+        match existing_solutions.get(i) {
+            Some(existing) if existing.notes == notes => {}
+            Some(existing) => {
+                diesel::update(schema::solution::table
+                    .filter(schema::solution::sid.eq(existing.sid)))
+                    .set(schema::solution::notes.eq(notes))
+                    .execute(conn)?;
+                summary.solutions_updated += 1;
+            }
+            None => {
+                diesel::insert_into(schema::solution::table)
+                    .values(&NewSolution { uid: the_uid, qid: the_qid, notes })
+                    .execute(conn)?;
+                summary.solutions_created += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn select_question_numbers(conn: &SqliteConnection) ->
+Result<HashMap<(i32, String), i32>, Box<dyn std::error::Error>> {
+    // (source, number the source lists the question under) -> qid, numbers
+    // uppercased since codeforces slugs come in either case
+    use crate::db::schema::question::dsl::*;
+
+    let source_refs = SourceRefs::load(conn)?;
+    let rows: Vec<Question> = question
+        .filter(source.is_not_null())
+        .load(conn)?;
+
+    Ok(rows.iter()
+        .filter_map(|q| {
+            let number = source_refs.get(q.qid, q.source, q.source_qid, q.title_slug.as_deref())?.number?;
+            Some(((q.source?, number.to_uppercase()), q.qid))
+        })
+        .collect())
+}
+
+fn find_question(conn: &SqliteConnection, fm: &FrontMatter, numbers: &HashMap<(i32, String), i32>) ->
+Result<Option<i32>, Box<dyn std::error::Error>> {
+    // source questions by slug then by number, slugs first since decks
+    // written before numbers were the source's own can carry stale ids.
+    // questions without a source by slug, then custom question title
+    use crate::db::schema::question::dsl::*;
+
+    if let Some(source_name) = &fm.source {
+        let src_id: Option<i32> = crate::db::schema::source::table
+            .filter(crate::db::schema::source::name.eq(source_name))
+            .select(crate::db::schema::source::sid)
+            .first(conn)
+            .optional()?;
+        let src_id = match src_id {
+            Some(src_id) => src_id,
+            None => return Ok(None),
+        };
+        if let Some(slug) = &fm.slug {
+            let by_slug = question
+                .filter(source.eq(src_id))
+                .filter(title_slug.eq(slug))
+                .select(qid)
+                .first::<i32>(conn)
+                .optional()?;
+            if by_slug.is_some() {
+                return Ok(by_slug);
+            }
+        }
+        return Ok(fm.id.as_ref()
+            .and_then(|n| numbers.get(&(src_id, n.to_uppercase())))
+            .copied());
+    }
+    if let Some(slug) = &fm.slug {
+        let by_slug = question
+            .filter(title_slug.eq(slug))
+            .select(qid)
+            .first::<i32>(conn)
+            .optional()?;
+        if by_slug.is_some() {
+            return Ok(by_slug);
+        }
+    }
+
+    Ok(question
+        .filter(source.is_null())
+        .filter(title.eq(&fm.title))
+        .select(qid)
+        .first::<i32>(conn)
+        .optional()?)
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::db_connect_in_memory;
+    use crate::db::test_util::{insert_lc_question, set_frontend_id};
+    use crate::db::schema;
+
+    const TWO_SUM_NOTE: &str = "---
+title: Two Sum
+leetcode: 1
+slug: two-sum
+difficulty: Easy
+topics:
+- Array
+- Hash Table
+---
+
+# Two Sum
+
+## Solution 1
+
+one pass with a map
+
+```python
+## Solution is not a heading in here
+seen = {}
+```
+";
+
+    fn temp_deck_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("dsa-flashcards-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_parse_note_ignores_headings_in_fences() {
+        let note = parse_note(TWO_SUM_NOTE).unwrap();
+        assert_eq!((note.front_matter.source.as_deref(), note.front_matter.id), (Some("leetcode"), Some("1".to_string())));
+        assert_eq!(note.front_matter.leetcode, None);
+        assert_eq!(note.front_matter.topics, vec!["Array", "Hash Table"]);
+        assert_eq!(note.solutions.len(), 1);
+        assert!(note.solutions[0].contains("seen = {}"));
+    }
+
+    #[test]
+    fn test_parse_note_without_solution_headings() {
+        let note = parse_note("---\ntitle: Mine\n---\n\n# Mine\n\njust notes\n").unwrap();
+        assert_eq!(note.solutions, vec!["just notes".to_string()]);
+        assert!(parse_note("# no front matter").is_err());
+    }
+
+    #[test]
+    fn test_render_parse_round_trip() {
+        let note = parse_note(TWO_SUM_NOTE).unwrap();
+        let rendered = render_note(&note).unwrap();
+        assert!(rendered.contains("title: Two Sum\nsource: leetcode\nid: 1\n"));
+        assert_eq!(parse_note(&rendered).unwrap(), note);
+        assert_eq!(render_note(&parse_note(&rendered).unwrap()).unwrap(), rendered);
+    }
+
+    #[test]
+    fn test_note_file_name() {
+        let mut fm = FrontMatter { title: "Two Sum II: Sorted!".to_string(), ..Default::default() };
+        assert_eq!(note_file_name(&fm, None), "two-sum-ii-sorted.md");
+        fm.slug = Some("two-sum-ii-input-array-is-sorted".to_string());
+        assert_eq!(note_file_name(&fm, Some("167")), "0167-two-sum-ii-input-array-is-sorted.md");
+        fm.slug = Some("1520f2".to_string());
+        assert_eq!(note_file_name(&fm, Some("1520F2")), "1520f2.md");
+    }
+
+    #[test]
+    fn test_import_then_export_is_stable() {
+        let conn = db_connect_in_memory();
+        let dir = temp_deck_dir("stable");
+        fs::write(dir.join("0001-two-sum.md"), TWO_SUM_NOTE).unwrap();
+
+        // numbers missing from the catalogue are skipped, not made up
+        let early = read_deck(&conn, 1, &dir).unwrap();
+        assert_eq!((early.questions_created, early.skipped.len()), (0, 1));
+        let num_questions: i64 = schema::question::table.count().first(&conn).unwrap();
+        assert_eq!(num_questions, 0);
+
+        let two_sum = insert_lc_question(&conn, "two-sum", 1, "");
+        set_frontend_id(&conn, two_sum, "1");
+        let imported = read_deck(&conn, 1, &dir).unwrap();
+        assert_eq!(imported.questions_created, 0);
+        assert_eq!(imported.solutions_created, 1);
+        let reimported = read_deck(&conn, 1, &dir).unwrap();
+        assert_eq!(reimported.questions_created, 0);
+        assert_eq!(reimported.solutions_created + reimported.solutions_updated, 0);
+
+        let exported = write_deck(&conn, 1, &dir).unwrap();
+        assert_eq!(exported.files, 1);
+        let first_export = fs::read_to_string(dir.join("0001-two-sum.md")).unwrap();
+        let again = write_deck(&conn, 1, &dir).unwrap();
+        assert_eq!(again.written, 0);
+
+        let num_topics: i64 = schema::question_topic::table.count().first(&conn).unwrap();
+        assert_eq!(num_topics, 2);
+        assert!(first_export.contains("difficulty: EASY"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_codeforces_questions_round_trip() {
+        let dir = temp_deck_dir("codeforces");
+        let insert_cf = |conn: &SqliteConnection| {
+            let cf = crate::sources::register_source(conn, "codeforces").unwrap();
+            diesel::insert_into(schema::question::table)
+                .values(&NewQuestion {
+                    title: "Guess the K-th Zero".to_string(),
+                    title_slug: "1520F2".to_string(),
+                    prompt: "".to_string(),
+                    difficulty: "HARD".to_string(),
+                    source: cf,
+                    source_qid: 1520006,
+                })
+                .execute(conn).unwrap();
+            schema::question::table.order_by(schema::question::qid.desc())
+                .select(schema::question::qid)
+                .first::<i32>(conn).unwrap()
+        };
+        let laptop = db_connect_in_memory();
+        let the_qid = insert_cf(&laptop);
+        diesel::insert_into(schema::solution::table)
+            .values(&NewSolution { uid: 1, qid: the_qid, notes: "binary search".to_string() })
+            .execute(&laptop).unwrap();
+        write_deck(&laptop, 1, &dir).unwrap();
+        let exported = fs::read_to_string(dir.join("1520F2.md")).unwrap();
+        assert!(exported.contains("source: codeforces\nid: 1520F2\n"));
+
+        let desktop = db_connect_in_memory();
+        insert_cf(&desktop);
+        let imported = read_deck(&desktop, 1, &dir).unwrap();
+        assert_eq!((imported.questions_created, imported.solutions_created), (0, 1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_leetcode_notes_use_frontend_ids() {
+        // leetcode's internal questionId drifts from the number it shows,
+        // decks carry the shown number both ways
+        let dir = temp_deck_dir("frontend-ids");
+        let laptop = db_connect_in_memory();
+        let peak = insert_lc_question(&laptop, "find-a-peak-element-ii", 2047, "");
+        set_frontend_id(&laptop, peak, "1901");
+        diesel::insert_into(schema::solution::table)
+            .values(&NewSolution { uid: 1, qid: peak, notes: "binary search on columns".to_string() })
+            .execute(&laptop).unwrap();
+        write_deck(&laptop, 1, &dir).unwrap();
+        let exported = fs::read_to_string(dir.join("1901-find-a-peak-element-ii.md")).unwrap();
+        assert!(exported.contains("source: leetcode\nid: 1901\n"));
+
+        // a note without a slug is matched on the number alone, never on a
+        // question whose internal id happens to be the same
+        fs::write(dir.join("1901-find-a-peak-element-ii.md"),
+            exported.replace("slug: find-a-peak-element-ii\n", "")).unwrap();
+        let desktop = db_connect_in_memory();
+        let decoy = insert_lc_question(&desktop, "decoy", 1901, "");
+        let peak = insert_lc_question(&desktop, "find-a-peak-element-ii", 2047, "");
+        set_frontend_id(&desktop, peak, "1901");
+        set_frontend_id(&desktop, decoy, "1800");
+        let imported = read_deck(&desktop, 1, &dir).unwrap();
+        assert_eq!((imported.questions_created, imported.solutions_created), (0, 1));
+        let solved: Vec<i32> = schema::solution::table
+            .select(schema::solution::qid)
+            .load(&desktop).unwrap();
+        assert_eq!(solved, vec![peak]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_custom_questions_with_same_title() {
+        let conn = db_connect_in_memory();
+        let dir = temp_deck_dir("same-title");
+        for _ in 0..2 {
+            diesel::insert_into(schema::question::table)
+                .values(schema::question::title.eq("My Question"))
+                .execute(&conn).unwrap();
+        }
+        let second_qid: i32 = schema::question::table
+            .order_by(schema::question::qid.desc())
+            .select(schema::question::qid)
+            .first(&conn).unwrap();
+
+        assert_eq!(write_deck(&conn, 1, &dir).unwrap().written, 2);
+        assert!(dir.join("my-question.md").exists());
+        assert!(dir.join(format!("my-question-{}.md", second_qid)).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,386 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use diesel::sqlite::SqliteConnection;
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+use crate::db::lib::db_connect;
+use crate::db::models::*;
+use crate::transfer::backup::*;
+
+// pulls the user data of another dsa-flashcards sqlite file into this one.
+// the other file is read through the same path as a backup export, so
+// questions are matched on source identity exactly like a restore. stars,
+// solutions, test cases, code templates, review logs, saved decks, custom
+// questions and users are merged. review logs are unioned and each card's
+// scheduler state is recomputed from the combined log in order of review time
+
+#[derive(Serialize, Debug)]
+pub struct MergeConflict {
+    pub kind: String,
+    pub detail: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct MergeReport {
+    pub added: BackupSummary,
+    pub duplicate_solutions: usize,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+
+pub async fn merge_database(path: String) ->
+Result<MergeReport, Box<dyn std::error::Error>> {
+    dotenv::dotenv().ok();
+    let own_path = env::var("DATABASE_URL")?;
+    if let (Ok(own), Ok(other)) = (fs::canonicalize(&own_path), fs::canonicalize(&path)) {
+        if own == other {
+            return Err("cannot merge a database into itself".into());
+        }
+    }
+
+    let conn = db_connect();
+    let other = read_other_database(&conn, Path::new(&path))?;
+
+    merge_backup(&conn, other)
+}
+
+pub fn read_other_database(conn: &SqliteConnection, path: &Path) ->
+Result<Backup, Box<dyn std::error::Error>> {
+    // the other file is copied and migrated before it is read, an older
+    // install's schema comes up to this one and the original stays untouched.
+    // establish would create a missing file, so that is checked first
+    if !path.is_file() {
+        return Err(format!("no database file at {}", path.display()).into());
+    }
+    let copy: PathBuf = env::temp_dir()
+        .join(format!("dsa-flashcards-merge-{}.sqlite", std::process::id()));
+    fs::copy(path, &copy)?;
+
+    let other = (|| -> Result<Backup, Box<dyn std::error::Error>> {
+        let copy_path = copy.to_str().ok_or("temp path is not valid utf-8")?;
+        let other_conn = SqliteConnection::establish(copy_path)?;
+        let own_version = latest_migration(conn)?;
+        let other_version = latest_migration(&other_conn)?;
+        if other_version > own_version {
+            return Err(format!("{} comes from a newer version of the app (schema {})",
+                path.display(), other_version.unwrap_or_default()).into());
+        }
+        crate::embedded_migrations::run(&other_conn)?;
+
+        build_backup(&other_conn)
+    })();
+    fs::remove_file(&copy).ok();
+
+    other
+}
+
+fn latest_migration(conn: &SqliteConnection) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::{Nullable, Text};
+
+    // a file that never ran migrations has no table to read from
+    let has_table: i64 = diesel::select(sql::<diesel::sql_types::BigInt>(
+        "(SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = '__diesel_schema_migrations')"))
+        .get_result(conn)?;
+    if has_table == 0 {
+        return Ok(None);
+    }
+
+    Ok(diesel::select(sql::<Nullable<Text>>("(SELECT MAX(version) FROM __diesel_schema_migrations)"))
+        .get_result(conn)?)
+}
+
+
+pub fn merge_backup(conn: &SqliteConnection, other: Backup) ->
+Result<MergeReport, Box<dyn std::error::Error>> {
+    conn.transaction::<_, Box<dyn std::error::Error>, _>(|| {
+        let mut report = MergeReport::default();
+        report_user_conflicts(conn, &other.users, &mut report)?;
+        report_custom_question_conflicts(conn, &other.custom_questions, &mut report)?;
+
+        let uid_map = restore_users(conn, &other.users, RestoreMode::Merge, &mut report.added)?;
+        let mut tid_map = restore_topics(conn, &other.topics, &mut report.added)?;
+        let sid_map = map_sources(conn, &other.sources)?;
+        let custom_qid_map = restore_custom_questions(
+            conn, &other.custom_questions, &mut tid_map, &mut report.added)?;
+
+        // stars are a plain union
+        let star_rows: Vec<Star> = crate::db::schema::star::table.load(conn)?;
+        let mut stars: HashSet<(i32, i32)> = star_rows.into_iter()
+            .map(|s| (s.uid, s.qid)).collect();
+        let mut new_stars: Vec<NewStar> = vec![];
+        for s in &other.stars {
+            let resolved = resolve_question_ref(conn, &s.question, &sid_map, &custom_qid_map)?;
+            match (uid_map.get(&s.uid), resolved) {
+                (Some(the_uid), Some(the_qid)) => {
+                    if stars.insert((*the_uid, the_qid)) {
+                        new_stars.push(NewStar { qid: the_qid, uid: *the_uid });
+                    }
+                }
+                _ => report.added.skipped.push(format!("star on {}", describe_ref(&s.question))),
+            }
+        }
+        report.added.stars = new_stars.len();
+        diesel::insert_into(crate::db::schema::star::table)
+            .values(&new_stars)
+            .execute(conn)?;
+
+        // solutions are deduplicated by content hash. two machines holding
+        // different solutions for the same question keep both and report it,
+        // several solutions that all come from the other machine are not a
+        // divergence
+        let solution_rows: Vec<Solution> = crate::db::schema::solution::table.load(conn)?;
+        let mut local_hashes: HashMap<(i32, i32), HashSet<String>> = HashMap::new();
+        for s in solution_rows {
+            local_hashes.entry((s.uid, s.qid)).or_default().insert(content_hash(&s.notes));
+        }
+        let mut remote_hashes: HashMap<(i32, i32), HashSet<String>> = HashMap::new();
+        let mut diverged: HashSet<(i32, i32)> = HashSet::new();
+        let mut new_solutions: Vec<NewSolution> = vec![];
+        for s in other.solutions {
+            let resolved = resolve_question_ref(conn, &s.question, &sid_map, &custom_qid_map)?;
+            let (the_uid, the_qid) = match (uid_map.get(&s.uid), resolved) {
+                (Some(u), Some(q)) => (*u, q),
+                _ => {
+                    report.added.skipped.push(format!("solution on {}", describe_ref(&s.question)));
+                    continue;
+                }
+            };
+            let hash = content_hash(&s.notes);
+            let local = local_hashes.get(&(the_uid, the_qid));
+            if local.map(|l| l.contains(&hash)).unwrap_or(false)
+                || !remote_hashes.entry((the_uid, the_qid)).or_default().insert(hash) {
+                report.duplicate_solutions += 1;
+                continue;
+            }
+            if local.map(|l| !l.is_empty()).unwrap_or(false) && diverged.insert((the_uid, the_qid)) {
+                report.conflicts.push(MergeConflict {
+                    kind: "solution".to_string(),
+                    detail: format!("user {} has diverging solutions for {}, kept both",
+                        the_uid, describe_ref(&s.question)),
+                });
+            }
+            new_solutions.push(NewSolution { uid: the_uid, qid: the_qid, notes: s.notes });
+        }
+        report.added.solutions = new_solutions.len();
+        diesel::insert_into(crate::db::schema::solution::table)
+            .values(&new_solutions)
+            .execute(conn)?;
+
+        // test cases are a union too, see restore_test_cases
+        restore_test_cases(conn, &other.test_cases, &uid_map, &sid_map, &custom_qid_map, &mut report.added)?;
+        restore_code_templates(conn, &other.code_templates, &uid_map, &mut tid_map, &mut report.added)?;
+        restore_review_logs(conn, &other.review_logs, &uid_map, &sid_map, &custom_qid_map, &mut report.added)?;
+        restore_saved_decks(conn, &other.saved_decks, &uid_map, &sid_map, &custom_qid_map, &mut report.added)?;
+
+        Ok(report)
+    })
+}
+
+pub fn content_hash(notes: &str) -> String {
+    // line endings and surrounding whitespace differ between editors and
+    // machines without the solution being any different
+    let normalized = notes.replace("\r\n", "\n");
+    let digest = Sha256::digest(normalized.trim().as_bytes());
+
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn report_user_conflicts(
+    conn: &SqliteConnection,
+    users: &[BackupUser],
+    report: &mut MergeReport
+) -> Result<(), Box<dyn std::error::Error>> {
+    // users are matched by name. local settings win
+    use crate::db::schema::user::dsl::*;
+
+    for u in users {
+        let local: Option<User> = user.filter(name.eq(&u.name)).first(conn).optional()?;
+        if let Some(local) = local {
+            let same_locale = u.prompt_locale.as_ref().map(|l| *l == local.prompt_locale).unwrap_or(true);
+            let same_language = u.preferred_language.as_ref().map(|l| *l == local.preferred_language).unwrap_or(true);
+            let same_settings = same_locale && same_language && (local.hide_diff, local.hide_cat, local.hide_solved, local.dark_mode)
+                == (u.hide_diff, u.hide_cat, u.hide_solved, u.dark_mode);
+            if !same_settings {
+                report.conflicts.push(MergeConflict {
+                    kind: "user".to_string(),
+                    detail: format!("settings for user {} differ, kept local settings", u.name),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn report_custom_question_conflicts(
+    conn: &SqliteConnection,
+    custom_questions: &[BackupCustomQuestion],
+    report: &mut MergeReport
+) -> Result<(), Box<dyn std::error::Error>> {
+    // custom questions are matched by title. local content wins
+    use crate::db::schema::question::dsl::*;
+
+    for cq in custom_questions {
+        let local: Option<Question> = question
+            .filter(source.is_null())
+            .filter(title.eq(&cq.title))
+            .first(conn)
+            .optional()?;
+        if let Some(local) = local {
+            if local.prompt != cq.prompt || local.difficulty != cq.difficulty {
+                report.conflicts.push(MergeConflict {
+                    kind: "custom_question".to_string(),
+                    detail: format!("custom question \"{}\" differs, kept local version", cq.title),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::db_connect_in_memory;
+    use crate::db::schema;
+    use crate::db::test_util::insert_lc_question;
+    use crate::review::scheduler::insert_review;
+
+    fn insert_solution(conn: &SqliteConnection, the_qid: i32, notes: &str) {
+        diesel::insert_into(schema::solution::table)
+            .values(&NewSolution { uid: 1, qid: the_qid, notes: notes.to_string() })
+            .execute(conn).unwrap();
+    }
+
+    #[test]
+    fn test_content_hash_normalizes_whitespace() {
+        assert_eq!(content_hash("a\r\nb\n"), content_hash("a\nb"));
+        assert_ne!(content_hash("a\nb"), content_hash("a b"));
+    }
+
+    #[test]
+    fn test_merge_backup() {
+        let laptop = db_connect_in_memory();
+        let laptop_q1 = insert_lc_question(&laptop, "lc-1", 1, "");
+        let laptop_q2 = insert_lc_question(&laptop, "lc-2", 2, "");
+        insert_solution(&laptop, laptop_q1, "hashmap");
+        diesel::insert_into(schema::star::table)
+            .values(&NewStar { qid: laptop_q2, uid: 1 })
+            .execute(&laptop).unwrap();
+
+        let desktop = db_connect_in_memory();
+        let desktop_q2 = insert_lc_question(&desktop, "lc-2", 2, "");
+        let desktop_q1 = insert_lc_question(&desktop, "lc-1", 1, "");
+        insert_solution(&desktop, desktop_q1, "hashmap\r\n");
+        insert_solution(&desktop, desktop_q2, "two pointers");
+        insert_solution(&desktop, desktop_q1, "sort first");
+        // two solutions that both only exist on the desktop are not a conflict
+        let desktop_q3 = insert_lc_question(&desktop, "lc-3", 3, "");
+        insert_solution(&desktop, desktop_q3, "dp");
+        insert_solution(&desktop, desktop_q3, "memoized recursion");
+        insert_lc_question(&laptop, "lc-3", 3, "");
+        diesel::insert_into(schema::star::table)
+            .values(&vec![NewStar { qid: desktop_q2, uid: 1 }, NewStar { qid: desktop_q1, uid: 1 }])
+            .execute(&desktop).unwrap();
+        let empty_case = |conn: &SqliteConnection, the_qid: i32, caught: bool| {
+            diesel::insert_into(schema::test_case::table)
+                .values(&NewTestCase {
+                    uid: 1,
+                    qid: the_qid,
+                    input: "[]\n0".to_string(),
+                    expected: "[]".to_string(),
+                    comparator: Some("exact".to_string()),
+                    note: Some("empty input".to_string()),
+                    caught_bug: caught,
+                })
+                .execute(conn).unwrap();
+        };
+        empty_case(&laptop, laptop_q1, false);
+        empty_case(&desktop, desktop_q1, true);
+        empty_case(&desktop, desktop_q2, false);
+
+        let report = merge_backup(&laptop, build_backup(&desktop).unwrap()).unwrap();
+        assert_eq!(report.added.users, 0);
+        assert_eq!(report.added.stars, 1);
+        assert_eq!(report.added.solutions, 4);
+        assert_eq!(report.duplicate_solutions, 1);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].kind, "solution");
+
+        let q2_solution: Solution = schema::solution::table
+            .filter(schema::solution::qid.eq(laptop_q2))
+            .first(&laptop).unwrap();
+        assert_eq!(q2_solution.notes, "two pointers");
+
+        // the shared case only picks up the caught_bug flag
+        assert_eq!(report.added.test_cases, 1);
+        let cases: Vec<TestCase> = schema::test_case::table
+            .order_by(schema::test_case::qid.asc())
+            .load(&laptop).unwrap();
+        assert_eq!(cases.iter().map(|t| (t.qid, t.caught_bug)).collect::<Vec<(i32, bool)>>(),
+            vec![(laptop_q1, true), (laptop_q2, false)]);
+
+        // merging again changes nothing
+        let again = merge_backup(&laptop, build_backup(&desktop).unwrap()).unwrap();
+        assert_eq!(again.added.stars + again.added.solutions + again.added.test_cases, 0);
+        assert_eq!(again.duplicate_solutions, 5);
+    }
+
+    #[test]
+    fn test_merge_review_logs_chronologically() {
+        const DAY: i64 = 24 * 60 * 60;
+        let laptop = db_connect_in_memory();
+        let laptop_q1 = insert_lc_question(&laptop, "lc-1", 1, "");
+        insert_review(&laptop, 1, laptop_q1, 0, 5).unwrap();
+        let laptop_only = insert_review(&laptop, 1, laptop_q1, 2 * DAY, 5).unwrap();
+        assert_eq!(laptop_only.repetitions, 2);
+
+        // the desktop forgot the card in between, which restarts it
+        let desktop = db_connect_in_memory();
+        let desktop_q1 = insert_lc_question(&desktop, "lc-1", 1, "");
+        insert_review(&desktop, 1, desktop_q1, 0, 5).unwrap();
+        insert_review(&desktop, 1, desktop_q1, DAY, 1).unwrap();
+
+        let report = merge_backup(&laptop, build_backup(&desktop).unwrap()).unwrap();
+        assert_eq!(report.added.review_logs, 1);
+        let merged: ReviewState = schema::review_state::table.first(&laptop).unwrap();
+        assert_eq!((merged.repetitions, merged.interval_days, merged.due_at), (1, 1, 3 * DAY));
+
+        let again = merge_backup(&laptop, build_backup(&desktop).unwrap()).unwrap();
+        assert_eq!(again.added.review_logs, 0);
+        let num_states: i64 = schema::review_state::table.count().first(&laptop).unwrap();
+        assert_eq!(num_states, 1);
+    }
+
+    #[test]
+    fn test_read_other_database() {
+        let conn = db_connect_in_memory();
+        let dir = env::temp_dir().join(format!("dsa-flashcards-other-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let missing = dir.join("missing.sqlite");
+        assert!(read_other_database(&conn, &missing).is_err());
+        assert!(!missing.exists());
+
+        // a file that never ran migrations gets them on the copy only
+        let older = dir.join("older.sqlite");
+        let older_conn = SqliteConnection::establish(older.to_str().unwrap()).unwrap();
+        let other = read_other_database(&conn, &older).unwrap();
+        assert_eq!(other.users.len(), 1);
+        assert_eq!(latest_migration(&older_conn).unwrap(), None);
+
+        crate::embedded_migrations::run(&older_conn).unwrap();
+        diesel::sql_query("INSERT INTO __diesel_schema_migrations (version) VALUES ('99990101000000')")
+            .execute(&older_conn).unwrap();
+        assert!(read_other_database(&conn, &older).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,5 @@
+pub mod backup;
+pub mod markdown;
+pub mod merge;
+pub mod obsidian;
+pub mod study_sheet;
@@ -0,0 +1,436 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::db::lib::{
+    db_connect,
+    query_questions,
+    select_solution_notes_by_qid,
+    select_topic_names,
+    QuestionOptions,
+    QuestionQueryResult,
+    TOPICLESS_QUESTION_TOPIC_ID
+};
+use crate::relations::graph::{select_all_neighbors, Neighbors};
+use crate::runner::cases::{select_test_cases_by_qid, UserTestCase};
+use crate::sources::{select_source_refs, SourceRef};
+
+// vault layout:
+//   Topics.md              progress across every topic
+//   Topics/<topic>.md      per-topic index of questions with progress
+//   Questions/<name>.md    one note per question
+// obsidian resolves [[wikilinks]] by note name, so note names must be unique
+// across the vault and free of characters obsidian does not allow in links
+
+const QUESTIONS_DIR: &str = "Questions";
+const TOPICS_DIR: &str = "Topics";
+const TOPICS_INDEX: &str = "Topics.md";
+const MAX_SIMILAR: usize = 5;
+
+#[derive(Serialize, Debug, Default)]
+pub struct VaultSummary {
+    pub questions: usize,
+    pub topics: usize,
+    pub written: usize,
+}
+
+
+pub async fn export_obsidian_vault(options: QuestionOptions, dir: String) ->
+Result<VaultSummary, Box<dyn std::error::Error>> {
+    let the_uid = options.user;
+    let questions = query_questions(options).await?;
+    let conn = db_connect();
+    let topic_names = select_topic_names(&conn)?;
+    let solutions = select_solution_notes_by_qid(&conn, the_uid)?;
+    let test_cases = select_test_cases_by_qid(&conn, the_uid)?;
+
+    let mut questions: Vec<QuestionQueryResult> = questions.into_values().collect();
+    questions.sort_by_key(|q| q.qid);
+    let similar = prefer_stored_relations(
+        &questions, similar_by_topic_overlap(&questions), &select_all_neighbors(&conn)?);
+    let source_refs = select_source_refs(&conn, &questions)?;
+    let notes = render_vault(&questions, &topic_names, &solutions, &test_cases, &similar, &source_refs);
+
+    let mut summary = VaultSummary {
+        questions: questions.len(),
+        topics: notes.keys().filter(|p| p.starts_with(TOPICS_DIR)).count(),
+        written: 0,
+    };
+    let root = Path::new(&dir);
+    for (rel_path, content) in notes {
+        let path = root.join(rel_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+            fs::write(&path, content)?;
+            summary.written += 1;
+        }
+    }
+
+    Ok(summary)
+}
+
+
+pub fn similar_by_topic_overlap(questions: &[QuestionQueryResult]) -> HashMap<i32, Vec<i32>> {
+    // questions sharing the most topics, at least two of them (or the only
+    // one a single-topic question has). ties go to the lower qid
+    let topic_sets: Vec<(i32, BTreeSet<i32>)> = questions.iter()
+        .map(|q| (q.qid, q.topics.iter().copied()
+            .filter(|t| *t != TOPICLESS_QUESTION_TOPIC_ID).collect()))
+        .collect();
+
+    let mut similar: HashMap<i32, Vec<i32>> = HashMap::new();
+    for (qid, topics) in &topic_sets {
+        let needed = topics.len().min(2);
+        if needed == 0 {
+            continue;
+        }
+        let mut scored: Vec<(usize, i32)> = topic_sets.iter()
+            .filter(|(other_qid, _)| other_qid != qid)
+            .map(|(other_qid, other_topics)|
+                (topics.intersection(other_topics).count(), *other_qid))
+            .filter(|(shared, _)| *shared >= needed)
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        similar.insert(*qid, scored.into_iter().take(MAX_SIMILAR).map(|(_, q)| q).collect());
+    }
+
+    similar
+}
+
+
+pub fn prefer_stored_relations(
+    questions: &[QuestionQueryResult],
+    mut similar: HashMap<i32, Vec<i32>>,
+    neighbors: &HashMap<i32, Neighbors>
+) -> HashMap<i32, Vec<i32>> {
+    // stored links (source similar questions, the user's own) replace the
+    // topic overlap guess for questions that have any in the vault
+    let exported: BTreeSet<i32> = questions.iter().map(|q| q.qid).collect();
+    for (qid, linked) in neighbors {
+        let in_vault: Vec<i32> = linked.keys().copied()
+            .filter(|q| exported.contains(q))
+            .take(MAX_SIMILAR)
+            .collect();
+        if exported.contains(qid) && !in_vault.is_empty() {
+            similar.insert(*qid, in_vault);
+        }
+    }
+
+    similar
+}
+
+
+pub fn render_vault(
+    questions: &[QuestionQueryResult],
+    topic_names: &HashMap<i32, String>,
+    solutions: &HashMap<i32, Vec<String>>,
+    test_cases: &HashMap<i32, Vec<UserTestCase>>,
+    similar: &HashMap<i32, Vec<i32>>,
+    source_refs: &HashMap<i32, SourceRef>
+) -> BTreeMap<PathBuf, String> {
+    // returns vault relative path -> note content
+    let note_names = question_note_names(questions, source_refs);
+    let mut by_topic: BTreeMap<String, Vec<&QuestionQueryResult>> = BTreeMap::new();
+    let mut notes: BTreeMap<PathBuf, String> = BTreeMap::new();
+
+    for q in questions {
+        let topics: BTreeSet<String> = q.topics.iter()
+            .filter_map(|t| topic_names.get(t))
+            .map(|t| sanitize_note_name(t))
+            .collect();
+        for t in &topics {
+            by_topic.entry(t.clone()).or_default().push(q);
+        }
+        let similar_names: Vec<&String> = similar.get(&q.qid)
+            .map(|qids| qids.iter().filter_map(|s| note_names.get(s)).collect())
+            .unwrap_or_default();
+        let question_solutions = solutions.get(&q.qid).map(|s| s.as_slice()).unwrap_or(&[]);
+        let question_cases = test_cases.get(&q.qid).map(|c| c.as_slice()).unwrap_or(&[]);
+
+        let path = PathBuf::from(QUESTIONS_DIR).join(format!("{}.md", note_names[&q.qid]));
+        notes.insert(path, render_question_note(
+            q, source_refs.get(&q.qid), &topics, &similar_names, question_solutions, question_cases));
+    }
+
+    let mut index = String::from("# Topics\n\n| Topic | Solved | Starred |\n| --- | --- | --- |\n");
+    for (topic, topic_questions) in &by_topic {
+        let solved = topic_questions.iter().filter(|q| q.solved).count();
+        let starred = topic_questions.iter().filter(|q| q.starred).count();
+        index.push_str(&format!("| [[{}]] | {} | {} |\n",
+            topic, progress(solved, topic_questions.len()), starred));
+
+        let path = PathBuf::from(TOPICS_DIR).join(format!("{}.md", topic));
+        notes.insert(path, render_topic_note(topic, topic_questions, &note_names));
+    }
+    notes.insert(PathBuf::from(TOPICS_INDEX), index);
+
+    notes
+}
+
+fn render_question_note(
+    q: &QuestionQueryResult,
+    source_ref: Option<&SourceRef>,
+    topics: &BTreeSet<String>,
+    similar_names: &[&String],
+    question_solutions: &[String],
+    question_cases: &[UserTestCase]
+) -> String {
+    let mut out = String::from("---\ntags:\n");
+    if let Some(diff) = &q.difficulty {
+        out.push_str(&format!("  - difficulty/{}\n", diff.to_lowercase()));
+    }
+    if q.starred {
+        out.push_str("  - starred\n");
+    }
+    if let Some(slug) = &q.title_slug {
+        out.push_str(&format!("aliases:\n  - {}\n", slug));
+    }
+    out.push_str(&format!("---\n\n# {}\n\n", q.title));
+
+    if let Some(diff) = &q.difficulty {
+        out.push_str(&format!("- Difficulty: {}\n", diff));
+    }
+    out.push_str(&format!("- Solved: {}\n", if q.solved { "yes" } else { "no" }));
+    if !topics.is_empty() {
+        let links: Vec<String> = topics.iter().map(|t| format!("[[{}]]", t)).collect();
+        out.push_str(&format!("- Topics: {}\n", links.join(", ")));
+    }
+    if let Some(SourceRef { label, url: Some(url), .. }) = source_ref {
+        out.push_str(&format!("- {}: {}\n", label, url));
+    }
+
+    if let Some(prompt) = q.prompt_markdown.as_ref().filter(|p| !p.is_empty()) {
+        out.push_str(&format!("\n## Prompt\n\n{}\n", prompt.trim()));
+    }
+    if !similar_names.is_empty() {
+        out.push_str("\n## Similar\n\n");
+        for name in similar_names {
+            out.push_str(&format!("- [[{}]]\n", name));
+        }
+    }
+    for (i, notes) in question_solutions.iter().enumerate() {
+        out.push_str(&format!("\n## Solution {}\n\n{}\n", i + 1, notes.trim()));
+    }
+    if !question_cases.is_empty() {
+        out.push_str("\n## Test cases\n");
+        for (i, case) in question_cases.iter().enumerate() {
+            let mut heading = format!("Case {}", i + 1);
+            if let Some(note) = &case.note {
+                heading.push_str(&format!(": {}", note));
+            }
+            if case.caught_bug {
+                heading.push_str(" (caught a bug)");
+            }
+            let compared = case.comparator.map(|c| format!(" ({})", c.as_str())).unwrap_or_default();
+            out.push_str(&format!("\n### {}\n\n```text\n{}\n```\n\nExpected{}: `{}`\n",
+                heading, case.input, compared, case.expected));
+        }
+    }
+
+    out
+}
+
+fn render_topic_note(
+    topic: &str,
+    topic_questions: &[&QuestionQueryResult],
+    note_names: &HashMap<i32, String>
+) -> String {
+    let solved = topic_questions.iter().filter(|q| q.solved).count();
+    let mut out = format!("---\ntags:\n  - topic\n---\n\n# {}\n\nProgress: {}\n\n",
+        topic, progress(solved, topic_questions.len()));
+    for q in topic_questions {
+        let check = if q.solved { "x" } else { " " };
+        let star = if q.starred { " #starred" } else { "" };
+        let diff = q.difficulty.as_ref()
+            .map(|d| format!(" #difficulty/{}", d.to_lowercase()))
+            .unwrap_or_default();
+        out.push_str(&format!("- [{}] [[{}]]{}{}\n", check, note_names[&q.qid], diff, star));
+    }
+
+    out
+}
+
+fn progress(solved: usize, total: usize) -> String {
+    let percent = (solved * 100).checked_div(total).unwrap_or(0);
+    format!("{}/{} ({}%)", solved, total, percent)
+}
+
+fn question_note_names(questions: &[QuestionQueryResult], source_refs: &HashMap<i32, SourceRef>) ->
+HashMap<i32, String> {
+    // source questions are prefixed with their number so notes sort like
+    // the problem list, plain numbers padded to four digits. clashing names
+    // get the qid appended
+    let mut names: HashMap<i32, String> = HashMap::new();
+    let mut taken: BTreeSet<String> = BTreeSet::new();
+    for q in questions {
+        let base = match source_refs.get(&q.qid).and_then(|r| r.number.as_ref()) {
+            Some(number) =>
+                sanitize_note_name(&format!("{:0>4} {}", number, q.title)),
+            None => sanitize_note_name(&q.title),
+        };
+        let name = if taken.contains(&base) { format!("{} ({})", base, q.qid) } else { base };
+        taken.insert(name.clone());
+        names.insert(q.qid, name);
+    }
+
+    names
+}
+
+fn sanitize_note_name(name: &str) -> String {
+    let cleaned: String = name.chars()
+        .filter(|c| !"\\/:*?\"<>|#^[]".contains(*c))
+        .collect();
+
+    cleaned.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::{db_connect_in_memory, LEETCODE_SOURCE_ID};
+    use crate::db::test_util::{insert_lc_question, set_frontend_id};
+
+    fn lc_refs(questions: &[QuestionQueryResult]) -> HashMap<i32, SourceRef> {
+        questions.iter()
+            .filter(|q| q.source.is_some())
+            .map(|q| (q.qid, SourceRef {
+                source_name: "leetcode".to_string(),
+                label: "LeetCode".to_string(),
+                number: Some(q.qid.to_string()),
+                url: q.title_slug.as_ref().map(|s| format!("https://leetcode.com/problems/{}/", s)),
+            }))
+            .collect()
+    }
+
+    fn test_question(qid: i32, title: &str, topics: Vec<i32>) -> QuestionQueryResult {
+        QuestionQueryResult {
+            qid,
+            starred: qid % 2 == 0,
+            solved: qid % 3 == 0,
+            topics,
+            title: title.to_string(),
+            title_slug: None,
+            prompt: None,
+            prompt_markdown: None,
+            prompt_text: None,
+            difficulty: Some("MEDIUM".to_string()),
+            source: Some(LEETCODE_SOURCE_ID),
+            source_qid: Some(qid),
+            archived: false,
+            paid_only: false,
+            acceptance_rate: None,
+            rating: None,
+        }
+    }
+
+    #[test]
+    fn test_similar_by_topic_overlap() {
+        let questions = vec![
+            test_question(1, "a", vec![1, 2, 3]),
+            test_question(2, "b", vec![1, 2]),
+            test_question(3, "c", vec![1]),
+            test_question(4, "d", vec![1, 2, 3]),
+            test_question(5, "e", vec![TOPICLESS_QUESTION_TOPIC_ID]),
+        ];
+        let similar = similar_by_topic_overlap(&questions);
+        assert_eq!(similar[&1], vec![4, 2]);
+        assert_eq!(similar[&3], vec![1, 2, 4]);
+        assert!(!similar.contains_key(&5));
+    }
+
+    #[test]
+    fn test_prefer_stored_relations() {
+        let questions = vec![
+            test_question(1, "a", vec![1, 2]),
+            test_question(2, "b", vec![1, 2]),
+            test_question(3, "c", vec![TOPICLESS_QUESTION_TOPIC_ID]),
+        ];
+        let neighbors: HashMap<i32, Neighbors> = HashMap::from([
+            (1, Neighbors::from([(3, BTreeSet::new()), (99, BTreeSet::new())])),
+            (3, Neighbors::from([(1, BTreeSet::new())])),
+            (99, Neighbors::from([(1, BTreeSet::new())])),
+        ]);
+        let similar = prefer_stored_relations(&questions, similar_by_topic_overlap(&questions), &neighbors);
+        assert_eq!(similar[&1], vec![3]);
+        assert_eq!(similar[&2], vec![1]);
+        assert_eq!(similar[&3], vec![1]);
+        assert!(!similar.contains_key(&99));
+    }
+
+    #[test]
+    fn test_render_vault_links_and_tags() {
+        let questions = vec![
+            test_question(2, "Add Two Numbers", vec![1]),
+            test_question(3, "Longest Substring: No Repeats?", vec![1, 2]),
+        ];
+        let topic_names = HashMap::from([(1, "Linked List".to_string()), (2, "Hash Table".to_string())]);
+        let solutions = HashMap::from([(3, vec!["sliding window".to_string()])]);
+        let test_cases = HashMap::from([(3, vec![UserTestCase {
+            tcid: 1,
+            qid: 3,
+            input: "\"\"".to_string(),
+            expected: "0".to_string(),
+            comparator: Some(crate::runner::lib::Comparator::Exact),
+            note: Some("empty string".to_string()),
+            caught_bug: true,
+        }])]);
+        let similar = HashMap::from([(3, vec![2])]);
+        let mut source_refs = lc_refs(&questions);
+        source_refs.insert(2, SourceRef {
+            source_name: "codeforces".to_string(),
+            label: "Codeforces".to_string(),
+            number: Some("1520F2".to_string()),
+            url: Some("https://codeforces.com/problemset/problem/1520/F2".to_string()),
+        });
+        let notes = render_vault(&questions, &topic_names, &solutions, &test_cases, &similar, &source_refs);
+
+        let q3 = &notes[&PathBuf::from("Questions/0003 Longest Substring No Repeats.md")];
+        assert!(q3.contains("[[Hash Table]], [[Linked List]]"));
+        assert!(q3.contains("- [[1520F2 Add Two Numbers]]"));
+        assert!(q3.contains("sliding window"));
+        assert!(q3.contains("### Case 1: empty string (caught a bug)\n\n```text\n\"\"\n```\n\nExpected (exact): `0`\n"));
+        let q2 = &notes[&PathBuf::from("Questions/1520F2 Add Two Numbers.md")];
+        assert!(q2.contains("  - difficulty/medium\n  - starred\n"));
+        assert!(q2.contains("- Codeforces: https://codeforces.com/problemset/problem/1520/F2\n"));
+
+        let topic = &notes[&PathBuf::from("Topics/Linked List.md")];
+        assert!(topic.contains("Progress: 1/2 (50%)"));
+        assert!(topic.contains("- [x] [[0003 Longest Substring No Repeats]]"));
+        assert!(notes[&PathBuf::from(TOPICS_INDEX)].contains("| [[Hash Table]] | 1/1 (100%) | 0 |"));
+    }
+
+    #[test]
+    fn test_notes_use_leetcode_frontend_ids() {
+        // leetcode's internal questionId is not the number it shows
+        let conn = db_connect_in_memory();
+        let the_qid = insert_lc_question(&conn, "find-a-peak-element-ii", 2047, "");
+        set_frontend_id(&conn, the_qid, "1901");
+        let mut peak = test_question(the_qid, "Find a Peak Element II", vec![1]);
+        peak.source_qid = Some(2047);
+        let questions = vec![peak];
+        let source_refs = select_source_refs(&conn, &questions).unwrap();
+        let topic_names = HashMap::from([(1, "Array".to_string())]);
+        let notes = render_vault(&questions, &topic_names, &HashMap::new(), &HashMap::new(), &HashMap::new(), &source_refs);
+
+        assert!(notes.contains_key(&PathBuf::from("Questions/1901 Find a Peak Element II.md")));
+        assert!(notes[&PathBuf::from("Topics/Array.md")].contains("[[1901 Find a Peak Element II]]"));
+        assert!(notes.values().all(|note| !note.contains("2047")));
+    }
+
+    #[test]
+    fn test_question_note_names_are_unique() {
+        let mut a = test_question(1, "Same", vec![]);
+        a.source = None;
+        let mut b = test_question(2, "Same", vec![]);
+        b.source = None;
+        let names = question_note_names(&[a, b], &HashMap::new());
+        assert_eq!(names[&1], "Same");
+        assert_eq!(names[&2], "Same (2)");
+    }
+}
@@ -0,0 +1,284 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use crate::db::lib::{
+    db_connect,
+    query_questions,
+    select_solution_notes_by_qid,
+    select_topic_names,
+    QuestionOptions,
+    QuestionQueryResult
+};
+use crate::sources::{select_source_refs, SourceRef};
+
+// renders the questions matching a QuestionOptions filter to one self
+// contained html page meant for "print to pdf". every question shows up once,
+// under its first topic by name (first one that was filtered on, if any).
+// the pattern note and code come from the user's oldest solution: the first
+// plain line of the notes and the first fenced code block
+
+const CODE_FENCE: &str = "```";
+const OTHER_TOPIC: &str = "Other";
+
+const PRINT_CSS: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; font-size: 10.5pt; margin: 2em; color: #111; }
+h1 { font-size: 18pt; margin-bottom: 0.2em; }
+h2 { font-size: 13pt; border-bottom: 1px solid #999; margin-top: 1.4em; padding-bottom: 0.1em; }
+.meta { color: #555; font-size: 9pt; }
+.question { break-inside: avoid; page-break-inside: avoid; margin: 0.6em 0 0.9em; }
+.question h3 { font-size: 11pt; margin: 0 0 0.2em; }
+.diff { font-size: 8pt; font-weight: normal; border: 1px solid #999; border-radius: 3px; padding: 0 4px; margin-left: 6px; }
+.pattern { font-style: italic; margin: 0.1em 0 0.3em; }
+pre { font-family: Menlo, Consolas, 'Courier New', monospace; font-size: 8.5pt; background: #f4f4f4; border: 1px solid #ddd; padding: 6px 8px; white-space: pre-wrap; word-break: break-word; margin: 0; }
+@page { margin: 1.5cm; }
+@media print {
+  body { margin: 0; }
+  h2 { break-after: avoid; page-break-after: avoid; }
+  pre { background: none; }
+}
+";
+
+#[derive(Serialize, Debug, Default)]
+pub struct StudySheetSummary {
+    pub questions: usize,
+    pub topics: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SolutionExcerpt {
+    pub pattern: Option<String>,
+    pub language: Option<String>,
+    pub code: Option<String>,
+}
+
+
+pub async fn export_study_sheet(options: QuestionOptions, path: String) ->
+Result<StudySheetSummary, Box<dyn std::error::Error>> {
+    let the_uid = options.user;
+    let topic_filter: Vec<i32> = options.topics.clone().unwrap_or_default();
+    let questions = query_questions(options).await?;
+    let conn = db_connect();
+    let topic_names = select_topic_names(&conn)?;
+    let solutions = select_solution_notes_by_qid(&conn, the_uid)?;
+
+    let mut questions: Vec<QuestionQueryResult> = questions.into_values().collect();
+    questions.sort_by_key(|q| (q.source, q.source_qid, q.qid));
+    let grouped = group_by_topic(&questions, &topic_names, &topic_filter);
+    let summary = StudySheetSummary { questions: questions.len(), topics: grouped.len() };
+    let source_refs = select_source_refs(&conn, &questions)?;
+    fs::write(&path, render_study_sheet(&grouped, &solutions, &source_refs))?;
+
+    Ok(summary)
+}
+
+
+pub fn group_by_topic<'a>(
+    questions: &'a [QuestionQueryResult],
+    topic_names: &HashMap<i32, String>,
+    topic_filter: &[i32]
+) -> BTreeMap<String, Vec<&'a QuestionQueryResult>> {
+    let filtered: HashSet<&i32> = topic_filter.iter().collect();
+    let mut grouped: BTreeMap<String, Vec<&QuestionQueryResult>> = BTreeMap::new();
+    for q in questions {
+        let mut candidates: Vec<&String> = q.topics.iter()
+            .filter(|t| filtered.is_empty() || filtered.contains(t))
+            .filter_map(|t| topic_names.get(t))
+            .collect();
+        candidates.sort();
+        let topic = candidates.first()
+            .map(|t| t.to_string())
+            .unwrap_or_else(|| OTHER_TOPIC.to_string());
+        grouped.entry(topic).or_default().push(q);
+    }
+
+    grouped
+}
+
+pub fn solution_excerpt(notes: &str) -> SolutionExcerpt {
+    let mut excerpt = SolutionExcerpt { pattern: None, language: None, code: None };
+    let mut code_lines: Option<Vec<&str>> = None;
+    for line in notes.lines() {
+        let trimmed = line.trim();
+        if let Some(lines) = code_lines.as_mut() {
+            if trimmed.starts_with(CODE_FENCE) {
+                excerpt.code = Some(lines.join("\n"));
+                break;
+            }
+            lines.push(line);
+            continue;
+        }
+        if let Some(lang) = trimmed.strip_prefix(CODE_FENCE) {
+            if !lang.trim().is_empty() {
+                excerpt.language = Some(lang.trim().to_string());
+            }
+            code_lines = Some(vec![]);
+            continue;
+        }
+        if excerpt.pattern.is_none() && !trimmed.is_empty() && !trimmed.starts_with('#') {
+            excerpt.pattern = Some(trimmed.to_string());
+        }
+    }
+
+    excerpt
+}
+
+pub fn render_study_sheet(
+    grouped: &BTreeMap<String, Vec<&QuestionQueryResult>>,
+    solutions: &HashMap<i32, Vec<String>>,
+    source_refs: &HashMap<i32, SourceRef>
+) -> String {
+    let total: usize = grouped.values().map(|qs| qs.len()).sum();
+    let mut out = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>Study sheet</title>\n<style>");
+    out.push_str(PRINT_CSS);
+    out.push_str("</style>\n</head>\n<body>\n<h1>Study sheet</h1>\n");
+    out.push_str(&format!("<p class=\"meta\">{} questions across {} topics</p>\n",
+        total, grouped.len()));
+
+    for (topic, questions) in grouped {
+        out.push_str(&format!("<h2>{}</h2>\n", escape_html(topic)));
+        for q in questions {
+            let number = source_refs.get(&q.qid)
+                .and_then(|r| r.number.as_ref())
+                .map(|n| format!("{}. ", n))
+                .unwrap_or_default();
+            out.push_str("<div class=\"question\">\n<h3>");
+            out.push_str(&escape_html(&format!("{}{}", number, q.title)));
+            if let Some(diff) = &q.difficulty {
+                out.push_str(&format!("<span class=\"diff\">{}</span>", escape_html(diff)));
+            }
+            out.push_str("</h3>\n");
+
+            let excerpt = solutions.get(&q.qid)
+                .and_then(|s| s.first())
+                .map(|notes| solution_excerpt(notes));
+            if let Some(excerpt) = excerpt {
+                if let Some(pattern) = &excerpt.pattern {
+                    out.push_str(&format!("<p class=\"pattern\">{}</p>\n", escape_html(pattern)));
+                }
+                if let Some(code) = &excerpt.code {
+                    let lang = excerpt.language.as_deref().unwrap_or("");
+                    out.push_str(&format!("<pre data-lang=\"{}\"><code>{}</code></pre>\n",
+                        escape_html(lang), escape_html(code)));
+                }
+            }
+            out.push_str("</div>\n");
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+
+///////////////////////////////////////
+////// ----- UNIT TESTS --------- /////
+///////////////////////////////////////
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::lib::{db_connect_in_memory, LEETCODE_SOURCE_ID};
+    use crate::db::test_util::{insert_lc_question, set_frontend_id};
+
+    fn test_question(qid: i32, title: &str, topics: Vec<i32>) -> QuestionQueryResult {
+        QuestionQueryResult {
+            qid,
+            starred: false,
+            solved: true,
+            topics,
+            title: title.to_string(),
+            title_slug: None,
+            prompt: None,
+            prompt_markdown: None,
+            prompt_text: None,
+            difficulty: Some("EASY".to_string()),
+            source: Some(LEETCODE_SOURCE_ID),
+            source_qid: Some(qid),
+            archived: false,
+            paid_only: false,
+            acceptance_rate: None,
+            rating: None,
+        }
+    }
+
+    #[test]
+    fn test_solution_excerpt() {
+        let notes = "## Approach\n\nHash map of complements, one pass.\n\n```python\nseen = {}\nfor i, n in enumerate(nums):\n    pass\n```\n\n```rust\nignored\n```";
+        let excerpt = solution_excerpt(notes);
+        assert_eq!(excerpt.pattern.as_deref(), Some("Hash map of complements, one pass."));
+        assert_eq!(excerpt.language.as_deref(), Some("python"));
+        assert_eq!(excerpt.code.as_deref(), Some("seen = {}\nfor i, n in enumerate(nums):\n    pass"));
+
+        let unterminated = solution_excerpt("```\nno closing fence");
+        assert_eq!(unterminated.code, None);
+    }
+
+    #[test]
+    fn test_group_by_topic() {
+        let questions = vec![
+            test_question(1, "a", vec![2, 1]),
+            test_question(2, "b", vec![2]),
+            test_question(3, "c", vec![0]),
+        ];
+        let topic_names = HashMap::from([(1, "Stack".to_string()), (2, "Array".to_string())]);
+
+        let grouped = group_by_topic(&questions, &topic_names, &[]);
+        assert_eq!(grouped["Array"].len(), 2);
+        assert_eq!(grouped[OTHER_TOPIC].len(), 1);
+
+        let filtered = group_by_topic(&questions, &topic_names, &[1]);
+        assert_eq!(filtered["Stack"].iter().map(|q| q.qid).collect::<Vec<i32>>(), vec![1]);
+    }
+
+    #[test]
+    fn test_render_study_sheet_escapes() {
+        let questions = vec![test_question(20, "Valid <Parentheses>", vec![1])];
+        let topic_names = HashMap::from([(1, "Stack & Queue".to_string())]);
+        let grouped = group_by_topic(&questions, &topic_names, &[]);
+        let solutions = HashMap::from([(20, vec!["push opens\n```cpp\nif (a < b && c) {}\n```".to_string()])]);
+        let source_refs = HashMap::from([(20, SourceRef {
+            source_name: "leetcode".to_string(),
+            label: "LeetCode".to_string(),
+            number: Some("20".to_string()),
+            url: None,
+        })]);
+        let html = render_study_sheet(&grouped, &solutions, &source_refs);
+
+        assert!(html.contains("<h2>Stack &amp; Queue</h2>"));
+        assert!(html.contains("20. Valid &lt;Parentheses&gt;"));
+        assert!(html.contains("<p class=\"pattern\">push opens</p>"));
+        assert!(html.contains("if (a &lt; b &amp;&amp; c) {}"));
+        assert!(!html.contains("<script"));
+    }
+
+    #[test]
+    fn test_printed_numbers_are_leetcode_frontend_ids() {
+        let conn = db_connect_in_memory();
+        let the_qid = insert_lc_question(&conn, "find-a-peak-element-ii", 2047, "");
+        set_frontend_id(&conn, the_qid, "1901");
+        let mut peak = test_question(the_qid, "Find a Peak Element II", vec![1]);
+        peak.source_qid = Some(2047);
+        let questions = vec![peak];
+        let grouped = group_by_topic(&questions, &HashMap::from([(1, "Array".to_string())]), &[]);
+        let html = render_study_sheet(&grouped, &HashMap::new(), &select_source_refs(&conn, &questions).unwrap());
+
+        assert!(html.contains("1901. Find a Peak Element II"));
+        assert!(!html.contains("2047"));
+    }
+}